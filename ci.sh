@@ -16,6 +16,7 @@ cargo fmt --all --manifest-path example/firmware/Cargo.toml -- --check
 cargo fmt --all --manifest-path example/nrf52840-serial/Cargo.toml -- --check
 cargo fmt --all --manifest-path example/esp32c6-serial/Cargo.toml -- --check
 cargo fmt --all --manifest-path source/postcard-rpc-test/Cargo.toml -- --check
+cargo fmt --all --manifest-path source/postcard-rpc-cli/Cargo.toml -- --check
 
 # Host + STD checks
 cargo check \
@@ -29,24 +30,24 @@ cargo test \
 cargo check \
     --manifest-path source/postcard-rpc/Cargo.toml \
     --no-default-features \
-    --features=use-std,cobs-serial,raw-nusb
+    --features=use-std,cobs-serial,raw-nusb,tcp,udp,stdio,websocket
 cargo test \
     --manifest-path source/postcard-rpc/Cargo.toml \
     --no-default-features \
-    --features=use-std,cobs-serial,raw-nusb
+    --features=use-std,cobs-serial,raw-nusb,tcp,udp,stdio,websocket
 
 # Host + wasm host-client impls
 RUSTFLAGS="--cfg=web_sys_unstable_apis" \
     cargo check \
         --manifest-path source/postcard-rpc/Cargo.toml \
         --no-default-features \
-        --features=use-std,webusb \
+        --features=use-std,webusb,websocket \
         --target wasm32-unknown-unknown
 RUSTFLAGS="--cfg=web_sys_unstable_apis" \
     cargo build \
         --manifest-path source/postcard-rpc/Cargo.toml \
         --no-default-features \
-        --features=use-std,webusb \
+        --features=use-std,webusb,websocket \
         --target wasm32-unknown-unknown
 
 # Embedded + embassy server impl
@@ -77,6 +78,24 @@ cargo check \
     --no-default-features \
     --features=use-std,tokio,usb-gadget
 
+# Tokio stream (TCP/Unix socket) server impl
+cargo check \
+    --manifest-path source/postcard-rpc/Cargo.toml \
+    --no-default-features \
+    --features=use-std,tokio-stream-server
+
+# Tokio UDP server impl
+cargo check \
+    --manifest-path source/postcard-rpc/Cargo.toml \
+    --no-default-features \
+    --features=use-std,udp-server
+
+# Tokio WebSocket server impl
+cargo check \
+    --manifest-path source/postcard-rpc/Cargo.toml \
+    --no-default-features \
+    --features=use-std,websocket-server
+
 # Example projects
 cargo build \
     --manifest-path example/workbook-host/Cargo.toml
@@ -102,3 +121,8 @@ cargo check \
 # Test Project
 cargo test \
     --manifest-path source/postcard-rpc-test/Cargo.toml
+
+# Command line tool
+cargo test \
+    --manifest-path source/postcard-rpc-cli/Cargo.toml \
+    --features loopback
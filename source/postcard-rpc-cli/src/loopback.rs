@@ -0,0 +1,168 @@
+//! A simulated device, running in the same process
+//!
+//! Useful to try the tool without hardware, and to test it.
+
+use std::{
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use postcard_rpc::{
+    define_dispatch, endpoints,
+    header::{VarHeader, VarSeq, VarSeqKind},
+    host_client::{test_channels, HostClient},
+    server::{
+        impls::test_channels::{
+            dispatch_impl::{new_server, Settings, WireSpawnImpl, WireTxImpl},
+            ChannelWireRx, ChannelWireSpawn, ChannelWireTx,
+        },
+        Dispatch, Sender, SpawnContext,
+    },
+    standard_icd::{DeviceInfo, WireError},
+    topics,
+};
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+/// The operands of the [`SumEndpoint`]
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct Operands {
+    pub a: u32,
+    pub b: u32,
+}
+
+endpoints! {
+    list = ENDPOINT_LIST;
+    | EndpointTy        | RequestTy     | ResponseTy    | Path                  |
+    | ----------        | ---------     | ----------    | ----                  |
+    | SumEndpoint       | Operands      | u64           | "loopback/sum"        |
+    | ReverseEndpoint   | String        | String        | "loopback/reverse"    |
+}
+
+topics! {
+    list = TOPICS_IN_LIST;
+    direction = postcard_rpc::TopicDirection::ToServer;
+    | TopicTy           | MessageTy     | Path                      |
+    | ----------        | ---------     | ----                      |
+    | SetCounterTopic   | u32           | "loopback/counter/set"    |
+}
+
+topics! {
+    list = TOPICS_OUT_LIST;
+    direction = postcard_rpc::TopicDirection::ToClient;
+    | TopicTy           | MessageTy     | Path                      |
+    | ----------        | ---------     | ----                      |
+    | CounterTopic      | u32           | "loopback/counter"        |
+}
+
+pub struct Context {
+    counter: Arc<AtomicU32>,
+}
+
+impl SpawnContext for Context {
+    type SpawnCtxt = ();
+
+    fn spawn_ctxt(&mut self) -> Self::SpawnCtxt {}
+}
+
+const DEVICE_INFO: DeviceInfo<'static> = DeviceInfo {
+    product: "postcard-rpc loopback",
+    firmware_version: env!("CARGO_PKG_VERSION"),
+    git_hash: "",
+    build_time: "",
+    serial_number: "loopback",
+};
+
+define_dispatch! {
+    app: LoopbackApp;
+    spawn_fn: spawn_fn;
+    tx_impl: WireTxImpl;
+    spawn_impl: WireSpawnImpl;
+    context: Context;
+    device_info: DEVICE_INFO;
+    standard: [schema_pages, schema_digest];
+
+    endpoints: {
+        list: ENDPOINT_LIST;
+
+        | EndpointTy        | kind      | handler               |
+        | ----------        | ----      | -------               |
+        | SumEndpoint       | blocking  | sum_handler           |
+        | ReverseEndpoint   | blocking  | reverse_handler       |
+    };
+    topics_in: {
+        list: TOPICS_IN_LIST;
+
+        | TopicTy           | kind      | handler               |
+        | ----------        | ----      | -------               |
+        | SetCounterTopic   | blocking  | set_counter_handler   |
+    };
+    topics_out: {
+        list: TOPICS_OUT_LIST;
+    };
+}
+
+fn sum_handler(_context: &mut Context, _header: VarHeader, body: Operands) -> u64 {
+    u64::from(body.a) + u64::from(body.b)
+}
+
+fn reverse_handler(_context: &mut Context, _header: VarHeader, body: String) -> String {
+    body.chars().rev().collect()
+}
+
+fn set_counter_handler(
+    context: &mut Context,
+    _header: VarHeader,
+    body: u32,
+    _out: &Sender<ChannelWireTx>,
+) {
+    eprintln!("loopback: counter set to {body}");
+    context.counter.store(body, Ordering::Relaxed);
+}
+
+/// Publishes the counter every 100ms, counting up
+async fn count(counter: Arc<AtomicU32>, sender: Sender<ChannelWireTx>) {
+    loop {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let n = counter.fetch_add(1, Ordering::Relaxed);
+        if sender
+            .publish::<CounterTopic>(VarSeq::Seq4(n), &n)
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+/// Start a simulated device, and connect to it
+pub fn connect() -> HostClient<WireError> {
+    let (client_tx, server_rx) = mpsc::channel(16);
+    let (server_tx, client_rx) = mpsc::channel(16);
+
+    let counter = Arc::new(AtomicU32::new(0));
+    let context = Context {
+        counter: counter.clone(),
+    };
+    let app = LoopbackApp::new(context, ChannelWireSpawn {});
+    let kkind = app.min_key_len();
+    let mut server = new_server(
+        app,
+        Settings {
+            tx: ChannelWireTx::new(server_tx),
+            rx: ChannelWireRx::new(server_rx),
+            buf: 1024,
+            kkind,
+        },
+    );
+    tokio::task::spawn(count(counter, server.sender()));
+    tokio::task::spawn(async move {
+        server.run().await;
+    });
+
+    test_channels::new_from_channels(client_tx, client_rx, VarSeqKind::Seq1)
+}
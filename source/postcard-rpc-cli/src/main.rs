@@ -0,0 +1,447 @@
+//! A command line tool for talking to any postcard-rpc device
+//!
+//! Values are encoded and decoded with the schema reported by the device, so
+//! no knowledge of its ICD is needed. See [`USAGE`] for the available commands.
+
+use std::{collections::BTreeSet, path::PathBuf, process::ExitCode, time::Duration};
+
+use postcard_rpc::{
+    header::{VarSeq, VarSeqKind},
+    host_client::{
+        dynamic::{DynamicClient, DynamicError},
+        HostClient, MultiSubRxError, SchemaCache, SchemaReport,
+    },
+    standard_icd::{
+        DeviceInfoEndpoint, PingEndpoint, WireError, ERROR_PATH, STANDARD_ICD_ENDPOINTS,
+        STANDARD_ICD_TOPICS_IN, STANDARD_ICD_TOPICS_OUT,
+    },
+};
+use postcard_schema::schema::owned::{OwnedDataModelType, OwnedDataModelVariant, OwnedNamedType};
+use serde_json::Value;
+use tokio::time::timeout;
+
+#[cfg(feature = "loopback")]
+mod loopback;
+
+const USAGE: &str = "\
+Usage: postcard-rpc [OPTIONS] <COMMAND>
+
+Commands:
+  list                       List attached USB devices and serial ports
+  info                       Show the identity of the device
+  ping [VALUE]               Ping the device, with a u32 (default 0)
+  schema [--json] [--all]    Show the schema of the device, including the
+                             standard items with --all
+  call PATH [JSON]           Call an endpoint, with a request (default null)
+  publish PATH JSON          Publish a message to a topic
+  subscribe PATH [--count N] Print messages of a topic as JSON lines
+
+Connection options (one of):
+  --usb VID:PID              A USB device, by hexadecimal vendor and product id
+  --serial PORT              A serial port, using COBS framing
+  --loopback                 A simulated device, running in this process
+                             (needs the `loopback` feature)
+
+Other options:
+  --usb-serial SERIAL        Only use the USB device with this serial number
+  --baud N                   The baud rate of the serial port [default: 115200]
+  --timeout MS               How long to wait for replies [default: 5000]
+  --cache DIR                Cache schemas in this directory
+  -h, --help                 Show this message
+";
+
+/// The depth of the outgoing queue, and of subscriptions
+const DEPTH: usize = 64;
+
+enum Connection {
+    Usb {
+        vid: u16,
+        pid: u16,
+        serial: Option<String>,
+    },
+    Serial {
+        port: String,
+        baud: u32,
+    },
+    Loopback,
+}
+
+enum Command {
+    List,
+    Info,
+    Ping(u32),
+    Schema { json: bool, all: bool },
+    Call { path: String, req: Value },
+    Publish { path: String, msg: Value },
+    Subscribe { path: String, count: Option<usize> },
+}
+
+struct Args {
+    connection: Option<Connection>,
+    command: Command,
+    timeout: Duration,
+    cache: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|a| a == "-h" || a == "--help") {
+        print!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    let res = match parse(args) {
+        Ok(args) => run(args).await,
+        Err(e) => Err(format!("{e}\n\n{USAGE}")),
+    };
+    match res {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse(args: Vec<String>) -> Result<Args, String> {
+    let mut usb = None;
+    let mut usb_serial = None;
+    let mut serial = None;
+    let mut baud = 115200;
+    let mut loopback = false;
+    let mut timeout = Duration::from_millis(5000);
+    let mut cache = None;
+    let mut json = false;
+    let mut all = false;
+    let mut count = None;
+    let mut positional = vec![];
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for {arg}"))
+        };
+        match arg.as_str() {
+            "--usb" => usb = Some(parse_vid_pid(&value()?)?),
+            "--usb-serial" => usb_serial = Some(value()?),
+            "--serial" => serial = Some(value()?),
+            "--baud" => baud = parse_num(&value()?, "baud rate")?,
+            "--loopback" => loopback = true,
+            "--timeout" => timeout = Duration::from_millis(parse_num(&value()?, "timeout")?),
+            "--cache" => cache = Some(PathBuf::from(value()?)),
+            "--json" => json = true,
+            "--all" => all = true,
+            "--count" => count = Some(parse_num(&value()?, "count")?),
+            a if a.starts_with("--") => return Err(format!("unknown option {a}")),
+            _ => positional.push(arg),
+        }
+    }
+
+    let connection = match (usb, serial, loopback) {
+        (None, None, false) => None,
+        (Some((vid, pid)), None, false) => Some(Connection::Usb {
+            vid,
+            pid,
+            serial: usb_serial,
+        }),
+        (None, Some(port), false) => Some(Connection::Serial { port, baud }),
+        (None, None, true) => Some(Connection::Loopback),
+        _ => return Err("only one of --usb, --serial and --loopback may be used".into()),
+    };
+
+    let mut positional = positional.into_iter();
+    let command = match positional.next().as_deref() {
+        Some("list") => Command::List,
+        Some("info") => Command::Info,
+        Some("ping") => Command::Ping(match positional.next() {
+            Some(n) => parse_num(&n, "ping value")?,
+            None => 0,
+        }),
+        Some("schema") => Command::Schema { json, all },
+        Some("call") => Command::Call {
+            path: positional.next().ok_or("missing endpoint path")?,
+            req: match positional.next() {
+                Some(req) => parse_json(&req)?,
+                None => Value::Null,
+            },
+        },
+        Some("publish") => Command::Publish {
+            path: positional.next().ok_or("missing topic path")?,
+            msg: parse_json(&positional.next().ok_or("missing message")?)?,
+        },
+        Some("subscribe") => Command::Subscribe {
+            path: positional.next().ok_or("missing topic path")?,
+            count,
+        },
+        Some(cmd) => return Err(format!("unknown command {cmd}")),
+        None => return Err("missing command".into()),
+    };
+    if let Some(arg) = positional.next() {
+        return Err(format!("unexpected argument {arg}"));
+    }
+
+    Ok(Args {
+        connection,
+        command,
+        timeout,
+        cache,
+    })
+}
+
+fn parse_vid_pid(s: &str) -> Result<(u16, u16), String> {
+    let err = || format!("invalid USB id {s}, expected VID:PID in hexadecimal");
+    let (vid, pid) = s.split_once(':').ok_or_else(err)?;
+    let vid = u16::from_str_radix(vid.trim_start_matches("0x"), 16).map_err(|_| err())?;
+    let pid = u16::from_str_radix(pid.trim_start_matches("0x"), 16).map_err(|_| err())?;
+    Ok((vid, pid))
+}
+
+fn parse_num<T: std::str::FromStr>(s: &str, what: &str) -> Result<T, String> {
+    s.parse().map_err(|_| format!("invalid {what} {s}"))
+}
+
+fn parse_json(s: &str) -> Result<Value, String> {
+    serde_json::from_str(s).map_err(|e| format!("invalid JSON {s}: {e}"))
+}
+
+async fn run(args: Args) -> Result<(), String> {
+    if let Command::List = args.command {
+        return list();
+    }
+    let client = match args.connection {
+        Some(Connection::Usb { vid, pid, serial }) => HostClient::try_new_raw_nusb(
+            |d| {
+                d.vendor_id() == vid
+                    && d.product_id() == pid
+                    && serial
+                        .as_deref()
+                        .is_none_or(|s| d.serial_number() == Some(s))
+            },
+            ERROR_PATH,
+            DEPTH,
+            VarSeqKind::Seq4,
+        )?,
+        Some(Connection::Serial { port, baud }) => {
+            HostClient::try_new_serial_cobs(&port, ERROR_PATH, DEPTH, baud, VarSeqKind::Seq4)?
+        }
+        #[cfg(feature = "loopback")]
+        Some(Connection::Loopback) => loopback::connect(),
+        #[cfg(not(feature = "loopback"))]
+        Some(Connection::Loopback) => {
+            return Err("--loopback needs a build with `--features loopback`".into())
+        }
+        None => return Err("no device given, use --usb, --serial or --loopback".into()),
+    };
+
+    let res = command(&client, args.command, args.timeout, args.cache).await;
+    client.close();
+    res
+}
+
+/// Lists USB devices with a vendor specific interface, and all serial ports
+fn list() -> Result<(), String> {
+    let devices = nusb::list_devices().map_err(|e| format!("listing USB devices: {e}"))?;
+    for dev in devices {
+        // NOTE: Interfaces can't be enumerated on Windows, list all devices there
+        #[cfg(not(target_os = "windows"))]
+        if !dev.interfaces().any(|i| i.class() == 0xFF) {
+            continue;
+        }
+        println!(
+            "usb     {:04x}:{:04x}  serial: {}  {} {}",
+            dev.vendor_id(),
+            dev.product_id(),
+            dev.serial_number().unwrap_or("-"),
+            dev.manufacturer_string().unwrap_or(""),
+            dev.product_string().unwrap_or(""),
+        );
+    }
+    let ports =
+        tokio_serial::available_ports().map_err(|e| format!("listing serial ports: {e}"))?;
+    for port in ports {
+        println!("serial  {}", port.port_name);
+    }
+    Ok(())
+}
+
+async fn command(
+    client: &HostClient<WireError>,
+    command: Command,
+    wait: Duration,
+    cache: Option<PathBuf>,
+) -> Result<(), String> {
+    match command {
+        Command::List => unreachable!("listing needs no device"),
+        Command::Info => {
+            let info = timeout(wait, client.send_resp::<DeviceInfoEndpoint>(&()))
+                .await
+                .map_err(|_| "timed out")?
+                .map_err(|e| format!("getting the device info failed: {e:?}"))?;
+            println!("product:          {}", info.product);
+            println!("firmware version: {}", info.firmware_version);
+            println!("git hash:         {}", info.git_hash);
+            println!("build time:       {}", info.build_time);
+            println!("serial number:    {}", info.serial_number);
+            return Ok(());
+        }
+        Command::Ping(n) => {
+            let resp = timeout(wait, client.send_resp::<PingEndpoint>(&n))
+                .await
+                .map_err(|_| "timed out")?
+                .map_err(|e| format!("ping failed: {e:?}"))?;
+            println!("{resp}");
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let report = timeout(wait, async {
+        match &cache {
+            Some(dir) => {
+                client
+                    .get_schema_report_cached(&SchemaCache::new(dir))
+                    .await
+            }
+            None => client.get_schema_report().await,
+        }
+    })
+    .await
+    .map_err(|_| "timed out")?
+    .map_err(|e| format!("getting the schema failed: {e}"))?;
+
+    match command {
+        Command::Schema { json: true, .. } => {
+            let json = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+            println!("{json}");
+            return Ok(());
+        }
+        Command::Schema { json: false, all } => {
+            print_schema(&report, all);
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let dynamic = DynamicClient::with_report(client.clone(), report);
+    match command {
+        Command::Call { path, req } => {
+            let resp = timeout(wait, dynamic.send_resp(&path, &req))
+                .await
+                .map_err(|_| "timed out")?
+                .map_err(dynamic_err)?;
+            println!("{resp}");
+        }
+        Command::Publish { path, msg } => {
+            dynamic
+                .publish(&path, VarSeq::Seq4(0), &msg)
+                .await
+                .map_err(dynamic_err)?;
+            // Publishing only queues the message. Frames are sent in order, so once
+            // a ping was answered the message was written, and the client can be closed.
+            timeout(wait, client.send_resp::<PingEndpoint>(&0))
+                .await
+                .map_err(|_| "timed out")?
+                .map_err(|e| format!("publishing failed: {e:?}"))?;
+        }
+        Command::Subscribe { path, count } => {
+            let mut sub = dynamic.subscribe(&path, DEPTH).await.map_err(dynamic_err)?;
+            let mut seen = 0;
+            while count.is_none_or(|c| seen < c) {
+                match sub.recv().await {
+                    Ok(msg) => println!("{msg}"),
+                    Err(MultiSubRxError::Lagged(n)) => eprintln!("warning: lost {n} messages"),
+                    Err(MultiSubRxError::IoClosed) => return Err("connection closed".into()),
+                }
+                seen += 1;
+            }
+        }
+        _ => unreachable!("handled above"),
+    }
+    Ok(())
+}
+
+fn dynamic_err(e: DynamicError<WireError>) -> String {
+    match e {
+        DynamicError::Comms(e) => format!("communication failed: {e:?}"),
+        e => e.to_string(),
+    }
+}
+
+/// Prints the paths and types of all items, then the definitions of the types they use
+fn print_schema(report: &SchemaReport, all: bool) {
+    let mut types = BTreeSet::new();
+    let mut used = |ty: &OwnedNamedType| {
+        collect_types(ty, &mut types);
+        ty.name.clone()
+    };
+
+    println!("endpoints:");
+    let std_eps: Vec<_> = STANDARD_ICD_ENDPOINTS
+        .endpoints
+        .iter()
+        .map(|e| e.0)
+        .collect();
+    for ep in report.endpoints.iter() {
+        if all || !std_eps.contains(&ep.path.as_str()) {
+            println!(
+                "  {}: {} -> {}",
+                ep.path,
+                used(&ep.req_ty),
+                used(&ep.resp_ty)
+            );
+        }
+    }
+    let std_in: Vec<_> = STANDARD_ICD_TOPICS_IN.topics.iter().map(|t| t.0).collect();
+    println!("topics in:");
+    for tp in report.topics_in.iter() {
+        if all || !std_in.contains(&tp.path.as_str()) {
+            println!("  {}: {}", tp.path, used(&tp.ty));
+        }
+    }
+    let std_out: Vec<_> = STANDARD_ICD_TOPICS_OUT.topics.iter().map(|t| t.0).collect();
+    println!("topics out:");
+    for tp in report.topics_out.iter() {
+        if all || !std_out.contains(&tp.path.as_str()) {
+            println!("  {}: {}", tp.path, used(&tp.ty));
+        }
+    }
+    println!("types:");
+    for def in types {
+        println!("  {def}");
+    }
+}
+
+/// Collects the definitions of all structs and enums used by a type
+///
+/// Unlike [`OwnedNamedType::all_used_types()`], this supports schemas containing schemas.
+fn collect_types(ty: &OwnedNamedType, types: &mut BTreeSet<String>) {
+    let children: Vec<&OwnedNamedType> = match &ty.ty {
+        OwnedDataModelType::Option(t)
+        | OwnedDataModelType::NewtypeStruct(t)
+        | OwnedDataModelType::Seq(t) => vec![t],
+        OwnedDataModelType::Tuple(ts) | OwnedDataModelType::TupleStruct(ts) => ts.iter().collect(),
+        OwnedDataModelType::Map { key, val } => vec![key, val],
+        OwnedDataModelType::Struct(fields) => fields.iter().map(|f| &f.ty).collect(),
+        OwnedDataModelType::Enum(variants) => variants
+            .iter()
+            .flat_map(|v| match &v.ty {
+                OwnedDataModelVariant::UnitVariant => vec![],
+                OwnedDataModelVariant::NewtypeVariant(t) => vec![&**t],
+                OwnedDataModelVariant::TupleVariant(ts) => ts.iter().collect(),
+                OwnedDataModelVariant::StructVariant(fields) => {
+                    fields.iter().map(|f| &f.ty).collect()
+                }
+            })
+            .collect(),
+        _ => vec![],
+    };
+    let def = ty.to_pseudocode();
+    // Primitives are defined by their name alone
+    if def != ty.name && !types.insert(def) {
+        return;
+    }
+    for child in children {
+        collect_types(child, types);
+    }
+}
@@ -0,0 +1,107 @@
+use std::process::{Command, Output};
+
+use serde_json::{json, Value};
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_postcard-rpc"))
+        .arg("--loopback")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(args: &[&str]) -> String {
+    let out = run(args);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    String::from_utf8(out.stdout).unwrap()
+}
+
+fn stderr(args: &[&str]) -> String {
+    let out = run(args);
+    assert!(!out.status.success());
+    String::from_utf8(out.stderr).unwrap()
+}
+
+#[test]
+fn ping_and_info() {
+    assert_eq!(stdout(&["ping", "42"]), "42\n");
+    assert!(stdout(&["info"]).contains("product:          postcard-rpc loopback"));
+}
+
+#[test]
+fn schema() {
+    let text = stdout(&["schema"]);
+    assert!(text.contains("  loopback/sum: Operands -> u64\n"));
+    assert!(text.contains("  loopback/counter/set: u32\n"));
+    assert!(text.contains("  loopback/counter: u32\n"));
+    assert!(text.contains("  struct Operands { a: u32, b: u32 }\n"));
+    // Standard items are only shown on request
+    assert!(!text.contains("postcard-rpc/ping"));
+    assert!(stdout(&["schema", "--all"]).contains("  postcard-rpc/ping: u32 -> u32\n"));
+
+    let json: Value = serde_json::from_str(&stdout(&["schema", "--json"])).unwrap();
+    let endpoints = json["endpoints"].as_array().unwrap();
+    assert!(endpoints.iter().any(|ep| ep["path"] == "loopback/sum"));
+}
+
+#[test]
+fn call() {
+    assert_eq!(
+        stdout(&["call", "loopback/sum", r#"{"a": 40, "b": 2}"#]),
+        "42\n"
+    );
+    assert_eq!(
+        stdout(&["call", "loopback/reverse", r#""olleh""#]),
+        "\"hello\"\n"
+    );
+    assert_eq!(
+        stderr(&["call", "loopback/sum", r#"{"a": -1, "b": 2}"#]),
+        "error: expected a u32 at '/a'\n"
+    );
+    assert_eq!(
+        stderr(&["call", "loopback/product", "{}"]),
+        "error: no endpoint with path 'loopback/product'\n"
+    );
+}
+
+#[test]
+fn topics() {
+    // The simulated device reports the messages it receives
+    let out = run(&["publish", "loopback/counter/set", "7"]);
+    assert!(out.status.success());
+    assert_eq!(
+        String::from_utf8(out.stderr).unwrap(),
+        "loopback: counter set to 7\n"
+    );
+    assert_eq!(
+        stderr(&["publish", "loopback/counter", "7"]),
+        "error: no topic with path 'loopback/counter'\n"
+    );
+
+    let lines = stdout(&["subscribe", "loopback/counter", "--count", "3"]);
+    let counts: Vec<Value> = lines
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(counts.len(), 3);
+    assert_eq!(counts[2], json!(counts[0].as_u64().unwrap() + 2));
+}
+
+#[test]
+fn usage() {
+    let err = stderr(&["frobnicate"]);
+    assert!(err.starts_with("error: unknown command frobnicate\n"));
+    assert!(err.contains("Usage: postcard-rpc"));
+    assert_eq!(
+        Command::new(env!("CARGO_BIN_EXE_postcard-rpc"))
+            .arg("ping")
+            .output()
+            .unwrap()
+            .stderr,
+        b"error: no device given, use --usb, --serial or --loopback\n"
+    );
+}
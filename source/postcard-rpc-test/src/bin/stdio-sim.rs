@@ -0,0 +1,88 @@
+//! A "device simulator", serving the `sim_icd` over stdin and stdout
+//!
+//! Used by the `stdio` tests.
+
+use postcard_rpc::{
+    define_dispatch,
+    header::VarHeader,
+    server::{
+        impls::tokio_stream::{
+            dispatch_impl::{new_server, spawn_fn, Settings, WireSpawnImpl, WireTxImpl},
+            new_from_stdio, StreamWireTx,
+        },
+        Dispatch, Sender, SpawnContext,
+    },
+};
+use postcard_rpc_test::sim_icd::{
+    AddEndpoint, AddReq, ExitEndpoint, ENDPOINT_LIST, TOPICS_IN_LIST, TOPICS_OUT_LIST,
+};
+
+pub struct SimContext;
+
+impl SpawnContext for SimContext {
+    type SpawnCtxt = ();
+
+    fn spawn_ctxt(&mut self) -> Self::SpawnCtxt {}
+}
+
+define_dispatch! {
+    app: SimDispatcher;
+    spawn_fn: spawn_fn;
+    tx_impl: WireTxImpl;
+    spawn_impl: WireSpawnImpl;
+    context: SimContext;
+
+    endpoints: {
+        list: ENDPOINT_LIST;
+
+        | EndpointTy        | kind      | handler               |
+        | ----------        | ----      | -------               |
+        | AddEndpoint       | spawn     | add_handler           |
+        | ExitEndpoint      | blocking  | exit_handler          |
+    };
+    topics_in: {
+        list: TOPICS_IN_LIST;
+
+        | TopicTy           | kind      | handler               |
+        | ----------        | ----      | -------               |
+    };
+    topics_out: {
+        list: TOPICS_OUT_LIST;
+    };
+}
+
+async fn add_handler(_context: (), header: VarHeader, body: AddReq, out: Sender<StreamWireTx>) {
+    // Diagnostics go to stderr, and must not disturb the protocol on stdout
+    eprintln!("stdio-sim: adding {} + {}", body.a, body.b);
+    let _ = out.log_str(&format!("{} + {}", body.a, body.b)).await;
+    let _ = out
+        .reply::<AddEndpoint>(header.seq_no, &body.a.wrapping_add(body.b))
+        .await;
+}
+
+fn exit_handler(_context: &mut SimContext, _header: VarHeader, _body: ()) {
+    eprintln!("stdio-sim: exiting");
+    std::process::exit(0);
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let (tx, rx) = new_from_stdio();
+    let app = SimDispatcher::new(
+        SimContext,
+        WireSpawnImpl::from(tokio::runtime::Handle::current()),
+    );
+    let kkind = app.min_key_len();
+    let mut server = new_server(
+        app,
+        Settings {
+            tx,
+            rx,
+            buf: 1024,
+            kkind,
+        },
+    );
+    // Returns once stdin is closed
+    let _ = server.run().await;
+    eprintln!("stdio-sim: stdin closed");
+}
@@ -1 +1,242 @@
 // I'm just here so we can write integration tests
+
+/// The interface of the `stdio-sim` device simulator, used by the `stdio` tests
+pub mod sim_icd {
+    use postcard_rpc::{endpoints, topics};
+    use postcard_schema::Schema;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+    pub struct AddReq {
+        pub a: u32,
+        pub b: u32,
+    }
+
+    endpoints! {
+        list = ENDPOINT_LIST;
+        | EndpointTy        | RequestTy     | ResponseTy    | Path          |
+        | ----------        | ---------     | ----------    | ----          |
+        | AddEndpoint       | AddReq        | u32           | "sim/add"     |
+        | ExitEndpoint      | ()            | ()            | "sim/exit"    |
+    }
+
+    topics! {
+        list = TOPICS_IN_LIST;
+        direction = postcard_rpc::TopicDirection::ToServer;
+        | TopicTy       | MessageTy     | Path      |
+        | ----------    | ---------     | ----      |
+    }
+
+    topics! {
+        list = TOPICS_OUT_LIST;
+        direction = postcard_rpc::TopicDirection::ToClient;
+        | TopicTy       | MessageTy     | Path      |
+        | ----------    | ---------     | ----      |
+    }
+}
+
+/// Host side wire of a pair of channels, for tests that need to wrap it
+///
+/// The wire used by `host_client::test_channels` can't be wrapped, for example
+/// with `Capture::tap()`.
+pub mod client_wire {
+    use postcard_rpc::host_client::{WireRx, WireSpawn, WireTx};
+    use tokio::sync::mpsc;
+
+    /// Sends frames to the server
+    pub struct ClientTx(pub mpsc::Sender<Vec<u8>>);
+    /// Receives frames from the server
+    pub struct ClientRx(pub mpsc::Receiver<Vec<u8>>);
+    /// Spawns on the tokio runtime
+    pub struct ClientSpawn;
+
+    /// The other side of the channel was dropped
+    #[derive(Debug)]
+    pub struct Closed;
+
+    impl std::fmt::Display for Closed {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("channel closed")
+        }
+    }
+
+    impl std::error::Error for Closed {}
+
+    impl WireTx for ClientTx {
+        type Error = Closed;
+
+        async fn send(&mut self, data: Vec<u8>) -> Result<(), Self::Error> {
+            self.0.send(data).await.map_err(|_| Closed)
+        }
+    }
+
+    impl WireRx for ClientRx {
+        type Error = Closed;
+
+        async fn receive(&mut self) -> Result<Vec<u8>, Self::Error> {
+            self.0.recv().await.ok_or(Closed)
+        }
+    }
+
+    impl WireSpawn for ClientSpawn {
+        fn spawn(&mut self, fut: impl core::future::Future<Output = ()> + Send + 'static) {
+            tokio::task::spawn(fut);
+        }
+    }
+}
+
+/// A server on the `test_channels` wire, and a client connected to it
+pub mod fixture {
+    use std::{future::Future, pin::Pin, time::Duration};
+
+    use postcard_rpc::{
+        header::VarSeqKind,
+        host_client::{test_channels, HostClient, HostClientConfig},
+        server::{
+            impls::test_channels::{
+                dispatch_impl::{new_server, Settings},
+                ChannelWireRx, ChannelWireTx,
+            },
+            Dispatch, Server,
+        },
+        standard_icd::WireError,
+    };
+    use tokio::sync::mpsc;
+
+    /// The server made by a [`Fixture`]
+    pub type TestServer<D> = Server<ChannelWireTx, ChannelWireRx, Box<[u8]>, D>;
+
+    type Configure<D> = Box<dyn FnOnce(TestServer<D>) -> TestServer<D>>;
+    type Relay = Box<
+        dyn FnOnce(
+            mpsc::Receiver<Vec<u8>>,
+            mpsc::Sender<Vec<u8>>,
+        ) -> Pin<Box<dyn Future<Output = ()> + Send>>,
+    >;
+
+    /// Serves a dispatcher to a client, over a pair of channels
+    ///
+    /// The server runs on a thread of its own, as the futures of generic
+    /// dispatchers can't be shown to be `Send`.
+    pub struct Fixture<D: Dispatch<Tx = ChannelWireTx>> {
+        app: D,
+        buf: usize,
+        configure: Configure<D>,
+        config: HostClientConfig<'static>,
+        to_server: Option<Relay>,
+        to_client: Option<Relay>,
+    }
+
+    impl<D> Fixture<D>
+    where
+        D: Dispatch<Tx = ChannelWireTx> + Send + 'static,
+    {
+        /// Serve `app` with a 1024 byte receive buffer, to a client using one byte
+        /// sequence numbers
+        pub fn new(app: D) -> Self {
+            Self {
+                app,
+                buf: 1024,
+                configure: Box::new(|server| server),
+                config: HostClientConfig::default()
+                    .with_seq_kind(VarSeqKind::Seq1)
+                    .with_outgoing_depth(64),
+                to_server: None,
+                to_client: None,
+            }
+        }
+
+        /// The size of the receive buffer of the server
+        pub fn buffer_len(mut self, buf: usize) -> Self {
+            self.buf = buf;
+            self
+        }
+
+        /// Configure the server before it runs, e.g. with `Server::with_credits()`
+        pub fn server(
+            mut self,
+            configure: impl FnOnce(TestServer<D>) -> TestServer<D> + 'static,
+        ) -> Self {
+            self.configure = Box::new(configure);
+            self
+        }
+
+        /// The configuration of the client
+        pub fn client_config(mut self, config: HostClientConfig<'static>) -> Self {
+            self.config = config;
+            self
+        }
+
+        /// Pass the frames sent to the server through `relay`, which forwards them
+        /// from the receiver to the sender
+        pub fn relay_to_server<F, Fut>(mut self, relay: F) -> Self
+        where
+            F: FnOnce(mpsc::Receiver<Vec<u8>>, mpsc::Sender<Vec<u8>>) -> Fut + 'static,
+            Fut: Future<Output = ()> + Send + 'static,
+        {
+            self.to_server = Some(Box::new(move |rx, tx| Box::pin(relay(rx, tx))));
+            self
+        }
+
+        /// Pass the frames sent to the client through `relay`, like
+        /// [`Fixture::relay_to_server()`]
+        pub fn relay_to_client<F, Fut>(mut self, relay: F) -> Self
+        where
+            F: FnOnce(mpsc::Receiver<Vec<u8>>, mpsc::Sender<Vec<u8>>) -> Fut + 'static,
+            Fut: Future<Output = ()> + Send + 'static,
+        {
+            self.to_client = Some(Box::new(move |rx, tx| Box::pin(relay(rx, tx))));
+            self
+        }
+
+        /// Start the server, and connect the client to it
+        ///
+        /// Must be called from within a tokio runtime.
+        pub fn connect(self) -> HostClient<WireError> {
+            let (client_tx, server_rx) = channel(self.to_server);
+            let (server_tx, client_rx) = channel(self.to_client);
+
+            let kkind = self.app.min_key_len();
+            let server = new_server(
+                self.app,
+                Settings {
+                    tx: ChannelWireTx::new(server_tx),
+                    rx: ChannelWireRx::new(server_rx),
+                    buf: self.buf,
+                    kkind,
+                },
+            );
+            let mut server = (self.configure)(server);
+            std::thread::spawn(move || {
+                tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .unwrap()
+                    .block_on(async move {
+                        server.run().await;
+                    });
+            });
+
+            test_channels::new_from_channels_with_config(client_tx, client_rx, &self.config)
+        }
+    }
+
+    /// The next report of a handler, waiting at most a second
+    pub async fn next_report<T>(reports: &mut mpsc::UnboundedReceiver<T>) -> T {
+        tokio::time::timeout(Duration::from_secs(1), reports.recv())
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    /// A channel, with the relay in between if there is one
+    fn channel(relay: Option<Relay>) -> (mpsc::Sender<Vec<u8>>, mpsc::Receiver<Vec<u8>>) {
+        let (tx, rx) = mpsc::channel(16);
+        let Some(relay) = relay else {
+            return (tx, rx);
+        };
+        let (relay_tx, relay_rx) = mpsc::channel(16);
+        tokio::task::spawn(relay(rx, relay_tx));
+        (tx, relay_rx)
+    }
+}
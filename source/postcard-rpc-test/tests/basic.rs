@@ -11,7 +11,7 @@ use tokio::{sync::mpsc, task::yield_now, time::timeout};
 use postcard_rpc::{
     define_dispatch, endpoints,
     header::{VarHeader, VarKey, VarKeyKind, VarSeq, VarSeqKind},
-    host_client::{test_channels as client, HostClient},
+    host_client::{test_channels as client, HostClient, HostClientConfig, HostErr},
     server::{
         impls::test_channels::{
             dispatch_impl::{
@@ -21,6 +21,7 @@ use postcard_rpc::{
         },
         Dispatch, Sender, SpawnContext,
     },
+    standard_icd::{DeviceInfo, OwnedDeviceInfo, WireError, STANDARD_ICD_DEVICE_INFO_ENDPOINTS},
     topics, Endpoint, Topic,
 };
 
@@ -80,6 +81,14 @@ endpoints! {
     | BorrowEndpoint4   | DoubleMessage<'a, 'b> | DoubleMessage<'c, 'd> | "borrow4"         |                        |
 }
 
+// Not handled by the server
+endpoints! {
+    list = UNHANDLED_LIST;
+    | EndpointTy        | RequestTy     | ResponseTy    | Path          |
+    | ----------        | ---------     | ----------    | ----          |
+    | UnhandledEndpoint | ()            | ()            | "unhandled"   |
+}
+
 topics! {
     list = TOPICS_IN_LIST;
     direction = postcard_rpc::TopicDirection::ToServer;
@@ -124,12 +133,21 @@ impl SpawnContext for TestContext {
     }
 }
 
+const DEVICE_INFO: DeviceInfo<'static> = DeviceInfo {
+    product: "basic-test",
+    firmware_version: env!("CARGO_PKG_VERSION"),
+    git_hash: "0123abc",
+    build_time: "2024-01-01T00:00:00Z",
+    serial_number: "0001",
+};
+
 define_dispatch! {
     app: SingleDispatcher;
     spawn_fn: spawn_fn;
     tx_impl: WireTxImpl;
     spawn_impl: WireSpawnImpl;
     context: TestContext;
+    device_info: DEVICE_INFO;
 
     endpoints: {
         list: crate::ENDPOINT_LIST;
@@ -411,6 +429,95 @@ async fn end_to_end() {
     assert_eq!(resp.0, 1234);
 }
 
+#[tokio::test]
+async fn protocol_errors() {
+    let (client_tx, server_rx) = mpsc::channel(16);
+    let (server_tx, client_rx) = mpsc::channel(16);
+
+    let app = SingleDispatcher::new(
+        TestContext {
+            ctr: Arc::new(AtomicUsize::new(0)),
+            topic_ctr: Arc::new(AtomicUsize::new(0)),
+            msg: String::from("hello"),
+        },
+        ChannelWireSpawn {},
+    );
+    let kkind = app.min_key_len();
+    let mut server = new_server(
+        app,
+        Settings {
+            tx: ChannelWireTx::new(server_tx),
+            rx: ChannelWireRx::new(server_rx),
+            buf: 1024,
+            kkind,
+        },
+    );
+    tokio::task::spawn(async move {
+        server.run().await;
+    });
+
+    // The application's errors use a different path than the protocol errors
+    let cli = client::new_from_channels_with_config(
+        client_tx,
+        client_rx,
+        &HostClientConfig::default()
+            .with_seq_kind(VarSeqKind::Seq1)
+            .with_err_uri_path("app/error")
+            .with_outgoing_depth(16)
+            .with_default_timeout(Some(Duration::from_secs(1))),
+    );
+
+    let resp = cli.send_resp::<UnhandledEndpoint>(&()).await;
+    assert_eq!(resp, Err(HostErr::Protocol(WireError::UnknownKey)));
+    let resp = cli.send_resp::<AlphaEndpoint>(&AReq(42)).await.unwrap();
+    assert_eq!(resp.0, 42);
+}
+
+#[tokio::test]
+async fn device_info() {
+    let (client_tx, server_rx) = mpsc::channel(16);
+    let (server_tx, client_rx) = mpsc::channel(16);
+
+    let app = SingleDispatcher::new(
+        TestContext {
+            ctr: Arc::new(AtomicUsize::new(0)),
+            topic_ctr: Arc::new(AtomicUsize::new(0)),
+            msg: String::from("hello"),
+        },
+        ChannelWireSpawn {},
+    );
+    let kkind = app.min_key_len();
+    let endpoints = STANDARD_ICD_DEVICE_INFO_ENDPOINTS.endpoints;
+    assert!(endpoints
+        .iter()
+        .all(|ep| app.device_map.endpoints.contains(ep)));
+    let mut server = new_server(
+        app,
+        Settings {
+            tx: ChannelWireTx::new(server_tx),
+            rx: ChannelWireRx::new(server_rx),
+            buf: 1024,
+            kkind,
+        },
+    );
+    tokio::task::spawn(async move {
+        server.run().await;
+    });
+
+    let cli = client::new_from_channels(client_tx, client_rx, VarSeqKind::Seq1);
+    let info = cli.get_device_info().await.unwrap();
+    assert_eq!(
+        info,
+        OwnedDeviceInfo {
+            product: "basic-test".into(),
+            firmware_version: env!("CARGO_PKG_VERSION").into(),
+            git_hash: "0123abc".into(),
+            build_time: "2024-01-01T00:00:00Z".into(),
+            serial_number: "0001".into(),
+        }
+    );
+}
+
 #[tokio::test]
 async fn end_to_end_schema() {
     let (client_tx, server_rx) = mpsc::channel(16);
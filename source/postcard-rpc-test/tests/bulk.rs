@@ -0,0 +1,173 @@
+use core::time::Duration;
+
+use tokio::{sync::mpsc, time::timeout};
+
+use postcard_rpc::{
+    define_dispatch, endpoints,
+    header::VarHeader,
+    host_client::{BulkUploadError, HostClient},
+    server::{
+        bulk::{BulkHandler, BulkReceiver},
+        impls::test_channels::{
+            dispatch_impl::{WireSpawnImpl, WireTxImpl},
+            ChannelWireSpawn,
+        },
+    },
+    standard_icd::{
+        BulkChunkEndpoint, BulkEnd, BulkEndEndpoint, BulkError, BulkResult, BulkStart,
+        BulkStartEndpoint, BulkStartResult, OwnedBulkChunk, WireError, STANDARD_ICD_BULK_ENDPOINTS,
+    },
+    topics,
+};
+use postcard_rpc_test::fixture::Fixture;
+
+const RX_BUF: usize = 256;
+const CAPACITY: usize = 4096;
+
+endpoints! {
+    list = ENDPOINT_LIST;
+    | EndpointTy        | RequestTy     | ResponseTy    | Path          |
+    | ----------        | ---------     | ----------    | ----          |
+}
+
+topics! {
+    list = TOPICS_IN_LIST;
+    direction = postcard_rpc::TopicDirection::ToServer;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+}
+
+topics! {
+    list = TOPICS_OUT_LIST;
+    direction = postcard_rpc::TopicDirection::ToClient;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+}
+
+/// Stores uploads in memory, and reports finished ones
+struct Storage {
+    data: Vec<u8>,
+    done: mpsc::UnboundedSender<Vec<u8>>,
+}
+
+impl BulkHandler for Storage {
+    fn capacity(&self) -> usize {
+        CAPACITY
+    }
+
+    fn start(&mut self, len: usize) -> Result<(), BulkError> {
+        self.data = vec![0; len];
+        Ok(())
+    }
+
+    fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), BulkError> {
+        self.data[offset..][..data.len()].copy_from_slice(data);
+        Ok(())
+    }
+
+    fn finish(&mut self, _len: usize) -> Result<(), BulkError> {
+        let _ = self.done.send(core::mem::take(&mut self.data));
+        Ok(())
+    }
+}
+
+pub struct TestContext {
+    bulk: BulkReceiver<Storage>,
+}
+
+define_dispatch! {
+    app: BulkDispatcher;
+    spawn_fn: spawn_fn;
+    tx_impl: WireTxImpl;
+    spawn_impl: WireSpawnImpl;
+    context: TestContext;
+
+    endpoints: {
+        list: crate::ENDPOINT_LIST;
+
+        | EndpointTy        | kind      | handler               |
+        | ----------        | ----      | -------               |
+        | BulkStartEndpoint | blocking  | bulk_start            |
+        | BulkChunkEndpoint | blocking  | bulk_chunk            |
+        | BulkEndEndpoint   | blocking  | bulk_end              |
+    };
+    topics_in: {
+        list: crate::TOPICS_IN_LIST;
+
+        | TopicTy           | kind      | handler               |
+        | ----------        | ----      | -------               |
+    };
+    topics_out: {
+        list: crate::TOPICS_OUT_LIST;
+    };
+}
+
+fn bulk_start(context: &mut TestContext, _header: VarHeader, req: BulkStart) -> BulkStartResult {
+    context.bulk.start(&req)
+}
+
+fn bulk_chunk(context: &mut TestContext, _header: VarHeader, req: OwnedBulkChunk) -> BulkResult {
+    context.bulk.chunk(req.offset, &req.data)
+}
+
+fn bulk_end(context: &mut TestContext, _header: VarHeader, req: BulkEnd) -> BulkResult {
+    context.bulk.end(&req)
+}
+
+fn setup() -> (HostClient<WireError>, mpsc::UnboundedReceiver<Vec<u8>>) {
+    let (done, uploads) = mpsc::unbounded_channel();
+    let storage = Storage { data: vec![], done };
+    let context = TestContext {
+        bulk: BulkReceiver::new(storage, RX_BUF, 4),
+    };
+    let app = BulkDispatcher::new(context, ChannelWireSpawn {});
+    let cli = Fixture::new(app).buffer_len(RX_BUF).connect();
+    (cli, uploads)
+}
+
+#[tokio::test]
+async fn bulk_upload() {
+    let (cli, mut uploads) = setup();
+
+    // Far larger than the receive buffer of the server
+    let blob: Vec<u8> = (0..3000u32).map(|i| (i * 7) as u8).collect();
+    timeout(Duration::from_secs(1), cli.bulk_upload(&blob))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(uploads.recv().await.unwrap(), blob);
+
+    // Uploads that don't fill a whole chunk work too
+    cli.bulk_upload(b"tiny").await.unwrap();
+    assert_eq!(uploads.recv().await.unwrap(), b"tiny");
+}
+
+#[tokio::test]
+async fn bulk_upload_rejected() {
+    let (cli, mut uploads) = setup();
+
+    let res = cli.bulk_upload(&[0u8; CAPACITY + 1]).await;
+    assert_eq!(res, Err(BulkUploadError::Rejected(BulkError::TooLarge)));
+
+    // Chunks sent without starting an upload are rejected
+    let res = cli
+        .send_resp::<BulkChunkEndpoint>(&OwnedBulkChunk {
+            offset: 0,
+            data: vec![1, 2, 3],
+        })
+        .await;
+    assert_eq!(res, Ok(Err(BulkError::NotStarted)));
+    assert!(uploads.try_recv().is_err());
+}
+
+#[test]
+fn advertised_when_handled() {
+    let (done, _uploads) = mpsc::unbounded_channel();
+    let context = TestContext {
+        bulk: BulkReceiver::new(Storage { data: vec![], done }, RX_BUF, 4),
+    };
+    let app = BulkDispatcher::new(context, ChannelWireSpawn {});
+    for ep in STANDARD_ICD_BULK_ENDPOINTS.endpoints {
+        assert!(app.device_map.endpoints.contains(ep));
+    }
+}
@@ -0,0 +1,206 @@
+use core::time::Duration;
+
+use tokio::sync::mpsc;
+
+use postcard_rpc::{
+    define_dispatch, endpoints,
+    header::{VarHeader, VarSeqKind},
+    host_client::{HostClient, HostClientConfig, HostErr},
+    server::{
+        impls::test_channels::{
+            dispatch_impl::{spawn_fn, WireSpawnImpl, WireTxImpl},
+            ChannelWireSpawn, ChannelWireTx,
+        },
+        CancelToken, Cancellations, Sender, SpawnContext,
+    },
+    standard_icd::{Capabilities, WireError, STANDARD_ICD_CANCEL_TOPICS},
+    topics,
+};
+use postcard_rpc_test::fixture::{next_report, Fixture};
+
+endpoints! {
+    list = ENDPOINT_LIST;
+    | EndpointTy        | RequestTy     | ResponseTy    | Path          |
+    | ----------        | ---------     | ----------    | ----          |
+    | WorkEndpoint      | u32           | u32           | "work"        |
+}
+
+topics! {
+    list = TOPICS_IN_LIST;
+    direction = postcard_rpc::TopicDirection::ToServer;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+}
+
+topics! {
+    list = TOPICS_OUT_LIST;
+    direction = postcard_rpc::TopicDirection::ToClient;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+}
+
+/// Reports how each request ended: `Ok(steps)` if completed, `Err(seq_no)` if cancelled
+type Report = mpsc::UnboundedSender<Result<u32, u32>>;
+
+pub struct TestContext {
+    report: Report,
+}
+
+impl SpawnContext for TestContext {
+    type SpawnCtxt = Report;
+
+    fn spawn_ctxt(&mut self) -> Self::SpawnCtxt {
+        self.report.clone()
+    }
+}
+
+define_dispatch! {
+    app: CancelDispatcher;
+    spawn_fn: spawn_fn;
+    tx_impl: WireTxImpl;
+    spawn_impl: WireSpawnImpl;
+    context: TestContext;
+    standard: [capabilities];
+
+    endpoints: {
+        list: crate::ENDPOINT_LIST;
+
+        | EndpointTy        | kind          | handler               |
+        | ----------        | ----          | -------               |
+        | WorkEndpoint      | spawn_cancel  | work_handler          |
+    };
+    topics_in: {
+        list: crate::TOPICS_IN_LIST;
+
+        | TopicTy           | kind      | handler               |
+        | ----------        | ----      | -------               |
+    };
+    topics_out: {
+        list: crate::TOPICS_OUT_LIST;
+    };
+}
+
+/// Works for `steps` milliseconds, unless cancelled
+async fn work_handler(
+    report: Report,
+    header: VarHeader,
+    steps: u32,
+    out: Sender<ChannelWireTx>,
+    cancel: CancelToken,
+) {
+    for _ in 0..steps {
+        if cancel.is_cancelled() {
+            let _ = report.send(Err(header.seq_no.into()));
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    let _ = report.send(Ok(steps));
+    let _ = out.reply::<WorkEndpoint>(header.seq_no, &steps).await;
+}
+
+/// Without `cancel_requests`, the client only cancels once it knows the server
+/// supports it
+fn setup(
+    cancel_requests: bool,
+) -> (
+    HostClient<WireError>,
+    mpsc::UnboundedReceiver<Result<u32, u32>>,
+) {
+    let (report, reports) = mpsc::unbounded_channel();
+    let app = CancelDispatcher::new(TestContext { report }, ChannelWireSpawn {});
+    let cli = Fixture::new(app)
+        .server(|server| server.with_cancellations(Box::leak(Box::new(Cancellations::new()))))
+        .client_config(
+            HostClientConfig::default()
+                .with_seq_kind(VarSeqKind::Seq1)
+                .with_cancel_requests(cancel_requests),
+        )
+        .connect();
+    (cli, reports)
+}
+
+#[tokio::test]
+async fn cancel_on_timeout() {
+    let (cli, mut reports) = setup(true);
+
+    let res = cli
+        .send_resp_timeout::<WorkEndpoint>(&60_000, Duration::from_millis(20))
+        .await;
+    assert_eq!(res, Err(HostErr::Timeout));
+    assert_eq!(next_report(&mut reports).await, Err(0));
+
+    // Requests that complete are not cancelled
+    let res = cli.send_resp::<WorkEndpoint>(&5).await;
+    assert_eq!(res, Ok(5));
+    assert_eq!(next_report(&mut reports).await, Ok(5));
+}
+
+#[tokio::test]
+async fn cancel_on_drop() {
+    let (cli, mut reports) = setup(true);
+
+    let short = tokio::task::spawn({
+        let cli = cli.clone();
+        async move { cli.send_resp::<WorkEndpoint>(&50).await }
+    });
+    let long = tokio::task::spawn({
+        let cli = cli.clone();
+        async move { cli.send_resp::<WorkEndpoint>(&60_000).await }
+    });
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    // Only the dropped request is cancelled
+    long.abort();
+    assert_eq!(next_report(&mut reports).await, Err(1));
+    assert_eq!(short.await.unwrap(), Ok(50));
+    assert_eq!(next_report(&mut reports).await, Ok(50));
+}
+
+#[tokio::test]
+async fn cancel_per_server() {
+    // Two servers of the same dispatcher, with requests using the same seq_no
+    let (cli_a, mut reports_a) = setup(true);
+    let (cli_b, mut reports_b) = setup(true);
+
+    let b = tokio::task::spawn(async move { cli_b.send_resp::<WorkEndpoint>(&50).await });
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    let res = cli_a
+        .send_resp_timeout::<WorkEndpoint>(&60_000, Duration::from_millis(10))
+        .await;
+    assert_eq!(res, Err(HostErr::Timeout));
+    assert_eq!(next_report(&mut reports_a).await, Err(0));
+
+    // Cancelling on one connection doesn't cancel the other
+    assert_eq!(b.await.unwrap(), Ok(50));
+    assert_eq!(next_report(&mut reports_b).await, Ok(50));
+}
+
+#[tokio::test]
+async fn cancel_if_advertised() {
+    let (cli, mut reports) = setup(false);
+
+    // Without knowing the capabilities, requests are not cancelled
+    let res = cli
+        .send_resp_timeout::<WorkEndpoint>(&30, Duration::from_millis(5))
+        .await;
+    assert_eq!(res, Err(HostErr::Timeout));
+    assert_eq!(next_report(&mut reports).await, Ok(30));
+
+    let caps = cli.query_capabilities().await.unwrap();
+    assert!(caps.supports(Capabilities::CANCEL));
+    let res = cli
+        .send_resp_timeout::<WorkEndpoint>(&60_000, Duration::from_millis(5))
+        .await;
+    assert_eq!(res, Err(HostErr::Timeout));
+    assert_eq!(next_report(&mut reports).await, Err(2));
+}
+
+#[test]
+fn advertised_when_cancellable() {
+    let (report, _reports) = mpsc::unbounded_channel();
+    let app = CancelDispatcher::new(TestContext { report }, ChannelWireSpawn {});
+    for tp in STANDARD_ICD_CANCEL_TOPICS.topics {
+        assert!(app.device_map.topics_in.contains(tp));
+    }
+}
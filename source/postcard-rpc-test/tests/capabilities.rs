@@ -0,0 +1,193 @@
+use core::time::Duration;
+
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+use tokio::{sync::mpsc, time::timeout};
+
+use postcard_rpc::{
+    define_dispatch, endpoints,
+    header::{VarHeader, VarKey, VarKeyKind, VarSeqKind},
+    host_client::{HostClient, HostClientConfig, HostErr},
+    server::{
+        impls::test_channels::{
+            dispatch_impl::{WireSpawnImpl, WireTxImpl},
+            ChannelWireSpawn,
+        },
+        Cancellations, Credits, SpawnContext,
+    },
+    standard_icd::{Capabilities, WireError},
+    topics,
+};
+use postcard_rpc_test::fixture::Fixture;
+
+/// The receive buffer of the server
+const BUF: usize = 128;
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct Blob(pub Vec<u8>);
+
+endpoints! {
+    list = ENDPOINT_LIST;
+    | EndpointTy        | RequestTy     | ResponseTy    | Path          |
+    | ----------        | ---------     | ----------    | ----          |
+    | LenEndpoint       | Blob          | u32           | "len"         |
+}
+
+topics! {
+    list = TOPICS_IN_LIST;
+    direction = postcard_rpc::TopicDirection::ToServer;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+}
+
+topics! {
+    list = TOPICS_OUT_LIST;
+    direction = postcard_rpc::TopicDirection::ToClient;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+}
+
+pub struct TestContext;
+
+impl SpawnContext for TestContext {
+    type SpawnCtxt = ();
+
+    fn spawn_ctxt(&mut self) -> Self::SpawnCtxt {}
+}
+
+define_dispatch! {
+    app: CapsDispatcher;
+    spawn_fn: spawn_fn;
+    tx_impl: WireTxImpl;
+    spawn_impl: WireSpawnImpl;
+    context: TestContext;
+    standard: [capabilities];
+
+    endpoints: {
+        list: crate::ENDPOINT_LIST;
+
+        | EndpointTy        | kind      | handler               |
+        | ----------        | ----      | -------               |
+        | LenEndpoint       | blocking  | len_handler           |
+    };
+    topics_in: {
+        list: crate::TOPICS_IN_LIST;
+
+        | TopicTy           | kind      | handler               |
+        | ----------        | ----      | -------               |
+    };
+    topics_out: {
+        list: crate::TOPICS_OUT_LIST;
+    };
+}
+
+fn len_handler(_context: &mut TestContext, _header: VarHeader, body: Blob) -> u32 {
+    body.0.len() as u32
+}
+
+/// Forwards frames, reporting the key of each one
+async fn keys(
+    mut rx: mpsc::Receiver<Vec<u8>>,
+    tx: mpsc::Sender<Vec<u8>>,
+    keys: mpsc::UnboundedSender<VarKey>,
+) {
+    while let Some(frame) = rx.recv().await {
+        let (hdr, _) = VarHeader::take_from_slice(&frame).unwrap();
+        let _ = keys.send(hdr.key);
+        if tx.send(frame).await.is_err() {
+            return;
+        }
+    }
+}
+
+fn setup(
+    config: HostClientConfig<'static>,
+) -> (HostClient<WireError>, mpsc::UnboundedReceiver<VarKey>) {
+    let (keys_tx, keys_rx) = mpsc::unbounded_channel();
+    let cli = Fixture::new(CapsDispatcher::new(TestContext, ChannelWireSpawn {}))
+        .buffer_len(BUF)
+        .client_config(config)
+        .relay_to_server(move |rx, tx| keys(rx, tx, keys_tx))
+        .connect();
+    (cli, keys_rx)
+}
+
+#[tokio::test]
+async fn query() {
+    let (cli, mut keys_rx) = setup(HostClientConfig::default().with_seq_kind(VarSeqKind::Seq1));
+
+    assert_eq!(cli.capabilities(), None);
+    // Until the capabilities are known, full keys are sent
+    let caps = cli.query_capabilities().await.unwrap();
+    assert_eq!(
+        caps,
+        Capabilities {
+            protocol_version: Capabilities::PROTOCOL_VERSION,
+            min_key_len: 1,
+            seq_sizes: 0b0111,
+            max_request_len: Some(BUF as u32),
+            max_response_len: None,
+            max_reassembly_len: None,
+            features: 0,
+        }
+    );
+    assert!(caps.supports_seq(VarSeqKind::Seq4));
+    assert!(!caps.supports(Capabilities::CANCEL));
+    assert_eq!(cli.capabilities(), Some(caps));
+    assert_eq!(keys_rx.recv().await.unwrap().kind(), VarKeyKind::Key8);
+
+    // Now the smallest keys are used, and long requests are never sent
+    assert_eq!(
+        cli.send_resp::<LenEndpoint>(&Blob(vec![0; 100])).await,
+        Ok(100)
+    );
+    assert_eq!(keys_rx.recv().await.unwrap().kind(), VarKeyKind::Key1);
+    let resp = cli.send_resp::<LenEndpoint>(&Blob(vec![0; BUF])).await;
+    // The header with a one byte key and the configured one byte sequence number,
+    // and two bytes of length
+    let len = 1 + 1 + 1 + 2 + BUF;
+    assert_eq!(resp, Err(HostErr::RequestTooLarge { len, max: BUF }));
+    assert!(keys_rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn query_on_connect() {
+    let (cli, mut keys_rx) = setup(
+        HostClientConfig::default()
+            .with_seq_kind(VarSeqKind::Seq1)
+            .with_query_capabilities(true),
+    );
+
+    // The query is sent right away, with a full key
+    let key = timeout(Duration::from_secs(1), keys_rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(key.kind(), VarKeyKind::Key8);
+    timeout(Duration::from_secs(1), async {
+        while cli.capabilities().is_none() {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    })
+    .await
+    .unwrap();
+
+    // Afterwards the smallest keys are used
+    assert_eq!(cli.send_resp::<LenEndpoint>(&Blob(vec![0; 4])).await, Ok(4));
+    assert_eq!(keys_rx.recv().await.unwrap().kind(), VarKeyKind::Key1);
+}
+
+#[tokio::test]
+async fn unused_features() {
+    // The server is configured with features its dispatcher doesn't use
+    let cli = Fixture::new(CapsDispatcher::new(TestContext, ChannelWireSpawn {}))
+        .server(|server| {
+            server
+                .with_cancellations(Box::leak(Box::new(Cancellations::new())))
+                .with_credits(Box::leak(Box::new(Credits::new())))
+        })
+        .connect();
+
+    let caps = cli.query_capabilities().await.unwrap();
+    assert_eq!(caps.features, 0);
+}
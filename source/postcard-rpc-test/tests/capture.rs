@@ -0,0 +1,202 @@
+use std::path::PathBuf;
+
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use postcard_rpc::{
+    capture::{Capture, CaptureReader, CapturedFrame, Direction, Tap},
+    define_dispatch, endpoints,
+    header::{VarHeader, VarKey, VarKeyKind, VarSeq, VarSeqKind},
+    host_client::HostClient,
+    server::{
+        impls::test_channels::{
+            dispatch_impl::WireSpawnImpl, ChannelWireRx, ChannelWireSpawn, ChannelWireTx,
+        },
+        Dispatch, Sender, Server, SpawnContext,
+    },
+    standard_icd::{LoggingTopic, WireError, ERROR_PATH},
+    topics, Endpoint, Topic,
+};
+use postcard_rpc_test::client_wire::{ClientRx, ClientSpawn, ClientTx};
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct Note(pub String);
+
+endpoints! {
+    list = ENDPOINT_LIST;
+    | EndpointTy        | RequestTy     | ResponseTy    | Path          |
+    | ----------        | ---------     | ----------    | ----          |
+    | DoubleEndpoint    | u32           | u32           | "double"      |
+}
+
+topics! {
+    list = TOPICS_IN_LIST;
+    direction = postcard_rpc::TopicDirection::ToServer;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+    | NoteTopic     | Note          | "note"    |
+}
+
+topics! {
+    list = TOPICS_OUT_LIST;
+    direction = postcard_rpc::TopicDirection::ToClient;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+}
+
+pub struct TestContext;
+
+impl SpawnContext for TestContext {
+    type SpawnCtxt = ();
+
+    fn spawn_ctxt(&mut self) -> Self::SpawnCtxt {}
+}
+
+define_dispatch! {
+    app: TappedDispatcher;
+    spawn_fn: spawn_fn;
+    tx_impl: Tap<ChannelWireTx>;
+    spawn_impl: WireSpawnImpl;
+    context: TestContext;
+
+    endpoints: {
+        list: crate::ENDPOINT_LIST;
+
+        | EndpointTy        | kind      | handler               |
+        | ----------        | ----      | -------               |
+        | DoubleEndpoint    | blocking  | double_handler        |
+    };
+    topics_in: {
+        list: crate::TOPICS_IN_LIST;
+
+        | TopicTy           | kind      | handler               |
+        | ----------        | ----      | -------               |
+        | NoteTopic         | async     | note_handler          |
+    };
+    topics_out: {
+        list: crate::TOPICS_OUT_LIST;
+    };
+}
+
+fn double_handler(_context: &mut TestContext, _header: VarHeader, body: u32) -> u32 {
+    body * 2
+}
+
+async fn note_handler(
+    _context: &mut TestContext,
+    _header: VarHeader,
+    body: Note,
+    out: &Sender<Tap<ChannelWireTx>>,
+) {
+    let _ = out.log_str(&format!("note: {}", body.0)).await;
+}
+
+fn read(path: &PathBuf) -> Vec<CapturedFrame> {
+    CaptureReader::open(path)
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+fn summary(frames: &[CapturedFrame]) -> Vec<(Direction, VarKey, u32)> {
+    frames
+        .iter()
+        .map(|f| {
+            (
+                f.direction,
+                f.frame.header.key,
+                f.frame.header.seq_no.into(),
+            )
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn capture_both_sides() {
+    let dir = std::env::temp_dir().join(format!("postcard-rpc-capture-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let server_path = dir.join("server.pcapng");
+    let client_path = dir.join("client.pcapng");
+
+    let (client_tx, server_rx) = mpsc::channel(16);
+    let (server_tx, client_rx) = mpsc::channel(16);
+
+    let server_capture = Capture::create(&server_path).unwrap();
+    let app = TappedDispatcher::new(TestContext, ChannelWireSpawn {});
+    let kkind = app.min_key_len();
+    let mut server = Server::new(
+        server_capture.tap(ChannelWireTx::new(server_tx)),
+        server_capture.tap(ChannelWireRx::new(server_rx)),
+        vec![0u8; 256].into_boxed_slice(),
+        app,
+        kkind,
+    );
+    tokio::task::spawn(async move {
+        server.run().await;
+    });
+
+    let client_capture = Capture::create(&client_path).unwrap();
+    let cli: HostClient<WireError> = HostClient::new_with_wire(
+        client_capture.tap(ClientTx(client_tx)),
+        client_capture.tap(ClientRx(client_rx)),
+        ClientSpawn,
+        VarSeqKind::Seq4,
+        ERROR_PATH,
+        8,
+    );
+
+    let mut logs = cli.subscribe_multi::<LoggingTopic>(8).await.unwrap();
+    assert_eq!(cli.send_resp::<DoubleEndpoint>(&21).await.unwrap(), 42);
+    cli.publish::<NoteTopic>(VarSeq::Seq4(7), &Note("hi".into()))
+        .await
+        .unwrap();
+    assert_eq!(logs.recv().await.unwrap(), "note: hi");
+
+    let req = VarKey::Key8(DoubleEndpoint::REQ_KEY);
+    let resp = VarKey::Key8(DoubleEndpoint::RESP_KEY);
+    let note = VarKey::Key8(NoteTopic::TOPIC_KEY);
+    let mut log = VarKey::Key8(LoggingTopic::TOPIC_KEY);
+    log.shrink_to(kkind);
+
+    // Wait until the frames were written
+    client_capture.flush().unwrap();
+    server_capture.flush().unwrap();
+    let client = read(&client_path);
+    let server = read(&server_path);
+    let seq: u32 = client[0].frame.header.seq_no.into();
+    assert_eq!(
+        summary(&client),
+        [
+            (Direction::Sent, req, seq),
+            (Direction::Received, resp, seq),
+            (Direction::Sent, note, 7),
+            (Direction::Received, log, 0),
+        ]
+    );
+    assert_eq!(
+        summary(&server),
+        [
+            (Direction::Received, req, seq),
+            (Direction::Sent, resp, seq),
+            (Direction::Received, note, 7),
+            (Direction::Sent, log, 0),
+        ]
+    );
+    assert_eq!(kkind, VarKeyKind::Key1);
+    assert!(matches!(server[3].frame.header.key, VarKey::Key1(_)));
+
+    // Both sides captured the same bytes
+    for (c, s) in client.iter().zip(server.iter()) {
+        assert_eq!(c.frame, s.frame);
+        assert!(c.fragment.is_none());
+    }
+    assert_eq!(
+        postcard::from_bytes::<<DoubleEndpoint as Endpoint>::Response>(&client[1].frame.body)
+            .unwrap(),
+        42
+    );
+    assert!(client[0].time <= server[0].time);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
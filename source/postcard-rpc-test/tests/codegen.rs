@@ -0,0 +1,352 @@
+//! Golden file tests for the generated bindings
+//!
+//! The bindings of the ICD below are compared with the files in `tests/golden`.
+//! Set `POSTCARD_RPC_BLESS=1` to update them after changing the generator.
+//!
+//! The `check` scripts next to them encode the same values as [`expected()`] with
+//! the generated code, which must produce the same bytes as postcard. They need
+//! `python3` and `node` (22.6 or newer), so they are ignored by default. CI runs
+//! them with `cargo test --test codegen -- --ignored`. With an older `node`, the
+//! TypeScript check is skipped with a message.
+
+use std::{collections::BTreeMap, path::PathBuf, process::Command};
+
+use postcard_rpc::{
+    codegen::{self, CodegenError},
+    endpoints,
+    header::{VarHeader, VarKey, VarSeq},
+    host_client::SchemaReport,
+    standard_icd::{WireError, ERROR_KEY},
+    topics, Endpoint, Key, Topic,
+};
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct Sample {
+    pub flag: bool,
+    pub tiny: i8,
+    pub byte: u8,
+    pub short: i16,
+    pub word: u32,
+    pub long: i64,
+    pub huge: u128,
+    pub neg: i128,
+    pub ratio: f32,
+    pub precise: f64,
+    pub letter: char,
+    pub name: String,
+    pub blob: Vec<u8>,
+    pub maybe: Option<u16>,
+    pub values: Vec<i32>,
+    pub pair: (u8, String),
+    pub table: BTreeMap<String, u64>,
+    pub nothing: (),
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub enum Shape {
+    Empty,
+    Circle(u32),
+    Rect(u32, u32),
+    Poly { sides: u8, name: Option<String> },
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct Meters(pub f64);
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct Point(pub i32, pub i32);
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct Marker;
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct Scene {
+    pub shapes: Vec<Shape>,
+    pub origin: Point,
+    pub scale: Meters,
+    pub marker: Marker,
+}
+
+endpoints! {
+    list = ENDPOINT_LIST;
+    | EndpointTy        | RequestTy     | ResponseTy    | Path              |
+    | ----------        | ---------     | ----------    | ----              |
+    | EchoEndpoint      | Sample        | Sample        | "sample/echo"     |
+    | AreaEndpoint      | Scene         | f64           | "scene/area"      |
+}
+
+topics! {
+    list = TOPICS_IN_LIST;
+    direction = postcard_rpc::TopicDirection::ToServer;
+    | TopicTy           | MessageTy     | Path              |
+    | ----------        | ---------     | ----              |
+    | AddShapeTopic     | Shape         | "scene/add"       |
+}
+
+topics! {
+    list = TOPICS_OUT_LIST;
+    direction = postcard_rpc::TopicDirection::ToClient;
+    | TopicTy           | MessageTy     | Path              |
+    | ----------        | ---------     | ----              |
+    | CountTopic        | u32           | "scene/count"     |
+}
+
+fn golden(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(name)
+}
+
+/// Compare generated code with a golden file, or update it
+fn compare(name: &str, code: &str) {
+    let path = golden(name);
+    if std::env::var_os("POSTCARD_RPC_BLESS").is_some() {
+        std::fs::write(&path, code).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path).unwrap();
+    assert!(
+        expected == code,
+        "{name} is outdated, run with POSTCARD_RPC_BLESS=1 to update it"
+    );
+}
+
+fn report() -> SchemaReport {
+    SchemaReport::from_icd(&ENDPOINT_LIST, &TOPICS_IN_LIST, &TOPICS_OUT_LIST).unwrap()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn frame(key: VarKey, seq_no: VarSeq, body: &[u8]) -> Vec<u8> {
+    let mut out = VarHeader { key, seq_no }.write_to_vec();
+    out.extend_from_slice(body);
+    out
+}
+
+/// The lines printed by the check scripts, encoded by postcard
+fn expected() -> String {
+    let sample = Sample {
+        flag: true,
+        tiny: -5,
+        byte: 200,
+        short: -300,
+        word: 70000,
+        long: -(1 << 40),
+        huge: 1 << 100,
+        neg: -(1 << 100),
+        ratio: 1.5,
+        precise: -0.1,
+        letter: 'é',
+        name: "hello".into(),
+        blob: vec![0, 255],
+        maybe: None,
+        values: vec![1, -1, 1000],
+        pair: (7, "x".into()),
+        table: [("a".to_string(), 1), ("b".to_string(), 1 << 40)]
+            .into_iter()
+            .collect(),
+        nothing: (),
+    };
+    let scene = Scene {
+        shapes: vec![Shape::Empty, Shape::Rect(3, 4)],
+        origin: Point(-1, 2),
+        scale: Meters(0.5),
+        marker: Marker,
+    };
+    let shapes = [
+        Shape::Empty,
+        Shape::Circle(300),
+        Shape::Rect(1, 2),
+        Shape::Poly {
+            sides: 5,
+            name: Some("pentagon".into()),
+        },
+    ];
+    let mut lines = vec![
+        format!("sample {}", hex(&ser(&sample))),
+        format!("scene {}", hex(&ser(&scene))),
+    ];
+    for (i, shape) in shapes.iter().enumerate() {
+        lines.push(format!("shape{i} {}", hex(&ser(shape))));
+    }
+    let req_key = VarKey::Key8(EchoEndpoint::REQ_KEY);
+    lines.push(format!(
+        "request {}",
+        hex(&frame(req_key, VarSeq::Seq4(0x01020304), &ser(&sample)))
+    ));
+    let mut short_key = VarKey::Key8(AddShapeTopic::TOPIC_KEY);
+    short_key.shrink_to(postcard_rpc::header::VarKeyKind::Key2);
+    lines.push(format!(
+        "publish {}",
+        hex(&frame(short_key, VarSeq::Seq1(9), &ser(&shapes[1])))
+    ));
+    let resp_key = VarKey::Key8(AreaEndpoint::RESP_KEY);
+    lines.push(format!(
+        "response {}",
+        hex(&frame(resp_key, VarSeq::Seq2(513), &ser(&2.5f64)))
+    ));
+    lines.push(format!(
+        "error {}",
+        hex(&frame(
+            VarKey::Key8(ERROR_KEY),
+            VarSeq::Seq4(5),
+            &ser(&WireError::UnknownKey)
+        ))
+    ));
+    lines.push(format!(
+        "count {}",
+        hex(&frame(
+            VarKey::Key8(CountTopic::TOPIC_KEY),
+            VarSeq::Seq4(7),
+            &ser(&42u32)
+        ))
+    ));
+    lines.join("\n") + "\n"
+}
+
+fn ser<T: Serialize>(v: &T) -> Vec<u8> {
+    postcard::to_stdvec(v).unwrap()
+}
+
+/// Run a check script, returning what it printed
+fn run_check(program: &str, args: &[&str]) -> String {
+    let out = Command::new(program)
+        .args(args)
+        .current_dir(golden(""))
+        // Keep `__pycache__` out of the golden files
+        .env("PYTHONDONTWRITEBYTECODE", "1")
+        .output()
+        .unwrap_or_else(|e| panic!("{program} is not available: {e}"));
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    String::from_utf8(out.stdout).unwrap()
+}
+
+/// The major and minor version of node, from `node --version` (`v22.6.0`)
+fn node_version(version: &str) -> Option<(u32, u32)> {
+    let mut parts = version.trim().strip_prefix('v')?.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+#[test]
+fn python() {
+    compare("icd.py", &codegen::python(&report()).unwrap());
+}
+
+#[test]
+#[ignore = "needs python3"]
+fn python_check() {
+    assert_eq!(run_check("python3", &["check.py"]), expected());
+}
+
+#[test]
+fn typescript() {
+    compare("icd.ts", &codegen::typescript(&report()).unwrap());
+}
+
+#[test]
+#[ignore = "needs node 22.6 or newer"]
+fn typescript_check() {
+    // Node runs TypeScript directly since version 22.6
+    let version = run_check("node", &["--version"]);
+    if node_version(&version) < Some((22, 6)) {
+        eprintln!(
+            "skipping the TypeScript check: needs node 22.6 or newer, found {}",
+            version.trim()
+        );
+        return;
+    }
+    let out = run_check(
+        "node",
+        &["--experimental-strip-types", "--no-warnings", "check.ts"],
+    );
+    assert_eq!(out, expected());
+}
+
+#[test]
+fn standard_items_are_left_out() {
+    let mut report = SchemaReport::default();
+    report.add_type(Sample::SCHEMA.into());
+    report
+        .add_endpoint(
+            "sample/echo".into(),
+            EchoEndpoint::REQ_KEY,
+            EchoEndpoint::RESP_KEY,
+        )
+        .unwrap();
+    let std_report = {
+        let mut r = report.clone();
+        r.add_type(u32::SCHEMA.into());
+        r.add_endpoint(
+            "postcard-rpc/ping".into(),
+            Key::for_path::<u32>("postcard-rpc/ping"),
+            Key::for_path::<u32>("postcard-rpc/ping"),
+        )
+        .unwrap();
+        r
+    };
+    assert_eq!(codegen::python(&report), codegen::python(&std_report));
+}
+
+#[test]
+fn conflicts() {
+    // Two items with the same constant name
+    let mut report = SchemaReport::default();
+    report.add_type(u32::SCHEMA.into());
+    report
+        .add_topic_out("a/b".into(), Key::for_path::<u32>("a/b"))
+        .unwrap();
+    report
+        .add_topic_out("a-b".into(), Key::for_path::<u32>("a-b"))
+        .unwrap();
+    assert_eq!(
+        codegen::python(&report),
+        Err(CodegenError::NameConflict("A_B".into()))
+    );
+
+    // Two different types with the same name
+    mod other {
+        #[derive(postcard_schema::Schema)]
+        #[allow(dead_code)]
+        pub struct Point(pub u8);
+    }
+    let mut report = SchemaReport::default();
+    report.add_type(Point::SCHEMA.into());
+    report.add_type(other::Point::SCHEMA.into());
+    report
+        .add_topic_out("a".into(), Key::for_path::<Point>("a"))
+        .unwrap();
+    report
+        .add_topic_out("b".into(), Key::for_path::<other::Point>("b"))
+        .unwrap();
+    assert_eq!(
+        codegen::typescript(&report),
+        Err(CodegenError::NameConflict("Point".into()))
+    );
+
+    // A type named like a part of the runtime
+    mod runtime {
+        #[derive(postcard_schema::Schema)]
+        #[allow(dead_code)]
+        pub struct Writer;
+    }
+    let mut report = SchemaReport::default();
+    report.add_type(runtime::Writer::SCHEMA.into());
+    report
+        .add_topic_out("w".into(), Key::for_path::<runtime::Writer>("w"))
+        .unwrap();
+    assert_eq!(
+        codegen::python(&report),
+        Err(CodegenError::NameConflict("Writer".into()))
+    );
+}
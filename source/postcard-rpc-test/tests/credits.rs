@@ -0,0 +1,204 @@
+use core::time::Duration;
+
+use tokio::time::timeout;
+
+use postcard_rpc::{
+    define_dispatch, endpoints,
+    header::{VarHeader, VarSeq},
+    host_client::HostClient,
+    server::{
+        impls::test_channels::{
+            dispatch_impl::{spawn_fn, WireSpawnImpl, WireTxImpl},
+            ChannelWireSpawn, ChannelWireTx,
+        },
+        Credits, PublishError, Sender, SpawnContext,
+    },
+    standard_icd::{CreditGrant, WireError},
+    topics, Topic,
+};
+use postcard_rpc_test::fixture::Fixture;
+
+endpoints! {
+    list = ENDPOINT_LIST;
+    | EndpointTy        | RequestTy     | ResponseTy    | Path          |
+    | ----------        | ---------     | ----------    | ----          |
+    | FloodEndpoint     | u32           | ()            | "flood"       |
+    | BurstEndpoint     | u32           | u32           | "burst"       |
+}
+
+topics! {
+    list = TOPICS_IN_LIST;
+    direction = postcard_rpc::TopicDirection::ToServer;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+}
+
+topics! {
+    list = TOPICS_OUT_LIST;
+    direction = postcard_rpc::TopicDirection::ToClient;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+    | SampleTopic   | u32           | "samples" |
+}
+
+pub struct TestContext;
+
+impl SpawnContext for TestContext {
+    type SpawnCtxt = ();
+
+    fn spawn_ctxt(&mut self) -> Self::SpawnCtxt {}
+}
+
+define_dispatch! {
+    app: CreditDispatcher;
+    spawn_fn: spawn_fn;
+    tx_impl: WireTxImpl;
+    spawn_impl: WireSpawnImpl;
+    context: TestContext;
+    standard: [credits];
+
+    endpoints: {
+        list: crate::ENDPOINT_LIST;
+
+        | EndpointTy        | kind      | handler               |
+        | ----------        | ----      | -------               |
+        | FloodEndpoint     | spawn     | flood_handler         |
+        | BurstEndpoint     | spawn     | burst_handler         |
+    };
+    topics_in: {
+        list: crate::TOPICS_IN_LIST;
+
+        | TopicTy           | kind      | handler               |
+        | ----------        | ----      | -------               |
+    };
+    topics_out: {
+        list: crate::TOPICS_OUT_LIST;
+    };
+}
+
+/// Publishes `0..count`, waiting for credits
+async fn flood_handler(_context: (), header: VarHeader, body: u32, out: Sender<ChannelWireTx>) {
+    for i in 0..body {
+        let _ = out.publish::<SampleTopic>(VarSeq::Seq4(i), &i).await;
+    }
+    let _ = out.reply::<FloodEndpoint>(header.seq_no, &()).await;
+}
+
+/// Publishes `0..count` without waiting, replies with the number of dropped messages
+async fn burst_handler(_context: (), header: VarHeader, body: u32, out: Sender<ChannelWireTx>) {
+    for i in 0..body {
+        match out.try_publish::<SampleTopic>(VarSeq::Seq4(i), &i).await {
+            Ok(()) | Err(PublishError::NoCredits) => {}
+            Err(PublishError::Tx(_)) => return,
+        }
+    }
+    let dropped = out.credits().unwrap().dropped(SampleTopic::TOPIC_KEY);
+    let _ = out.reply::<BurstEndpoint>(header.seq_no, &dropped).await;
+}
+
+fn setup() -> (HostClient<WireError>, &'static Credits) {
+    // Each test gets its own credits
+    let credits: &'static Credits = Box::leak(Box::new(Credits::new()));
+    let app = CreditDispatcher::new(TestContext, ChannelWireSpawn {});
+    let cli = Fixture::new(app)
+        .server(move |server| server.with_credits(credits))
+        .connect();
+    (cli, credits)
+}
+
+#[tokio::test]
+async fn credited_subscription() {
+    let (cli, _credits) = setup();
+
+    // Far more messages than the subscription can hold, received by a slow client
+    let mut sub = cli.subscribe_credited::<SampleTopic>(4).await.unwrap();
+    let flood = tokio::task::spawn({
+        let cli = cli.clone();
+        async move { cli.send_resp::<FloodEndpoint>(&100).await }
+    });
+    for i in 0..100 {
+        let msg = timeout(Duration::from_secs(1), sub.recv()).await.unwrap();
+        assert_eq!(msg, Some(i));
+        if i % 10 == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+    assert_eq!(flood.await.unwrap(), Ok(()));
+}
+
+#[tokio::test]
+async fn dropped_on_server() {
+    let (cli, _credits) = setup();
+    let mut sub = cli.subscribe_exclusive::<SampleTopic>(16).await.unwrap();
+
+    // Topics are not limited until the client grants credits
+    assert_eq!(cli.send_resp::<BurstEndpoint>(&5).await, Ok(0));
+    for i in 0..5 {
+        assert_eq!(sub.recv().await, Some(i));
+    }
+
+    cli.grant_credits::<SampleTopic>(3).await.unwrap();
+    assert_eq!(cli.send_resp::<BurstEndpoint>(&10).await, Ok(7));
+    for i in 0..3 {
+        assert_eq!(sub.recv().await, Some(i));
+    }
+
+    // Turning flow control off again
+    cli.grant_credits::<SampleTopic>(CreditGrant::UNLIMITED)
+        .await
+        .unwrap();
+    assert_eq!(cli.send_resp::<BurstEndpoint>(&4).await, Ok(0));
+    for i in 0..4 {
+        assert_eq!(sub.recv().await, Some(i));
+    }
+    assert!(timeout(Duration::from_millis(10), sub.recv())
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn credited_subscription_dropped() {
+    let (cli, _credits) = setup();
+
+    let mut sub = cli.subscribe_credited::<SampleTopic>(4).await.unwrap();
+    let flood = tokio::task::spawn({
+        let cli = cli.clone();
+        async move { cli.send_resp::<FloodEndpoint>(&100).await }
+    });
+    assert_eq!(sub.recv().await, Some(0));
+    assert_eq!(sub.recv().await, Some(1));
+
+    // The server is no longer limited, instead of waiting for credits forever
+    drop(sub);
+    let res = timeout(Duration::from_secs(1), flood).await.unwrap();
+    assert_eq!(res.unwrap(), Ok(()));
+}
+
+#[tokio::test]
+async fn reset_on_disconnect() {
+    let (cli, credits) = setup();
+
+    let _sub = cli.subscribe_exclusive::<SampleTopic>(16).await.unwrap();
+    cli.grant_credits::<SampleTopic>(2).await.unwrap();
+    let _flood = tokio::task::spawn({
+        let cli = cli.clone();
+        async move { cli.send_resp::<FloodEndpoint>(&100).await }
+    });
+    timeout(Duration::from_secs(1), async {
+        while credits.available(SampleTopic::TOPIC_KEY) != Some(0) {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    })
+    .await
+    .unwrap();
+
+    // Once the client is gone, the grants are forgotten
+    cli.close();
+    timeout(Duration::from_secs(1), async {
+        while credits.available(SampleTopic::TOPIC_KEY).is_some() {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
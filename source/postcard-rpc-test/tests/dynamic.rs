@@ -0,0 +1,192 @@
+use core::time::Duration;
+
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::time::timeout;
+
+use postcard_rpc::{
+    define_dispatch, endpoints,
+    header::{VarHeader, VarSeq, VarSeqKind},
+    host_client::{
+        dynamic::{DynamicClient, DynamicError, ValueError},
+        HostClientConfig, HostErr,
+    },
+    server::{
+        impls::test_channels::{
+            dispatch_impl::{WireSpawnImpl, WireTxImpl},
+            ChannelWireSpawn, ChannelWireTx,
+        },
+        Sender, SpawnContext,
+    },
+    standard_icd::WireError,
+    topics,
+};
+use postcard_rpc_test::fixture::Fixture;
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct Operands {
+    pub a: u32,
+    pub b: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub enum Event {
+    Reset,
+    Level(u8),
+}
+
+endpoints! {
+    list = ENDPOINT_LIST;
+    | EndpointTy        | RequestTy     | ResponseTy    | Path          |
+    | ----------        | ---------     | ----------    | ----          |
+    | SumEndpoint       | Operands      | u64           | "sum"         |
+}
+
+topics! {
+    list = TOPICS_IN_LIST;
+    direction = postcard_rpc::TopicDirection::ToServer;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+    | LevelTopic    | u8            | "level"   |
+}
+
+topics! {
+    list = TOPICS_OUT_LIST;
+    direction = postcard_rpc::TopicDirection::ToClient;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+    | EventTopic    | Event         | "events"  |
+}
+
+pub struct TestContext;
+
+impl SpawnContext for TestContext {
+    type SpawnCtxt = ();
+
+    fn spawn_ctxt(&mut self) -> Self::SpawnCtxt {}
+}
+
+define_dispatch! {
+    app: DynDispatcher;
+    spawn_fn: spawn_fn;
+    tx_impl: WireTxImpl;
+    spawn_impl: WireSpawnImpl;
+    context: TestContext;
+
+    endpoints: {
+        list: crate::ENDPOINT_LIST;
+
+        | EndpointTy        | kind      | handler               |
+        | ----------        | ----      | -------               |
+        | SumEndpoint       | blocking  | sum_handler           |
+    };
+    topics_in: {
+        list: crate::TOPICS_IN_LIST;
+
+        | TopicTy           | kind      | handler               |
+        | ----------        | ----      | -------               |
+        | LevelTopic        | async     | level_handler         |
+    };
+    topics_out: {
+        list: crate::TOPICS_OUT_LIST;
+    };
+}
+
+fn sum_handler(_context: &mut TestContext, _header: VarHeader, body: Operands) -> u64 {
+    u64::from(body.a) + u64::from(body.b.unwrap_or(0))
+}
+
+/// Reports the level as an event, and a reset for level zero
+async fn level_handler(
+    _context: &mut TestContext,
+    header: VarHeader,
+    body: u8,
+    out: &Sender<ChannelWireTx>,
+) {
+    let event = match body {
+        0 => Event::Reset,
+        n => Event::Level(n),
+    };
+    let _ = out.publish::<EventTopic>(header.seq_no, &event).await;
+}
+
+async fn setup() -> DynamicClient<WireError> {
+    let app = DynDispatcher::new(TestContext, ChannelWireSpawn {});
+    let cli = Fixture::new(app).connect();
+    DynamicClient::new(cli).await.unwrap()
+}
+
+#[tokio::test]
+async fn endpoints() {
+    let cli = setup().await;
+
+    assert_eq!(
+        cli.send_resp("sum", &json!({"a": 40, "b": 2})).await,
+        Ok(json!(42))
+    );
+    // Missing options are `None`
+    assert_eq!(cli.send_resp("sum", &json!({"a": 7})).await, Ok(json!(7)));
+    assert_eq!(
+        cli.send_resp("sum", &json!({"a": -1})).await,
+        Err(DynamicError::Value(ValueError::Mismatch {
+            path: "/a".into(),
+            expected: "a u32".into(),
+        }))
+    );
+    assert_eq!(
+        cli.send_resp("product", &json!({})).await,
+        Err(DynamicError::UnknownEndpoint("product".into()))
+    );
+}
+
+#[tokio::test]
+async fn topics() {
+    let cli = setup().await;
+
+    let mut sub = cli.subscribe("events", 8).await.unwrap();
+    cli.publish("level", VarSeq::Seq4(1), &json!(3))
+        .await
+        .unwrap();
+    cli.publish("level", VarSeq::Seq4(2), &json!(0))
+        .await
+        .unwrap();
+    let wait = Duration::from_secs(1);
+    assert_eq!(
+        timeout(wait, sub.recv()).await.unwrap(),
+        Ok(json!({"Level": 3}))
+    );
+    assert_eq!(timeout(wait, sub.recv()).await.unwrap(), Ok(json!("Reset")));
+
+    // Topics are looked up in their direction
+    assert!(matches!(
+        cli.subscribe("level", 8).await,
+        Err(DynamicError::UnknownTopic(_))
+    ));
+}
+
+#[tokio::test]
+async fn publish_errors() {
+    let cli = setup().await;
+    let report = cli.report().clone();
+    cli.client().close();
+    assert_eq!(
+        cli.publish("level", VarSeq::Seq4(1), &json!(3)).await,
+        Err(DynamicError::Comms(HostErr::Closed))
+    );
+
+    // Fragments of ten bytes only fit the header, so no message can be sent
+    let app = DynDispatcher::new(TestContext, ChannelWireSpawn {});
+    let config = HostClientConfig::default()
+        .with_seq_kind(VarSeqKind::Seq1)
+        .with_fragment_size(Some(10));
+    let cli = Fixture::new(app).client_config(config).connect();
+    let cli = DynamicClient::with_report(cli, report);
+    assert_eq!(
+        cli.publish("level", VarSeq::Seq1(2), &json!(3)).await,
+        Err(DynamicError::Comms(HostErr::RequestTooLarge {
+            len: 1,
+            max: 0
+        }))
+    );
+}
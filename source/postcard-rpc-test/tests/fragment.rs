@@ -0,0 +1,248 @@
+use core::time::Duration;
+
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+use tokio::{sync::mpsc, time::timeout};
+
+use postcard_rpc::{
+    define_dispatch, endpoints,
+    header::{VarHeader, VarSeqKind},
+    host_client::{HostClient, HostClientConfig, HostErr},
+    server::{
+        impls::test_channels::{
+            dispatch_impl::{spawn_fn, WireSpawnImpl, WireTxImpl},
+            ChannelWireSpawn, ChannelWireTx,
+        },
+        Sender, SpawnContext,
+    },
+    standard_icd::{FrameTooLong, WireError},
+    topics,
+};
+use postcard_rpc_test::fixture::Fixture;
+
+/// The largest frame either side sends
+const FRAG_SIZE: usize = 64;
+/// The largest message the server reassembles
+const REASSEMBLY: usize = 4096;
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct Blob(pub Vec<u8>);
+
+endpoints! {
+    list = ENDPOINT_LIST;
+    | EndpointTy        | RequestTy     | ResponseTy    | Path          |
+    | ----------        | ---------     | ----------    | ----          |
+    | EchoEndpoint      | Blob          | Blob          | "echo"        |
+    | FillEndpoint      | u32           | Blob          | "fill"        |
+}
+
+topics! {
+    list = TOPICS_IN_LIST;
+    direction = postcard_rpc::TopicDirection::ToServer;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+}
+
+topics! {
+    list = TOPICS_OUT_LIST;
+    direction = postcard_rpc::TopicDirection::ToClient;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+}
+
+pub struct TestContext;
+
+impl SpawnContext for TestContext {
+    type SpawnCtxt = ();
+
+    fn spawn_ctxt(&mut self) -> Self::SpawnCtxt {}
+}
+
+define_dispatch! {
+    app: FragmentDispatcher;
+    spawn_fn: spawn_fn;
+    tx_impl: WireTxImpl;
+    spawn_impl: WireSpawnImpl;
+    context: TestContext;
+
+    endpoints: {
+        list: crate::ENDPOINT_LIST;
+
+        | EndpointTy        | kind      | handler               |
+        | ----------        | ----      | -------               |
+        | EchoEndpoint      | spawn     | echo_handler          |
+        | FillEndpoint      | spawn     | fill_handler          |
+    };
+    topics_in: {
+        list: crate::TOPICS_IN_LIST;
+
+        | TopicTy           | kind      | handler               |
+        | ----------        | ----      | -------               |
+    };
+    topics_out: {
+        list: crate::TOPICS_OUT_LIST;
+    };
+}
+
+async fn echo_handler(_context: (), header: VarHeader, body: Blob, out: Sender<ChannelWireTx>) {
+    let mut scratch = vec![0; REASSEMBLY + 32];
+    let _ = out
+        .reply_fragmented::<EchoEndpoint>(header.seq_no, &body, &mut scratch, FRAG_SIZE)
+        .await;
+}
+
+async fn fill_handler(_context: (), header: VarHeader, body: u32, out: Sender<ChannelWireTx>) {
+    let resp = Blob((0..body).map(|i| i as u8).collect());
+    let mut scratch = vec![0; 256];
+    let _ = out
+        .reply_fragmented::<FillEndpoint>(header.seq_no, &resp, &mut scratch, FRAG_SIZE)
+        .await;
+}
+
+/// Forwards frames, checking that none of them are larger than `FRAG_SIZE`
+async fn check_frames(mut rx: mpsc::Receiver<Vec<u8>>, tx: mpsc::Sender<Vec<u8>>) {
+    while let Some(frame) = rx.recv().await {
+        assert!(frame.len() <= FRAG_SIZE, "{} byte frame", frame.len());
+        if tx.send(frame).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Forwards frames, losing the second one
+async fn lose_second(mut rx: mpsc::Receiver<Vec<u8>>, tx: mpsc::Sender<Vec<u8>>) {
+    let mut count = 0;
+    while let Some(frame) = rx.recv().await {
+        count += 1;
+        if count != 2 && tx.send(frame).await.is_err() {
+            return;
+        }
+    }
+}
+
+fn setup() -> HostClient<WireError> {
+    setup_with(
+        HostClientConfig::default()
+            .with_seq_kind(VarSeqKind::Seq1)
+            .with_outgoing_depth(16)
+            .with_default_timeout(Some(Duration::from_secs(1)))
+            .with_fragment_size(Some(FRAG_SIZE)),
+    )
+}
+
+fn setup_with(config: HostClientConfig<'static>) -> HostClient<WireError> {
+    let app = FragmentDispatcher::new(TestContext, ChannelWireSpawn {});
+    Fixture::new(app)
+        .buffer_len(FRAG_SIZE)
+        .server(|server| server.with_reassembly(vec![0; REASSEMBLY].into_boxed_slice()))
+        .client_config(config)
+        .relay_to_server(check_frames)
+        .relay_to_client(check_frames)
+        .connect()
+}
+
+#[tokio::test]
+async fn fragmented_roundtrip() {
+    let cli = setup();
+
+    // Far larger than a single frame, both ways
+    let blob: Vec<u8> = (0..3000u32).map(|i| (i * 7) as u8).collect();
+    let res = timeout(
+        Duration::from_secs(1),
+        cli.send_resp::<EchoEndpoint>(&Blob(blob.clone())),
+    )
+    .await
+    .unwrap();
+    assert_eq!(res, Ok(Blob(blob)));
+
+    // Small messages still work
+    let res = cli.send_resp::<EchoEndpoint>(&Blob(vec![1, 2, 3])).await;
+    assert_eq!(res, Ok(Blob(vec![1, 2, 3])));
+}
+
+#[tokio::test]
+async fn fragmented_reply() {
+    let cli = setup();
+
+    let res = cli.send_resp::<FillEndpoint>(&200).await.unwrap();
+    assert_eq!(res.0, (0..200).map(|i| i as u8).collect::<Vec<u8>>());
+}
+
+#[tokio::test]
+async fn reassembly_too_large() {
+    let cli = setup();
+
+    let res = cli
+        .send_resp::<EchoEndpoint>(&Blob(vec![0; REASSEMBLY + 1]))
+        .await;
+    let Err(HostErr::Wire(WireError::FrameTooLong(FrameTooLong { len, max }))) = res else {
+        panic!("unexpected result: {res:?}");
+    };
+    assert!(len as usize > REASSEMBLY);
+    assert_eq!(max as usize, REASSEMBLY);
+
+    // The server recovers
+    let res = cli.send_resp::<EchoEndpoint>(&Blob(vec![4; 100])).await;
+    assert_eq!(res, Ok(Blob(vec![4; 100])));
+}
+
+#[tokio::test]
+async fn lost_fragment() {
+    let app = FragmentDispatcher::new(TestContext, ChannelWireSpawn {});
+    let cli = Fixture::new(app)
+        .buffer_len(FRAG_SIZE)
+        .server(|server| server.with_reassembly(vec![0; REASSEMBLY].into_boxed_slice()))
+        .client_config(
+            HostClientConfig::default()
+                .with_seq_kind(VarSeqKind::Seq1)
+                .with_default_timeout(Some(Duration::from_secs(1)))
+                .with_fragment_size(Some(FRAG_SIZE)),
+        )
+        .relay_to_server(lose_second)
+        .connect();
+
+    // The server replies with an error once, instead of ignoring the request
+    let res = cli.send_resp::<EchoEndpoint>(&Blob(vec![0; 200])).await;
+    assert_eq!(res, Err(HostErr::Wire(WireError::DeserFailed)));
+    let res = cli.send_resp::<EchoEndpoint>(&Blob(vec![4; 200])).await;
+    assert_eq!(res, Ok(Blob(vec![4; 200])));
+    assert_eq!(cli.late_responses(), 0);
+}
+
+#[tokio::test]
+async fn too_many_fragments() {
+    let cli = setup();
+
+    // One byte of flags, the full 8 byte key, the one byte sequence number, and two
+    // bytes of extension
+    let max = (FRAG_SIZE - 1 - 8 - 1 - 2) * 0x8000;
+    let res = cli.send_resp::<EchoEndpoint>(&Blob(vec![0; max])).await;
+    // The length prefix of the blob makes it too long, it is not sent
+    let Err(HostErr::RequestTooLarge { len, max: limit }) = res else {
+        panic!("unexpected result: {res:?}");
+    };
+    assert!(len > max);
+    assert_eq!(limit, max);
+
+    // The client still works
+    let res = cli.send_resp::<EchoEndpoint>(&Blob(vec![4; 100])).await;
+    assert_eq!(res, Ok(Blob(vec![4; 100])));
+}
+
+#[tokio::test]
+async fn client_reassembly_limit() {
+    let cli = setup_with(
+        HostClientConfig::default()
+            .with_seq_kind(VarSeqKind::Seq1)
+            .with_default_timeout(Some(Duration::from_millis(100)))
+            .with_fragment_size(Some(FRAG_SIZE))
+            .with_max_reassembly_len(128),
+    );
+
+    // Longer responses are dropped
+    let res = cli.send_resp::<FillEndpoint>(&200).await;
+    assert_eq!(res, Err(HostErr::Timeout));
+
+    let res = cli.send_resp::<FillEndpoint>(&100).await.unwrap();
+    assert_eq!(res.0, (0..100).map(|i| i as u8).collect::<Vec<u8>>());
+}
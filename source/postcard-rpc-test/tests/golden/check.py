@@ -0,0 +1,65 @@
+"""Encodes the values of `expected()` in `tests/codegen.rs` with the generated bindings"""
+
+from icd import *
+
+sample = Sample(
+    flag=True,
+    tiny=-5,
+    byte=200,
+    short=-300,
+    word=70000,
+    long=-(1 << 40),
+    huge=1 << 100,
+    neg=-(1 << 100),
+    ratio=1.5,
+    precise=-0.1,
+    letter="é",
+    name="hello",
+    blob=b"\x00\xff",
+    maybe=None,
+    values=[1, -1, 1000],
+    pair=(7, "x"),
+    table={"a": 1, "b": 1 << 40},
+    nothing=None,
+)
+scene = Scene(
+    shapes=[ShapeEmpty(), ShapeRect(3, 4)],
+    origin=Point(-1, 2),
+    scale=Meters(0.5),
+    marker=Marker(),
+)
+shapes = [ShapeEmpty(), ShapeCircle(300), ShapeRect(1, 2), ShapePoly(5, "pentagon")]
+
+
+def round_trip(codec, value):
+    data = codec.to_bytes(value)
+    assert codec.from_bytes(data) == value, value
+    return data.hex()
+
+
+print("sample", round_trip(SAMPLE_ECHO.req, sample))
+print("scene", round_trip(SCENE_AREA.req, scene))
+for i, shape in enumerate(shapes):
+    print(f"shape{i}", round_trip(SCENE_ADD.msg, shape))
+
+print("request", request(SAMPLE_ECHO, 0x01020304, sample).hex())
+
+frame = publish(SCENE_ADD, 9, shapes[1], key_len=2, seq_len=1)
+assert message(SCENE_ADD, frame) == (VarHeader(frame[1:3], 9, 1), shapes[1])
+print("publish", frame.hex())
+
+frame = VarHeader(SCENE_AREA.resp_key, 513, 2).to_bytes() + SCENE_AREA.resp.to_bytes(2.5)
+assert response(SCENE_AREA, frame)[1] == 2.5
+print("response", frame.hex())
+
+frame = VarHeader(ERROR_KEY, 5).to_bytes() + WIRE_ERROR.to_bytes(WireErrorUnknownKey())
+try:
+    response(SAMPLE_ECHO, frame)
+    raise AssertionError("expected an error")
+except WireErrorResponse as e:
+    assert e.error == WireErrorUnknownKey()
+print("error", frame.hex())
+
+frame = publish(SCENE_COUNT, 7, 42)
+assert message(SCENE_COUNT, frame)[1] == 42
+print("count", frame.hex())
@@ -0,0 +1,108 @@
+// Encodes the values of `expected()` in `tests/codegen.rs` with the generated bindings
+
+import { deepStrictEqual, throws } from "node:assert";
+
+import {
+  type Codec,
+  type Sample,
+  type Scene,
+  type Shape,
+  ERROR_KEY,
+  SAMPLE_ECHO,
+  SCENE_ADD,
+  SCENE_AREA,
+  SCENE_COUNT,
+  WIRE_ERROR,
+  WireErrorResponse,
+  encodeVarHeader,
+  fromBytes,
+  message,
+  publish,
+  request,
+  response,
+  toBytes,
+} from "./icd.ts";
+
+const sample: Sample = {
+  flag: true,
+  tiny: -5,
+  byte: 200,
+  short: -300,
+  word: 70000,
+  long: -(1n << 40n),
+  huge: 1n << 100n,
+  neg: -(1n << 100n),
+  ratio: 1.5,
+  precise: -0.1,
+  letter: "é",
+  name: "hello",
+  blob: new Uint8Array([0x00, 0xff]),
+  maybe: null,
+  values: [1, -1, 1000],
+  pair: [7, "x"],
+  table: new Map([
+    ["a", 1n],
+    ["b", 1n << 40n],
+  ]),
+  nothing: null,
+};
+const scene: Scene = {
+  shapes: [{ tag: "Empty" }, { tag: "Rect", value: [3, 4] }],
+  origin: [-1, 2],
+  scale: 0.5,
+  marker: null,
+};
+const shapes: Shape[] = [
+  { tag: "Empty" },
+  { tag: "Circle", value: 300 },
+  { tag: "Rect", value: [1, 2] },
+  { tag: "Poly", value: { sides: 5, name: "pentagon" } },
+];
+
+function hex(data: Uint8Array): string {
+  return Array.from(data, (b) => b.toString(16).padStart(2, "0")).join("");
+}
+
+function concat(a: Uint8Array, b: Uint8Array): Uint8Array {
+  const out = new Uint8Array(a.length + b.length);
+  out.set(a);
+  out.set(b, a.length);
+  return out;
+}
+
+function roundTrip<T>(codec: Codec<T>, value: T): string {
+  const data = toBytes(codec, value);
+  deepStrictEqual(fromBytes(codec, data), value);
+  return hex(data);
+}
+
+console.log("sample", roundTrip(SAMPLE_ECHO.req, sample));
+console.log("scene", roundTrip(SCENE_AREA.req, scene));
+shapes.forEach((shape, i) => console.log(`shape${i}`, roundTrip(SCENE_ADD.msg, shape)));
+
+console.log("request", hex(request(SAMPLE_ECHO, 0x01020304, sample)));
+
+let frame = publish(SCENE_ADD, 9, shapes[1], 2, 1);
+deepStrictEqual(message(SCENE_ADD, frame), [{ key: frame.slice(1, 3), seqNo: 9, seqLen: 1 }, shapes[1]]);
+console.log("publish", hex(frame));
+
+frame = concat(
+  encodeVarHeader({ key: SCENE_AREA.respKey, seqNo: 513, seqLen: 2 }),
+  toBytes(SCENE_AREA.resp, 2.5),
+);
+deepStrictEqual(response(SCENE_AREA, frame)[1], 2.5);
+console.log("response", hex(frame));
+
+frame = concat(
+  encodeVarHeader({ key: ERROR_KEY, seqNo: 5, seqLen: 4 }),
+  toBytes(WIRE_ERROR, { tag: "UnknownKey" }),
+);
+throws(
+  () => response(SAMPLE_ECHO, frame),
+  (e) => e instanceof WireErrorResponse && e.error.tag === "UnknownKey",
+);
+console.log("error", hex(frame));
+
+frame = publish(SCENE_COUNT, 7, 42);
+deepStrictEqual(message(SCENE_COUNT, frame)[1], 42);
+console.log("count", hex(frame));
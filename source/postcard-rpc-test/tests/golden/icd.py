@@ -0,0 +1,632 @@
+# Generated by postcard-rpc, do not edit.
+
+from __future__ import annotations
+
+import struct
+from dataclasses import dataclass
+from typing import Any, Callable, Dict, Generic, List, Optional, Tuple, TypeVar
+
+T = TypeVar("T")
+Req = TypeVar("Req")
+Resp = TypeVar("Resp")
+
+
+class DecodeError(Exception):
+    """The data does not match the schema"""
+
+
+class Writer:
+    """Encodes values in the postcard wire format"""
+
+    def __init__(self) -> None:
+        self.buf = bytearray()
+
+    def varint(self, v: int) -> None:
+        while v >= 0x80:
+            self.buf.append((v & 0x7F) | 0x80)
+            v >>= 7
+        self.buf.append(v)
+
+    def uint(self, v: int, bits: int) -> None:
+        if not 0 <= v < (1 << bits):
+            raise ValueError(f"{v} is not a u{bits}")
+        if bits == 8:
+            self.buf.append(v)
+        else:
+            self.varint(v)
+
+    def sint(self, v: int, bits: int) -> None:
+        if not -(1 << (bits - 1)) <= v < (1 << (bits - 1)):
+            raise ValueError(f"{v} is not an i{bits}")
+        if bits == 8:
+            self.buf.append(v & 0xFF)
+        else:
+            self.varint(v << 1 if v >= 0 else (-v << 1) - 1)
+
+    def boolean(self, v: bool) -> None:
+        self.buf.append(1 if v else 0)
+
+    def f32(self, v: float) -> None:
+        self.buf += struct.pack("<f", v)
+
+    def f64(self, v: float) -> None:
+        self.buf += struct.pack("<d", v)
+
+    def byte_array(self, v: bytes) -> None:
+        self.varint(len(v))
+        self.buf += v
+
+    def string(self, v: str) -> None:
+        self.byte_array(v.encode("utf-8"))
+
+    def char(self, v: str) -> None:
+        if len(v) != 1:
+            raise ValueError(f"{v!r} is not a single character")
+        self.string(v)
+
+    def unit(self, v: None) -> None:
+        pass
+
+    def option(self, v: Optional[Any], f: Callable[[Any], None]) -> None:
+        if v is None:
+            self.buf.append(0)
+        else:
+            self.buf.append(1)
+            f(v)
+
+    def seq(self, v: List[Any], f: Callable[[Any], None]) -> None:
+        self.varint(len(v))
+        for x in v:
+            f(x)
+
+    def map(
+        self, v: Dict[Any, Any], kf: Callable[[Any], None], vf: Callable[[Any], None]
+    ) -> None:
+        self.varint(len(v))
+        for k, x in v.items():
+            kf(k)
+            vf(x)
+
+    def tuple(self, v: Tuple[Any, ...], fs: List[Callable[[Any], None]]) -> None:
+        if len(v) != len(fs):
+            raise ValueError(f"expected a tuple of {len(fs)} items")
+        for x, f in zip(v, fs):
+            f(x)
+
+
+class Reader:
+    """Decodes values in the postcard wire format"""
+
+    def __init__(self, data: bytes) -> None:
+        self.data = bytes(data)
+        self.pos = 0
+
+    def take(self, n: int) -> bytes:
+        if self.pos + n > len(self.data):
+            raise DecodeError("unexpected end of data")
+        out = self.data[self.pos : self.pos + n]
+        self.pos += n
+        return out
+
+    def varint(self, bits: int) -> int:
+        v = 0
+        for i in range((bits + 6) // 7):
+            b = self.take(1)[0]
+            v |= (b & 0x7F) << (7 * i)
+            if b < 0x80:
+                if v >= (1 << bits):
+                    raise DecodeError(f"{v} does not fit in {bits} bits")
+                return v
+        raise DecodeError("varint is too long")
+
+    def uint(self, bits: int) -> int:
+        if bits == 8:
+            return self.take(1)[0]
+        return self.varint(bits)
+
+    def sint(self, bits: int) -> int:
+        if bits == 8:
+            v = self.take(1)[0]
+            return v - 0x100 if v >= 0x80 else v
+        z = self.varint(bits)
+        return (z >> 1) ^ -(z & 1)
+
+    def boolean(self) -> bool:
+        b = self.take(1)[0]
+        if b > 1:
+            raise DecodeError(f"{b} is not a bool")
+        return b == 1
+
+    def f32(self) -> float:
+        return struct.unpack("<f", self.take(4))[0]
+
+    def f64(self) -> float:
+        return struct.unpack("<d", self.take(8))[0]
+
+    def byte_array(self) -> bytes:
+        return self.take(self.varint(64))
+
+    def string(self) -> str:
+        try:
+            return self.byte_array().decode("utf-8")
+        except UnicodeDecodeError as e:
+            raise DecodeError(str(e)) from e
+
+    def char(self) -> str:
+        v = self.string()
+        if len(v) != 1:
+            raise DecodeError(f"{v!r} is not a single character")
+        return v
+
+    def unit(self) -> None:
+        return None
+
+    def option(self, f: Callable[[], T]) -> Optional[T]:
+        return f() if self.boolean() else None
+
+    def seq(self, f: Callable[[], T]) -> List[T]:
+        return [f() for _ in range(self.varint(64))]
+
+    def map(self, kf: Callable[[], Any], vf: Callable[[], Any]) -> Dict[Any, Any]:
+        out = {}
+        for _ in range(self.varint(64)):
+            k = kf()
+            out[k] = vf()
+        return out
+
+    def tuple(self, fs: List[Callable[[], Any]]) -> Tuple[Any, ...]:
+        return tuple(f() for f in fs)
+
+    def finish(self) -> None:
+        if self.pos != len(self.data):
+            raise DecodeError(f"{len(self.data) - self.pos} bytes left over")
+
+
+@dataclass(frozen=True)
+class Codec(Generic[T]):
+    """Encodes and decodes values of one type"""
+
+    encode: Callable[[Writer, T], None]
+    decode: Callable[[Reader], T]
+
+    def to_bytes(self, v: T) -> bytes:
+        w = Writer()
+        self.encode(w, v)
+        return bytes(w.buf)
+
+    def from_bytes(self, data: bytes) -> T:
+        r = Reader(data)
+        v = self.decode(r)
+        r.finish()
+        return v
+
+
+@dataclass(frozen=True)
+class Endpoint(Generic[Req, Resp]):
+    """An endpoint, with the keys and codecs of its request and response"""
+
+    path: str
+    req_key: bytes
+    resp_key: bytes
+    req: Codec[Req]
+    resp: Codec[Resp]
+
+
+@dataclass(frozen=True)
+class Topic(Generic[T]):
+    """A topic, with the key and codec of its messages"""
+
+    path: str
+    key: bytes
+    msg: Codec[T]
+
+
+@dataclass(frozen=True)
+class VarHeader:
+    """The header of a frame
+
+    The key has 1, 2, 4, or 8 bytes, the sequence number 1, 2, or 4 bytes.
+    """
+
+    key: bytes
+    seq_no: int
+    seq_len: int = 4
+
+    def to_bytes(self) -> bytes:
+        key_bits = {1: 0x00, 2: 0x40, 4: 0x80, 8: 0xC0}[len(self.key)]
+        seq_bits = {1: 0x00, 2: 0x10, 4: 0x20}[self.seq_len]
+        seq_no = self.seq_no.to_bytes(self.seq_len, "little")
+        return bytes([key_bits | seq_bits]) + self.key + seq_no
+
+    @staticmethod
+    def take_from(frame: bytes) -> Tuple[VarHeader, bytes]:
+        """Split a frame into its header and body"""
+        if not frame or frame[0] & 0x0F != 0:
+            raise DecodeError("not a frame header")
+        key_len = 1 << (frame[0] >> 6)
+        seq_len = {0x00: 1, 0x10: 2, 0x20: 4}.get(frame[0] & 0x30)
+        if seq_len is None:
+            raise DecodeError("invalid sequence number length")
+        end = 1 + key_len + seq_len
+        if len(frame) < end:
+            raise DecodeError("unexpected end of data")
+        seq_no = int.from_bytes(frame[1 + key_len : end], "little")
+        return VarHeader(frame[1 : 1 + key_len], seq_no, seq_len), frame[end:]
+
+
+def shrink_key(key: bytes, key_len: int) -> bytes:
+    """Shrink an 8-byte key to 1, 2, or 4 bytes"""
+    while len(key) > key_len:
+        key = bytes(a ^ b for a, b in zip(key[0::2], key[1::2]))
+    return key
+
+
+class WireErrorResponse(Exception):
+    """The server replied with an error instead of a response"""
+
+    def __init__(self, header: VarHeader, error: WireError) -> None:
+        super().__init__(f"the server replied with {error}")
+        self.header = header
+        self.error = error
+
+
+def request(
+    ep: Endpoint[Req, Resp], seq_no: int, req: Req, key_len: int = 8, seq_len: int = 4
+) -> bytes:
+    """Frame a request to an endpoint"""
+    header = VarHeader(shrink_key(ep.req_key, key_len), seq_no, seq_len)
+    return header.to_bytes() + ep.req.to_bytes(req)
+
+
+def response(ep: Endpoint[Req, Resp], frame: bytes) -> Tuple[VarHeader, Resp]:
+    """Decode the response of an endpoint
+
+    Raises a WireErrorResponse if the server replied with an error.
+    """
+    header, body = VarHeader.take_from(frame)
+    if header.key == shrink_key(ep.resp_key, len(header.key)):
+        return header, ep.resp.from_bytes(body)
+    if header.key == shrink_key(ERROR_KEY, len(header.key)):
+        raise WireErrorResponse(header, WIRE_ERROR.from_bytes(body))
+    raise DecodeError(f"unexpected key {header.key.hex()}")
+
+
+def publish(tp: Topic[T], seq_no: int, msg: T, key_len: int = 8, seq_len: int = 4) -> bytes:
+    """Frame a message of a topic"""
+    header = VarHeader(shrink_key(tp.key, key_len), seq_no, seq_len)
+    return header.to_bytes() + tp.msg.to_bytes(msg)
+
+
+def message(tp: Topic[T], frame: bytes) -> Tuple[VarHeader, T]:
+    """Decode a message of a topic"""
+    header, body = VarHeader.take_from(frame)
+    if header.key != shrink_key(tp.key, len(header.key)):
+        raise DecodeError(f"unexpected key {header.key.hex()}")
+    return header, tp.msg.from_bytes(body)
+
+
+@dataclass
+class FrameTooLong:
+    len: int
+    max: int
+
+    def encode(self, w: Writer) -> None:
+        w.uint(self.len, 32)
+        w.uint(self.max, 32)
+
+    @staticmethod
+    def decode(r: Reader) -> FrameTooLong:
+        return FrameTooLong(
+            r.uint(32),
+            r.uint(32),
+        )
+
+
+@dataclass
+class FrameTooShort:
+    len: int
+
+    def encode(self, w: Writer) -> None:
+        w.uint(self.len, 32)
+
+    @staticmethod
+    def decode(r: Reader) -> FrameTooShort:
+        return FrameTooShort(
+            r.uint(32),
+        )
+
+
+@dataclass
+class Marker:
+    def encode(self, w: Writer) -> None:
+        pass
+
+    @staticmethod
+    def decode(r: Reader) -> Marker:
+        return Marker()
+
+
+@dataclass
+class Meters:
+    _0: float
+
+    def encode(self, w: Writer) -> None:
+        w.f64(self._0)
+
+    @staticmethod
+    def decode(r: Reader) -> Meters:
+        return Meters(
+            r.f64(),
+        )
+
+
+@dataclass
+class Point:
+    _0: int
+    _1: int
+
+    def encode(self, w: Writer) -> None:
+        w.sint(self._0, 32)
+        w.sint(self._1, 32)
+
+    @staticmethod
+    def decode(r: Reader) -> Point:
+        return Point(
+            r.sint(32),
+            r.sint(32),
+        )
+
+
+@dataclass
+class Sample:
+    flag: bool
+    tiny: int
+    byte: int
+    short: int
+    word: int
+    long: int
+    huge: int
+    neg: int
+    ratio: float
+    precise: float
+    letter: str
+    name: str
+    blob: bytes
+    maybe: Optional[int]
+    values: List[int]
+    pair: Tuple[int, str]
+    table: Dict[str, int]
+    nothing: None
+
+    def encode(self, w: Writer) -> None:
+        w.boolean(self.flag)
+        w.sint(self.tiny, 8)
+        w.uint(self.byte, 8)
+        w.sint(self.short, 16)
+        w.uint(self.word, 32)
+        w.sint(self.long, 64)
+        w.uint(self.huge, 128)
+        w.sint(self.neg, 128)
+        w.f32(self.ratio)
+        w.f64(self.precise)
+        w.char(self.letter)
+        w.string(self.name)
+        w.byte_array(self.blob)
+        w.option(self.maybe, lambda v0: w.uint(v0, 16))
+        w.seq(self.values, lambda v0: w.sint(v0, 32))
+        w.tuple(self.pair, [lambda v0: w.uint(v0, 8), lambda v0: w.string(v0)])
+        w.map(self.table, lambda v0: w.string(v0), lambda v0: w.uint(v0, 64))
+        w.unit(self.nothing)
+
+    @staticmethod
+    def decode(r: Reader) -> Sample:
+        return Sample(
+            r.boolean(),
+            r.sint(8),
+            r.uint(8),
+            r.sint(16),
+            r.uint(32),
+            r.sint(64),
+            r.uint(128),
+            r.sint(128),
+            r.f32(),
+            r.f64(),
+            r.char(),
+            r.string(),
+            r.byte_array(),
+            r.option(lambda: r.uint(16)),
+            r.seq(lambda: r.sint(32)),
+            r.tuple([lambda: r.uint(8), lambda: r.string()]),
+            r.map(lambda: r.string(), lambda: r.uint(64)),
+            r.unit(),
+        )
+
+
+@dataclass
+class Scene:
+    shapes: List[Shape]
+    origin: Point
+    scale: Meters
+    marker: Marker
+
+    def encode(self, w: Writer) -> None:
+        w.seq(self.shapes, lambda v0: v0.encode(w))
+        self.origin.encode(w)
+        self.scale.encode(w)
+        self.marker.encode(w)
+
+    @staticmethod
+    def decode(r: Reader) -> Scene:
+        return Scene(
+            r.seq(lambda: Shape.decode(r)),
+            Point.decode(r),
+            Meters.decode(r),
+            Marker.decode(r),
+        )
+
+
+class Shape:
+    def encode(self, w: Writer) -> None:
+        raise NotImplementedError
+
+    @staticmethod
+    def decode(r: Reader) -> Shape:
+        tag = r.uint(32)
+        if tag == 0:
+            return ShapeEmpty()
+        if tag == 1:
+            return ShapeCircle(r.uint(32))
+        if tag == 2:
+            return ShapeRect(r.uint(32), r.uint(32))
+        if tag == 3:
+            return ShapePoly(r.uint(8), r.option(lambda: r.string()))
+        raise DecodeError(f"unknown variant {tag} of Shape")
+
+
+@dataclass
+class ShapeEmpty(Shape):
+    def encode(self, w: Writer) -> None:
+        w.uint(0, 32)
+
+
+@dataclass
+class ShapeCircle(Shape):
+    _0: int
+
+    def encode(self, w: Writer) -> None:
+        w.uint(1, 32)
+        w.uint(self._0, 32)
+
+
+@dataclass
+class ShapeRect(Shape):
+    _0: int
+    _1: int
+
+    def encode(self, w: Writer) -> None:
+        w.uint(2, 32)
+        w.uint(self._0, 32)
+        w.uint(self._1, 32)
+
+
+@dataclass
+class ShapePoly(Shape):
+    sides: int
+    name: Optional[str]
+
+    def encode(self, w: Writer) -> None:
+        w.uint(3, 32)
+        w.uint(self.sides, 8)
+        w.option(self.name, lambda v0: w.string(v0))
+
+
+class WireError:
+    def encode(self, w: Writer) -> None:
+        raise NotImplementedError
+
+    @staticmethod
+    def decode(r: Reader) -> WireError:
+        tag = r.uint(32)
+        if tag == 0:
+            return WireErrorFrameTooLong(FrameTooLong.decode(r))
+        if tag == 1:
+            return WireErrorFrameTooShort(FrameTooShort.decode(r))
+        if tag == 2:
+            return WireErrorDeserFailed()
+        if tag == 3:
+            return WireErrorSerFailed()
+        if tag == 4:
+            return WireErrorUnknownKey()
+        if tag == 5:
+            return WireErrorFailedToSpawn()
+        if tag == 6:
+            return WireErrorKeyTooSmall()
+        raise DecodeError(f"unknown variant {tag} of WireError")
+
+
+@dataclass
+class WireErrorFrameTooLong(WireError):
+    _0: FrameTooLong
+
+    def encode(self, w: Writer) -> None:
+        w.uint(0, 32)
+        self._0.encode(w)
+
+
+@dataclass
+class WireErrorFrameTooShort(WireError):
+    _0: FrameTooShort
+
+    def encode(self, w: Writer) -> None:
+        w.uint(1, 32)
+        self._0.encode(w)
+
+
+@dataclass
+class WireErrorDeserFailed(WireError):
+    def encode(self, w: Writer) -> None:
+        w.uint(2, 32)
+
+
+@dataclass
+class WireErrorSerFailed(WireError):
+    def encode(self, w: Writer) -> None:
+        w.uint(3, 32)
+
+
+@dataclass
+class WireErrorUnknownKey(WireError):
+    def encode(self, w: Writer) -> None:
+        w.uint(4, 32)
+
+
+@dataclass
+class WireErrorFailedToSpawn(WireError):
+    def encode(self, w: Writer) -> None:
+        w.uint(5, 32)
+
+
+@dataclass
+class WireErrorKeyTooSmall(WireError):
+    def encode(self, w: Writer) -> None:
+        w.uint(6, 32)
+
+
+# Errors
+
+ERROR_KEY = bytes.fromhex("35b333d568af659b")
+WIRE_ERROR: Codec[WireError] = Codec(lambda w, v: v.encode(w), WireError.decode)
+
+# Endpoints
+
+SAMPLE_ECHO: Endpoint[Sample, Sample] = Endpoint(
+    "sample/echo",
+    bytes.fromhex("96fdce3ce93651ad"),
+    bytes.fromhex("96fdce3ce93651ad"),
+    Codec(lambda w, v: v.encode(w), Sample.decode),
+    Codec(lambda w, v: v.encode(w), Sample.decode),
+)
+
+SCENE_AREA: Endpoint[Scene, float] = Endpoint(
+    "scene/area",
+    bytes.fromhex("f05b160ef2218c9e"),
+    bytes.fromhex("3cb33985312eac7a"),
+    Codec(lambda w, v: v.encode(w), Scene.decode),
+    Codec(lambda w, v: w.f64(v), lambda r: r.f64()),
+)
+
+# Topics to the server
+
+SCENE_ADD: Topic[Shape] = Topic(
+    "scene/add",
+    bytes.fromhex("8eb396d51582e48e"),
+    Codec(lambda w, v: v.encode(w), Shape.decode),
+)
+
+# Topics to the client
+
+SCENE_COUNT: Topic[int] = Topic(
+    "scene/count",
+    bytes.fromhex("4869dae7147f037b"),
+    Codec(lambda w, v: w.uint(v, 32), lambda r: r.uint(32)),
+)
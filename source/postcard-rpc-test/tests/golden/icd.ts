@@ -0,0 +1,735 @@
+// Generated by postcard-rpc, do not edit.
+
+/** The data does not match the schema */
+export class DecodeError extends Error {}
+
+/** Encodes values in the postcard wire format */
+export class Writer {
+  private bytes: number[] = [];
+
+  finish(): Uint8Array {
+    return Uint8Array.from(this.bytes);
+  }
+
+  varint(v: bigint): void {
+    while (v >= 0x80n) {
+      this.bytes.push(Number(v & 0x7fn) | 0x80);
+      v >>= 7n;
+    }
+    this.bytes.push(Number(v));
+  }
+
+  uint(v: number, bits: number): void {
+    if (!Number.isInteger(v) || v < 0 || v >= 2 ** bits) {
+      throw new RangeError(`${v} is not a u${bits}`);
+    }
+    if (bits === 8) {
+      this.bytes.push(v);
+    } else {
+      this.varint(BigInt(v));
+    }
+  }
+
+  sint(v: number, bits: number): void {
+    if (!Number.isInteger(v) || v < -(2 ** (bits - 1)) || v >= 2 ** (bits - 1)) {
+      throw new RangeError(`${v} is not an i${bits}`);
+    }
+    if (bits === 8) {
+      this.bytes.push(v & 0xff);
+    } else {
+      this.zigzag(BigInt(v));
+    }
+  }
+
+  ubig(v: bigint, bits: number): void {
+    if (v < 0n || v >= 1n << BigInt(bits)) {
+      throw new RangeError(`${v} is not a u${bits}`);
+    }
+    this.varint(v);
+  }
+
+  sbig(v: bigint, bits: number): void {
+    const half = 1n << BigInt(bits - 1);
+    if (v < -half || v >= half) {
+      throw new RangeError(`${v} is not an i${bits}`);
+    }
+    this.zigzag(v);
+  }
+
+  private zigzag(v: bigint): void {
+    this.varint(v >= 0n ? v << 1n : (-v << 1n) - 1n);
+  }
+
+  boolean(v: boolean): void {
+    this.bytes.push(v ? 1 : 0);
+  }
+
+  f32(v: number): void {
+    const view = new DataView(new ArrayBuffer(4));
+    view.setFloat32(0, v, true);
+    this.raw(new Uint8Array(view.buffer));
+  }
+
+  f64(v: number): void {
+    const view = new DataView(new ArrayBuffer(8));
+    view.setFloat64(0, v, true);
+    this.raw(new Uint8Array(view.buffer));
+  }
+
+  byteArray(v: Uint8Array): void {
+    this.varint(BigInt(v.length));
+    this.raw(v);
+  }
+
+  string(v: string): void {
+    this.byteArray(new TextEncoder().encode(v));
+  }
+
+  char(v: string): void {
+    if ([...v].length !== 1) {
+      throw new RangeError(`${JSON.stringify(v)} is not a single character`);
+    }
+    this.string(v);
+  }
+
+  unit(_v: null): void {}
+
+  option<T>(v: T | null, f: (v: T) => void): void {
+    if (v === null) {
+      this.bytes.push(0);
+    } else {
+      this.bytes.push(1);
+      f(v);
+    }
+  }
+
+  seq<T>(v: T[], f: (v: T) => void): void {
+    this.varint(BigInt(v.length));
+    for (const x of v) {
+      f(x);
+    }
+  }
+
+  map<K, V>(v: Map<K, V>, kf: (k: K) => void, vf: (v: V) => void): void {
+    this.varint(BigInt(v.size));
+    for (const [k, x] of v) {
+      kf(k);
+      vf(x);
+    }
+  }
+
+  tuple(v: readonly unknown[], fs: ((v: any) => void)[]): void {
+    if (v.length !== fs.length) {
+      throw new RangeError(`expected a tuple of ${fs.length} items`);
+    }
+    fs.forEach((f, i) => f(v[i]));
+  }
+
+  private raw(v: Uint8Array): void {
+    for (const b of v) {
+      this.bytes.push(b);
+    }
+  }
+}
+
+/** Decodes values in the postcard wire format */
+export class Reader {
+  private data: Uint8Array;
+  private pos = 0;
+
+  constructor(data: Uint8Array) {
+    this.data = data;
+  }
+
+  take(n: number): Uint8Array {
+    if (this.pos + n > this.data.length) {
+      throw new DecodeError("unexpected end of data");
+    }
+    const out = this.data.subarray(this.pos, this.pos + n);
+    this.pos += n;
+    return out;
+  }
+
+  varint(bits: number): bigint {
+    let v = 0n;
+    for (let i = 0; i < Math.ceil(bits / 7); i++) {
+      const b = this.take(1)[0];
+      v |= BigInt(b & 0x7f) << BigInt(7 * i);
+      if (b < 0x80) {
+        if (v >= 1n << BigInt(bits)) {
+          throw new DecodeError(`${v} does not fit in ${bits} bits`);
+        }
+        return v;
+      }
+    }
+    throw new DecodeError("varint is too long");
+  }
+
+  uint(bits: number): number {
+    if (bits === 8) {
+      return this.take(1)[0];
+    }
+    return Number(this.varint(bits));
+  }
+
+  sint(bits: number): number {
+    if (bits === 8) {
+      const v = this.take(1)[0];
+      return v >= 0x80 ? v - 0x100 : v;
+    }
+    return Number(this.sbig(bits));
+  }
+
+  ubig(bits: number): bigint {
+    return this.varint(bits);
+  }
+
+  sbig(bits: number): bigint {
+    const z = this.varint(bits);
+    return z & 1n ? -(z >> 1n) - 1n : z >> 1n;
+  }
+
+  boolean(): boolean {
+    const b = this.take(1)[0];
+    if (b > 1) {
+      throw new DecodeError(`${b} is not a bool`);
+    }
+    return b === 1;
+  }
+
+  f32(): number {
+    const b = this.take(4);
+    return new DataView(b.buffer, b.byteOffset, 4).getFloat32(0, true);
+  }
+
+  f64(): number {
+    const b = this.take(8);
+    return new DataView(b.buffer, b.byteOffset, 8).getFloat64(0, true);
+  }
+
+  byteArray(): Uint8Array {
+    return this.take(Number(this.varint(64))).slice();
+  }
+
+  string(): string {
+    const b = this.byteArray();
+    try {
+      return new TextDecoder("utf-8", { fatal: true }).decode(b);
+    } catch (e) {
+      throw new DecodeError(String(e));
+    }
+  }
+
+  char(): string {
+    const v = this.string();
+    if ([...v].length !== 1) {
+      throw new DecodeError(`${JSON.stringify(v)} is not a single character`);
+    }
+    return v;
+  }
+
+  unit(): null {
+    return null;
+  }
+
+  option<T>(f: () => T): T | null {
+    return this.boolean() ? f() : null;
+  }
+
+  seq<T>(f: () => T): T[] {
+    const n = Number(this.varint(64));
+    const out: T[] = [];
+    for (let i = 0; i < n; i++) {
+      out.push(f());
+    }
+    return out;
+  }
+
+  map<K, V>(kf: () => K, vf: () => V): Map<K, V> {
+    const n = Number(this.varint(64));
+    const out = new Map<K, V>();
+    for (let i = 0; i < n; i++) {
+      const k = kf();
+      out.set(k, vf());
+    }
+    return out;
+  }
+
+  tuple(fs: (() => unknown)[]): unknown[] {
+    return fs.map((f) => f());
+  }
+
+  finish(): void {
+    if (this.pos !== this.data.length) {
+      throw new DecodeError(`${this.data.length - this.pos} bytes left over`);
+    }
+  }
+}
+
+/** Encodes and decodes values of one type */
+export interface Codec<T> {
+  encode: (w: Writer, v: T) => void;
+  decode: (r: Reader) => T;
+}
+
+export function toBytes<T>(codec: Codec<T>, v: T): Uint8Array {
+  const w = new Writer();
+  codec.encode(w, v);
+  return w.finish();
+}
+
+export function fromBytes<T>(codec: Codec<T>, data: Uint8Array): T {
+  const r = new Reader(data);
+  const v = codec.decode(r);
+  r.finish();
+  return v;
+}
+
+/** An endpoint, with the keys and codecs of its request and response */
+export interface Endpoint<Req, Resp> {
+  path: string;
+  reqKey: Uint8Array;
+  respKey: Uint8Array;
+  req: Codec<Req>;
+  resp: Codec<Resp>;
+}
+
+/** A topic, with the key and codec of its messages */
+export interface Topic<T> {
+  path: string;
+  key: Uint8Array;
+  msg: Codec<T>;
+}
+
+/**
+ * The header of a frame
+ *
+ * The key has 1, 2, 4, or 8 bytes, the sequence number 1, 2, or 4 bytes.
+ */
+export interface VarHeader {
+  key: Uint8Array;
+  seqNo: number;
+  seqLen: 1 | 2 | 4;
+}
+
+const KEY_LENS = [1, 2, 4, 8];
+const SEQ_LENS = [1, 2, 4];
+
+export function encodeVarHeader(h: VarHeader): Uint8Array {
+  const keyBits = KEY_LENS.indexOf(h.key.length);
+  const seqBits = SEQ_LENS.indexOf(h.seqLen);
+  if (keyBits < 0 || seqBits < 0) {
+    throw new RangeError("invalid key or sequence number length");
+  }
+  const out = new Uint8Array(1 + h.key.length + h.seqLen);
+  out[0] = (keyBits << 6) | (seqBits << 4);
+  out.set(h.key, 1);
+  for (let i = 0; i < h.seqLen; i++) {
+    out[1 + h.key.length + i] = (h.seqNo >>> (8 * i)) & 0xff;
+  }
+  return out;
+}
+
+/** Split a frame into its header and body */
+export function decodeVarHeader(frame: Uint8Array): [VarHeader, Uint8Array] {
+  if (frame.length < 1 || (frame[0] & 0x0f) !== 0) {
+    throw new DecodeError("not a frame header");
+  }
+  const keyLen = KEY_LENS[frame[0] >> 6];
+  const seqLen = SEQ_LENS[(frame[0] >> 4) & 0x03];
+  if (seqLen === undefined) {
+    throw new DecodeError("invalid sequence number length");
+  }
+  const end = 1 + keyLen + seqLen;
+  if (frame.length < end) {
+    throw new DecodeError("unexpected end of data");
+  }
+  let seqNo = 0;
+  for (let i = 0; i < seqLen; i++) {
+    seqNo += frame[1 + keyLen + i] * 2 ** (8 * i);
+  }
+  const header = { key: frame.slice(1, 1 + keyLen), seqNo, seqLen: seqLen as 1 | 2 | 4 };
+  return [header, frame.subarray(end)];
+}
+
+/** Shrink an 8-byte key to 1, 2, or 4 bytes */
+export function shrinkKey(key: Uint8Array, keyLen: number): Uint8Array {
+  while (key.length > keyLen) {
+    const next = new Uint8Array(key.length / 2);
+    for (let i = 0; i < next.length; i++) {
+      next[i] = key[2 * i] ^ key[2 * i + 1];
+    }
+    key = next;
+  }
+  return key;
+}
+
+function sameKey(header: VarHeader, key: Uint8Array): boolean {
+  const short = shrinkKey(key, header.key.length);
+  return short.length === header.key.length && short.every((b, i) => b === header.key[i]);
+}
+
+function hex(key: Uint8Array): string {
+  return Array.from(key, (b) => b.toString(16).padStart(2, "0")).join("");
+}
+
+/** The server replied with an error instead of a response */
+export class WireErrorResponse extends Error {
+  readonly header: VarHeader;
+  readonly error: WireError;
+
+  constructor(header: VarHeader, error: WireError) {
+    super(`the server replied with ${JSON.stringify(error)}`);
+    this.header = header;
+    this.error = error;
+  }
+}
+
+/** Frame a request to an endpoint */
+export function request<Req, Resp>(
+  ep: Endpoint<Req, Resp>,
+  seqNo: number,
+  req: Req,
+  keyLen = 8,
+  seqLen: 1 | 2 | 4 = 4,
+): Uint8Array {
+  const header = encodeVarHeader({ key: shrinkKey(ep.reqKey, keyLen), seqNo, seqLen });
+  const body = toBytes(ep.req, req);
+  const out = new Uint8Array(header.length + body.length);
+  out.set(header);
+  out.set(body, header.length);
+  return out;
+}
+
+/**
+ * Decode the response of an endpoint
+ *
+ * Throws a WireErrorResponse if the server replied with an error.
+ */
+export function response<Req, Resp>(ep: Endpoint<Req, Resp>, frame: Uint8Array): [VarHeader, Resp] {
+  const [header, body] = decodeVarHeader(frame);
+  if (sameKey(header, ep.respKey)) {
+    return [header, fromBytes(ep.resp, body)];
+  }
+  if (sameKey(header, ERROR_KEY)) {
+    throw new WireErrorResponse(header, fromBytes(WIRE_ERROR, body));
+  }
+  throw new DecodeError(`unexpected key ${hex(header.key)}`);
+}
+
+/** Frame a message of a topic */
+export function publish<T>(
+  tp: Topic<T>,
+  seqNo: number,
+  msg: T,
+  keyLen = 8,
+  seqLen: 1 | 2 | 4 = 4,
+): Uint8Array {
+  const header = encodeVarHeader({ key: shrinkKey(tp.key, keyLen), seqNo, seqLen });
+  const body = toBytes(tp.msg, msg);
+  const out = new Uint8Array(header.length + body.length);
+  out.set(header);
+  out.set(body, header.length);
+  return out;
+}
+
+/** Decode a message of a topic */
+export function message<T>(tp: Topic<T>, frame: Uint8Array): [VarHeader, T] {
+  const [header, body] = decodeVarHeader(frame);
+  if (!sameKey(header, tp.key)) {
+    throw new DecodeError(`unexpected key ${hex(header.key)}`);
+  }
+  return [header, fromBytes(tp.msg, body)];
+}
+
+export interface FrameTooLong {
+  len: number;
+  max: number;
+}
+
+export function encodeFrameTooLong(w: Writer, v: FrameTooLong): void {
+  w.uint(v.len, 32);
+  w.uint(v.max, 32);
+}
+
+export function decodeFrameTooLong(r: Reader): FrameTooLong {
+  return {
+    len: r.uint(32),
+    max: r.uint(32),
+  };
+}
+
+export interface FrameTooShort {
+  len: number;
+}
+
+export function encodeFrameTooShort(w: Writer, v: FrameTooShort): void {
+  w.uint(v.len, 32);
+}
+
+export function decodeFrameTooShort(r: Reader): FrameTooShort {
+  return {
+    len: r.uint(32),
+  };
+}
+
+export type Marker = null;
+
+export function encodeMarker(w: Writer, v: Marker): void {
+  w.unit(v);
+}
+
+export function decodeMarker(r: Reader): Marker {
+  return r.unit();
+}
+
+export type Meters = number;
+
+export function encodeMeters(w: Writer, v: Meters): void {
+  w.f64(v);
+}
+
+export function decodeMeters(r: Reader): Meters {
+  return r.f64();
+}
+
+export type Point = [number, number];
+
+export function encodePoint(w: Writer, v: Point): void {
+  w.sint(v[0], 32);
+  w.sint(v[1], 32);
+}
+
+export function decodePoint(r: Reader): Point {
+  return [r.sint(32), r.sint(32)];
+}
+
+export interface Sample {
+  flag: boolean;
+  tiny: number;
+  byte: number;
+  short: number;
+  word: number;
+  long: bigint;
+  huge: bigint;
+  neg: bigint;
+  ratio: number;
+  precise: number;
+  letter: string;
+  name: string;
+  blob: Uint8Array;
+  maybe: number | null;
+  values: Array<number>;
+  pair: [number, string];
+  table: Map<string, bigint>;
+  nothing: null;
+}
+
+export function encodeSample(w: Writer, v: Sample): void {
+  w.boolean(v.flag);
+  w.sint(v.tiny, 8);
+  w.uint(v.byte, 8);
+  w.sint(v.short, 16);
+  w.uint(v.word, 32);
+  w.sbig(v.long, 64);
+  w.ubig(v.huge, 128);
+  w.sbig(v.neg, 128);
+  w.f32(v.ratio);
+  w.f64(v.precise);
+  w.char(v.letter);
+  w.string(v.name);
+  w.byteArray(v.blob);
+  w.option(v.maybe, (v0) => w.uint(v0, 16));
+  w.seq(v.values, (v0) => w.sint(v0, 32));
+  w.tuple(v.pair, [(v0) => w.uint(v0, 8), (v0) => w.string(v0)]);
+  w.map(v.table, (v0) => w.string(v0), (v0) => w.ubig(v0, 64));
+  w.unit(v.nothing);
+}
+
+export function decodeSample(r: Reader): Sample {
+  return {
+    flag: r.boolean(),
+    tiny: r.sint(8),
+    byte: r.uint(8),
+    short: r.sint(16),
+    word: r.uint(32),
+    long: r.sbig(64),
+    huge: r.ubig(128),
+    neg: r.sbig(128),
+    ratio: r.f32(),
+    precise: r.f64(),
+    letter: r.char(),
+    name: r.string(),
+    blob: r.byteArray(),
+    maybe: r.option(() => r.uint(16)),
+    values: r.seq(() => r.sint(32)),
+    pair: (r.tuple([() => r.uint(8), () => r.string()]) as [number, string]),
+    table: r.map(() => r.string(), () => r.ubig(64)),
+    nothing: r.unit(),
+  };
+}
+
+export interface Scene {
+  shapes: Array<Shape>;
+  origin: Point;
+  scale: Meters;
+  marker: Marker;
+}
+
+export function encodeScene(w: Writer, v: Scene): void {
+  w.seq(v.shapes, (v0) => encodeShape(w, v0));
+  encodePoint(w, v.origin);
+  encodeMeters(w, v.scale);
+  encodeMarker(w, v.marker);
+}
+
+export function decodeScene(r: Reader): Scene {
+  return {
+    shapes: r.seq(() => decodeShape(r)),
+    origin: decodePoint(r),
+    scale: decodeMeters(r),
+    marker: decodeMarker(r),
+  };
+}
+
+export type Shape =
+  | { tag: "Empty" }
+  | { tag: "Circle"; value: number }
+  | { tag: "Rect"; value: [number, number] }
+  | { tag: "Poly"; value: { sides: number; name: string | null } };
+
+export function encodeShape(w: Writer, v: Shape): void {
+  switch (v.tag) {
+    case "Empty":
+      w.uint(0, 32);
+      break;
+    case "Circle":
+      w.uint(1, 32);
+      w.uint(v.value, 32);
+      break;
+    case "Rect":
+      w.uint(2, 32);
+      w.uint(v.value[0], 32);
+      w.uint(v.value[1], 32);
+      break;
+    case "Poly":
+      w.uint(3, 32);
+      w.uint(v.value.sides, 8);
+      w.option(v.value.name, (v0) => w.string(v0));
+      break;
+  }
+}
+
+export function decodeShape(r: Reader): Shape {
+  const tag = r.uint(32);
+  switch (tag) {
+    case 0:
+      return { tag: "Empty" };
+    case 1:
+      return { tag: "Circle", value: r.uint(32) };
+    case 2:
+      return { tag: "Rect", value: [r.uint(32), r.uint(32)] };
+    case 3:
+      return { tag: "Poly", value: { sides: r.uint(8), name: r.option(() => r.string()) } };
+    default:
+      throw new DecodeError(`unknown variant ${tag} of Shape`);
+  }
+}
+
+export type WireError =
+  | { tag: "FrameTooLong"; value: FrameTooLong }
+  | { tag: "FrameTooShort"; value: FrameTooShort }
+  | { tag: "DeserFailed" }
+  | { tag: "SerFailed" }
+  | { tag: "UnknownKey" }
+  | { tag: "FailedToSpawn" }
+  | { tag: "KeyTooSmall" };
+
+export function encodeWireError(w: Writer, v: WireError): void {
+  switch (v.tag) {
+    case "FrameTooLong":
+      w.uint(0, 32);
+      encodeFrameTooLong(w, v.value);
+      break;
+    case "FrameTooShort":
+      w.uint(1, 32);
+      encodeFrameTooShort(w, v.value);
+      break;
+    case "DeserFailed":
+      w.uint(2, 32);
+      break;
+    case "SerFailed":
+      w.uint(3, 32);
+      break;
+    case "UnknownKey":
+      w.uint(4, 32);
+      break;
+    case "FailedToSpawn":
+      w.uint(5, 32);
+      break;
+    case "KeyTooSmall":
+      w.uint(6, 32);
+      break;
+  }
+}
+
+export function decodeWireError(r: Reader): WireError {
+  const tag = r.uint(32);
+  switch (tag) {
+    case 0:
+      return { tag: "FrameTooLong", value: decodeFrameTooLong(r) };
+    case 1:
+      return { tag: "FrameTooShort", value: decodeFrameTooShort(r) };
+    case 2:
+      return { tag: "DeserFailed" };
+    case 3:
+      return { tag: "SerFailed" };
+    case 4:
+      return { tag: "UnknownKey" };
+    case 5:
+      return { tag: "FailedToSpawn" };
+    case 6:
+      return { tag: "KeyTooSmall" };
+    default:
+      throw new DecodeError(`unknown variant ${tag} of WireError`);
+  }
+}
+
+// Errors
+
+export const ERROR_KEY = new Uint8Array([0x35, 0xb3, 0x33, 0xd5, 0x68, 0xaf, 0x65, 0x9b]);
+export const WIRE_ERROR: Codec<WireError> = { encode: encodeWireError, decode: decodeWireError };
+
+// Endpoints
+
+export const SAMPLE_ECHO: Endpoint<Sample, Sample> = {
+  path: "sample/echo",
+  reqKey: new Uint8Array([0x96, 0xfd, 0xce, 0x3c, 0xe9, 0x36, 0x51, 0xad]),
+  respKey: new Uint8Array([0x96, 0xfd, 0xce, 0x3c, 0xe9, 0x36, 0x51, 0xad]),
+  req: { encode: encodeSample, decode: decodeSample },
+  resp: { encode: encodeSample, decode: decodeSample },
+};
+
+export const SCENE_AREA: Endpoint<Scene, number> = {
+  path: "scene/area",
+  reqKey: new Uint8Array([0xf0, 0x5b, 0x16, 0x0e, 0xf2, 0x21, 0x8c, 0x9e]),
+  respKey: new Uint8Array([0x3c, 0xb3, 0x39, 0x85, 0x31, 0x2e, 0xac, 0x7a]),
+  req: { encode: encodeScene, decode: decodeScene },
+  resp: { encode: (w, v) => w.f64(v), decode: (r) => r.f64() },
+};
+
+// Topics to the server
+
+export const SCENE_ADD: Topic<Shape> = {
+  path: "scene/add",
+  key: new Uint8Array([0x8e, 0xb3, 0x96, 0xd5, 0x15, 0x82, 0xe4, 0x8e]),
+  msg: { encode: encodeShape, decode: decodeShape },
+};
+
+// Topics to the client
+
+export const SCENE_COUNT: Topic<number> = {
+  path: "scene/count",
+  key: new Uint8Array([0x48, 0x69, 0xda, 0xe7, 0x14, 0x7f, 0x03, 0x7b]),
+  msg: { encode: (w, v) => w.uint(v, 32), decode: (r) => r.uint(32) },
+};
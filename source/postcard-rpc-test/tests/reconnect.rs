@@ -0,0 +1,300 @@
+use core::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+use std::sync::{Arc, Mutex};
+
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    select,
+    sync::{mpsc, oneshot, Semaphore},
+    time::timeout,
+};
+
+use postcard_rpc::{
+    define_dispatch, endpoints,
+    header::{VarHeader, VarSeq, VarSeqKind},
+    host_client::{
+        test_channels as client, HostClient, HostClientConfig, HostErr, ReconnectBackoff,
+    },
+    server::{
+        impls::test_channels::{
+            dispatch_impl::{new_server, spawn_fn, Settings, WireSpawnImpl, WireTxImpl},
+            ChannelWireRx, ChannelWireSpawn, ChannelWireTx,
+        },
+        Dispatch, Sender, SpawnContext,
+    },
+    standard_icd::{Capabilities, WireError},
+    topics,
+};
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct Announcement(pub u32);
+
+endpoints! {
+    list = ENDPOINT_LIST;
+    | EndpointTy        | RequestTy     | ResponseTy    | Path          |
+    | ----------        | ---------     | ----------    | ----          |
+    | AnnounceEndpoint  | u32           | u32           | "announce"    |
+    | HangEndpoint      | ()            | ()            | "hang"        |
+}
+
+topics! {
+    list = TOPICS_IN_LIST;
+    direction = postcard_rpc::TopicDirection::ToServer;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+}
+
+topics! {
+    list = TOPICS_OUT_LIST;
+    direction = postcard_rpc::TopicDirection::ToClient;
+    | TopicTy           | MessageTy     | Path              |
+    | ----------        | ---------     | ----              |
+    | AnnounceTopic     | Announcement  | "announcements"   |
+}
+
+pub struct TestContext;
+
+impl SpawnContext for TestContext {
+    type SpawnCtxt = ();
+
+    fn spawn_ctxt(&mut self) -> Self::SpawnCtxt {}
+}
+
+define_dispatch! {
+    app: ReconnectDispatcher;
+    spawn_fn: spawn_fn;
+    tx_impl: WireTxImpl;
+    spawn_impl: WireSpawnImpl;
+    context: TestContext;
+    standard: [capabilities];
+
+    endpoints: {
+        list: crate::ENDPOINT_LIST;
+
+        | EndpointTy        | kind      | handler               |
+        | ----------        | ----      | -------               |
+        | AnnounceEndpoint  | spawn     | announce_handler      |
+        | HangEndpoint      | spawn     | hang_handler          |
+    };
+    topics_in: {
+        list: crate::TOPICS_IN_LIST;
+
+        | TopicTy           | kind      | handler               |
+        | ----------        | ----      | -------               |
+    };
+    topics_out: {
+        list: crate::TOPICS_OUT_LIST;
+    };
+}
+
+async fn announce_handler(_context: (), header: VarHeader, body: u32, out: Sender<ChannelWireTx>) {
+    let _ = out
+        .publish::<AnnounceTopic>(VarSeq::Seq4(body), &Announcement(body))
+        .await;
+    let _ = out.reply::<AnnounceEndpoint>(header.seq_no, &body).await;
+}
+
+async fn hang_handler(_context: (), _header: VarHeader, _body: (), _out: Sender<ChannelWireTx>) {
+    core::future::pending::<()>().await;
+}
+
+/// Forward frames between a client and a server until unplugged
+async fn cable(
+    mut client_rx: mpsc::Receiver<Vec<u8>>,
+    client_tx: mpsc::Sender<Vec<u8>>,
+    mut server_rx: mpsc::Receiver<Vec<u8>>,
+    server_tx: mpsc::Sender<Vec<u8>>,
+    mut unplug: oneshot::Receiver<()>,
+) {
+    loop {
+        select! {
+            _ = &mut unplug => return,
+            Some(msg) = client_rx.recv() => { let _ = server_tx.send(msg).await; }
+            Some(msg) = server_rx.recv() => { let _ = client_tx.send(msg).await; }
+            else => return,
+        }
+    }
+}
+
+/// A fake "device", which is created fresh for each connection
+struct Device {
+    /// Number of connection attempts
+    attempts: AtomicUsize,
+    /// Connection attempts after the first wait for a permit
+    permits: Semaphore,
+    /// Unplugs the currently connected cable
+    unplug: Mutex<Option<oneshot::Sender<()>>>,
+}
+
+impl Device {
+    async fn connect(&self) -> Result<HostClient<WireError>, &'static str> {
+        if self.attempts.fetch_add(1, Ordering::Relaxed) == 0 {
+            return Err("device not found");
+        }
+        self.permits.acquire().await.unwrap().forget();
+
+        let (client_tx, cable_rx) = mpsc::channel(16);
+        let (cable_tx, client_rx) = mpsc::channel(16);
+        let (to_server, server_rx) = mpsc::channel(16);
+        let (server_tx, from_server) = mpsc::channel(16);
+
+        let app = ReconnectDispatcher::new(TestContext, ChannelWireSpawn {});
+        let kkind = app.min_key_len();
+        let mut server = new_server(
+            app,
+            Settings {
+                tx: ChannelWireTx::new(server_tx),
+                rx: ChannelWireRx::new(server_rx),
+                buf: 1024,
+                kkind,
+            },
+        );
+        tokio::task::spawn(async move {
+            server.run().await;
+        });
+
+        let (unplug_tx, unplug_rx) = oneshot::channel();
+        *self.unplug.lock().unwrap() = Some(unplug_tx);
+        tokio::task::spawn(cable(cable_rx, cable_tx, from_server, to_server, unplug_rx));
+
+        Ok(client::new_from_channels(
+            client_tx,
+            client_rx,
+            VarSeqKind::Seq1,
+        ))
+    }
+
+    fn unplug(&self) {
+        let _ = self.unplug.lock().unwrap().take().unwrap().send(());
+    }
+}
+
+#[tokio::test]
+async fn reconnecting_client() {
+    let device = Arc::new(Device {
+        attempts: AtomicUsize::new(0),
+        permits: Semaphore::new(1),
+        unplug: Mutex::new(None),
+    });
+
+    let cli = HostClient::<WireError>::new_reconnecting(
+        {
+            let device = device.clone();
+            move || {
+                let device = device.clone();
+                async move { device.connect().await }
+            }
+        },
+        &HostClientConfig::default().with_seq_kind(VarSeqKind::Seq1),
+        ReconnectBackoff {
+            initial: Duration::from_millis(10),
+            max: Duration::from_millis(20),
+        },
+    );
+
+    // The first attempt fails, the second one succeeds after a backoff
+    timeout(Duration::from_secs(1), cli.wait_connected())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(cli.is_connected());
+    assert_eq!(device.attempts.load(Ordering::Relaxed), 2);
+
+    let mut sub = cli.subscribe_multi::<AnnounceTopic>(8).await.unwrap();
+    assert_eq!(cli.send_resp::<AnnounceEndpoint>(&1).await.unwrap(), 1);
+    assert_eq!(sub.recv().await.unwrap(), Announcement(1));
+
+    // Requests in-flight when the connection is lost fail
+    let hang = tokio::task::spawn({
+        let cli = cli.clone();
+        async move { cli.send_resp::<HangEndpoint>(&()).await }
+    });
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    device.unplug();
+    let res = timeout(Duration::from_secs(1), hang)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(res, Err(HostErr::Reconnecting));
+
+    // New requests fail while reconnecting, without closing the client
+    assert!(!cli.is_connected());
+    let res = cli.send_resp::<AnnounceEndpoint>(&2).await;
+    assert_eq!(res, Err(HostErr::Reconnecting));
+    assert!(!cli.is_closed());
+
+    // Once the device is back, the subscription picks up where it left off
+    device.permits.add_permits(1);
+    timeout(Duration::from_secs(1), cli.wait_connected())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(cli.send_resp::<AnnounceEndpoint>(&3).await.unwrap(), 3);
+    assert_eq!(sub.recv().await.unwrap(), Announcement(3));
+    assert_eq!(device.attempts.load(Ordering::Relaxed), 3);
+
+    // Closing the client stops reconnecting
+    cli.close();
+    device.unplug();
+    assert!(cli.wait_connected().await.is_err());
+}
+
+#[tokio::test]
+async fn capabilities_after_reconnect() {
+    let device = Arc::new(Device {
+        attempts: AtomicUsize::new(1),
+        permits: Semaphore::new(1),
+        unplug: Mutex::new(None),
+    });
+
+    let cli = HostClient::<WireError>::new_reconnecting(
+        {
+            let device = device.clone();
+            move || {
+                let device = device.clone();
+                async move { device.connect().await }
+            }
+        },
+        &HostClientConfig::default()
+            .with_seq_kind(VarSeqKind::Seq1)
+            .with_query_capabilities(true),
+        ReconnectBackoff::default(),
+    );
+    let queried = || async {
+        timeout(Duration::from_secs(1), async {
+            loop {
+                if let Some(caps) = cli.capabilities() {
+                    return caps;
+                }
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .unwrap()
+    };
+
+    let caps = queried().await;
+    assert_eq!(caps.protocol_version, Capabilities::PROTOCOL_VERSION);
+
+    // The capabilities are forgotten when the connection is lost...
+    device.unplug();
+    timeout(Duration::from_secs(1), async {
+        while cli.is_connected() {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(cli.capabilities(), None);
+
+    // ...and queried again after reconnecting
+    device.permits.add_permits(1);
+    assert_eq!(queried().await, caps);
+    assert_eq!(device.attempts.load(Ordering::Relaxed), 3);
+    assert_eq!(cli.send_resp::<AnnounceEndpoint>(&1).await.unwrap(), 1);
+
+    cli.close();
+}
@@ -0,0 +1,349 @@
+use std::{
+    future::Future,
+    sync::atomic::{AtomicU32, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use postcard_rpc::{
+    capture::{Capture, CaptureReader, CapturedFrame, Direction},
+    define_dispatch, endpoints,
+    header::{VarHeader, VarKey, VarSeq, VarSeqKind},
+    host_client::HostClient,
+    server::{
+        impls::test_channels::{
+            dispatch_impl::WireSpawnImpl, ChannelWireRx, ChannelWireSpawn, ChannelWireTx,
+        },
+        Dispatch, Sender, Server, SpawnContext,
+    },
+    standard_icd::{WireError, ERROR_PATH},
+    test_utils::replay::{Divergence, Replay},
+    topics, Endpoint,
+};
+use postcard_rpc_test::client_wire::{ClientRx, ClientSpawn, ClientTx};
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct Stamped {
+    pub value: u32,
+    pub nanos: u32,
+}
+
+endpoints! {
+    list = ENDPOINT_LIST;
+    | EndpointTy        | RequestTy     | ResponseTy    | Path          |
+    | ----------        | ---------     | ----------    | ----          |
+    | DoubleEndpoint    | u32           | u32           | "double"      |
+    | StampEndpoint     | u32           | Stamped       | "stamp"       |
+}
+
+topics! {
+    list = TOPICS_IN_LIST;
+    direction = postcard_rpc::TopicDirection::ToServer;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+    | TickTopic     | u32           | "tick"    |
+}
+
+topics! {
+    list = TOPICS_OUT_LIST;
+    direction = postcard_rpc::TopicDirection::ToClient;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+    | TockTopic     | u32           | "tock"    |
+}
+
+pub struct TestContext {
+    factor: u32,
+}
+
+impl SpawnContext for TestContext {
+    type SpawnCtxt = ();
+
+    fn spawn_ctxt(&mut self) -> Self::SpawnCtxt {}
+}
+
+define_dispatch! {
+    app: ReplayDispatcher;
+    spawn_fn: spawn_fn;
+    tx_impl: ChannelWireTx;
+    spawn_impl: WireSpawnImpl;
+    context: TestContext;
+
+    endpoints: {
+        list: crate::ENDPOINT_LIST;
+
+        | EndpointTy        | kind      | handler               |
+        | ----------        | ----      | -------               |
+        | DoubleEndpoint    | blocking  | double_handler        |
+        | StampEndpoint     | blocking  | stamp_handler         |
+    };
+    topics_in: {
+        list: crate::TOPICS_IN_LIST;
+
+        | TopicTy           | kind      | handler               |
+        | ----------        | ----      | -------               |
+        | TickTopic         | async     | tick_handler          |
+    };
+    topics_out: {
+        list: crate::TOPICS_OUT_LIST;
+    };
+}
+
+fn double_handler(context: &mut TestContext, _header: VarHeader, body: u32) -> u32 {
+    body * context.factor
+}
+
+fn stamp_handler(_context: &mut TestContext, _header: VarHeader, body: u32) -> Stamped {
+    Stamped {
+        value: body + 1,
+        nanos: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .subsec_nanos(),
+    }
+}
+
+/// The sequence number of the next published message, like a device counting
+/// messages since it booted, so it differs between runs
+static PUBLISHED: AtomicU32 = AtomicU32::new(0);
+
+async fn tick_handler(
+    context: &mut TestContext,
+    _header: VarHeader,
+    body: u32,
+    out: &Sender<ChannelWireTx>,
+) {
+    let seq_no = VarSeq::Seq4(PUBLISHED.fetch_add(1, Ordering::Relaxed));
+    let _ = out
+        .publish::<TockTopic>(seq_no, &(body * context.factor))
+        .await;
+}
+
+fn app(factor: u32) -> ReplayDispatcher {
+    ReplayDispatcher::new(TestContext { factor }, ChannelWireSpawn {})
+}
+
+/// Record the usual session with a tapped client
+async fn record(name: &str) -> Vec<CapturedFrame> {
+    let frames = record_session(name, |cli| async move {
+        assert_eq!(cli.send_resp::<DoubleEndpoint>(&21).await.unwrap(), 42);
+        assert_eq!(cli.send_resp::<StampEndpoint>(&1).await.unwrap().value, 2);
+        assert_eq!(cli.send_resp::<DoubleEndpoint>(&5).await.unwrap(), 10);
+    })
+    .await;
+    assert_eq!(frames.len(), 6);
+    frames
+}
+
+/// Record a session with a tapped client
+async fn record_session<F, Fut>(name: &str, session: F) -> Vec<CapturedFrame>
+where
+    F: FnOnce(HostClient<WireError>) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let path = std::env::temp_dir().join(format!(
+        "postcard-rpc-replay-{name}-{}.pcapng",
+        std::process::id()
+    ));
+
+    let (client_tx, server_rx) = mpsc::channel(16);
+    let (server_tx, client_rx) = mpsc::channel(16);
+    let app = app(2);
+    let kkind = app.min_key_len();
+    let mut server = Server::new(
+        ChannelWireTx::new(server_tx),
+        ChannelWireRx::new(server_rx),
+        vec![0u8; 256].into_boxed_slice(),
+        app,
+        kkind,
+    );
+    tokio::task::spawn(async move {
+        server.run().await;
+    });
+
+    let capture = Capture::create(&path).unwrap();
+    let cli: HostClient<WireError> = HostClient::new_with_wire(
+        capture.tap(ClientTx(client_tx)),
+        capture.tap(ClientRx(client_rx)),
+        ClientSpawn,
+        VarSeqKind::Seq4,
+        ERROR_PATH,
+        8,
+    );
+    session(cli.clone()).await;
+    cli.close();
+    capture.flush().unwrap();
+
+    let frames = CaptureReader::open(&path)
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    std::fs::remove_file(path).unwrap();
+    frames
+}
+
+fn unstamped(frames: &[CapturedFrame]) -> Replay {
+    Replay::from_client_capture(frames.iter().cloned())
+        .mask_response::<StampEndpoint>(|resp| resp.nanos = 0)
+}
+
+#[tokio::test]
+async fn replay_matches() {
+    let frames = record("matches").await;
+
+    let report = unstamped(&frames).run(app(2), app(2).device_map).await;
+    assert!(report.is_match(), "{report}");
+    assert_eq!((report.requests, report.responses), (3, 3));
+}
+
+#[tokio::test]
+async fn replay_nondeterministic() {
+    let frames = record("nondeterministic").await;
+
+    // Wait for the clock to move on
+    tokio::time::sleep(Duration::from_millis(2)).await;
+    let report = Replay::from_client_capture(frames)
+        .run(app(2), app(2).device_map)
+        .await;
+    assert_eq!(report.divergences.len(), 1, "{report}");
+    let Divergence::Mismatch { path, header, .. } = &report.divergences[0] else {
+        panic!("{report}");
+    };
+    assert_eq!(*path, Some("stamp"));
+    assert_eq!(header.key, VarKey::Key8(StampEndpoint::RESP_KEY));
+}
+
+#[tokio::test]
+async fn replay_divergences() {
+    let mut frames = record("divergences").await;
+
+    // Both responses of "double" change with the context
+    let report = unstamped(&frames).run(app(3), app(3).device_map).await;
+    let paths = report
+        .divergences
+        .iter()
+        .map(|d| match d {
+            Divergence::Mismatch {
+                path,
+                expected,
+                actual,
+                ..
+            } => (*path, expected.clone(), actual.clone()),
+            _ => panic!("{report}"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        [
+            (Some("double"), vec![42], vec![63]),
+            (Some("double"), vec![10], vec![15]),
+        ]
+    );
+    assert!(report.to_string().contains("different frame for 'double'"));
+
+    // A response the server never sends
+    let mut extra = frames[1].clone();
+    extra.frame.header.seq_no = VarSeq::Seq4(1000);
+    frames.push(extra);
+    let report = unstamped(&frames)
+        .timeout(Duration::from_millis(50))
+        .run(app(2), app(2).device_map)
+        .await;
+    assert!(
+        matches!(
+            report.divergences[..],
+            [Divergence::Missing {
+                path: Some("double"),
+                ..
+            }]
+        ),
+        "{report}"
+    );
+
+    // A request whose response was not recorded
+    let frames = &frames[..1];
+    assert_eq!(frames[0].direction, Direction::Sent);
+    let report = unstamped(frames).run(app(2), app(2).device_map).await;
+    let [Divergence::Unexpected {
+        path: Some("double"),
+        actual,
+        ..
+    }] = &report.divergences[..]
+    else {
+        panic!("{report}");
+    };
+    assert_eq!(
+        postcard::from_bytes::<<DoubleEndpoint as Endpoint>::Response>(actual).unwrap(),
+        42
+    );
+}
+
+#[tokio::test]
+async fn replay_topic_seq() {
+    let frames = record_session("topic-seq", |cli| async move {
+        cli.publish::<TickTopic>(VarSeq::Seq4(0), &1).await.unwrap();
+        cli.publish::<TickTopic>(VarSeq::Seq4(1), &2).await.unwrap();
+        // Handled after the published messages, so their replies are recorded
+        assert_eq!(cli.send_resp::<DoubleEndpoint>(&3).await.unwrap(), 6);
+    })
+    .await;
+    assert_eq!(frames.len(), 6);
+
+    // The counter moved on, so the sequence numbers differ
+    let report = Replay::from_client_capture(frames.iter().cloned())
+        .timeout(Duration::from_millis(50))
+        .run(app(2), app(2).device_map)
+        .await;
+    assert!(
+        report.divergences.iter().all(|d| matches!(
+            d,
+            Divergence::Missing {
+                path: Some("tock"),
+                ..
+            } | Divergence::Unexpected {
+                path: Some("tock"),
+                ..
+            }
+        )),
+        "{report}"
+    );
+    assert_eq!(report.divergences.len(), 4, "{report}");
+
+    // Matched in order instead
+    let report = Replay::from_client_capture(frames.iter().cloned())
+        .ignore_topic_seq::<TockTopic>()
+        .run(app(2), app(2).device_map)
+        .await;
+    assert!(report.is_match(), "{report}");
+    assert_eq!((report.requests, report.responses), (3, 3));
+
+    // The bodies are still compared, in order
+    let report = Replay::from_client_capture(frames)
+        .ignore_topic_seq::<TockTopic>()
+        .run(app(3), app(3).device_map)
+        .await;
+    let bodies = report
+        .divergences
+        .iter()
+        .map(|d| match d {
+            Divergence::Mismatch {
+                path,
+                expected,
+                actual,
+                ..
+            } => (*path, expected.clone(), actual.clone()),
+            _ => panic!("{report}"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        bodies,
+        [
+            (Some("tock"), vec![2], vec![3]),
+            (Some("tock"), vec![4], vec![6]),
+            (Some("double"), vec![6], vec![9]),
+        ]
+    );
+}
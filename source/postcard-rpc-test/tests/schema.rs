@@ -0,0 +1,304 @@
+use core::time::Duration;
+use std::collections::HashSet;
+
+use tokio::{sync::mpsc, time::timeout};
+
+use postcard_rpc::{
+    define_dispatch, endpoints,
+    header::{VarHeader, VarKey, VarSeq},
+    host_client::{HostClient, ItemDiff, KeyMismatch, SchemaCache},
+    server::{
+        impls::test_channels::{
+            dispatch_impl::{WireSpawnImpl, WireTxImpl},
+            ChannelWireSpawn,
+        },
+        SpawnContext, TopicFilter,
+    },
+    standard_icd::{
+        GetSchemaItemTopic, GetSchemaPageEndpoint, SchemaDigestEndpoint, WireError,
+        STANDARD_ICD_OPTIONAL_ENDPOINTS, STANDARD_ICD_OPTIONAL_TOPICS_IN,
+        STANDARD_ICD_OPTIONAL_TOPICS_OUT,
+    },
+    topics, DeviceMap, Endpoint, Topic,
+};
+use postcard_rpc_test::fixture::Fixture;
+
+endpoints! {
+    list = ENDPOINT_LIST;
+    | EndpointTy        | RequestTy     | ResponseTy    | Path          |
+    | ----------        | ---------     | ----------    | ----          |
+    | DoubleEndpoint    | u32           | u64           | "double"      |
+    | NegateEndpoint    | i16           | i32           | "negate"      |
+}
+
+topics! {
+    list = TOPICS_IN_LIST;
+    direction = postcard_rpc::TopicDirection::ToServer;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+    | InTopic       | u8            | "in"      |
+}
+
+topics! {
+    list = TOPICS_OUT_LIST;
+    direction = postcard_rpc::TopicDirection::ToClient;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+    | OutTopic      | i8            | "out"     |
+}
+
+/// The ICD of a host that is out of date with the device
+mod host {
+    use postcard_rpc::{endpoints, topics};
+
+    endpoints! {
+        list = ENDPOINT_LIST;
+        | EndpointTy        | RequestTy     | ResponseTy    | Path          |
+        | ----------        | ---------     | ----------    | ----          |
+        | DoubleEndpoint    | u32           | u64           | "double"      |
+        | NegateEndpoint    | i16           | i16           | "negate"      |
+        | HalveEndpoint     | u32           | u32           | "halve"       |
+    }
+
+    topics! {
+        list = TOPICS_IN_LIST;
+        direction = postcard_rpc::TopicDirection::ToServer;
+        | TopicTy       | MessageTy     | Path      |
+        | ----------    | ---------     | ----      |
+        | InTopic       | u8            | "in"      |
+    }
+
+    topics! {
+        list = TOPICS_OUT_LIST;
+        direction = postcard_rpc::TopicDirection::ToClient;
+        | TopicTy       | MessageTy     | Path      |
+        | ----------    | ---------     | ----      |
+    }
+}
+
+pub struct TestContext;
+
+impl SpawnContext for TestContext {
+    type SpawnCtxt = ();
+
+    fn spawn_ctxt(&mut self) -> Self::SpawnCtxt {}
+}
+
+define_dispatch! {
+    app: SchemaDispatcher;
+    spawn_fn: spawn_fn;
+    tx_impl: WireTxImpl;
+    spawn_impl: WireSpawnImpl;
+    context: TestContext;
+    standard: [schema_pages, schema_digest];
+
+    endpoints: {
+        list: crate::ENDPOINT_LIST;
+
+        | EndpointTy        | kind      | handler               |
+        | ----------        | ----      | -------               |
+        | DoubleEndpoint    | blocking  | double_handler        |
+        | NegateEndpoint    | blocking  | negate_handler        |
+    };
+    topics_in: {
+        list: crate::TOPICS_IN_LIST;
+
+        | TopicTy           | kind      | handler               |
+        | ----------        | ----      | -------               |
+    };
+    topics_out: {
+        list: crate::TOPICS_OUT_LIST;
+    };
+}
+
+fn double_handler(_context: &mut TestContext, _header: VarHeader, body: u32) -> u64 {
+    u64::from(body) * 2
+}
+
+fn negate_handler(_context: &mut TestContext, _header: VarHeader, body: i16) -> i32 {
+    -i32::from(body)
+}
+
+/// Forwards frames to the client, dropping the first schema item with each index in `drop`
+async fn lossy(
+    mut rx: mpsc::Receiver<Vec<u8>>,
+    tx: mpsc::Sender<Vec<u8>>,
+    mut drop: HashSet<u32>,
+    dropped: mpsc::UnboundedSender<u32>,
+) {
+    let item_key = VarKey::Key8(GetSchemaItemTopic::TOPIC_KEY);
+    while let Some(frame) = rx.recv().await {
+        let (hdr, _) = VarHeader::take_from_slice(&frame).unwrap();
+        if let (true, VarSeq::Seq4(index)) = (hdr.key == item_key, hdr.seq_no) {
+            if drop.remove(&index) {
+                let _ = dropped.send(index);
+                continue;
+            }
+        }
+        if tx.send(frame).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Start a server whose frames pass through a relay dropping the chosen schema items
+fn setup(
+    drop: impl FnOnce(&DeviceMap) -> Vec<u32>,
+) -> (
+    HostClient<WireError>,
+    &'static DeviceMap,
+    mpsc::UnboundedReceiver<u32>,
+) {
+    let (dropped_tx, dropped_rx) = mpsc::unbounded_channel();
+
+    // Schema items are sent even if all other topics are disabled
+    static FILTER: TopicFilter = TopicFilter::new_disabled();
+    let app = SchemaDispatcher::new(TestContext, ChannelWireSpawn {});
+    let device_map = app.device_map;
+    let drop = drop(device_map).into_iter().collect();
+    let cli = Fixture::new(app)
+        .server(|server| server.with_topic_filter(&FILTER))
+        .relay_to_client(move |rx, tx| lossy(rx, tx, drop, dropped_tx))
+        .connect();
+    (cli, device_map, dropped_rx)
+}
+
+fn assert_matches(schema: &postcard_rpc::host_client::SchemaReport, device_map: &DeviceMap) {
+    assert_eq!(schema.endpoints.len(), device_map.endpoints.len());
+    assert_eq!(schema.topics_in.len(), device_map.topics_in.len());
+    assert_eq!(schema.topics_out.len(), device_map.topics_out.len());
+    let double = schema
+        .endpoints
+        .iter()
+        .find(|ep| ep.path == "double")
+        .unwrap();
+    assert_eq!(double.req_ty.to_string(), "u32");
+    assert_eq!(double.resp_ty.to_string(), "u64");
+}
+
+#[tokio::test]
+async fn paged() {
+    let (cli, device_map, _) = setup(|_| vec![]);
+
+    // Finishes as soon as all items arrived, instead of waiting for silence
+    let schema = timeout(Duration::from_millis(250), cli.get_schema_report())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_matches(&schema, device_map);
+}
+
+#[tokio::test]
+async fn lost_items_are_requested_again() {
+    // The first type, the first endpoint, and the last topic
+    let (cli, device_map, mut dropped) = setup(|map| {
+        let types = map.types.len() as u32;
+        let total =
+            types + (map.endpoints.len() + map.topics_out.len() + map.topics_in.len()) as u32;
+        vec![0, types, total - 1]
+    });
+
+    let schema = timeout(Duration::from_millis(250), cli.get_schema_report())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_matches(&schema, device_map);
+    let mut n = 0;
+    while dropped.try_recv().is_ok() {
+        n += 1;
+    }
+    assert_eq!(n, 3);
+}
+
+#[tokio::test]
+async fn cached() {
+    let (cli, device_map, _) = setup(|_| vec![]);
+    let digest = cli.get_schema_digest().await.unwrap();
+    assert_eq!(digest, device_map.digest());
+
+    let dir = std::env::temp_dir().join(format!("postcard-rpc-schema-{}", std::process::id()));
+    let cache = SchemaCache::new(&dir);
+    assert!(cache.load(digest).is_none());
+
+    // Requested and stored on the first connect
+    let schema = cli.get_schema_report_cached(&cache).await.unwrap();
+    assert_matches(&schema, device_map);
+    assert_eq!(cache.load(digest), Some(schema.clone()));
+
+    // Later, only the digest is requested
+    let mut stale = schema.clone();
+    stale.endpoints.clear();
+    cache.store(digest, &stale).unwrap();
+    let schema = cli.get_schema_report_cached(&cache).await.unwrap();
+    assert_eq!(schema, stale);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn verify() {
+    let (cli, _, _) = setup(|_| vec![]);
+
+    let diff = cli
+        .verify(&ENDPOINT_LIST, &TOPICS_IN_LIST, &TOPICS_OUT_LIST)
+        .await
+        .unwrap();
+    assert!(diff.is_empty());
+
+    let diff = cli
+        .verify(
+            &host::ENDPOINT_LIST,
+            &host::TOPICS_IN_LIST,
+            &host::TOPICS_OUT_LIST,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        diff.endpoints,
+        ItemDiff {
+            missing: vec!["halve".into()],
+            extra: vec![],
+            mismatched: vec![KeyMismatch {
+                path: "negate".into(),
+                expected: host::NegateEndpoint::RESP_KEY,
+                found: NegateEndpoint::RESP_KEY,
+            }],
+        }
+    );
+    assert!(diff.topics_in.is_empty());
+    assert_eq!(diff.topics_out.extra, vec!["out".to_string()]);
+    assert!(!diff.is_compatible());
+    assert!(diff.to_string().contains("missing endpoint 'halve'\n"));
+}
+
+#[test]
+fn standard_items() {
+    let map = SchemaDispatcher::new(TestContext, ChannelWireSpawn {}).device_map;
+    // Of the optional parts of the standard ICD, only those opted in to are used
+    let used = |path: &str| {
+        let paths = [
+            GetSchemaPageEndpoint::PATH,
+            GetSchemaItemTopic::PATH,
+            SchemaDigestEndpoint::PATH,
+        ];
+        paths.contains(&path)
+    };
+    for ep in STANDARD_ICD_OPTIONAL_ENDPOINTS
+        .iter()
+        .flat_map(|m| m.endpoints)
+    {
+        assert_eq!(map.endpoints.contains(ep), used(ep.0), "{}", ep.0);
+    }
+    for tp in STANDARD_ICD_OPTIONAL_TOPICS_IN
+        .iter()
+        .flat_map(|m| m.topics)
+    {
+        assert_eq!(map.topics_in.contains(tp), used(tp.0), "{}", tp.0);
+    }
+    for tp in STANDARD_ICD_OPTIONAL_TOPICS_OUT
+        .iter()
+        .flat_map(|m| m.topics)
+    {
+        assert_eq!(map.topics_out.contains(tp), used(tp.0), "{}", tp.0);
+    }
+}
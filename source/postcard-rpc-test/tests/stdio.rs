@@ -0,0 +1,66 @@
+use std::process::Stdio;
+
+use tokio::process::Command;
+
+use postcard_rpc::{
+    header::VarSeqKind,
+    host_client::HostClient,
+    standard_icd::{LoggingTopic, PingEndpoint, WireError, ERROR_PATH},
+};
+use postcard_rpc_test::sim_icd::{AddEndpoint, AddReq, ExitEndpoint};
+
+fn sim_command() -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_stdio-sim"));
+    // Keep the test output clean, the simulator's logs go nowhere
+    command.stderr(Stdio::null());
+    command
+}
+
+#[tokio::test]
+async fn stdio_host_client() {
+    let client =
+        HostClient::<WireError>::spawn_stdio(sim_command(), ERROR_PATH, 8, VarSeqKind::Seq2)
+            .unwrap();
+    let mut logs = client.subscribe_multi::<LoggingTopic>(8).await.unwrap();
+
+    for i in 0..16u32 {
+        let resp = client
+            .send_resp::<AddEndpoint>(&AddReq { a: i, b: 0x0A00 })
+            .await;
+        assert_eq!(resp.unwrap(), i + 0x0A00);
+        assert_eq!(logs.recv().await.unwrap(), format!("{i} + 2560"));
+    }
+    let resp = client.send_resp::<PingEndpoint>(&0xACAB_1234).await;
+    assert_eq!(resp.unwrap(), 0xACAB_1234);
+
+    // Closing the client closes the simulator's stdin
+    client.close();
+    client.wait_closed().await;
+}
+
+#[tokio::test]
+async fn stdio_child_exit() {
+    let client =
+        HostClient::<WireError>::spawn_stdio(sim_command(), ERROR_PATH, 8, VarSeqKind::Seq1)
+            .unwrap();
+    let resp = client
+        .send_resp::<AddEndpoint>(&AddReq { a: 1, b: 2 })
+        .await;
+    assert_eq!(resp.unwrap(), 3);
+
+    // The simulator exits without replying, which closes the client
+    let resp = client.send_resp::<ExitEndpoint>(&()).await;
+    assert!(resp.is_err());
+    assert!(client.is_closed());
+}
+
+#[tokio::test]
+async fn stdio_spawn_failure() {
+    let res = HostClient::<WireError>::spawn_stdio(
+        Command::new("./this-simulator-does-not-exist"),
+        ERROR_PATH,
+        8,
+        VarSeqKind::Seq1,
+    );
+    assert!(res.is_err());
+}
@@ -0,0 +1,209 @@
+use core::time::Duration;
+
+use tokio::{sync::mpsc, time::timeout};
+
+use postcard_rpc::{
+    define_dispatch, endpoints,
+    header::{VarHeader, VarSeq, VarSeqKind},
+    host_client::{HostClient, HostClientConfig, HostErr},
+    server::{
+        impls::test_channels::{
+            dispatch_impl::{spawn_fn, WireSpawnImpl, WireTxImpl},
+            ChannelWireSpawn, ChannelWireTx,
+        },
+        Cancellations, SpawnContext, StreamSender, TopicFilter,
+    },
+    standard_icd::{WireError, STANDARD_ICD_STREAM_TOPICS},
+    topics,
+};
+use postcard_rpc_test::fixture::{next_report, Fixture};
+
+endpoints! {
+    list = ENDPOINT_LIST;
+    | EndpointTy        | RequestTy     | ResponseTy    | Path          |
+    | ----------        | ---------     | ----------    | ----          |
+    | CountEndpoint     | u32           | u32 stream    | "count"       |
+    | FailEndpoint      | u32           | u32 stream    | "fail"        |
+}
+
+topics! {
+    list = TOPICS_IN_LIST;
+    direction = postcard_rpc::TopicDirection::ToServer;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+}
+
+topics! {
+    list = TOPICS_OUT_LIST;
+    direction = postcard_rpc::TopicDirection::ToClient;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+}
+
+/// Reports how each stream ended: `Ok(sent)` if completed, `Err(sent)` if cancelled
+type Report = mpsc::UnboundedSender<Result<u32, u32>>;
+
+pub struct TestContext {
+    report: Report,
+}
+
+impl SpawnContext for TestContext {
+    type SpawnCtxt = Report;
+
+    fn spawn_ctxt(&mut self) -> Self::SpawnCtxt {
+        self.report.clone()
+    }
+}
+
+define_dispatch! {
+    app: StreamDispatcher;
+    spawn_fn: spawn_fn;
+    tx_impl: WireTxImpl;
+    spawn_impl: WireSpawnImpl;
+    context: TestContext;
+
+    endpoints: {
+        list: crate::ENDPOINT_LIST;
+
+        | EndpointTy        | kind      | handler               |
+        | ----------        | ----      | -------               |
+        | CountEndpoint     | stream    | count_handler         |
+        | FailEndpoint      | stream    | fail_handler          |
+    };
+    topics_in: {
+        list: crate::TOPICS_IN_LIST;
+
+        | TopicTy           | kind      | handler               |
+        | ----------        | ----      | -------               |
+    };
+    topics_out: {
+        list: crate::TOPICS_OUT_LIST;
+    };
+}
+
+/// Sends `0..count`, one per millisecond, unless cancelled
+async fn count_handler(
+    report: Report,
+    _header: VarHeader,
+    count: u32,
+    stream: StreamSender<ChannelWireTx, CountEndpoint>,
+) {
+    for i in 0..count {
+        if stream.is_cancelled() {
+            let _ = report.send(Err(i));
+            return;
+        }
+        let _ = stream.send(&i).await;
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    let _ = report.send(Ok(count));
+    let _ = stream.end().await;
+}
+
+/// Sends `0..count`, then fails
+async fn fail_handler(
+    _report: Report,
+    _header: VarHeader,
+    count: u32,
+    stream: StreamSender<ChannelWireTx, FailEndpoint>,
+) {
+    for i in 0..count {
+        let _ = stream.send(&i).await;
+    }
+    let _ = stream.error(WireError::FailedToSpawn).await;
+}
+
+fn setup() -> (
+    HostClient<WireError>,
+    mpsc::UnboundedReceiver<Result<u32, u32>>,
+) {
+    let (report, reports) = mpsc::unbounded_channel();
+    let app = StreamDispatcher::new(TestContext { report }, ChannelWireSpawn {});
+    let cli = Fixture::new(app)
+        .server(|server| {
+            server
+                .with_cancellations(Box::leak(Box::new(Cancellations::new())))
+                // The end of a stream is never filtered, even with all topics disabled
+                .with_topic_filter(Box::leak(Box::new(TopicFilter::new_disabled())))
+        })
+        .client_config(
+            HostClientConfig::default()
+                .with_seq_kind(VarSeqKind::Seq1)
+                .with_cancel_requests(true),
+        )
+        .connect();
+    (cli, reports)
+}
+
+#[tokio::test]
+async fn stream_to_end() {
+    let (cli, mut reports) = setup();
+
+    let mut stream = cli.send_stream::<CountEndpoint>(&5).await.unwrap();
+    // The configured sequence number size is used
+    assert!(matches!(stream.seq_no(), VarSeq::Seq1(0)));
+    let mut items = vec![];
+    while let Some(item) = timeout(Duration::from_secs(1), stream.recv())
+        .await
+        .unwrap()
+    {
+        items.push(item.unwrap());
+    }
+    assert_eq!(items, [0, 1, 2, 3, 4]);
+    assert_eq!(next_report(&mut reports).await, Ok(5));
+
+    // Streams that ended stay ended
+    assert!(stream.recv().await.is_none());
+
+    // Empty streams end right away
+    let mut stream = cli.send_stream::<CountEndpoint>(&0).await.unwrap();
+    assert!(stream.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn stream_error() {
+    let (cli, _reports) = setup();
+
+    let mut stream = cli.send_stream::<FailEndpoint>(&2).await.unwrap();
+    assert_eq!(stream.recv().await, Some(Ok(0)));
+    assert_eq!(stream.recv().await, Some(Ok(1)));
+    assert_eq!(
+        stream.recv().await,
+        Some(Err(HostErr::Wire(WireError::FailedToSpawn)))
+    );
+    assert!(stream.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn stream_cancel_on_drop() {
+    let (cli, mut reports) = setup();
+
+    let mut long = cli.send_stream::<CountEndpoint>(&60_000).await.unwrap();
+    let mut short = cli.send_stream::<CountEndpoint>(&20).await.unwrap();
+    assert_eq!(long.recv().await, Some(Ok(0)));
+    assert_eq!(long.recv().await, Some(Ok(1)));
+
+    // Only the dropped stream is cancelled
+    drop(long);
+    let Err(sent) = next_report(&mut reports).await else {
+        panic!("stream was not cancelled");
+    };
+    assert!(sent >= 2);
+
+    let mut items = 0;
+    while let Some(item) = short.recv().await {
+        assert_eq!(item, Ok(items));
+        items += 1;
+    }
+    assert_eq!(items, 20);
+    assert_eq!(next_report(&mut reports).await, Ok(20));
+}
+
+#[test]
+fn advertised_when_streaming() {
+    let (report, _reports) = mpsc::unbounded_channel();
+    let app = StreamDispatcher::new(TestContext { report }, ChannelWireSpawn {});
+    for tp in STANDARD_ICD_STREAM_TOPICS.topics {
+        assert!(app.device_map.topics_out.contains(tp));
+    }
+}
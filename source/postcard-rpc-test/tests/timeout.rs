@@ -0,0 +1,114 @@
+use core::time::Duration;
+
+use tokio::sync::mpsc;
+
+use postcard_rpc::{
+    header::{VarHeader, VarKey, VarSeqKind},
+    host_client::{test_channels as client, HostClientConfig, HostErr},
+    standard_icd::{CancelTopic, PingEndpoint},
+    Endpoint, Topic,
+};
+
+/// Plays the server: receive one request, and reply to it with `body`
+async fn reply(
+    rx: &mut mpsc::Receiver<Vec<u8>>,
+    tx: &mpsc::Sender<Vec<u8>>,
+    body: u32,
+) -> VarHeader {
+    let req = rx.recv().await.unwrap();
+    let (hdr, _body) = VarHeader::take_from_slice(&req).unwrap();
+    let mut resp = VarHeader {
+        key: VarKey::Key8(PingEndpoint::RESP_KEY),
+        seq_no: hdr.seq_no,
+    }
+    .write_to_vec();
+    resp.extend_from_slice(&postcard::to_stdvec(&body).unwrap());
+    tx.send(resp).await.unwrap();
+    hdr
+}
+
+/// Plays the server: receive the cancellation of the request with `seq_no`
+async fn expect_cancel(rx: &mut mpsc::Receiver<Vec<u8>>, seq_no: u32) {
+    let msg = rx.recv().await.unwrap();
+    let (hdr, body) = VarHeader::take_from_slice(&msg).unwrap();
+    assert_eq!(hdr.key, VarKey::Key8(CancelTopic::TOPIC_KEY));
+    assert_eq!(postcard::from_bytes::<u32>(body).unwrap(), seq_no);
+}
+
+#[tokio::test]
+async fn per_call_timeout() {
+    let (client_tx, mut server_rx) = mpsc::channel(16);
+    let (server_tx, client_rx) = mpsc::channel(16);
+    let cli = client::new_from_channels_with_config(
+        client_tx,
+        client_rx,
+        &HostClientConfig::default()
+            .with_seq_kind(VarSeqKind::Seq1)
+            .with_outgoing_depth(16)
+            .with_cancel_requests(true),
+    );
+
+    // Nobody answers in time
+    let res = cli
+        .send_resp_timeout::<PingEndpoint>(&1, Duration::from_millis(10))
+        .await;
+    assert_eq!(res, Err(HostErr::Timeout));
+    assert_eq!(cli.late_responses(), 0);
+
+    // The late response is counted, and doesn't confuse later requests
+    let hdr = reply(&mut server_rx, &server_tx, 1).await;
+    expect_cancel(&mut server_rx, hdr.seq_no.into()).await;
+    let (res, _) = tokio::join!(
+        cli.send_resp_timeout::<PingEndpoint>(&2, Duration::from_secs(1)),
+        reply(&mut server_rx, &server_tx, 2),
+    );
+    assert_eq!(res, Ok(2));
+    assert_eq!(cli.late_responses(), 1);
+
+    // Unrelated unexpected responses are not counted
+    let mut resp = VarHeader {
+        key: VarKey::Key8(PingEndpoint::RESP_KEY),
+        seq_no: 100u32.into(),
+    }
+    .write_to_vec();
+    resp.extend_from_slice(&postcard::to_stdvec(&100u32).unwrap());
+    server_tx.send(resp).await.unwrap();
+    let (res, _) = tokio::join!(
+        cli.send_resp_timeout::<PingEndpoint>(&3, Duration::from_secs(1)),
+        reply(&mut server_rx, &server_tx, 3),
+    );
+    assert_eq!(res, Ok(3));
+    assert_eq!(cli.late_responses(), 1);
+}
+
+#[tokio::test]
+async fn default_timeout() {
+    let (client_tx, mut server_rx) = mpsc::channel(16);
+    let (server_tx, client_rx) = mpsc::channel(16);
+    let cli = client::new_from_channels_with_config(
+        client_tx,
+        client_rx,
+        &HostClientConfig::default()
+            .with_seq_kind(VarSeqKind::Seq1)
+            .with_outgoing_depth(16)
+            .with_default_timeout(Some(Duration::from_millis(10)))
+            .with_cancel_requests(true),
+    );
+
+    let res = cli.send_resp::<PingEndpoint>(&1).await;
+    assert_eq!(res, Err(HostErr::Timeout));
+
+    // The per-call timeout overrides the default
+    let (res, _) = tokio::join!(
+        cli.send_resp_timeout::<PingEndpoint>(&2, Duration::from_secs(1)),
+        async {
+            // Skip the expired request
+            server_rx.recv().await.unwrap();
+            expect_cancel(&mut server_rx, 0).await;
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            reply(&mut server_rx, &server_tx, 2).await
+        },
+    );
+    assert_eq!(res, Ok(2));
+    assert!(!cli.is_closed());
+}
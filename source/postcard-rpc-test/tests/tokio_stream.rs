@@ -0,0 +1,324 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+use postcard_rpc::{
+    define_dispatch, endpoints,
+    framing::{FrameDecoder, Framing},
+    header::{VarHeader, VarKey, VarSeq, VarSeqKind},
+    host_client::HostClient,
+    server::{
+        impls::tokio_stream::{
+            dispatch_impl::{new_server, spawn_fn, Settings, WireSpawnImpl, WireTxImpl},
+            new_from_tcp_listener, StreamWireTx,
+        },
+        Dispatch, Sender, SpawnContext,
+    },
+    standard_icd::{PingEndpoint, WireError, ERROR_PATH},
+    topics, Endpoint,
+};
+
+#[derive(Serialize, Deserialize, Schema)]
+pub struct AReq(pub u8);
+#[derive(Serialize, Deserialize, Schema)]
+pub struct AResp(pub u8);
+#[derive(Serialize, Deserialize, Schema)]
+pub struct BReq(pub u16);
+#[derive(Serialize, Deserialize, Schema)]
+pub struct BResp(pub u32);
+
+endpoints! {
+    list = ENDPOINT_LIST;
+    | EndpointTy        | RequestTy     | ResponseTy    | Path      |
+    | ----------        | ---------     | ----------    | ----      |
+    | AlphaEndpoint     | AReq          | AResp         | "alpha"   |
+    | BetaEndpoint      | BReq          | BResp         | "beta"    |
+}
+
+topics! {
+    list = TOPICS_IN_LIST;
+    direction = postcard_rpc::TopicDirection::ToServer;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+}
+
+topics! {
+    list = TOPICS_OUT_LIST;
+    direction = postcard_rpc::TopicDirection::ToClient;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+}
+
+pub struct TestContext {
+    pub ctr: Arc<AtomicUsize>,
+}
+
+pub struct TestSpawnContext {
+    pub ctr: Arc<AtomicUsize>,
+}
+
+impl SpawnContext for TestContext {
+    type SpawnCtxt = TestSpawnContext;
+
+    fn spawn_ctxt(&mut self) -> Self::SpawnCtxt {
+        TestSpawnContext {
+            ctr: self.ctr.clone(),
+        }
+    }
+}
+
+define_dispatch! {
+    app: StreamDispatcher;
+    spawn_fn: spawn_fn;
+    tx_impl: WireTxImpl;
+    spawn_impl: WireSpawnImpl;
+    context: TestContext;
+
+    endpoints: {
+        list: crate::ENDPOINT_LIST;
+
+        | EndpointTy        | kind      | handler               |
+        | ----------        | ----      | -------               |
+        | AlphaEndpoint     | async     | test_alpha_handler    |
+        | BetaEndpoint      | spawn     | test_beta_handler     |
+    };
+    topics_in: {
+        list: crate::TOPICS_IN_LIST;
+
+        | TopicTy           | kind      | handler               |
+        | ----------        | ----      | -------               |
+    };
+    topics_out: {
+        list: crate::TOPICS_OUT_LIST;
+    };
+}
+
+async fn test_alpha_handler(context: &mut TestContext, _header: VarHeader, body: AReq) -> AResp {
+    context.ctr.fetch_add(1, Ordering::Relaxed);
+    AResp(body.0)
+}
+
+async fn test_beta_handler(
+    context: TestSpawnContext,
+    header: VarHeader,
+    body: BReq,
+    out: Sender<StreamWireTx>,
+) {
+    context.ctr.fetch_add(1, Ordering::Relaxed);
+    let _ = out
+        .reply::<BetaEndpoint>(header.seq_no, &BResp(body.0.into()))
+        .await;
+}
+
+/// Starts a server on a random local TCP port, which will serve clients forever
+async fn tcp_server(framing: Framing) -> (std::net::SocketAddr, Arc<AtomicUsize>) {
+    let ctr = Arc::new(AtomicUsize::new(0));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = new_from_tcp_listener(listener, framing);
+
+    let app = StreamDispatcher::new(
+        TestContext { ctr: ctr.clone() },
+        WireSpawnImpl::from(tokio::runtime::Handle::current()),
+    );
+    let kkind = app.min_key_len();
+    let mut server = new_server(
+        app,
+        Settings {
+            tx,
+            rx,
+            buf: 1024,
+            kkind,
+        },
+    );
+    tokio::task::spawn(async move {
+        loop {
+            // Returns when each client disconnects
+            let _ = server.run().await;
+        }
+    });
+    (addr, ctr)
+}
+
+#[tokio::test]
+async fn tcp_host_client() {
+    for framing in [Framing::LengthPrefixed, Framing::Cobs] {
+        let (addr, ctr) = tcp_server(framing).await;
+
+        // Connect more than once, one after another
+        for round in 0..3 {
+            let client = HostClient::<WireError>::try_new_tcp(
+                addr,
+                framing,
+                ERROR_PATH,
+                8,
+                VarSeqKind::Seq2,
+            )
+            .await
+            .unwrap();
+
+            let resp = client.send_resp::<AlphaEndpoint>(&AReq(round)).await;
+            assert_eq!(resp.unwrap().0, round);
+            let resp = client.send_resp::<BetaEndpoint>(&BReq(1000)).await;
+            assert_eq!(resp.unwrap().0, 1000);
+            let resp = client.send_resp::<PingEndpoint>(&0xACAB_1234).await;
+            assert_eq!(resp.unwrap(), 0xACAB_1234);
+
+            client.close();
+            client.wait_closed().await;
+        }
+        assert_eq!(ctr.load(Ordering::Relaxed), 6);
+    }
+}
+
+#[tokio::test]
+async fn tcp_oversized_frame() {
+    let (addr, _ctr) = tcp_server(Framing::LengthPrefixed).await;
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+
+    // A frame too large for the server's 1024 byte buffer is dropped, the
+    // server carries on with the next one.
+    let framing = Framing::LengthPrefixed;
+    let mut msg = VarHeader {
+        key: VarKey::Key8(AlphaEndpoint::REQ_KEY),
+        seq_no: VarSeq::Seq4(1),
+    }
+    .write_to_vec();
+    msg.extend_from_slice(&[0u8; 2048]);
+    stream.write_all(&framing.encode(&msg)).await.unwrap();
+
+    let mut msg = VarHeader {
+        key: VarKey::Key8(AlphaEndpoint::REQ_KEY),
+        seq_no: VarSeq::Seq4(2),
+    }
+    .write_to_vec();
+    msg.extend_from_slice(&postcard::to_stdvec(&AReq(42)).unwrap());
+    stream.write_all(&framing.encode(&msg)).await.unwrap();
+
+    let mut dec = FrameDecoder::new(framing, 1024);
+    let mut buf = [0u8; 64];
+    let frame = loop {
+        if let Some(frame) = dec.next_frame() {
+            break frame.unwrap();
+        }
+        let used = stream.read(&mut buf).await.unwrap();
+        assert_ne!(used, 0);
+        dec.push(&buf[..used]);
+    };
+    let (hdr, body) = VarHeader::take_from_slice(&frame).unwrap();
+    assert_eq!(hdr.seq_no, VarSeq::Seq4(2));
+    assert_eq!(postcard::from_bytes::<AResp>(body).unwrap().0, 42);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn unix_socket() {
+    use postcard_rpc::server::impls::tokio_stream::new_from_unix_listener;
+    use tokio::net::{UnixListener, UnixStream};
+
+    let dir = std::env::temp_dir().join(format!("postcard-rpc-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("server.sock");
+    let _ = std::fs::remove_file(&path);
+
+    let framing = Framing::Cobs;
+    let listener = UnixListener::bind(&path).unwrap();
+    let (tx, rx) = new_from_unix_listener(listener, framing);
+    let app = StreamDispatcher::new(
+        TestContext {
+            ctr: Arc::new(AtomicUsize::new(0)),
+        },
+        WireSpawnImpl::from(tokio::runtime::Handle::current()),
+    );
+    let kkind = app.min_key_len();
+    let mut server = new_server(
+        app,
+        Settings {
+            tx,
+            rx,
+            buf: 1024,
+            kkind,
+        },
+    );
+    tokio::task::spawn(async move {
+        let _ = server.run().await;
+    });
+
+    let mut stream = UnixStream::connect(&path).await.unwrap();
+    let mut msg = VarHeader {
+        key: VarKey::Key8(BetaEndpoint::REQ_KEY),
+        seq_no: VarSeq::Seq2(7),
+    }
+    .write_to_vec();
+    msg.extend_from_slice(&postcard::to_stdvec(&BReq(500)).unwrap());
+    stream.write_all(&framing.encode(&msg)).await.unwrap();
+
+    let mut dec = FrameDecoder::new(framing, 1024);
+    let mut buf = [0u8; 64];
+    let frame = loop {
+        if let Some(frame) = dec.next_frame() {
+            break frame.unwrap();
+        }
+        let used = stream.read(&mut buf).await.unwrap();
+        assert_ne!(used, 0);
+        dec.push(&buf[..used]);
+    };
+    let (hdr, body) = VarHeader::take_from_slice(&frame).unwrap();
+    assert_eq!(hdr.seq_no, VarSeq::Seq2(7));
+    assert_eq!(hdr.key, VarKey::Key8(BetaEndpoint::RESP_KEY));
+    assert_eq!(postcard::from_bytes::<BResp>(body).unwrap().0, 500);
+
+    drop(stream);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// Run a server over one end of a pty, and a [`HostClient`] over the other
+#[cfg(unix)]
+#[tokio::test]
+async fn pty_cobs_stream() {
+    use postcard_rpc::server::impls::tokio_stream::new_from_stream;
+    use tokio_serial::SerialStream;
+
+    let (server_end, client_end) = SerialStream::pair().unwrap();
+
+    let ctr = Arc::new(AtomicUsize::new(0));
+    let (tx, rx) = new_from_stream(server_end, Framing::Cobs);
+    let app = StreamDispatcher::new(
+        TestContext { ctr: ctr.clone() },
+        WireSpawnImpl::from(tokio::runtime::Handle::current()),
+    );
+    let kkind = app.min_key_len();
+    let mut server = new_server(
+        app,
+        Settings {
+            tx,
+            rx,
+            buf: 1024,
+            kkind,
+        },
+    );
+    tokio::task::spawn(async move {
+        let _ = server.run().await;
+    });
+
+    let (reader, writer) = tokio::io::split(client_end);
+    let client =
+        HostClient::<WireError>::new_cobs_stream(reader, writer, ERROR_PATH, 8, VarSeqKind::Seq1);
+
+    // Include zeroes and newlines, which must survive the pty untouched
+    for val in [0u16, 0x0A0D, 0x0A00, 0xFFFF] {
+        let resp = client.send_resp::<BetaEndpoint>(&BReq(val)).await;
+        assert_eq!(resp.unwrap().0, u32::from(val));
+    }
+    let resp = client.send_resp::<AlphaEndpoint>(&AReq(0x0A)).await;
+    assert_eq!(resp.unwrap().0, 0x0A);
+    assert_eq!(ctr.load(Ordering::Relaxed), 5);
+
+    client.close();
+}
@@ -0,0 +1,165 @@
+use core::time::Duration;
+
+use tokio::time::timeout;
+
+use postcard_rpc::{
+    define_dispatch, endpoints,
+    header::{VarHeader, VarSeq},
+    host_client::HostClient,
+    server::{
+        impls::test_channels::{
+            dispatch_impl::{spawn_fn, WireSpawnImpl, WireTxImpl},
+            ChannelWireSpawn, ChannelWireTx,
+        },
+        Sender, SpawnContext, TopicFilter,
+    },
+    standard_icd::{Capabilities, PingEndpoint, TopicControlError, WireError},
+    topics, Topic,
+};
+use postcard_rpc_test::fixture::Fixture;
+
+endpoints! {
+    list = ENDPOINT_LIST;
+    | EndpointTy        | RequestTy     | ResponseTy    | Path          |
+    | ----------        | ---------     | ----------    | ----          |
+    | TickEndpoint      | u32           | ()            | "tick"        |
+}
+
+topics! {
+    list = TOPICS_IN_LIST;
+    direction = postcard_rpc::TopicDirection::ToServer;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+}
+
+topics! {
+    list = TOPICS_OUT_LIST;
+    direction = postcard_rpc::TopicDirection::ToClient;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+    | TickTopic     | u32           | "ticks"   |
+}
+
+topics! {
+    list = OTHER_TOPICS_LIST;
+    direction = postcard_rpc::TopicDirection::ToClient;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+    | OtherTopic    | u32           | "other"   |
+}
+
+pub struct TestContext;
+
+impl SpawnContext for TestContext {
+    type SpawnCtxt = ();
+
+    fn spawn_ctxt(&mut self) -> Self::SpawnCtxt {}
+}
+
+define_dispatch! {
+    app: TopicDispatcher;
+    spawn_fn: spawn_fn;
+    tx_impl: WireTxImpl;
+    spawn_impl: WireSpawnImpl;
+    context: TestContext;
+    standard: [capabilities, topic_filter];
+
+    endpoints: {
+        list: crate::ENDPOINT_LIST;
+
+        | EndpointTy        | kind      | handler               |
+        | ----------        | ----      | -------               |
+        | TickEndpoint      | spawn     | tick_handler          |
+    };
+    topics_in: {
+        list: crate::TOPICS_IN_LIST;
+
+        | TopicTy           | kind      | handler               |
+        | ----------        | ----      | -------               |
+    };
+    topics_out: {
+        list: crate::TOPICS_OUT_LIST;
+    };
+}
+
+/// Publishes `0..count`
+async fn tick_handler(_context: (), header: VarHeader, body: u32, out: Sender<ChannelWireTx>) {
+    for i in 0..body {
+        let _ = out.publish::<TickTopic>(VarSeq::Seq4(i), &i).await;
+    }
+    let _ = out.reply::<TickEndpoint>(header.seq_no, &()).await;
+}
+
+fn setup() -> (HostClient<WireError>, &'static TopicFilter) {
+    // Each test gets its own filter
+    let filter: &'static TopicFilter = Box::leak(Box::new(TopicFilter::new_disabled()));
+    let app = TopicDispatcher::new(TestContext, ChannelWireSpawn {});
+    let cli = Fixture::new(app)
+        .server(move |server| server.with_topic_filter(filter))
+        .connect();
+    (cli, filter)
+}
+
+#[tokio::test]
+async fn enabled_by_subscribing() {
+    let (cli, filter) = setup();
+    let caps = cli.query_capabilities().await.unwrap();
+    assert!(caps.supports(Capabilities::TOPIC_FILTER));
+
+    // Not sent while nobody is subscribed
+    cli.send_resp::<TickEndpoint>(&3).await.unwrap();
+    assert!(!filter.is_enabled(TickTopic::TOPIC_KEY));
+
+    let mut sub1 = cli.subscribe_multi::<TickTopic>(8).await.unwrap();
+    let sub2 = cli.subscribe_multi::<TickTopic>(8).await.unwrap();
+    cli.send_resp::<TickEndpoint>(&3).await.unwrap();
+    for i in 0..3 {
+        let msg = timeout(Duration::from_secs(1), sub1.recv()).await.unwrap();
+        assert_eq!(msg, Ok(i));
+    }
+    assert!(filter.is_enabled(TickTopic::TOPIC_KEY));
+
+    // Disabled once the last subscription is dropped. Requests are handled in
+    // order, so the ping is answered after the topic was disabled.
+    drop(sub1);
+    cli.send_resp::<PingEndpoint>(&1).await.unwrap();
+    assert!(filter.is_enabled(TickTopic::TOPIC_KEY));
+    drop(sub2);
+    cli.send_resp::<PingEndpoint>(&2).await.unwrap();
+    assert!(!filter.is_enabled(TickTopic::TOPIC_KEY));
+}
+
+#[tokio::test]
+async fn enabled_after_query() {
+    let (cli, filter) = setup();
+
+    // Until the server is known to support it, topics are not enabled
+    let mut sub = cli.subscribe_multi::<TickTopic>(8).await.unwrap();
+    cli.send_resp::<PingEndpoint>(&1).await.unwrap();
+    assert!(!filter.is_enabled(TickTopic::TOPIC_KEY));
+
+    cli.query_capabilities().await.unwrap();
+    cli.send_resp::<TickEndpoint>(&1).await.unwrap();
+    let msg = timeout(Duration::from_secs(1), sub.recv()).await.unwrap();
+    assert_eq!(msg, Ok(0));
+}
+
+#[tokio::test]
+async fn explicit_control() {
+    let (cli, filter) = setup();
+
+    assert_eq!(cli.enable_topic::<TickTopic>(None).await, Ok(Ok(())));
+    assert!(filter.is_enabled(TickTopic::TOPIC_KEY));
+    assert_eq!(cli.disable_topic::<TickTopic>().await, Ok(Ok(())));
+    assert!(!filter.is_enabled(TickTopic::TOPIC_KEY));
+
+    // The server has no clock to limit the rate with
+    assert_eq!(
+        cli.enable_topic::<TickTopic>(Some(10)).await,
+        Ok(Err(TopicControlError::RateUnsupported))
+    );
+    assert_eq!(
+        cli.enable_topic::<OtherTopic>(None).await,
+        Ok(Err(TopicControlError::UnknownTopic))
+    );
+}
@@ -0,0 +1,259 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+use tokio::{net::UdpSocket, time::timeout};
+
+use postcard_rpc::{
+    define_dispatch, endpoints,
+    header::{VarHeader, VarKey, VarSeq, VarSeqKind},
+    host_client::{HostClient, HostErr},
+    server::{
+        impls::udp::{
+            dispatch_impl::{new_server, spawn_fn, Settings, WireSpawnImpl, WireTxImpl},
+            new_from_socket, UdpWireTx,
+        },
+        Dispatch, Sender, SpawnContext,
+    },
+    standard_icd::{FrameTooLong, PingEndpoint, WireError, ERROR_KEY, ERROR_PATH},
+    topics, Endpoint,
+};
+
+#[derive(Serialize, Deserialize, Schema)]
+pub struct AReq(pub u8);
+#[derive(Serialize, Deserialize, Schema)]
+pub struct AResp(pub u8);
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct Blob(pub Vec<u8>);
+
+endpoints! {
+    list = ENDPOINT_LIST;
+    | EndpointTy        | RequestTy     | ResponseTy    | Path      |
+    | ----------        | ---------     | ----------    | ----      |
+    | AlphaEndpoint     | AReq          | AResp         | "alpha"   |
+    | EchoEndpoint      | Blob          | Blob          | "echo"    |
+    | BigEndpoint       | u32           | Blob          | "big"     |
+    | SameSeqEndpoint   | u32           | bool          | "same_seq"|
+}
+
+topics! {
+    list = TOPICS_IN_LIST;
+    direction = postcard_rpc::TopicDirection::ToServer;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+}
+
+topics! {
+    list = TOPICS_OUT_LIST;
+    direction = postcard_rpc::TopicDirection::ToClient;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+    | BigTopic      | Blob          | "big/topic"|
+}
+
+pub struct TestContext {
+    pub ctr: Arc<AtomicUsize>,
+}
+
+impl SpawnContext for TestContext {
+    type SpawnCtxt = ();
+
+    fn spawn_ctxt(&mut self) -> Self::SpawnCtxt {}
+}
+
+define_dispatch! {
+    app: UdpDispatcher;
+    spawn_fn: spawn_fn;
+    tx_impl: WireTxImpl;
+    spawn_impl: WireSpawnImpl;
+    context: TestContext;
+
+    endpoints: {
+        list: crate::ENDPOINT_LIST;
+
+        | EndpointTy        | kind      | handler               |
+        | ----------        | ----      | -------               |
+        | AlphaEndpoint     | async     | test_alpha_handler    |
+        | EchoEndpoint      | spawn     | test_echo_handler     |
+        | BigEndpoint       | blocking  | test_big_handler      |
+        | SameSeqEndpoint   | spawn     | test_same_seq_handler |
+    };
+    topics_in: {
+        list: crate::TOPICS_IN_LIST;
+
+        | TopicTy           | kind      | handler               |
+        | ----------        | ----      | -------               |
+    };
+    topics_out: {
+        list: crate::TOPICS_OUT_LIST;
+    };
+}
+
+async fn test_alpha_handler(context: &mut TestContext, _header: VarHeader, body: AReq) -> AResp {
+    context.ctr.fetch_add(1, Ordering::Relaxed);
+    AResp(body.0)
+}
+
+async fn test_echo_handler(_context: (), header: VarHeader, body: Blob, out: Sender<UdpWireTx>) {
+    let _ = out.reply::<EchoEndpoint>(header.seq_no, &body).await;
+}
+
+async fn test_same_seq_handler(_context: (), header: VarHeader, body: u32, out: Sender<UdpWireTx>) {
+    // A topic message that is too long, using the seq_no of the request in flight
+    let res = out
+        .publish::<BigTopic>(header.seq_no, &Blob(vec![0xAA; body as usize]))
+        .await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let _ = out
+        .reply::<SameSeqEndpoint>(header.seq_no, &res.is_err())
+        .await;
+}
+
+fn test_big_handler(_context: &mut TestContext, _header: VarHeader, body: u32) -> Blob {
+    Blob(vec![0xAA; body as usize])
+}
+
+/// Starts a server on a random local UDP port
+async fn udp_server() -> (SocketAddr, Arc<AtomicUsize>) {
+    let ctr = Arc::new(AtomicUsize::new(0));
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let addr = socket.local_addr().unwrap();
+    let (tx, rx) = new_from_socket(socket, 512);
+
+    let app = UdpDispatcher::new(
+        TestContext { ctr: ctr.clone() },
+        WireSpawnImpl::from(tokio::runtime::Handle::current()),
+    );
+    let kkind = app.min_key_len();
+    let mut server = new_server(
+        app,
+        Settings {
+            tx,
+            rx,
+            buf: 1024,
+            kkind,
+        },
+    );
+    tokio::task::spawn(async move {
+        let _ = server.run().await;
+    });
+    (addr, ctr)
+}
+
+#[tokio::test]
+async fn udp_host_client() {
+    let (addr, ctr) = udp_server().await;
+    let client = HostClient::<WireError>::try_new_udp(addr, ERROR_PATH, 8, VarSeqKind::Seq2)
+        .await
+        .unwrap();
+
+    for i in 0..8 {
+        let resp = client.send_resp::<AlphaEndpoint>(&AReq(i)).await;
+        assert_eq!(resp.unwrap().0, i);
+    }
+    let resp = client.send_resp::<PingEndpoint>(&1234).await;
+    assert_eq!(resp.unwrap(), 1234);
+    let resp = client.send_resp::<EchoEndpoint>(&Blob(vec![1, 2, 3])).await;
+    assert_eq!(resp.unwrap(), Blob(vec![1, 2, 3]));
+    assert_eq!(ctr.load(Ordering::Relaxed), 8);
+}
+
+#[tokio::test]
+async fn udp_too_long() {
+    let (addr, _ctr) = udp_server().await;
+    let client = HostClient::<WireError>::try_new_udp(addr, ERROR_PATH, 8, VarSeqKind::Seq2)
+        .await
+        .unwrap();
+
+    // Too long for the host to send at all
+    let resp = client.send_resp::<EchoEndpoint>(&Blob(vec![0; 2000])).await;
+    let Err(HostErr::Wire(WireError::FrameTooLong(FrameTooLong { max: 1472, .. }))) = resp else {
+        panic!("{resp:?}");
+    };
+
+    // Too long for the server to receive
+    let resp = client.send_resp::<EchoEndpoint>(&Blob(vec![0; 1000])).await;
+    let Err(HostErr::Wire(WireError::FrameTooLong(FrameTooLong { max: 512, .. }))) = resp else {
+        panic!("{resp:?}");
+    };
+
+    // Too long for the server to send
+    let resp = client.send_resp::<BigEndpoint>(&1000).await;
+    let Err(HostErr::Wire(WireError::FrameTooLong(FrameTooLong { max: 512, .. }))) = resp else {
+        panic!("{resp:?}");
+    };
+
+    // Everything still works afterwards
+    let resp = client.send_resp::<BigEndpoint>(&100).await;
+    assert_eq!(resp.unwrap(), Blob(vec![0xAA; 100]));
+}
+
+#[tokio::test]
+async fn udp_too_long_topic() {
+    let (addr, _ctr) = udp_server().await;
+    let client = HostClient::<WireError>::try_new_udp(addr, ERROR_PATH, 8, VarSeqKind::Seq2)
+        .await
+        .unwrap();
+
+    // The publisher is told the topic message was too long, and the request that
+    // shares its seq_no is not failed with an error meant for someone else
+    let resp = client.send_resp::<SameSeqEndpoint>(&1000).await;
+    assert!(resp.unwrap());
+
+    // Topic messages that fit are still sent
+    let resp = client.send_resp::<SameSeqEndpoint>(&100).await;
+    assert!(!resp.unwrap());
+}
+
+#[tokio::test]
+async fn udp_reply_to_latest_peer() {
+    let (addr, _ctr) = udp_server().await;
+
+    // Two different peers take turns, each gets their own replies
+    let peer_a = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let peer_b = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    for (i, peer) in [&peer_a, &peer_b, &peer_a].into_iter().enumerate() {
+        let mut msg = VarHeader {
+            key: VarKey::Key8(AlphaEndpoint::REQ_KEY),
+            seq_no: VarSeq::Seq4(i as u32),
+        }
+        .write_to_vec();
+        msg.extend_from_slice(&postcard::to_stdvec(&AReq(i as u8)).unwrap());
+        peer.send_to(&msg, addr).await.unwrap();
+
+        let mut buf = [0u8; 64];
+        let used = timeout(Duration::from_secs(1), peer.recv(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+        let (hdr, body) = VarHeader::take_from_slice(&buf[..used]).unwrap();
+        assert_eq!(hdr.key, VarKey::Key8(AlphaEndpoint::RESP_KEY));
+        assert_eq!(hdr.seq_no, VarSeq::Seq4(i as u32));
+        assert_eq!(postcard::from_bytes::<AResp>(body).unwrap().0, i as u8);
+    }
+
+    // A request that is too long gets an error with the same seq_no
+    let mut msg = VarHeader {
+        key: VarKey::Key8(EchoEndpoint::REQ_KEY),
+        seq_no: VarSeq::Seq4(99),
+    }
+    .write_to_vec();
+    msg.extend_from_slice(&[0u8; 600]);
+    peer_b.send_to(&msg, addr).await.unwrap();
+    let mut buf = [0u8; 64];
+    let used = timeout(Duration::from_secs(1), peer_b.recv(&mut buf))
+        .await
+        .unwrap()
+        .unwrap();
+    let (hdr, body) = VarHeader::take_from_slice(&buf[..used]).unwrap();
+    assert_eq!(hdr.key, VarKey::Key8(ERROR_KEY));
+    assert_eq!(hdr.seq_no, VarSeq::Seq4(99));
+    assert_eq!(
+        postcard::from_bytes::<WireError>(body).unwrap(),
+        WireError::FrameTooLong(FrameTooLong {
+            len: msg.len() as u32,
+            max: 512
+        })
+    );
+}
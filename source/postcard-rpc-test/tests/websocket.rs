@@ -0,0 +1,194 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use futures_util::{SinkExt, StreamExt};
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+use tokio::{net::TcpListener, time::timeout};
+use tokio_tungstenite::tungstenite::Message;
+
+use postcard_rpc::{
+    define_dispatch, endpoints,
+    header::{VarHeader, VarKey, VarSeq, VarSeqKind},
+    host_client::HostClient,
+    server::{
+        impls::websocket::{
+            dispatch_impl::{new_server, spawn_fn, Settings, WireSpawnImpl, WireTxImpl},
+            new_from_tcp_listener, WsWireTx,
+        },
+        Dispatch, Sender, SpawnContext,
+    },
+    standard_icd::{PingEndpoint, WireError, ERROR_PATH},
+    topics, Endpoint,
+};
+
+#[derive(Serialize, Deserialize, Schema)]
+pub struct AReq(pub u8);
+#[derive(Serialize, Deserialize, Schema)]
+pub struct AResp(pub u8);
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct Blob(pub Vec<u8>);
+
+endpoints! {
+    list = ENDPOINT_LIST;
+    | EndpointTy        | RequestTy     | ResponseTy    | Path      |
+    | ----------        | ---------     | ----------    | ----      |
+    | AlphaEndpoint     | AReq          | AResp         | "alpha"   |
+    | EchoEndpoint      | Blob          | Blob          | "echo"    |
+}
+
+topics! {
+    list = TOPICS_IN_LIST;
+    direction = postcard_rpc::TopicDirection::ToServer;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+}
+
+topics! {
+    list = TOPICS_OUT_LIST;
+    direction = postcard_rpc::TopicDirection::ToClient;
+    | TopicTy       | MessageTy     | Path      |
+    | ----------    | ---------     | ----      |
+}
+
+pub struct TestContext {
+    pub ctr: Arc<AtomicUsize>,
+}
+
+impl SpawnContext for TestContext {
+    type SpawnCtxt = ();
+
+    fn spawn_ctxt(&mut self) -> Self::SpawnCtxt {}
+}
+
+define_dispatch! {
+    app: WsDispatcher;
+    spawn_fn: spawn_fn;
+    tx_impl: WireTxImpl;
+    spawn_impl: WireSpawnImpl;
+    context: TestContext;
+
+    endpoints: {
+        list: crate::ENDPOINT_LIST;
+
+        | EndpointTy        | kind      | handler               |
+        | ----------        | ----      | -------               |
+        | AlphaEndpoint     | async     | test_alpha_handler    |
+        | EchoEndpoint      | spawn     | test_echo_handler     |
+    };
+    topics_in: {
+        list: crate::TOPICS_IN_LIST;
+
+        | TopicTy           | kind      | handler               |
+        | ----------        | ----      | -------               |
+    };
+    topics_out: {
+        list: crate::TOPICS_OUT_LIST;
+    };
+}
+
+async fn test_alpha_handler(context: &mut TestContext, _header: VarHeader, body: AReq) -> AResp {
+    context.ctr.fetch_add(1, Ordering::Relaxed);
+    AResp(body.0)
+}
+
+async fn test_echo_handler(_context: (), header: VarHeader, body: Blob, out: Sender<WsWireTx>) {
+    let _ = out.reply::<EchoEndpoint>(header.seq_no, &body).await;
+}
+
+/// Starts a server on a random local TCP port, which will serve clients forever
+async fn ws_server() -> (SocketAddr, Arc<AtomicUsize>) {
+    let ctr = Arc::new(AtomicUsize::new(0));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = new_from_tcp_listener(listener);
+
+    let app = WsDispatcher::new(
+        TestContext { ctr: ctr.clone() },
+        WireSpawnImpl::from(tokio::runtime::Handle::current()),
+    );
+    let kkind = app.min_key_len();
+    let mut server = new_server(
+        app,
+        Settings {
+            tx,
+            rx,
+            buf: 1024,
+            kkind,
+        },
+    );
+    tokio::task::spawn(async move {
+        loop {
+            // Returns when each client disconnects
+            let _ = server.run().await;
+        }
+    });
+    (addr, ctr)
+}
+
+#[tokio::test]
+async fn websocket_host_client() {
+    let (addr, ctr) = ws_server().await;
+    let url = format!("ws://{addr}");
+
+    // Connect more than once, one after another
+    for round in 0..3 {
+        let client =
+            HostClient::<WireError>::try_new_websocket(&url, ERROR_PATH, 8, VarSeqKind::Seq2)
+                .await
+                .unwrap();
+
+        let resp = client.send_resp::<AlphaEndpoint>(&AReq(round)).await;
+        assert_eq!(resp.unwrap().0, round);
+        let resp = client.send_resp::<EchoEndpoint>(&Blob(vec![0, 1, 2])).await;
+        assert_eq!(resp.unwrap(), Blob(vec![0, 1, 2]));
+        let resp = client.send_resp::<PingEndpoint>(&0xACAB_1234).await;
+        assert_eq!(resp.unwrap(), 0xACAB_1234);
+
+        client.close();
+        client.wait_closed().await;
+    }
+    assert_eq!(ctr.load(Ordering::Relaxed), 3);
+}
+
+#[tokio::test]
+async fn websocket_ignored_messages() {
+    let (addr, _ctr) = ws_server().await;
+    let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{addr}"))
+        .await
+        .unwrap();
+
+    // Text messages and messages too large for the server's 1024 byte buffer
+    // are dropped, the server carries on with the next one.
+    ws.send(Message::Text("hello".into())).await.unwrap();
+    let mut msg = VarHeader {
+        key: VarKey::Key8(AlphaEndpoint::REQ_KEY),
+        seq_no: VarSeq::Seq4(1),
+    }
+    .write_to_vec();
+    msg.extend_from_slice(&[0u8; 2048]);
+    ws.send(Message::Binary(msg.into())).await.unwrap();
+
+    let mut msg = VarHeader {
+        key: VarKey::Key8(AlphaEndpoint::REQ_KEY),
+        seq_no: VarSeq::Seq4(2),
+    }
+    .write_to_vec();
+    msg.extend_from_slice(&postcard::to_stdvec(&AReq(42)).unwrap());
+    ws.send(Message::Binary(msg.into())).await.unwrap();
+
+    let frame = loop {
+        let msg = timeout(Duration::from_secs(1), ws.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        if let Message::Binary(frame) = msg {
+            break frame;
+        }
+    };
+    let (hdr, body) = VarHeader::take_from_slice(&frame).unwrap();
+    assert_eq!(hdr.key, VarKey::Key8(AlphaEndpoint::RESP_KEY));
+    assert_eq!(hdr.seq_no, VarSeq::Seq4(2));
+    assert_eq!(postcard::from_bytes::<AResp>(body).unwrap().0, 42);
+}
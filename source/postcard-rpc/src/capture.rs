@@ -0,0 +1,861 @@
+//! Capturing the frames sent and received over a connection
+//!
+//! A [`Capture`] records frames to a [pcapng] file, which can be inspected with
+//! Wireshark or read back with a [`CaptureReader`]. Connections are captured by
+//! wrapping their wire implementations with [`Capture::tap()`], which works for
+//! both the host [`WireTx`][host_client::WireTx] and [`WireRx`][host_client::WireRx]
+//! traits, and the server [`WireTx`][server::WireTx] and [`WireRx`][server::WireRx]
+//! traits:
+//!
+//! ```rust,no_run
+//! # use postcard_rpc::{capture::Capture, server::impls::test_channels::*};
+//! # fn demo(tx: ChannelWireTx, rx: ChannelWireRx) -> std::io::Result<()> {
+//! let capture = Capture::create("server.pcapng")?;
+//! let tx = capture.tap(tx);
+//! let rx = capture.tap(rx);
+//! // Use `tx` and `rx` to create the server, with `Tap<ChannelWireTx>` as the
+//! // `tx_impl` of the dispatcher
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## Capture format
+//!
+//! Captures are little endian pcapng files with a single interface, using the
+//! `LINKTYPE_USER0` (147) link type, as postcard-rpc has no link type of its own.
+//! Each frame is stored in an Enhanced Packet Block:
+//!
+//! * The packet data is the frame, starting with its [`VarHeader`]
+//! * The timestamp is the time the frame was recorded, in microseconds
+//! * The `epb_flags` option holds the direction: inbound for received frames,
+//!   and outbound for sent frames
+//! * The `opt_comment` option holds the decoded header, like
+//!   `key 5d1af42f8bf3a1e8, seq 12`, for display in Wireshark
+//!
+//! [pcapng]: https://www.ietf.org/archive/id/draft-ietf-opsawg-pcapng-02.html
+
+use std::{
+    fmt::{Arguments, Write as _},
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{
+    header::{Fragment, VarHeader, VarKey, VarKeyKind, VarSeq},
+    host_client::{self, RpcFrame},
+    server,
+    standard_icd::LoggingTopic,
+    Topic,
+};
+
+const SECTION_HEADER: u32 = 0x0A0D_0D0A;
+const INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+const ENHANCED_PACKET: u32 = 0x0000_0006;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+const LINKTYPE_USER0: u16 = 147;
+
+const OPT_END: u16 = 0;
+const OPT_COMMENT: u16 = 1;
+const OPT_IF_TSRESOL: u16 = 9;
+const OPT_EPB_FLAGS: u16 = 2;
+
+const FLAGS_INBOUND: u32 = 0b01;
+const FLAGS_OUTBOUND: u32 = 0b10;
+const FLAGS_DIRECTION_MASK: u32 = 0b11;
+
+/// The direction of a captured frame, as seen by the tapped side
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    /// The frame was sent to the other side
+    Sent,
+    /// The frame was received from the other side
+    Received,
+}
+
+/// Records frames to a pcapng file
+///
+/// Cloning a [`Capture`] gives another handle to the same file, so the frames of
+/// both directions end up in order in a single capture.
+///
+/// Frames are written by a dedicated thread, so recording never blocks the tapped
+/// connection. Use [`Capture::flush()`] to wait until all recorded frames were
+/// written.
+#[derive(Clone)]
+pub struct Capture {
+    tx: mpsc::Sender<Command>,
+    flush_each_frame: bool,
+}
+
+/// A request to the writer thread
+enum Command {
+    /// Write a block, and flush afterwards if requested
+    Block { block: Vec<u8>, flush: bool },
+    /// Flush the writer, and report the result
+    Flush(mpsc::Sender<io::Result<()>>),
+}
+
+impl Capture {
+    /// Create a capture file at the given path, replacing any existing file
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+
+    /// Write a capture to any writer
+    ///
+    /// By default, the writer is flushed after every frame, so the capture stays
+    /// readable if the program stops unexpectedly, see
+    /// [`Capture::flush_each_frame()`].
+    pub fn new(mut out: impl Write + Send + 'static) -> io::Result<Self> {
+        // Section header, with an unknown section length
+        let mut shb = Vec::new();
+        shb.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+        shb.extend_from_slice(&1u16.to_le_bytes());
+        shb.extend_from_slice(&0u16.to_le_bytes());
+        shb.extend_from_slice(&(-1i64).to_le_bytes());
+        write_option(&mut shb, OPT_END, &[]);
+        write_block(&mut out, SECTION_HEADER, &shb)?;
+
+        // Interface description, without a snap length
+        let mut idb = Vec::new();
+        idb.extend_from_slice(&LINKTYPE_USER0.to_le_bytes());
+        idb.extend_from_slice(&0u16.to_le_bytes());
+        idb.extend_from_slice(&0u32.to_le_bytes());
+        write_option(&mut idb, OPT_END, &[]);
+        write_block(&mut out, INTERFACE_DESCRIPTION, &idb)?;
+
+        out.flush()?;
+
+        let (tx, rx) = mpsc::channel();
+        thread::Builder::new()
+            .name("postcard-rpc capture".into())
+            .spawn(move || writer(out, rx))?;
+        Ok(Self {
+            tx,
+            flush_each_frame: true,
+        })
+    }
+
+    /// Flush the writer after every frame, `true` by default
+    ///
+    /// Without this, frames may stay in the buffer of the writer until
+    /// [`Capture::flush()`] is called, or all handles of the capture are dropped.
+    /// Applies to frames recorded with this handle, and the taps and clones made
+    /// from it afterwards.
+    pub fn flush_each_frame(mut self, flush: bool) -> Self {
+        self.flush_each_frame = flush;
+        self
+    }
+
+    /// Wait until all frames recorded so far were written, and flush the writer
+    pub fn flush(&self) -> io::Result<()> {
+        let (tx, rx) = mpsc::channel();
+        self.tx.send(Command::Flush(tx)).map_err(|_| stopped())?;
+        rx.recv().map_err(|_| stopped())?
+    }
+
+    /// Record a frame, timestamped with the current time
+    ///
+    /// The frame is written in the background. Fails if writing a previous frame
+    /// failed, which stops the capture.
+    pub fn record(&self, direction: Direction, frame: &[u8]) -> io::Result<()> {
+        let micros = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        let flags = match direction {
+            Direction::Sent => FLAGS_OUTBOUND,
+            Direction::Received => FLAGS_INBOUND,
+        };
+
+        let mut epb = Vec::new();
+        epb.extend_from_slice(&0u32.to_le_bytes());
+        epb.extend_from_slice(&((micros >> 32) as u32).to_le_bytes());
+        epb.extend_from_slice(&(micros as u32).to_le_bytes());
+        epb.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        epb.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        epb.extend_from_slice(frame);
+        pad(&mut epb);
+        write_option(&mut epb, OPT_EPB_FLAGS, &flags.to_le_bytes());
+        write_option(&mut epb, OPT_COMMENT, describe(frame).as_bytes());
+        write_option(&mut epb, OPT_END, &[]);
+
+        let mut block = Vec::with_capacity(epb.len() + 12);
+        write_block(&mut block, ENHANCED_PACKET, &epb)?;
+        let flush = self.flush_each_frame;
+        self.tx
+            .send(Command::Block { block, flush })
+            .map_err(|_| stopped())
+    }
+
+    /// Wrap a wire implementation, recording all frames passing through it
+    pub fn tap<T>(&self, inner: T) -> Tap<T> {
+        Tap {
+            inner,
+            capture: self.clone(),
+            log_ctr: Arc::new(AtomicU32::new(0)),
+        }
+    }
+
+    /// Record a frame, only logging failures so the connection is not affected
+    fn record_or_warn(&self, direction: Direction, frame: &[u8]) {
+        if let Err(e) = self.record(direction, frame) {
+            tracing::warn!("Failed to record frame: {e:?}");
+        }
+    }
+}
+
+/// A wire implementation wrapped by [`Capture::tap()`]
+///
+/// Received frames are recorded once they are received, and sent frames are
+/// recorded before they are handed to the wrapped implementation, so they are
+/// recorded even if sending fails.
+///
+/// For the server [`WireTx`][server::WireTx], log messages are sent with
+/// [`WireTx::send()`][server::WireTx::send] using sequence numbers counted by
+/// the tap, so they can be recorded too.
+#[derive(Clone)]
+pub struct Tap<T> {
+    inner: T,
+    capture: Capture,
+    log_ctr: Arc<AtomicU32>,
+}
+
+impl<T> Tap<T> {
+    /// The wrapped wire implementation
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Stop recording, returning the wrapped wire implementation
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn log_header(&self, kkind: VarKeyKind) -> VarHeader {
+        let mut key = VarKey::Key8(LoggingTopic::TOPIC_KEY);
+        key.shrink_to(kkind);
+        VarHeader {
+            key,
+            seq_no: VarSeq::Seq4(self.log_ctr.fetch_add(1, Ordering::Relaxed)),
+        }
+    }
+}
+
+impl<T: host_client::WireTx> host_client::WireTx for Tap<T> {
+    type Error = T::Error;
+
+    async fn send(&mut self, data: Vec<u8>) -> Result<(), Self::Error> {
+        self.capture.record_or_warn(Direction::Sent, &data);
+        self.inner.send(data).await
+    }
+}
+
+impl<T: host_client::WireRx> host_client::WireRx for Tap<T> {
+    type Error = T::Error;
+
+    async fn receive(&mut self) -> Result<Vec<u8>, Self::Error> {
+        let data = self.inner.receive().await?;
+        self.capture.record_or_warn(Direction::Received, &data);
+        Ok(data)
+    }
+}
+
+impl<T: server::WireTx> server::WireTx for Tap<T> {
+    type Error = T::Error;
+
+    async fn wait_connection(&self) {
+        self.inner.wait_connection().await
+    }
+
+    async fn send<M: Serialize + ?Sized>(
+        &self,
+        hdr: VarHeader,
+        msg: &M,
+    ) -> Result<(), Self::Error> {
+        // If this fails, so does sending the message
+        if let Ok(body) = postcard::to_stdvec(msg) {
+            let mut frame = hdr.write_to_vec();
+            frame.extend_from_slice(&body);
+            self.capture.record_or_warn(Direction::Sent, &frame);
+        }
+        self.inner.send(hdr, msg).await
+    }
+
+    async fn send_raw(&self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.capture.record_or_warn(Direction::Sent, buf);
+        self.inner.send_raw(buf).await
+    }
+
+    async fn send_log_str(&self, kkind: VarKeyKind, s: &str) -> Result<(), Self::Error> {
+        let hdr = self.log_header(kkind);
+        self.send::<<LoggingTopic as Topic>::Message>(hdr, &s.to_string())
+            .await
+    }
+
+    async fn send_log_fmt<'a>(
+        &self,
+        kkind: VarKeyKind,
+        a: Arguments<'a>,
+    ) -> Result<(), Self::Error> {
+        let hdr = self.log_header(kkind);
+        self.send::<<LoggingTopic as Topic>::Message>(hdr, &a.to_string())
+            .await
+    }
+
+    fn max_frame_len(&self) -> Option<usize> {
+        self.inner.max_frame_len()
+    }
+}
+
+impl<T: server::WireRx> server::WireRx for Tap<T> {
+    type Error = T::Error;
+
+    async fn wait_connection(&mut self) {
+        self.inner.wait_connection().await
+    }
+
+    async fn receive<'a>(&mut self, buf: &'a mut [u8]) -> Result<&'a mut [u8], Self::Error> {
+        let frame = self.inner.receive(buf).await?;
+        self.capture.record_or_warn(Direction::Received, frame);
+        Ok(frame)
+    }
+}
+
+/// A frame read from a capture
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapturedFrame {
+    /// The time the frame was recorded
+    pub time: SystemTime,
+    /// The direction of the frame
+    pub direction: Direction,
+    /// The position of the frame in a fragmented message, if it is a fragment
+    pub fragment: Option<Fragment>,
+    /// The header and body of the frame
+    pub frame: RpcFrame,
+}
+
+/// An error reading a capture
+#[derive(Debug, Error)]
+pub enum CaptureError {
+    /// Reading the capture failed
+    #[error("reading the capture failed: {0}")]
+    Io(#[from] io::Error),
+    /// The data is not a little endian pcapng file
+    #[error("not a little endian pcapng file")]
+    NotPcapng,
+    /// A block of the file could not be decoded
+    #[error("malformed {0} block")]
+    Malformed(&'static str),
+    /// A frame has no direction
+    #[error("a frame has no direction")]
+    NoDirection,
+    /// A frame does not start with a valid [`VarHeader`]
+    ///
+    /// Reading can continue with the next frame.
+    #[error("a frame does not start with a valid header")]
+    InvalidHeader(Vec<u8>),
+}
+
+/// An interface of the section being read
+struct Interface {
+    linktype: u16,
+    /// The `if_tsresol` option
+    tsresol: u8,
+}
+
+/// Reads the frames of a capture written by a [`Capture`]
+///
+/// Packets of interfaces with another link type than the one used by
+/// [`Capture`] are skipped, as are unknown blocks.
+pub struct CaptureReader<R> {
+    input: R,
+    interfaces: Vec<Interface>,
+    done: bool,
+}
+
+impl CaptureReader<BufReader<File>> {
+    /// Open a capture file
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, CaptureError> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// Read a capture, checking that it starts with a pcapng section header
+    pub fn new(mut input: R) -> Result<Self, CaptureError> {
+        let (kind, body) = match read_block(&mut input) {
+            Ok(Some(block)) => block,
+            Err(CaptureError::Io(e)) if e.kind() != io::ErrorKind::UnexpectedEof => {
+                return Err(e.into())
+            }
+            // Empty, truncated, or not made of blocks at all
+            _ => return Err(CaptureError::NotPcapng),
+        };
+        if kind != SECTION_HEADER {
+            return Err(CaptureError::NotPcapng);
+        }
+        check_section(&body)?;
+        Ok(Self {
+            input,
+            interfaces: Vec::new(),
+            done: false,
+        })
+    }
+
+    /// Read the next frame, or `None` at the end of the capture
+    fn next_frame(&mut self) -> Result<Option<CapturedFrame>, CaptureError> {
+        loop {
+            let Some((kind, body)) = read_block(&mut self.input)? else {
+                return Ok(None);
+            };
+            match kind {
+                SECTION_HEADER => {
+                    check_section(&body)?;
+                    self.interfaces.clear();
+                }
+                INTERFACE_DESCRIPTION => {
+                    let iface = parse_interface(&body)?;
+                    self.interfaces.push(iface);
+                }
+                ENHANCED_PACKET => {
+                    if let Some(frame) = self.parse_packet(&body)? {
+                        return Ok(Some(frame));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn parse_packet(&self, body: &[u8]) -> Result<Option<CapturedFrame>, CaptureError> {
+        const MALFORMED: CaptureError = CaptureError::Malformed("enhanced packet");
+        let iface = u32_at(body, 0).ok_or(MALFORMED)?;
+        let iface = self.interfaces.get(iface as usize).ok_or(MALFORMED)?;
+        if iface.linktype != LINKTYPE_USER0 {
+            return Ok(None);
+        }
+        let hi = u32_at(body, 4).ok_or(MALFORMED)?;
+        let lo = u32_at(body, 8).ok_or(MALFORMED)?;
+        let caplen = u32_at(body, 12).ok_or(MALFORMED)? as usize;
+        let data = body.get(20..20 + caplen).ok_or(MALFORMED)?;
+        let options = &body[(20 + caplen).next_multiple_of(4).min(body.len())..];
+
+        let mut flags = None;
+        for (code, value) in parse_options(options).ok_or(MALFORMED)? {
+            if code == OPT_EPB_FLAGS {
+                flags = u32_at(value, 0);
+            }
+        }
+        let direction = match flags.map(|f| f & FLAGS_DIRECTION_MASK) {
+            Some(FLAGS_INBOUND) => Direction::Received,
+            Some(FLAGS_OUTBOUND) => Direction::Sent,
+            _ => return Err(CaptureError::NoDirection),
+        };
+
+        let ticks = (u64::from(hi) << 32) | u64::from(lo);
+        let time = UNIX_EPOCH + timestamp(ticks, iface.tsresol);
+
+        let Some((header, fragment, body)) = VarHeader::take_fragment_from_slice(data) else {
+            return Err(CaptureError::InvalidHeader(data.to_vec()));
+        };
+        Ok(Some(CapturedFrame {
+            time,
+            direction,
+            fragment,
+            frame: RpcFrame {
+                header,
+                body: body.to_vec(),
+            },
+        }))
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = Result<CapturedFrame, CaptureError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_frame() {
+            Ok(frame) => {
+                self.done = frame.is_none();
+                frame.map(Ok)
+            }
+            Err(e) => {
+                // The position in the file is lost, unless only the frame was invalid
+                self.done = !matches!(e, CaptureError::InvalidHeader(_));
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Describe the header of a frame, for the comment of its packet
+pub(crate) fn describe(frame: &[u8]) -> String {
+    let Some((header, fragment, _)) = VarHeader::take_fragment_from_slice(frame) else {
+        return "invalid header".into();
+    };
+    let key_len = match header.key.kind() {
+        VarKeyKind::Key1 => 1,
+        VarKeyKind::Key2 => 2,
+        VarKeyKind::Key4 => 4,
+        VarKeyKind::Key8 => 8,
+    };
+    let mut out = String::from("key ");
+    for b in &frame[1..1 + key_len] {
+        let _ = write!(out, "{b:02x}");
+    }
+    let seq: u32 = header.seq_no.into();
+    let _ = write!(out, ", seq {seq}");
+    if let Some(frag) = fragment {
+        let _ = write!(out, ", fragment {}", frag.index);
+        if frag.last {
+            out.push_str(" (last)");
+        }
+    }
+    out
+}
+
+fn pad(buf: &mut Vec<u8>) {
+    buf.resize(buf.len().next_multiple_of(4), 0);
+}
+
+fn write_option(buf: &mut Vec<u8>, code: u16, value: &[u8]) {
+    buf.extend_from_slice(&code.to_le_bytes());
+    buf.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    buf.extend_from_slice(value);
+    pad(buf);
+}
+
+/// Writes the blocks sent by the [`Capture`] handles, until they are all dropped
+/// or writing fails
+fn writer(mut out: impl Write, rx: mpsc::Receiver<Command>) {
+    for cmd in rx {
+        match cmd {
+            Command::Block { block, flush } => {
+                let res = out
+                    .write_all(&block)
+                    .and_then(|()| if flush { out.flush() } else { Ok(()) });
+                if let Err(e) = res {
+                    tracing::warn!("Failed to write capture, stopping: {e:?}");
+                    return;
+                }
+            }
+            Command::Flush(done) => {
+                let _ = done.send(out.flush());
+            }
+        }
+    }
+    if let Err(e) = out.flush() {
+        tracing::warn!("Failed to write capture: {e:?}");
+    }
+}
+
+/// The error returned once the writer thread stopped
+fn stopped() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "the capture writer stopped")
+}
+
+/// Write a block, with a body that is already padded
+fn write_block(out: &mut dyn Write, kind: u32, body: &[u8]) -> io::Result<()> {
+    let len = (body.len() as u32 + 12).to_le_bytes();
+    out.write_all(&kind.to_le_bytes())?;
+    out.write_all(&len)?;
+    out.write_all(body)?;
+    out.write_all(&len)
+}
+
+/// Read the type and body of the next block, or `None` at the end of the input
+fn read_block(input: &mut impl Read) -> Result<Option<(u32, Vec<u8>)>, CaptureError> {
+    let mut head = [0u8; 8];
+    // Only stop at the end of a block, a partial block is an error
+    let mut got = 0;
+    while got < head.len() {
+        match input.read(&mut head[got..]) {
+            Ok(0) if got == 0 => return Ok(None),
+            Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+            Ok(n) => got += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    let kind = u32::from_le_bytes([head[0], head[1], head[2], head[3]]);
+    let len = u32::from_le_bytes([head[4], head[5], head[6], head[7]]) as usize;
+    if len < 12 || !len.is_multiple_of(4) {
+        return Err(if kind == SECTION_HEADER {
+            CaptureError::NotPcapng
+        } else {
+            CaptureError::Malformed("pcapng")
+        });
+    }
+    let mut body = vec![0u8; len - 8];
+    input.read_exact(&mut body)?;
+    let trailer = body.split_off(len - 12);
+    if trailer != head[4..8] {
+        return Err(CaptureError::Malformed("pcapng"));
+    }
+    Ok(Some((kind, body)))
+}
+
+fn check_section(body: &[u8]) -> Result<(), CaptureError> {
+    match u32_at(body, 0) {
+        Some(BYTE_ORDER_MAGIC) => Ok(()),
+        _ => Err(CaptureError::NotPcapng),
+    }
+}
+
+fn parse_interface(body: &[u8]) -> Result<Interface, CaptureError> {
+    const MALFORMED: CaptureError = CaptureError::Malformed("interface description");
+    let linktype = body.get(..2).ok_or(MALFORMED)?;
+    let mut iface = Interface {
+        linktype: u16::from_le_bytes([linktype[0], linktype[1]]),
+        // Microseconds, the default
+        tsresol: 6,
+    };
+    let options = body.get(8..).ok_or(MALFORMED)?;
+    for (code, value) in parse_options(options).ok_or(MALFORMED)? {
+        if code == OPT_IF_TSRESOL {
+            iface.tsresol = *value.first().ok_or(MALFORMED)?;
+        }
+    }
+    Ok(iface)
+}
+
+/// Split the options of a block into their codes and values
+fn parse_options(mut options: &[u8]) -> Option<Vec<(u16, &[u8])>> {
+    let mut out = Vec::new();
+    while options.len() >= 4 {
+        let code = u16::from_le_bytes([options[0], options[1]]);
+        let len = u16::from_le_bytes([options[2], options[3]]) as usize;
+        if code == OPT_END {
+            break;
+        }
+        let value = options.get(4..4 + len)?;
+        out.push((code, value));
+        options = options.get((4 + len).next_multiple_of(4)..)?;
+    }
+    Some(out)
+}
+
+fn u32_at(buf: &[u8], pos: usize) -> Option<u32> {
+    let b = buf.get(pos..pos + 4)?;
+    Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// The time since the epoch of a timestamp, with the given `if_tsresol`
+fn timestamp(ticks: u64, tsresol: u8) -> Duration {
+    let ticks = u128::from(ticks);
+    let nanos = if tsresol & 0x80 == 0 {
+        let exp = u32::from(tsresol);
+        if exp <= 9 {
+            ticks * 10u128.pow(9 - exp)
+        } else {
+            ticks / 10u128.pow((exp - 9).min(38))
+        }
+    } else {
+        (ticks * 1_000_000_000) >> (tsresol & 0x7F).min(127)
+    };
+    Duration::from_nanos(nanos.min(u128::from(u64::MAX)) as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::Key;
+
+    /// A writer whose data can still be read after it was moved into a [`Capture`]
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let key = Key::for_path::<u32>("some/path");
+        let mut short_key = VarKey::Key8(key);
+        short_key.shrink_to(VarKeyKind::Key2);
+        let request = VarHeader {
+            key: VarKey::Key8(key),
+            seq_no: VarSeq::Seq4(1234),
+        };
+        let response = VarHeader {
+            key: short_key,
+            seq_no: VarSeq::Seq1(7),
+        };
+        let fragment = Fragment {
+            index: 3,
+            last: true,
+        };
+
+        let out = Shared::default();
+        let capture = Capture::new(out.clone()).unwrap();
+        let before = SystemTime::now() - Duration::from_secs(1);
+        capture
+            .record(
+                Direction::Sent,
+                &[request.write_to_vec(), vec![1, 2, 3]].concat(),
+            )
+            .unwrap();
+        capture
+            .record(
+                Direction::Received,
+                &response.write_fragment_to_vec(fragment),
+            )
+            .unwrap();
+        capture.record(Direction::Received, &[0x0F]).unwrap();
+        capture
+            .record(Direction::Received, &response.write_to_vec())
+            .unwrap();
+        capture.flush().unwrap();
+
+        let data = out.0.lock().unwrap().clone();
+        assert_eq!(data.len() % 4, 0);
+        let mut reader = CaptureReader::new(&data[..]).unwrap();
+
+        let first = reader.next().unwrap().unwrap();
+        assert_eq!(first.direction, Direction::Sent);
+        assert_eq!(first.frame.header, request);
+        assert_eq!(first.frame.body, [1, 2, 3]);
+        assert_eq!(first.fragment, None);
+        assert!(first.time > before && first.time <= SystemTime::now());
+
+        let second = reader.next().unwrap().unwrap();
+        assert_eq!(second.direction, Direction::Received);
+        assert!(matches!(second.frame.header.key, VarKey::Key2(_)));
+        assert_eq!(second.frame.header, response);
+        assert_eq!(second.fragment, Some(fragment));
+        assert!(second.frame.body.is_empty());
+
+        // Invalid frames are reported, but don't end the capture
+        assert!(matches!(
+            reader.next(),
+            Some(Err(CaptureError::InvalidHeader(f))) if f == [0x0F]
+        ));
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().is_none());
+    }
+
+    /// A writer that fails once `limit` bytes were written, and counts flushes
+    struct Limited {
+        written: usize,
+        limit: usize,
+        flushes: Arc<AtomicU32>,
+    }
+
+    impl Write for Limited {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.written + buf.len() > self.limit {
+                return Err(io::ErrorKind::WriteZero.into());
+            }
+            self.written += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.flushes.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn flushing() {
+        let frame = VarHeader {
+            key: VarKey::Key8(Key::for_path::<u32>("some/path")),
+            seq_no: VarSeq::Seq4(1),
+        }
+        .write_to_vec();
+        let flushes = Arc::new(AtomicU32::new(0));
+        let limited = |limit| Limited {
+            written: 0,
+            limit,
+            flushes: flushes.clone(),
+        };
+
+        // Once after the headers, and after each frame
+        let capture = Capture::new(limited(4096)).unwrap();
+        capture.record(Direction::Sent, &frame).unwrap();
+        capture.record(Direction::Sent, &frame).unwrap();
+        capture.flush().unwrap();
+        assert_eq!(flushes.swap(0, Ordering::Relaxed), 4);
+
+        // Only when asked to
+        let capture = Capture::new(limited(4096)).unwrap().flush_each_frame(false);
+        capture.record(Direction::Sent, &frame).unwrap();
+        capture.record(Direction::Sent, &frame).unwrap();
+        capture.flush().unwrap();
+        assert_eq!(flushes.swap(0, Ordering::Relaxed), 2);
+
+        // Failing to write stops the capture
+        let capture = Capture::new(limited(100)).unwrap();
+        capture.record(Direction::Sent, &frame).unwrap();
+        assert_eq!(
+            capture.flush().unwrap_err().kind(),
+            io::ErrorKind::BrokenPipe
+        );
+        assert!(capture.record(Direction::Sent, &frame).is_err());
+    }
+
+    #[test]
+    fn descriptions() {
+        let key = Key::for_path::<u32>("some/path");
+        let header = VarHeader {
+            key: VarKey::Key8(key),
+            seq_no: VarSeq::Seq2(513),
+        };
+        let hex: String = key.to_bytes().iter().map(|b| format!("{b:02x}")).collect();
+        assert_eq!(
+            describe(&header.write_to_vec()),
+            format!("key {hex}, seq 513")
+        );
+        let frag = Fragment {
+            index: 2,
+            last: false,
+        };
+        assert_eq!(
+            describe(&header.write_fragment_to_vec(frag)),
+            format!("key {hex}, seq 513, fragment 2")
+        );
+        assert_eq!(describe(&[0xFF]), "invalid header");
+    }
+
+    #[test]
+    fn not_pcapng() {
+        assert!(matches!(
+            CaptureReader::new(&b"not a capture at all"[..]),
+            Err(CaptureError::NotPcapng)
+        ));
+        assert!(matches!(
+            CaptureReader::new(&[][..]),
+            Err(CaptureError::NotPcapng)
+        ));
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(timestamp(1_500_000, 6), Duration::from_millis(1500));
+        assert_eq!(timestamp(1_500, 3), Duration::from_millis(1500));
+        assert_eq!(timestamp(3, 9), Duration::from_nanos(3));
+        assert_eq!(timestamp(1 << 10, 0x8A), Duration::from_secs(1));
+    }
+}
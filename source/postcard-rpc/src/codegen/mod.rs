@@ -0,0 +1,319 @@
+//! Generating bindings for other languages
+//!
+//! The generated code is a single self-contained file, containing:
+//!
+//! * A type for each struct and enum used by the ICD
+//! * Encoders and decoders for the postcard wire format
+//! * A constant for each endpoint and topic, with its path, [`Key`]s, and
+//!   the codecs of its types
+//! * Functions to frame requests and messages with a [`VarHeader`], and to
+//!   decode responses, including the [`WireError`] replied by the server
+//!
+//! Bindings are generated from a [`SchemaReport`], which can be obtained from a
+//! connected device, loaded from a file, or built from the lists of an ICD crate
+//! with [`SchemaReport::from_device_map()`] or [`SchemaReport::from_icd()`].
+//! Items of the standard ICD are left out.
+//!
+//! Constants are named after the path of their item, with all characters other
+//! than letters and digits replaced, so `"sensors/temp"` becomes `SENSORS_TEMP`.
+//!
+//! [`VarHeader`]: crate::header::VarHeader
+//! [`WireError`]: crate::standard_icd::WireError
+
+use std::collections::BTreeMap;
+
+use postcard_schema::{
+    schema::owned::{OwnedDataModelType, OwnedDataModelVariant, OwnedNamedType},
+    Schema,
+};
+use thiserror::Error;
+
+use crate::{
+    host_client::{EndpointReport, SchemaReport, TopicReport},
+    standard_icd::{
+        standard_endpoints, standard_topics_in, standard_topics_out, WireError, ERROR_KEY,
+    },
+    Key,
+};
+
+mod python;
+mod typescript;
+
+/// An error generating bindings
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum CodegenError {
+    /// Two different types, or two items, would get the same name
+    #[error("the name '{0}' is used more than once")]
+    NameConflict(String),
+    /// A type that can't be represented in the target language
+    #[error("the type '{0}' is not supported")]
+    Unsupported(String),
+}
+
+/// Generate Python bindings, requiring Python 3.8 or newer
+///
+/// Integers are `int`s, byte sequences are `bytes`, structs are dataclasses and
+/// enums are base classes, with a dataclass for each variant named after the enum
+/// and the variant. Options are `None` when empty, and maps are `dict`s.
+pub fn python(report: &SchemaReport) -> Result<String, CodegenError> {
+    let icd = Icd::new(report, python::RESERVED)?;
+    python::generate(&icd)
+}
+
+/// Generate TypeScript bindings
+///
+/// Integers of up to 32 bits are `number`s, larger ones are `bigint`s, byte
+/// sequences are `Uint8Array`s, structs are interfaces and enums are unions
+/// tagged with the name of the variant, holding their fields in `value`.
+/// Options are `null` when empty, and maps are `Map`s.
+pub fn typescript(report: &SchemaReport) -> Result<String, CodegenError> {
+    let icd = Icd::new(report, typescript::RESERVED)?;
+    Ok(typescript::generate(&icd))
+}
+
+/// The items and types to generate bindings for
+struct Icd<'a> {
+    /// Named types (structs and enums), by name
+    types: BTreeMap<String, OwnedNamedType>,
+    /// Endpoints, with the name of their constant
+    endpoints: Vec<(String, &'a EndpointReport)>,
+    /// Topics to the server, with the name of their constant
+    topics_in: Vec<(String, &'a TopicReport)>,
+    /// Topics to the client, with the name of their constant
+    topics_out: Vec<(String, &'a TopicReport)>,
+    /// The type of errors replied by the server
+    error: OwnedNamedType,
+    /// The key of errors replied by the server
+    error_key: Key,
+}
+
+impl<'a> Icd<'a> {
+    /// Collect all items outside the standard ICD and the types they use
+    ///
+    /// `reserved` lists the names used by the generated runtime code.
+    fn new(report: &'a SchemaReport, reserved: &[&str]) -> Result<Self, CodegenError> {
+        let std_eps: Vec<&str> = standard_endpoints().map(|e| e.0).collect();
+        let std_in: Vec<&str> = standard_topics_in().map(|t| t.0).collect();
+        let std_out: Vec<&str> = standard_topics_out().map(|t| t.0).collect();
+
+        let mut names: Vec<String> = reserved.iter().map(|r| r.to_string()).collect();
+        let mut constant = |path: &str| {
+            let name = constant_name(path);
+            if names.contains(&name) {
+                return Err(CodegenError::NameConflict(name));
+            }
+            names.push(name.clone());
+            Ok(name)
+        };
+        let endpoints = report
+            .endpoints
+            .iter()
+            .filter(|ep| !std_eps.contains(&ep.path.as_str()))
+            .map(|ep| Ok((constant(&ep.path)?, ep)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let topics_in = report
+            .topics_in
+            .iter()
+            .filter(|tp| !std_in.contains(&tp.path.as_str()))
+            .map(|tp| Ok((constant(&tp.path)?, tp)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let topics_out = report
+            .topics_out
+            .iter()
+            .filter(|tp| !std_out.contains(&tp.path.as_str()))
+            .map(|tp| Ok((constant(&tp.path)?, tp)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let error = OwnedNamedType::from(WireError::SCHEMA);
+        let mut types = BTreeMap::new();
+        collect_types(&error, &mut types)?;
+        for (_, ep) in endpoints.iter() {
+            collect_types(&ep.req_ty, &mut types)?;
+            collect_types(&ep.resp_ty, &mut types)?;
+        }
+        for (_, tp) in topics_in.iter().chain(topics_out.iter()) {
+            collect_types(&tp.ty, &mut types)?;
+        }
+        if let Some(name) = types.keys().find(|name| names.contains(name)) {
+            return Err(CodegenError::NameConflict(name.clone()));
+        }
+
+        Ok(Self {
+            types,
+            endpoints,
+            topics_in,
+            topics_out,
+            error,
+            error_key: ERROR_KEY,
+        })
+    }
+}
+
+/// Is this a struct or enum, which gets its own type in the bindings?
+fn is_named(ty: &OwnedDataModelType) -> bool {
+    matches!(
+        ty,
+        OwnedDataModelType::Struct(_)
+            | OwnedDataModelType::Enum(_)
+            | OwnedDataModelType::TupleStruct(_)
+            | OwnedDataModelType::NewtypeStruct(_)
+            | OwnedDataModelType::UnitStruct
+    )
+}
+
+/// Collect all named types used by a type, by name
+fn collect_types(
+    ty: &OwnedNamedType,
+    types: &mut BTreeMap<String, OwnedNamedType>,
+) -> Result<(), CodegenError> {
+    if is_named(&ty.ty) {
+        match types.get(&ty.name) {
+            Some(known) if known == ty => return Ok(()),
+            Some(_) => return Err(CodegenError::NameConflict(ty.name.clone())),
+            None => {
+                types.insert(ty.name.clone(), ty.clone());
+            }
+        }
+    }
+    match &ty.ty {
+        OwnedDataModelType::Usize | OwnedDataModelType::Isize | OwnedDataModelType::Schema => {
+            return Err(CodegenError::Unsupported(ty.name.clone()))
+        }
+        OwnedDataModelType::Option(t)
+        | OwnedDataModelType::NewtypeStruct(t)
+        | OwnedDataModelType::Seq(t) => collect_types(t, types)?,
+        OwnedDataModelType::Tuple(ts) | OwnedDataModelType::TupleStruct(ts) => {
+            for t in ts.iter() {
+                collect_types(t, types)?;
+            }
+        }
+        OwnedDataModelType::Map { key, val } => {
+            collect_types(key, types)?;
+            collect_types(val, types)?;
+        }
+        OwnedDataModelType::Struct(fields) => {
+            for f in fields.iter() {
+                collect_types(&f.ty, types)?;
+            }
+        }
+        OwnedDataModelType::Enum(variants) => {
+            for v in variants.iter() {
+                match &v.ty {
+                    OwnedDataModelVariant::UnitVariant => {}
+                    OwnedDataModelVariant::NewtypeVariant(t) => collect_types(t, types)?,
+                    OwnedDataModelVariant::TupleVariant(ts) => {
+                        for t in ts.iter() {
+                            collect_types(t, types)?;
+                        }
+                    }
+                    OwnedDataModelVariant::StructVariant(fields) => {
+                        for f in fields.iter() {
+                            collect_types(&f.ty, types)?;
+                        }
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// The name of the constant of an item, from its path
+fn constant_name(path: &str) -> String {
+    let mut name: String = path
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' => c.to_ascii_uppercase(),
+            _ => '_',
+        })
+        .collect();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+/// Is this a sequence of bytes, which is represented like a byte array?
+fn is_bytes(ty: &OwnedDataModelType) -> bool {
+    match ty {
+        OwnedDataModelType::ByteArray => true,
+        OwnedDataModelType::Seq(t) => t.ty == OwnedDataModelType::U8,
+        _ => false,
+    }
+}
+
+/// Indented lines of code
+struct Code {
+    out: String,
+    indent: usize,
+    step: &'static str,
+}
+
+impl Code {
+    fn new(step: &'static str) -> Self {
+        Self {
+            out: String::new(),
+            indent: 0,
+            step,
+        }
+    }
+
+    /// Add a line at the current indentation, or an empty one
+    fn line(&mut self, line: impl AsRef<str>) {
+        let line = line.as_ref();
+        if !line.is_empty() {
+            for _ in 0..self.indent {
+                self.out.push_str(self.step);
+            }
+            self.out.push_str(line);
+        }
+        self.out.push('\n');
+    }
+
+    fn indent(&mut self) {
+        self.indent += 1;
+    }
+
+    fn dedent(&mut self) {
+        self.indent -= 1;
+    }
+}
+
+/// A string literal, valid in both Python and TypeScript
+fn quote(s: &str) -> String {
+    let mut out = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// The bytes of a key, as lowercase hex
+fn key_hex(key: &Key) -> String {
+    key.to_bytes().iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn constant_names() {
+        assert_eq!(constant_name("sensors/temp"), "SENSORS_TEMP");
+        assert_eq!(constant_name("led-1/set"), "LED_1_SET");
+        assert_eq!(constant_name("1st"), "_1ST");
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(quote("a/b"), r#""a/b""#);
+        assert_eq!(quote("say \"hi\"\\\n"), r#""say \"hi\"\\\u000a""#);
+    }
+}
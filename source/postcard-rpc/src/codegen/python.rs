@@ -0,0 +1,715 @@
+//! Python bindings
+
+use postcard_schema::schema::owned::{
+    OwnedDataModelType, OwnedDataModelVariant, OwnedNamedType, OwnedNamedValue,
+};
+
+use super::{is_bytes, is_named, key_hex, quote, Code, CodegenError, Icd};
+
+/// Names defined by the runtime, or imported by it
+pub(super) const RESERVED: &[&str] = &[
+    "Any",
+    "Callable",
+    "Codec",
+    "DecodeError",
+    "Dict",
+    "ERROR_KEY",
+    "Endpoint",
+    "Exception",
+    "Generic",
+    "List",
+    "Optional",
+    "Reader",
+    "Req",
+    "Resp",
+    "T",
+    "Topic",
+    "Tuple",
+    "TypeVar",
+    "ValueError",
+    "VarHeader",
+    "WIRE_ERROR",
+    "WireErrorResponse",
+    "Writer",
+];
+
+/// Python keywords, and the methods of generated classes
+const KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "decode", "def", "del", "elif", "else", "encode", "except", "finally", "for", "from", "global",
+    "if", "import", "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return",
+    "try", "while", "with", "yield",
+];
+
+const RUNTIME: &str = r#"# Generated by postcard-rpc, do not edit.
+
+from __future__ import annotations
+
+import struct
+from dataclasses import dataclass
+from typing import Any, Callable, Dict, Generic, List, Optional, Tuple, TypeVar
+
+T = TypeVar("T")
+Req = TypeVar("Req")
+Resp = TypeVar("Resp")
+
+
+class DecodeError(Exception):
+    """The data does not match the schema"""
+
+
+class Writer:
+    """Encodes values in the postcard wire format"""
+
+    def __init__(self) -> None:
+        self.buf = bytearray()
+
+    def varint(self, v: int) -> None:
+        while v >= 0x80:
+            self.buf.append((v & 0x7F) | 0x80)
+            v >>= 7
+        self.buf.append(v)
+
+    def uint(self, v: int, bits: int) -> None:
+        if not 0 <= v < (1 << bits):
+            raise ValueError(f"{v} is not a u{bits}")
+        if bits == 8:
+            self.buf.append(v)
+        else:
+            self.varint(v)
+
+    def sint(self, v: int, bits: int) -> None:
+        if not -(1 << (bits - 1)) <= v < (1 << (bits - 1)):
+            raise ValueError(f"{v} is not an i{bits}")
+        if bits == 8:
+            self.buf.append(v & 0xFF)
+        else:
+            self.varint(v << 1 if v >= 0 else (-v << 1) - 1)
+
+    def boolean(self, v: bool) -> None:
+        self.buf.append(1 if v else 0)
+
+    def f32(self, v: float) -> None:
+        self.buf += struct.pack("<f", v)
+
+    def f64(self, v: float) -> None:
+        self.buf += struct.pack("<d", v)
+
+    def byte_array(self, v: bytes) -> None:
+        self.varint(len(v))
+        self.buf += v
+
+    def string(self, v: str) -> None:
+        self.byte_array(v.encode("utf-8"))
+
+    def char(self, v: str) -> None:
+        if len(v) != 1:
+            raise ValueError(f"{v!r} is not a single character")
+        self.string(v)
+
+    def unit(self, v: None) -> None:
+        pass
+
+    def option(self, v: Optional[Any], f: Callable[[Any], None]) -> None:
+        if v is None:
+            self.buf.append(0)
+        else:
+            self.buf.append(1)
+            f(v)
+
+    def seq(self, v: List[Any], f: Callable[[Any], None]) -> None:
+        self.varint(len(v))
+        for x in v:
+            f(x)
+
+    def map(
+        self, v: Dict[Any, Any], kf: Callable[[Any], None], vf: Callable[[Any], None]
+    ) -> None:
+        self.varint(len(v))
+        for k, x in v.items():
+            kf(k)
+            vf(x)
+
+    def tuple(self, v: Tuple[Any, ...], fs: List[Callable[[Any], None]]) -> None:
+        if len(v) != len(fs):
+            raise ValueError(f"expected a tuple of {len(fs)} items")
+        for x, f in zip(v, fs):
+            f(x)
+
+
+class Reader:
+    """Decodes values in the postcard wire format"""
+
+    def __init__(self, data: bytes) -> None:
+        self.data = bytes(data)
+        self.pos = 0
+
+    def take(self, n: int) -> bytes:
+        if self.pos + n > len(self.data):
+            raise DecodeError("unexpected end of data")
+        out = self.data[self.pos : self.pos + n]
+        self.pos += n
+        return out
+
+    def varint(self, bits: int) -> int:
+        v = 0
+        for i in range((bits + 6) // 7):
+            b = self.take(1)[0]
+            v |= (b & 0x7F) << (7 * i)
+            if b < 0x80:
+                if v >= (1 << bits):
+                    raise DecodeError(f"{v} does not fit in {bits} bits")
+                return v
+        raise DecodeError("varint is too long")
+
+    def uint(self, bits: int) -> int:
+        if bits == 8:
+            return self.take(1)[0]
+        return self.varint(bits)
+
+    def sint(self, bits: int) -> int:
+        if bits == 8:
+            v = self.take(1)[0]
+            return v - 0x100 if v >= 0x80 else v
+        z = self.varint(bits)
+        return (z >> 1) ^ -(z & 1)
+
+    def boolean(self) -> bool:
+        b = self.take(1)[0]
+        if b > 1:
+            raise DecodeError(f"{b} is not a bool")
+        return b == 1
+
+    def f32(self) -> float:
+        return struct.unpack("<f", self.take(4))[0]
+
+    def f64(self) -> float:
+        return struct.unpack("<d", self.take(8))[0]
+
+    def byte_array(self) -> bytes:
+        return self.take(self.varint(64))
+
+    def string(self) -> str:
+        try:
+            return self.byte_array().decode("utf-8")
+        except UnicodeDecodeError as e:
+            raise DecodeError(str(e)) from e
+
+    def char(self) -> str:
+        v = self.string()
+        if len(v) != 1:
+            raise DecodeError(f"{v!r} is not a single character")
+        return v
+
+    def unit(self) -> None:
+        return None
+
+    def option(self, f: Callable[[], T]) -> Optional[T]:
+        return f() if self.boolean() else None
+
+    def seq(self, f: Callable[[], T]) -> List[T]:
+        return [f() for _ in range(self.varint(64))]
+
+    def map(self, kf: Callable[[], Any], vf: Callable[[], Any]) -> Dict[Any, Any]:
+        out = {}
+        for _ in range(self.varint(64)):
+            k = kf()
+            out[k] = vf()
+        return out
+
+    def tuple(self, fs: List[Callable[[], Any]]) -> Tuple[Any, ...]:
+        return tuple(f() for f in fs)
+
+    def finish(self) -> None:
+        if self.pos != len(self.data):
+            raise DecodeError(f"{len(self.data) - self.pos} bytes left over")
+
+
+@dataclass(frozen=True)
+class Codec(Generic[T]):
+    """Encodes and decodes values of one type"""
+
+    encode: Callable[[Writer, T], None]
+    decode: Callable[[Reader], T]
+
+    def to_bytes(self, v: T) -> bytes:
+        w = Writer()
+        self.encode(w, v)
+        return bytes(w.buf)
+
+    def from_bytes(self, data: bytes) -> T:
+        r = Reader(data)
+        v = self.decode(r)
+        r.finish()
+        return v
+
+
+@dataclass(frozen=True)
+class Endpoint(Generic[Req, Resp]):
+    """An endpoint, with the keys and codecs of its request and response"""
+
+    path: str
+    req_key: bytes
+    resp_key: bytes
+    req: Codec[Req]
+    resp: Codec[Resp]
+
+
+@dataclass(frozen=True)
+class Topic(Generic[T]):
+    """A topic, with the key and codec of its messages"""
+
+    path: str
+    key: bytes
+    msg: Codec[T]
+
+
+@dataclass(frozen=True)
+class VarHeader:
+    """The header of a frame
+
+    The key has 1, 2, 4, or 8 bytes, the sequence number 1, 2, or 4 bytes.
+    """
+
+    key: bytes
+    seq_no: int
+    seq_len: int = 4
+
+    def to_bytes(self) -> bytes:
+        key_bits = {1: 0x00, 2: 0x40, 4: 0x80, 8: 0xC0}[len(self.key)]
+        seq_bits = {1: 0x00, 2: 0x10, 4: 0x20}[self.seq_len]
+        seq_no = self.seq_no.to_bytes(self.seq_len, "little")
+        return bytes([key_bits | seq_bits]) + self.key + seq_no
+
+    @staticmethod
+    def take_from(frame: bytes) -> Tuple[VarHeader, bytes]:
+        """Split a frame into its header and body"""
+        if not frame or frame[0] & 0x0F != 0:
+            raise DecodeError("not a frame header")
+        key_len = 1 << (frame[0] >> 6)
+        seq_len = {0x00: 1, 0x10: 2, 0x20: 4}.get(frame[0] & 0x30)
+        if seq_len is None:
+            raise DecodeError("invalid sequence number length")
+        end = 1 + key_len + seq_len
+        if len(frame) < end:
+            raise DecodeError("unexpected end of data")
+        seq_no = int.from_bytes(frame[1 + key_len : end], "little")
+        return VarHeader(frame[1 : 1 + key_len], seq_no, seq_len), frame[end:]
+
+
+def shrink_key(key: bytes, key_len: int) -> bytes:
+    """Shrink an 8-byte key to 1, 2, or 4 bytes"""
+    while len(key) > key_len:
+        key = bytes(a ^ b for a, b in zip(key[0::2], key[1::2]))
+    return key
+
+
+class WireErrorResponse(Exception):
+    """The server replied with an error instead of a response"""
+
+    def __init__(self, header: VarHeader, error: WireError) -> None:
+        super().__init__(f"the server replied with {error}")
+        self.header = header
+        self.error = error
+
+
+def request(
+    ep: Endpoint[Req, Resp], seq_no: int, req: Req, key_len: int = 8, seq_len: int = 4
+) -> bytes:
+    """Frame a request to an endpoint"""
+    header = VarHeader(shrink_key(ep.req_key, key_len), seq_no, seq_len)
+    return header.to_bytes() + ep.req.to_bytes(req)
+
+
+def response(ep: Endpoint[Req, Resp], frame: bytes) -> Tuple[VarHeader, Resp]:
+    """Decode the response of an endpoint
+
+    Raises a WireErrorResponse if the server replied with an error.
+    """
+    header, body = VarHeader.take_from(frame)
+    if header.key == shrink_key(ep.resp_key, len(header.key)):
+        return header, ep.resp.from_bytes(body)
+    if header.key == shrink_key(ERROR_KEY, len(header.key)):
+        raise WireErrorResponse(header, WIRE_ERROR.from_bytes(body))
+    raise DecodeError(f"unexpected key {header.key.hex()}")
+
+
+def publish(tp: Topic[T], seq_no: int, msg: T, key_len: int = 8, seq_len: int = 4) -> bytes:
+    """Frame a message of a topic"""
+    header = VarHeader(shrink_key(tp.key, key_len), seq_no, seq_len)
+    return header.to_bytes() + tp.msg.to_bytes(msg)
+
+
+def message(tp: Topic[T], frame: bytes) -> Tuple[VarHeader, T]:
+    """Decode a message of a topic"""
+    header, body = VarHeader.take_from(frame)
+    if header.key != shrink_key(tp.key, len(header.key)):
+        raise DecodeError(f"unexpected key {header.key.hex()}")
+    return header, tp.msg.from_bytes(body)
+"#;
+
+pub(super) fn generate(icd: &Icd<'_>) -> Result<String, CodegenError> {
+    // Variants get their own classes, which must not clash with anything else
+    let mut variants: Vec<String> = vec![];
+    for ty in icd.types.values() {
+        if let OwnedDataModelType::Enum(vs) = &ty.ty {
+            for v in vs.iter() {
+                let name = variant_class(&ty.name, &v.name);
+                if RESERVED.contains(&name.as_str())
+                    || icd.types.contains_key(&name)
+                    || variants.contains(&name)
+                {
+                    return Err(CodegenError::NameConflict(name));
+                }
+                variants.push(name);
+            }
+        }
+    }
+
+    let mut code = Code::new("    ");
+    code.out.push_str(RUNTIME);
+
+    for ty in icd.types.values() {
+        code.line("");
+        code.line("");
+        named_type(&mut code, ty);
+    }
+
+    code.line("");
+    code.line("");
+    code.line("# Errors");
+    code.line("");
+    code.line(format!(
+        "ERROR_KEY = bytes.fromhex({})",
+        quote(&key_hex(&icd.error_key))
+    ));
+    code.line(format!(
+        "WIRE_ERROR: Codec[{}] = {}",
+        py_type(&icd.error),
+        codec(&icd.error)
+    ));
+
+    if !icd.endpoints.is_empty() {
+        code.line("");
+        code.line("# Endpoints");
+        for (name, ep) in icd.endpoints.iter() {
+            code.line("");
+            code.line(format!(
+                "{name}: Endpoint[{}, {}] = Endpoint(",
+                py_type(&ep.req_ty),
+                py_type(&ep.resp_ty)
+            ));
+            code.indent();
+            code.line(format!("{},", quote(&ep.path)));
+            code.line(format!("bytes.fromhex({}),", quote(&key_hex(&ep.req_key))));
+            code.line(format!("bytes.fromhex({}),", quote(&key_hex(&ep.resp_key))));
+            code.line(format!("{},", codec(&ep.req_ty)));
+            code.line(format!("{},", codec(&ep.resp_ty)));
+            code.dedent();
+            code.line(")");
+        }
+    }
+
+    for (title, topics) in [
+        ("# Topics to the server", &icd.topics_in),
+        ("# Topics to the client", &icd.topics_out),
+    ] {
+        if topics.is_empty() {
+            continue;
+        }
+        code.line("");
+        code.line(title);
+        for (name, tp) in topics.iter() {
+            code.line("");
+            code.line(format!("{name}: Topic[{}] = Topic(", py_type(&tp.ty)));
+            code.indent();
+            code.line(format!("{},", quote(&tp.path)));
+            code.line(format!("bytes.fromhex({}),", quote(&key_hex(&tp.key))));
+            code.line(format!("{},", codec(&tp.ty)));
+            code.dedent();
+            code.line(")");
+        }
+    }
+
+    Ok(code.out)
+}
+
+/// The class of an enum variant
+fn variant_class(ty: &str, variant: &str) -> String {
+    format!("{ty}{variant}")
+}
+
+/// The name of a field, avoiding keywords
+fn field(name: &str) -> String {
+    if KEYWORDS.contains(&name) {
+        format!("{name}_")
+    } else {
+        name.to_string()
+    }
+}
+
+/// A class for a struct, or a class for an enum and each of its variants
+fn named_type(code: &mut Code, ty: &OwnedNamedType) {
+    let name = &ty.name;
+    match &ty.ty {
+        OwnedDataModelType::Struct(fields) => {
+            dataclass(code, name, None, &named_fields(fields));
+        }
+        OwnedDataModelType::TupleStruct(tys) => {
+            dataclass(code, name, None, &positional(tys));
+        }
+        OwnedDataModelType::NewtypeStruct(t) => {
+            dataclass(code, name, None, &[("_0".to_string(), &**t)]);
+        }
+        OwnedDataModelType::UnitStruct => {
+            dataclass(code, name, None, &[]);
+        }
+        OwnedDataModelType::Enum(variants) => {
+            code.line(format!("class {name}:"));
+            code.indent();
+            code.line("def encode(self, w: Writer) -> None:");
+            code.indent();
+            code.line("raise NotImplementedError");
+            code.dedent();
+            code.line("");
+            code.line("@staticmethod");
+            code.line(format!("def decode(r: Reader) -> {name}:"));
+            code.indent();
+            code.line("tag = r.uint(32)");
+            for (i, v) in variants.iter().enumerate() {
+                let class = variant_class(name, &v.name);
+                let args: Vec<String> = match &v.ty {
+                    OwnedDataModelVariant::UnitVariant => vec![],
+                    OwnedDataModelVariant::NewtypeVariant(t) => vec![dec(t)],
+                    OwnedDataModelVariant::TupleVariant(tys) => tys.iter().map(dec).collect(),
+                    OwnedDataModelVariant::StructVariant(fields) => {
+                        fields.iter().map(|f| dec(&f.ty)).collect()
+                    }
+                };
+                code.line(format!("if tag == {i}:"));
+                code.indent();
+                code.line(format!("return {class}({})", args.join(", ")));
+                code.dedent();
+            }
+            code.line(format!(
+                "raise DecodeError(f\"unknown variant {{tag}} of {name}\")"
+            ));
+            code.dedent();
+            code.dedent();
+
+            for (i, v) in variants.iter().enumerate() {
+                let class = variant_class(name, &v.name);
+                let fields: Vec<(String, &OwnedNamedType)> = match &v.ty {
+                    OwnedDataModelVariant::UnitVariant => vec![],
+                    OwnedDataModelVariant::NewtypeVariant(t) => vec![("_0".to_string(), &**t)],
+                    OwnedDataModelVariant::TupleVariant(tys) => positional(tys),
+                    OwnedDataModelVariant::StructVariant(fields) => named_fields(fields),
+                };
+                code.line("");
+                code.line("");
+                dataclass(code, &class, Some((name, i)), &fields);
+            }
+        }
+        _ => unreachable!("only named types get classes"),
+    }
+}
+
+fn positional(tys: &[OwnedNamedType]) -> Vec<(String, &OwnedNamedType)> {
+    tys.iter()
+        .enumerate()
+        .map(|(i, t)| (format!("_{i}"), t))
+        .collect()
+}
+
+fn named_fields(fields: &[OwnedNamedValue]) -> Vec<(String, &OwnedNamedType)> {
+    fields.iter().map(|f| (field(&f.name), &f.ty)).collect()
+}
+
+/// A dataclass with the given fields
+///
+/// Variants of enums derive from the enum, and encode their index first. Their
+/// decoder is inherited from the enum, which is returned by `decode`.
+fn dataclass(
+    code: &mut Code,
+    class: &str,
+    variant: Option<(&str, usize)>,
+    fields: &[(String, &OwnedNamedType)],
+) {
+    code.line("@dataclass");
+    match variant {
+        Some((base, _)) => code.line(format!("class {class}({base}):")),
+        None => code.line(format!("class {class}:")),
+    }
+    code.indent();
+    for (name, ty) in fields.iter() {
+        code.line(format!("{name}: {}", py_type(ty)));
+    }
+    if !fields.is_empty() {
+        code.line("");
+    }
+    code.line("def encode(self, w: Writer) -> None:");
+    code.indent();
+    if let Some((_, i)) = variant {
+        code.line(format!("w.uint({i}, 32)"));
+    }
+    for (name, ty) in fields.iter() {
+        code.line(enc(ty, &format!("self.{name}"), 0));
+    }
+    if variant.is_none() && fields.is_empty() {
+        code.line("pass");
+    }
+    code.dedent();
+    if variant.is_none() {
+        code.line("");
+        code.line("@staticmethod");
+        code.line(format!("def decode(r: Reader) -> {class}:"));
+        code.indent();
+        if fields.is_empty() {
+            code.line(format!("return {class}()"));
+        } else {
+            code.line(format!("return {class}("));
+            code.indent();
+            for (_, ty) in fields.iter() {
+                code.line(format!("{},", dec(ty)));
+            }
+            code.dedent();
+            code.line(")");
+        }
+        code.dedent();
+    }
+    code.dedent();
+}
+
+/// The type annotation of a type
+fn py_type(ty: &OwnedNamedType) -> String {
+    if is_bytes(&ty.ty) {
+        return "bytes".into();
+    }
+    if is_named(&ty.ty) {
+        return ty.name.clone();
+    }
+    match &ty.ty {
+        OwnedDataModelType::Bool => "bool".into(),
+        OwnedDataModelType::I8
+        | OwnedDataModelType::U8
+        | OwnedDataModelType::I16
+        | OwnedDataModelType::I32
+        | OwnedDataModelType::I64
+        | OwnedDataModelType::I128
+        | OwnedDataModelType::U16
+        | OwnedDataModelType::U32
+        | OwnedDataModelType::U64
+        | OwnedDataModelType::U128 => "int".into(),
+        OwnedDataModelType::F32 | OwnedDataModelType::F64 => "float".into(),
+        OwnedDataModelType::Char | OwnedDataModelType::String => "str".into(),
+        OwnedDataModelType::Unit => "None".into(),
+        OwnedDataModelType::Option(t) => format!("Optional[{}]", py_type(t)),
+        OwnedDataModelType::Seq(t) => format!("List[{}]", py_type(t)),
+        OwnedDataModelType::Tuple(tys) => {
+            let tys: Vec<String> = tys.iter().map(py_type).collect();
+            format!("Tuple[{}]", tys.join(", "))
+        }
+        OwnedDataModelType::Map { key, val } => {
+            format!("Dict[{}, {}]", py_type(key), py_type(val))
+        }
+        _ => unreachable!("unsupported types are rejected"),
+    }
+}
+
+/// The bit width of an integer, and whether it is signed
+fn int(ty: &OwnedDataModelType) -> Option<(u32, bool)> {
+    Some(match ty {
+        OwnedDataModelType::U8 => (8, false),
+        OwnedDataModelType::U16 => (16, false),
+        OwnedDataModelType::U32 => (32, false),
+        OwnedDataModelType::U64 => (64, false),
+        OwnedDataModelType::U128 => (128, false),
+        OwnedDataModelType::I8 => (8, true),
+        OwnedDataModelType::I16 => (16, true),
+        OwnedDataModelType::I32 => (32, true),
+        OwnedDataModelType::I64 => (64, true),
+        OwnedDataModelType::I128 => (128, true),
+        _ => return None,
+    })
+}
+
+/// An expression writing `val` to the writer `w`
+///
+/// Nested values are named after their `depth`, to avoid shadowing.
+fn enc(ty: &OwnedNamedType, val: &str, depth: usize) -> String {
+    if is_bytes(&ty.ty) {
+        return format!("w.byte_array({val})");
+    }
+    if is_named(&ty.ty) {
+        return format!("{val}.encode(w)");
+    }
+    if let Some((bits, signed)) = int(&ty.ty) {
+        let f = if signed { "sint" } else { "uint" };
+        return format!("w.{f}({val}, {bits})");
+    }
+    let v = format!("v{depth}");
+    let lambda = |t: &OwnedNamedType| format!("lambda {v}: {}", enc(t, &v, depth + 1));
+    match &ty.ty {
+        OwnedDataModelType::Bool => format!("w.boolean({val})"),
+        OwnedDataModelType::F32 => format!("w.f32({val})"),
+        OwnedDataModelType::F64 => format!("w.f64({val})"),
+        OwnedDataModelType::Char => format!("w.char({val})"),
+        OwnedDataModelType::String => format!("w.string({val})"),
+        OwnedDataModelType::Unit => format!("w.unit({val})"),
+        OwnedDataModelType::Option(t) => format!("w.option({val}, {})", lambda(t)),
+        OwnedDataModelType::Seq(t) => format!("w.seq({val}, {})", lambda(t)),
+        OwnedDataModelType::Tuple(tys) => {
+            let fs: Vec<String> = tys.iter().map(lambda).collect();
+            format!("w.tuple({val}, [{}])", fs.join(", "))
+        }
+        OwnedDataModelType::Map { key, val: value } => {
+            format!("w.map({val}, {}, {})", lambda(key), lambda(value))
+        }
+        _ => unreachable!("unsupported types are rejected"),
+    }
+}
+
+/// An expression reading a value from the reader `r`
+fn dec(ty: &OwnedNamedType) -> String {
+    if is_bytes(&ty.ty) {
+        return "r.byte_array()".into();
+    }
+    if is_named(&ty.ty) {
+        return format!("{}.decode(r)", ty.name);
+    }
+    if let Some((bits, signed)) = int(&ty.ty) {
+        let f = if signed { "sint" } else { "uint" };
+        return format!("r.{f}({bits})");
+    }
+    let lambda = |t: &OwnedNamedType| format!("lambda: {}", dec(t));
+    match &ty.ty {
+        OwnedDataModelType::Bool => "r.boolean()".into(),
+        OwnedDataModelType::F32 => "r.f32()".into(),
+        OwnedDataModelType::F64 => "r.f64()".into(),
+        OwnedDataModelType::Char => "r.char()".into(),
+        OwnedDataModelType::String => "r.string()".into(),
+        OwnedDataModelType::Unit => "r.unit()".into(),
+        OwnedDataModelType::Option(t) => format!("r.option({})", lambda(t)),
+        OwnedDataModelType::Seq(t) => format!("r.seq({})", lambda(t)),
+        OwnedDataModelType::Tuple(tys) => {
+            let fs: Vec<String> = tys.iter().map(lambda).collect();
+            format!("r.tuple([{}])", fs.join(", "))
+        }
+        OwnedDataModelType::Map { key, val } => {
+            format!("r.map({}, {})", lambda(key), lambda(val))
+        }
+        _ => unreachable!("unsupported types are rejected"),
+    }
+}
+
+/// A `Codec` for a type
+fn codec(ty: &OwnedNamedType) -> String {
+    if is_named(&ty.ty) {
+        return format!("Codec(lambda w, v: v.encode(w), {}.decode)", ty.name);
+    }
+    format!(
+        "Codec(lambda w, v: {}, lambda r: {})",
+        enc(ty, "v", 0),
+        dec(ty)
+    )
+}
@@ -0,0 +1,871 @@
+//! TypeScript bindings
+
+use postcard_schema::schema::owned::{OwnedDataModelType, OwnedDataModelVariant, OwnedNamedType};
+
+use super::{is_bytes, is_named, quote, Code, Icd};
+use crate::Key;
+
+/// Names defined by the runtime, and globals used by it
+pub(super) const RESERVED: &[&str] = &[
+    "Array",
+    "ArrayBuffer",
+    "BigInt",
+    "Codec",
+    "DataView",
+    "DecodeError",
+    "ERROR_KEY",
+    "Endpoint",
+    "Error",
+    "KEY_LENS",
+    "Map",
+    "Math",
+    "Number",
+    "RangeError",
+    "Reader",
+    "SEQ_LENS",
+    "String",
+    "TextDecoder",
+    "TextEncoder",
+    "Topic",
+    "Uint8Array",
+    "VarHeader",
+    "WIRE_ERROR",
+    "WireErrorResponse",
+    "Writer",
+];
+
+const RUNTIME: &str = r#"// Generated by postcard-rpc, do not edit.
+
+/** The data does not match the schema */
+export class DecodeError extends Error {}
+
+/** Encodes values in the postcard wire format */
+export class Writer {
+  private bytes: number[] = [];
+
+  finish(): Uint8Array {
+    return Uint8Array.from(this.bytes);
+  }
+
+  varint(v: bigint): void {
+    while (v >= 0x80n) {
+      this.bytes.push(Number(v & 0x7fn) | 0x80);
+      v >>= 7n;
+    }
+    this.bytes.push(Number(v));
+  }
+
+  uint(v: number, bits: number): void {
+    if (!Number.isInteger(v) || v < 0 || v >= 2 ** bits) {
+      throw new RangeError(`${v} is not a u${bits}`);
+    }
+    if (bits === 8) {
+      this.bytes.push(v);
+    } else {
+      this.varint(BigInt(v));
+    }
+  }
+
+  sint(v: number, bits: number): void {
+    if (!Number.isInteger(v) || v < -(2 ** (bits - 1)) || v >= 2 ** (bits - 1)) {
+      throw new RangeError(`${v} is not an i${bits}`);
+    }
+    if (bits === 8) {
+      this.bytes.push(v & 0xff);
+    } else {
+      this.zigzag(BigInt(v));
+    }
+  }
+
+  ubig(v: bigint, bits: number): void {
+    if (v < 0n || v >= 1n << BigInt(bits)) {
+      throw new RangeError(`${v} is not a u${bits}`);
+    }
+    this.varint(v);
+  }
+
+  sbig(v: bigint, bits: number): void {
+    const half = 1n << BigInt(bits - 1);
+    if (v < -half || v >= half) {
+      throw new RangeError(`${v} is not an i${bits}`);
+    }
+    this.zigzag(v);
+  }
+
+  private zigzag(v: bigint): void {
+    this.varint(v >= 0n ? v << 1n : (-v << 1n) - 1n);
+  }
+
+  boolean(v: boolean): void {
+    this.bytes.push(v ? 1 : 0);
+  }
+
+  f32(v: number): void {
+    const view = new DataView(new ArrayBuffer(4));
+    view.setFloat32(0, v, true);
+    this.raw(new Uint8Array(view.buffer));
+  }
+
+  f64(v: number): void {
+    const view = new DataView(new ArrayBuffer(8));
+    view.setFloat64(0, v, true);
+    this.raw(new Uint8Array(view.buffer));
+  }
+
+  byteArray(v: Uint8Array): void {
+    this.varint(BigInt(v.length));
+    this.raw(v);
+  }
+
+  string(v: string): void {
+    this.byteArray(new TextEncoder().encode(v));
+  }
+
+  char(v: string): void {
+    if ([...v].length !== 1) {
+      throw new RangeError(`${JSON.stringify(v)} is not a single character`);
+    }
+    this.string(v);
+  }
+
+  unit(_v: null): void {}
+
+  option<T>(v: T | null, f: (v: T) => void): void {
+    if (v === null) {
+      this.bytes.push(0);
+    } else {
+      this.bytes.push(1);
+      f(v);
+    }
+  }
+
+  seq<T>(v: T[], f: (v: T) => void): void {
+    this.varint(BigInt(v.length));
+    for (const x of v) {
+      f(x);
+    }
+  }
+
+  map<K, V>(v: Map<K, V>, kf: (k: K) => void, vf: (v: V) => void): void {
+    this.varint(BigInt(v.size));
+    for (const [k, x] of v) {
+      kf(k);
+      vf(x);
+    }
+  }
+
+  tuple(v: readonly unknown[], fs: ((v: any) => void)[]): void {
+    if (v.length !== fs.length) {
+      throw new RangeError(`expected a tuple of ${fs.length} items`);
+    }
+    fs.forEach((f, i) => f(v[i]));
+  }
+
+  private raw(v: Uint8Array): void {
+    for (const b of v) {
+      this.bytes.push(b);
+    }
+  }
+}
+
+/** Decodes values in the postcard wire format */
+export class Reader {
+  private data: Uint8Array;
+  private pos = 0;
+
+  constructor(data: Uint8Array) {
+    this.data = data;
+  }
+
+  take(n: number): Uint8Array {
+    if (this.pos + n > this.data.length) {
+      throw new DecodeError("unexpected end of data");
+    }
+    const out = this.data.subarray(this.pos, this.pos + n);
+    this.pos += n;
+    return out;
+  }
+
+  varint(bits: number): bigint {
+    let v = 0n;
+    for (let i = 0; i < Math.ceil(bits / 7); i++) {
+      const b = this.take(1)[0];
+      v |= BigInt(b & 0x7f) << BigInt(7 * i);
+      if (b < 0x80) {
+        if (v >= 1n << BigInt(bits)) {
+          throw new DecodeError(`${v} does not fit in ${bits} bits`);
+        }
+        return v;
+      }
+    }
+    throw new DecodeError("varint is too long");
+  }
+
+  uint(bits: number): number {
+    if (bits === 8) {
+      return this.take(1)[0];
+    }
+    return Number(this.varint(bits));
+  }
+
+  sint(bits: number): number {
+    if (bits === 8) {
+      const v = this.take(1)[0];
+      return v >= 0x80 ? v - 0x100 : v;
+    }
+    return Number(this.sbig(bits));
+  }
+
+  ubig(bits: number): bigint {
+    return this.varint(bits);
+  }
+
+  sbig(bits: number): bigint {
+    const z = this.varint(bits);
+    return z & 1n ? -(z >> 1n) - 1n : z >> 1n;
+  }
+
+  boolean(): boolean {
+    const b = this.take(1)[0];
+    if (b > 1) {
+      throw new DecodeError(`${b} is not a bool`);
+    }
+    return b === 1;
+  }
+
+  f32(): number {
+    const b = this.take(4);
+    return new DataView(b.buffer, b.byteOffset, 4).getFloat32(0, true);
+  }
+
+  f64(): number {
+    const b = this.take(8);
+    return new DataView(b.buffer, b.byteOffset, 8).getFloat64(0, true);
+  }
+
+  byteArray(): Uint8Array {
+    return this.take(Number(this.varint(64))).slice();
+  }
+
+  string(): string {
+    const b = this.byteArray();
+    try {
+      return new TextDecoder("utf-8", { fatal: true }).decode(b);
+    } catch (e) {
+      throw new DecodeError(String(e));
+    }
+  }
+
+  char(): string {
+    const v = this.string();
+    if ([...v].length !== 1) {
+      throw new DecodeError(`${JSON.stringify(v)} is not a single character`);
+    }
+    return v;
+  }
+
+  unit(): null {
+    return null;
+  }
+
+  option<T>(f: () => T): T | null {
+    return this.boolean() ? f() : null;
+  }
+
+  seq<T>(f: () => T): T[] {
+    const n = Number(this.varint(64));
+    const out: T[] = [];
+    for (let i = 0; i < n; i++) {
+      out.push(f());
+    }
+    return out;
+  }
+
+  map<K, V>(kf: () => K, vf: () => V): Map<K, V> {
+    const n = Number(this.varint(64));
+    const out = new Map<K, V>();
+    for (let i = 0; i < n; i++) {
+      const k = kf();
+      out.set(k, vf());
+    }
+    return out;
+  }
+
+  tuple(fs: (() => unknown)[]): unknown[] {
+    return fs.map((f) => f());
+  }
+
+  finish(): void {
+    if (this.pos !== this.data.length) {
+      throw new DecodeError(`${this.data.length - this.pos} bytes left over`);
+    }
+  }
+}
+
+/** Encodes and decodes values of one type */
+export interface Codec<T> {
+  encode: (w: Writer, v: T) => void;
+  decode: (r: Reader) => T;
+}
+
+export function toBytes<T>(codec: Codec<T>, v: T): Uint8Array {
+  const w = new Writer();
+  codec.encode(w, v);
+  return w.finish();
+}
+
+export function fromBytes<T>(codec: Codec<T>, data: Uint8Array): T {
+  const r = new Reader(data);
+  const v = codec.decode(r);
+  r.finish();
+  return v;
+}
+
+/** An endpoint, with the keys and codecs of its request and response */
+export interface Endpoint<Req, Resp> {
+  path: string;
+  reqKey: Uint8Array;
+  respKey: Uint8Array;
+  req: Codec<Req>;
+  resp: Codec<Resp>;
+}
+
+/** A topic, with the key and codec of its messages */
+export interface Topic<T> {
+  path: string;
+  key: Uint8Array;
+  msg: Codec<T>;
+}
+
+/**
+ * The header of a frame
+ *
+ * The key has 1, 2, 4, or 8 bytes, the sequence number 1, 2, or 4 bytes.
+ */
+export interface VarHeader {
+  key: Uint8Array;
+  seqNo: number;
+  seqLen: 1 | 2 | 4;
+}
+
+const KEY_LENS = [1, 2, 4, 8];
+const SEQ_LENS = [1, 2, 4];
+
+export function encodeVarHeader(h: VarHeader): Uint8Array {
+  const keyBits = KEY_LENS.indexOf(h.key.length);
+  const seqBits = SEQ_LENS.indexOf(h.seqLen);
+  if (keyBits < 0 || seqBits < 0) {
+    throw new RangeError("invalid key or sequence number length");
+  }
+  const out = new Uint8Array(1 + h.key.length + h.seqLen);
+  out[0] = (keyBits << 6) | (seqBits << 4);
+  out.set(h.key, 1);
+  for (let i = 0; i < h.seqLen; i++) {
+    out[1 + h.key.length + i] = (h.seqNo >>> (8 * i)) & 0xff;
+  }
+  return out;
+}
+
+/** Split a frame into its header and body */
+export function decodeVarHeader(frame: Uint8Array): [VarHeader, Uint8Array] {
+  if (frame.length < 1 || (frame[0] & 0x0f) !== 0) {
+    throw new DecodeError("not a frame header");
+  }
+  const keyLen = KEY_LENS[frame[0] >> 6];
+  const seqLen = SEQ_LENS[(frame[0] >> 4) & 0x03];
+  if (seqLen === undefined) {
+    throw new DecodeError("invalid sequence number length");
+  }
+  const end = 1 + keyLen + seqLen;
+  if (frame.length < end) {
+    throw new DecodeError("unexpected end of data");
+  }
+  let seqNo = 0;
+  for (let i = 0; i < seqLen; i++) {
+    seqNo += frame[1 + keyLen + i] * 2 ** (8 * i);
+  }
+  const header = { key: frame.slice(1, 1 + keyLen), seqNo, seqLen: seqLen as 1 | 2 | 4 };
+  return [header, frame.subarray(end)];
+}
+
+/** Shrink an 8-byte key to 1, 2, or 4 bytes */
+export function shrinkKey(key: Uint8Array, keyLen: number): Uint8Array {
+  while (key.length > keyLen) {
+    const next = new Uint8Array(key.length / 2);
+    for (let i = 0; i < next.length; i++) {
+      next[i] = key[2 * i] ^ key[2 * i + 1];
+    }
+    key = next;
+  }
+  return key;
+}
+
+function sameKey(header: VarHeader, key: Uint8Array): boolean {
+  const short = shrinkKey(key, header.key.length);
+  return short.length === header.key.length && short.every((b, i) => b === header.key[i]);
+}
+
+function hex(key: Uint8Array): string {
+  return Array.from(key, (b) => b.toString(16).padStart(2, "0")).join("");
+}
+
+/** The server replied with an error instead of a response */
+export class WireErrorResponse extends Error {
+  readonly header: VarHeader;
+  readonly error: WireError;
+
+  constructor(header: VarHeader, error: WireError) {
+    super(`the server replied with ${JSON.stringify(error)}`);
+    this.header = header;
+    this.error = error;
+  }
+}
+
+/** Frame a request to an endpoint */
+export function request<Req, Resp>(
+  ep: Endpoint<Req, Resp>,
+  seqNo: number,
+  req: Req,
+  keyLen = 8,
+  seqLen: 1 | 2 | 4 = 4,
+): Uint8Array {
+  const header = encodeVarHeader({ key: shrinkKey(ep.reqKey, keyLen), seqNo, seqLen });
+  const body = toBytes(ep.req, req);
+  const out = new Uint8Array(header.length + body.length);
+  out.set(header);
+  out.set(body, header.length);
+  return out;
+}
+
+/**
+ * Decode the response of an endpoint
+ *
+ * Throws a WireErrorResponse if the server replied with an error.
+ */
+export function response<Req, Resp>(ep: Endpoint<Req, Resp>, frame: Uint8Array): [VarHeader, Resp] {
+  const [header, body] = decodeVarHeader(frame);
+  if (sameKey(header, ep.respKey)) {
+    return [header, fromBytes(ep.resp, body)];
+  }
+  if (sameKey(header, ERROR_KEY)) {
+    throw new WireErrorResponse(header, fromBytes(WIRE_ERROR, body));
+  }
+  throw new DecodeError(`unexpected key ${hex(header.key)}`);
+}
+
+/** Frame a message of a topic */
+export function publish<T>(
+  tp: Topic<T>,
+  seqNo: number,
+  msg: T,
+  keyLen = 8,
+  seqLen: 1 | 2 | 4 = 4,
+): Uint8Array {
+  const header = encodeVarHeader({ key: shrinkKey(tp.key, keyLen), seqNo, seqLen });
+  const body = toBytes(tp.msg, msg);
+  const out = new Uint8Array(header.length + body.length);
+  out.set(header);
+  out.set(body, header.length);
+  return out;
+}
+
+/** Decode a message of a topic */
+export function message<T>(tp: Topic<T>, frame: Uint8Array): [VarHeader, T] {
+  const [header, body] = decodeVarHeader(frame);
+  if (!sameKey(header, tp.key)) {
+    throw new DecodeError(`unexpected key ${hex(header.key)}`);
+  }
+  return [header, fromBytes(tp.msg, body)];
+}
+"#;
+
+pub(super) fn generate(icd: &Icd<'_>) -> String {
+    let mut code = Code::new("  ");
+    code.out.push_str(RUNTIME);
+
+    for ty in icd.types.values() {
+        code.line("");
+        named_type(&mut code, ty);
+    }
+
+    code.line("");
+    code.line("// Errors");
+    code.line("");
+    code.line(format!(
+        "export const ERROR_KEY = {};",
+        key_array(&icd.error_key)
+    ));
+    code.line(format!(
+        "export const WIRE_ERROR: Codec<{}> = {};",
+        ts_type(&icd.error),
+        codec(&icd.error)
+    ));
+
+    if !icd.endpoints.is_empty() {
+        code.line("");
+        code.line("// Endpoints");
+        for (name, ep) in icd.endpoints.iter() {
+            code.line("");
+            code.line(format!(
+                "export const {name}: Endpoint<{}, {}> = {{",
+                ts_type(&ep.req_ty),
+                ts_type(&ep.resp_ty)
+            ));
+            code.indent();
+            code.line(format!("path: {},", quote(&ep.path)));
+            code.line(format!("reqKey: {},", key_array(&ep.req_key)));
+            code.line(format!("respKey: {},", key_array(&ep.resp_key)));
+            code.line(format!("req: {},", codec(&ep.req_ty)));
+            code.line(format!("resp: {},", codec(&ep.resp_ty)));
+            code.dedent();
+            code.line("};");
+        }
+    }
+
+    for (title, topics) in [
+        ("// Topics to the server", &icd.topics_in),
+        ("// Topics to the client", &icd.topics_out),
+    ] {
+        if topics.is_empty() {
+            continue;
+        }
+        code.line("");
+        code.line(title);
+        for (name, tp) in topics.iter() {
+            code.line("");
+            code.line(format!(
+                "export const {name}: Topic<{}> = {{",
+                ts_type(&tp.ty)
+            ));
+            code.indent();
+            code.line(format!("path: {},", quote(&tp.path)));
+            code.line(format!("key: {},", key_array(&tp.key)));
+            code.line(format!("msg: {},", codec(&tp.ty)));
+            code.dedent();
+            code.line("};");
+        }
+    }
+
+    code.out
+}
+
+/// A `Uint8Array` holding the bytes of a key
+fn key_array(key: &Key) -> String {
+    let bytes: Vec<String> = key
+        .to_bytes()
+        .iter()
+        .map(|b| format!("0x{b:02x}"))
+        .collect();
+    format!("new Uint8Array([{}])", bytes.join(", "))
+}
+
+/// A type, with an encoder and a decoder function
+fn named_type(code: &mut Code, ty: &OwnedNamedType) {
+    let name = &ty.name;
+    match &ty.ty {
+        OwnedDataModelType::Struct(fields) => {
+            code.line(format!("export interface {name} {{"));
+            code.indent();
+            for f in fields.iter() {
+                code.line(format!("{}: {};", f.name, ts_type(&f.ty)));
+            }
+            code.dedent();
+            code.line("}");
+            encoder(code, name, |code| {
+                for f in fields.iter() {
+                    code.line(format!("{};", enc(&f.ty, &format!("v.{}", f.name), 0)));
+                }
+            });
+            decoder(code, name, |code| {
+                code.line("return {");
+                code.indent();
+                for f in fields.iter() {
+                    code.line(format!("{}: {},", f.name, dec(&f.ty)));
+                }
+                code.dedent();
+                code.line("};");
+            });
+        }
+        OwnedDataModelType::TupleStruct(tys) => {
+            let tys_ts: Vec<String> = tys.iter().map(ts_type).collect();
+            code.line(format!("export type {name} = [{}];", tys_ts.join(", ")));
+            encoder(code, name, |code| {
+                for (i, t) in tys.iter().enumerate() {
+                    code.line(format!("{};", enc(t, &format!("v[{i}]"), 0)));
+                }
+            });
+            decoder(code, name, |code| {
+                let decs: Vec<String> = tys.iter().map(dec).collect();
+                code.line(format!("return [{}];", decs.join(", ")));
+            });
+        }
+        OwnedDataModelType::NewtypeStruct(t) => {
+            code.line(format!("export type {name} = {};", ts_type(t)));
+            encoder(code, name, |code| code.line(format!("{};", enc(t, "v", 0))));
+            decoder(code, name, |code| code.line(format!("return {};", dec(t))));
+        }
+        OwnedDataModelType::UnitStruct => {
+            code.line(format!("export type {name} = null;"));
+            encoder(code, name, |code| code.line("w.unit(v);"));
+            decoder(code, name, |code| code.line("return r.unit();"));
+        }
+        OwnedDataModelType::Enum(variants) => {
+            code.line(format!("export type {name} ="));
+            code.indent();
+            for (i, v) in variants.iter().enumerate() {
+                let end = if i + 1 == variants.len() { ";" } else { "" };
+                let tag = quote(&v.name);
+                match variant_type(&v.ty) {
+                    Some(value) => code.line(format!("| {{ tag: {tag}; value: {value} }}{end}")),
+                    None => code.line(format!("| {{ tag: {tag} }}{end}")),
+                }
+            }
+            if variants.is_empty() {
+                code.line("never;");
+            }
+            code.dedent();
+            encoder(code, name, |code| {
+                code.line("switch (v.tag) {");
+                code.indent();
+                for (i, v) in variants.iter().enumerate() {
+                    code.line(format!("case {}:", quote(&v.name)));
+                    code.indent();
+                    code.line(format!("w.uint({i}, 32);"));
+                    match &v.ty {
+                        OwnedDataModelVariant::UnitVariant => {}
+                        OwnedDataModelVariant::NewtypeVariant(t) => {
+                            code.line(format!("{};", enc(t, "v.value", 0)));
+                        }
+                        OwnedDataModelVariant::TupleVariant(tys) => {
+                            for (i, t) in tys.iter().enumerate() {
+                                code.line(format!("{};", enc(t, &format!("v.value[{i}]"), 0)));
+                            }
+                        }
+                        OwnedDataModelVariant::StructVariant(fields) => {
+                            for f in fields.iter() {
+                                let val = format!("v.value.{}", f.name);
+                                code.line(format!("{};", enc(&f.ty, &val, 0)));
+                            }
+                        }
+                    }
+                    code.line("break;");
+                    code.dedent();
+                }
+                code.dedent();
+                code.line("}");
+            });
+            decoder(code, name, |code| {
+                code.line("const tag = r.uint(32);");
+                code.line("switch (tag) {");
+                code.indent();
+                for (i, v) in variants.iter().enumerate() {
+                    code.line(format!("case {i}:"));
+                    code.indent();
+                    let tag = quote(&v.name);
+                    let value = match &v.ty {
+                        OwnedDataModelVariant::UnitVariant => None,
+                        OwnedDataModelVariant::NewtypeVariant(t) => Some(dec(t)),
+                        OwnedDataModelVariant::TupleVariant(tys) => {
+                            let decs: Vec<String> = tys.iter().map(dec).collect();
+                            Some(format!("[{}]", decs.join(", ")))
+                        }
+                        OwnedDataModelVariant::StructVariant(fields) => {
+                            let decs: Vec<String> = fields
+                                .iter()
+                                .map(|f| format!("{}: {}", f.name, dec(&f.ty)))
+                                .collect();
+                            Some(format!("{{ {} }}", decs.join(", ")))
+                        }
+                    };
+                    match value {
+                        Some(value) => {
+                            code.line(format!("return {{ tag: {tag}, value: {value} }};"))
+                        }
+                        None => code.line(format!("return {{ tag: {tag} }};")),
+                    }
+                    code.dedent();
+                }
+                code.line("default:");
+                code.indent();
+                code.line(format!(
+                    "throw new DecodeError(`unknown variant ${{tag}} of {name}`);"
+                ));
+                code.dedent();
+                code.dedent();
+                code.line("}");
+            });
+        }
+        _ => unreachable!("only named types get declarations"),
+    }
+}
+
+fn encoder(code: &mut Code, name: &str, body: impl FnOnce(&mut Code)) {
+    code.line("");
+    code.line(format!(
+        "export function encode{name}(w: Writer, v: {name}): void {{"
+    ));
+    code.indent();
+    body(code);
+    code.dedent();
+    code.line("}");
+}
+
+fn decoder(code: &mut Code, name: &str, body: impl FnOnce(&mut Code)) {
+    code.line("");
+    code.line(format!(
+        "export function decode{name}(r: Reader): {name} {{"
+    ));
+    code.indent();
+    body(code);
+    code.dedent();
+    code.line("}");
+}
+
+/// The type of the `value` of a variant, if it has one
+fn variant_type(ty: &OwnedDataModelVariant) -> Option<String> {
+    match ty {
+        OwnedDataModelVariant::UnitVariant => None,
+        OwnedDataModelVariant::NewtypeVariant(t) => Some(ts_type(t)),
+        OwnedDataModelVariant::TupleVariant(tys) => {
+            let tys: Vec<String> = tys.iter().map(ts_type).collect();
+            Some(format!("[{}]", tys.join(", ")))
+        }
+        OwnedDataModelVariant::StructVariant(fields) => {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|f| format!("{}: {}", f.name, ts_type(&f.ty)))
+                .collect();
+            Some(format!("{{ {} }}", fields.join("; ")))
+        }
+    }
+}
+
+/// The TypeScript type of a type
+fn ts_type(ty: &OwnedNamedType) -> String {
+    if is_bytes(&ty.ty) {
+        return "Uint8Array".into();
+    }
+    if is_named(&ty.ty) {
+        return ty.name.clone();
+    }
+    match &ty.ty {
+        OwnedDataModelType::Bool => "boolean".into(),
+        OwnedDataModelType::I8
+        | OwnedDataModelType::U8
+        | OwnedDataModelType::I16
+        | OwnedDataModelType::I32
+        | OwnedDataModelType::U16
+        | OwnedDataModelType::U32
+        | OwnedDataModelType::F32
+        | OwnedDataModelType::F64 => "number".into(),
+        OwnedDataModelType::I64
+        | OwnedDataModelType::I128
+        | OwnedDataModelType::U64
+        | OwnedDataModelType::U128 => "bigint".into(),
+        OwnedDataModelType::Char | OwnedDataModelType::String => "string".into(),
+        OwnedDataModelType::Unit => "null".into(),
+        OwnedDataModelType::Option(t) => format!("{} | null", ts_type(t)),
+        OwnedDataModelType::Seq(t) => format!("Array<{}>", ts_type(t)),
+        OwnedDataModelType::Tuple(tys) => {
+            let tys: Vec<String> = tys.iter().map(ts_type).collect();
+            format!("[{}]", tys.join(", "))
+        }
+        OwnedDataModelType::Map { key, val } => {
+            format!("Map<{}, {}>", ts_type(key), ts_type(val))
+        }
+        _ => unreachable!("unsupported types are rejected"),
+    }
+}
+
+/// An expression writing `val` to the writer `w`
+///
+/// Nested values are named after their `depth`, to avoid shadowing.
+fn enc(ty: &OwnedNamedType, val: &str, depth: usize) -> String {
+    if is_bytes(&ty.ty) {
+        return format!("w.byteArray({val})");
+    }
+    if is_named(&ty.ty) {
+        return format!("encode{}(w, {val})", ty.name);
+    }
+    let v = format!("v{depth}");
+    let lambda = |t: &OwnedNamedType| format!("({v}) => {}", enc(t, &v, depth + 1));
+    match &ty.ty {
+        OwnedDataModelType::Bool => format!("w.boolean({val})"),
+        OwnedDataModelType::U8 => format!("w.uint({val}, 8)"),
+        OwnedDataModelType::U16 => format!("w.uint({val}, 16)"),
+        OwnedDataModelType::U32 => format!("w.uint({val}, 32)"),
+        OwnedDataModelType::U64 => format!("w.ubig({val}, 64)"),
+        OwnedDataModelType::U128 => format!("w.ubig({val}, 128)"),
+        OwnedDataModelType::I8 => format!("w.sint({val}, 8)"),
+        OwnedDataModelType::I16 => format!("w.sint({val}, 16)"),
+        OwnedDataModelType::I32 => format!("w.sint({val}, 32)"),
+        OwnedDataModelType::I64 => format!("w.sbig({val}, 64)"),
+        OwnedDataModelType::I128 => format!("w.sbig({val}, 128)"),
+        OwnedDataModelType::F32 => format!("w.f32({val})"),
+        OwnedDataModelType::F64 => format!("w.f64({val})"),
+        OwnedDataModelType::Char => format!("w.char({val})"),
+        OwnedDataModelType::String => format!("w.string({val})"),
+        OwnedDataModelType::Unit => format!("w.unit({val})"),
+        OwnedDataModelType::Option(t) => format!("w.option({val}, {})", lambda(t)),
+        OwnedDataModelType::Seq(t) => format!("w.seq({val}, {})", lambda(t)),
+        OwnedDataModelType::Tuple(tys) => {
+            let fs: Vec<String> = tys.iter().map(lambda).collect();
+            format!("w.tuple({val}, [{}])", fs.join(", "))
+        }
+        OwnedDataModelType::Map { key, val: value } => {
+            format!("w.map({val}, {}, {})", lambda(key), lambda(value))
+        }
+        _ => unreachable!("unsupported types are rejected"),
+    }
+}
+
+/// An expression reading a value from the reader `r`
+fn dec(ty: &OwnedNamedType) -> String {
+    if is_bytes(&ty.ty) {
+        return "r.byteArray()".into();
+    }
+    if is_named(&ty.ty) {
+        return format!("decode{}(r)", ty.name);
+    }
+    let lambda = |t: &OwnedNamedType| format!("() => {}", dec(t));
+    match &ty.ty {
+        OwnedDataModelType::Bool => "r.boolean()".into(),
+        OwnedDataModelType::U8 => "r.uint(8)".into(),
+        OwnedDataModelType::U16 => "r.uint(16)".into(),
+        OwnedDataModelType::U32 => "r.uint(32)".into(),
+        OwnedDataModelType::U64 => "r.ubig(64)".into(),
+        OwnedDataModelType::U128 => "r.ubig(128)".into(),
+        OwnedDataModelType::I8 => "r.sint(8)".into(),
+        OwnedDataModelType::I16 => "r.sint(16)".into(),
+        OwnedDataModelType::I32 => "r.sint(32)".into(),
+        OwnedDataModelType::I64 => "r.sbig(64)".into(),
+        OwnedDataModelType::I128 => "r.sbig(128)".into(),
+        OwnedDataModelType::F32 => "r.f32()".into(),
+        OwnedDataModelType::F64 => "r.f64()".into(),
+        OwnedDataModelType::Char => "r.char()".into(),
+        OwnedDataModelType::String => "r.string()".into(),
+        OwnedDataModelType::Unit => "r.unit()".into(),
+        OwnedDataModelType::Option(t) => format!("r.option({})", lambda(t)),
+        OwnedDataModelType::Seq(t) => format!("r.seq({})", lambda(t)),
+        OwnedDataModelType::Tuple(tys) => {
+            let fs: Vec<String> = tys.iter().map(lambda).collect();
+            format!("(r.tuple([{}]) as {})", fs.join(", "), ts_type(ty))
+        }
+        OwnedDataModelType::Map { key, val } => {
+            format!("r.map({}, {})", lambda(key), lambda(val))
+        }
+        _ => unreachable!("unsupported types are rejected"),
+    }
+}
+
+/// A `Codec` for a type
+fn codec(ty: &OwnedNamedType) -> String {
+    if is_named(&ty.ty) {
+        return format!("{{ encode: encode{0}, decode: decode{0} }}", ty.name);
+    }
+    format!(
+        "{{ encode: (w, v) => {}, decode: (r) => {} }}",
+        enc(ty, "v", 0),
+        dec(ty)
+    )
+}
@@ -0,0 +1,202 @@
+//! A const digest of a [`DeviceMap`]
+//!
+//! The digest is an FNV-1a hash over everything a `SchemaReport` is built from:
+//! the full types, including their names, and the paths and keys of all endpoints
+//! and topics. Two devices with the same digest report the same schema.
+
+use postcard_schema::schema::{
+    DataModelType, DataModelVariant, NamedType, NamedValue, NamedVariant,
+};
+
+use crate::{DeviceMap, Key};
+
+const BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Calculate the digest of a [`DeviceMap`]
+pub(crate) const fn device_map(map: &DeviceMap) -> u64 {
+    let mut state = BASIS;
+
+    state = update_len(state, map.types.len());
+    let mut idx = 0;
+    while idx < map.types.len() {
+        state = named_type(state, map.types[idx]);
+        idx += 1;
+    }
+
+    state = update_len(state, map.endpoints.len());
+    let mut idx = 0;
+    while idx < map.endpoints.len() {
+        let (path, req_key, resp_key) = &map.endpoints[idx];
+        state = update_str(state, path);
+        state = update_key(state, req_key);
+        state = update_key(state, resp_key);
+        idx += 1;
+    }
+
+    state = topics(state, map.topics_in);
+    topics(state, map.topics_out)
+}
+
+const fn topics(mut state: u64, topics: &[(&str, Key)]) -> u64 {
+    state = update_len(state, topics.len());
+    let mut idx = 0;
+    while idx < topics.len() {
+        let (path, key) = &topics[idx];
+        state = update_str(state, path);
+        state = update_key(state, key);
+        idx += 1;
+    }
+    state
+}
+
+const fn update(mut state: u64, bytes: &[u8]) -> u64 {
+    let mut idx = 0;
+    while idx < bytes.len() {
+        state ^= bytes[idx] as u64;
+        state = state.wrapping_mul(PRIME);
+        idx += 1;
+    }
+    state
+}
+
+/// Lengths keep neighbouring strings and lists apart
+const fn update_len(state: u64, len: usize) -> u64 {
+    update(state, &(len as u32).to_le_bytes())
+}
+
+const fn update_str(state: u64, s: &str) -> u64 {
+    let state = update_len(state, s.len());
+    update(state, s.as_bytes())
+}
+
+const fn update_key(state: u64, key: &Key) -> u64 {
+    update(state, &key.to_bytes())
+}
+
+const fn named_types(mut state: u64, nts: &[&NamedType]) -> u64 {
+    state = update_len(state, nts.len());
+    let mut idx = 0;
+    while idx < nts.len() {
+        state = named_type(state, nts[idx]);
+        idx += 1;
+    }
+    state
+}
+
+const fn named_values(mut state: u64, nvs: &[&NamedValue]) -> u64 {
+    state = update_len(state, nvs.len());
+    let mut idx = 0;
+    while idx < nvs.len() {
+        state = update_str(state, nvs[idx].name);
+        state = named_type(state, nvs[idx].ty);
+        idx += 1;
+    }
+    state
+}
+
+const fn named_variants(mut state: u64, nvs: &[&NamedVariant]) -> u64 {
+    state = update_len(state, nvs.len());
+    let mut idx = 0;
+    while idx < nvs.len() {
+        state = update_str(state, nvs[idx].name);
+        state = match nvs[idx].ty {
+            DataModelVariant::UnitVariant => update(state, &[0]),
+            DataModelVariant::NewtypeVariant(nt) => named_type(update(state, &[1]), nt),
+            DataModelVariant::TupleVariant(nts) => named_types(update(state, &[2]), nts),
+            DataModelVariant::StructVariant(nvs) => named_values(update(state, &[3]), nvs),
+        };
+        idx += 1;
+    }
+    state
+}
+
+/// Unlike keys, the digest includes the names of types
+const fn named_type(state: u64, nt: &NamedType) -> u64 {
+    let state = update_str(state, nt.name);
+    match nt.ty {
+        DataModelType::Bool => update(state, &[0]),
+        DataModelType::I8 => update(state, &[1]),
+        DataModelType::U8 => update(state, &[2]),
+        DataModelType::I16 => update(state, &[3]),
+        DataModelType::I32 => update(state, &[4]),
+        DataModelType::I64 => update(state, &[5]),
+        DataModelType::I128 => update(state, &[6]),
+        DataModelType::U16 => update(state, &[7]),
+        DataModelType::U32 => update(state, &[8]),
+        DataModelType::U64 => update(state, &[9]),
+        DataModelType::U128 => update(state, &[10]),
+        DataModelType::Usize => update(state, &[11]),
+        DataModelType::Isize => update(state, &[12]),
+        DataModelType::F32 => update(state, &[13]),
+        DataModelType::F64 => update(state, &[14]),
+        DataModelType::Char => update(state, &[15]),
+        DataModelType::String => update(state, &[16]),
+        DataModelType::ByteArray => update(state, &[17]),
+        DataModelType::Option(nt) => named_type(update(state, &[18]), nt),
+        DataModelType::Unit => update(state, &[19]),
+        DataModelType::UnitStruct => update(state, &[20]),
+        DataModelType::NewtypeStruct(nt) => named_type(update(state, &[21]), nt),
+        DataModelType::Seq(nt) => named_type(update(state, &[22]), nt),
+        DataModelType::Tuple(nts) => named_types(update(state, &[23]), nts),
+        DataModelType::TupleStruct(nts) => named_types(update(state, &[24]), nts),
+        DataModelType::Map { key, val } => {
+            let state = named_type(update(state, &[25]), key);
+            named_type(state, val)
+        }
+        DataModelType::Struct(nvs) => named_values(update(state, &[26]), nvs),
+        DataModelType::Enum(nvs) => named_variants(update(state, &[27]), nvs),
+        DataModelType::Schema => update(state, &[28]),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use postcard_schema::{schema::NamedType, Schema};
+    use serde::{Deserialize, Serialize};
+
+    use crate::{header::VarKeyKind, DeviceMap, Key};
+
+    #[derive(Serialize, Deserialize, Schema)]
+    struct Alpha {
+        a: u8,
+    }
+
+    #[derive(Serialize, Deserialize, Schema)]
+    struct Beta {
+        a: u8,
+    }
+
+    #[derive(Serialize, Deserialize, Schema)]
+    struct Gamma {
+        b: u8,
+    }
+
+    const fn map(
+        types: &'static [&'static NamedType],
+        endpoints: &'static [(&'static str, Key, Key)],
+    ) -> DeviceMap {
+        DeviceMap {
+            types,
+            endpoints,
+            topics_in: &[],
+            topics_out: &[],
+            min_key_len: VarKeyKind::Key8,
+        }
+    }
+
+    #[test]
+    fn digest() {
+        const KEY_A: Key = Key::for_path::<Alpha>("a");
+        const KEY_B: Key = Key::for_path::<Alpha>("b");
+        const BASE: u64 = map(&[Alpha::SCHEMA], &[("a", KEY_A, KEY_A)]).digest();
+
+        assert_eq!(BASE, map(&[Alpha::SCHEMA], &[("a", KEY_A, KEY_A)]).digest());
+        // Type names, field names, paths and keys all matter
+        assert_ne!(BASE, map(&[Beta::SCHEMA], &[("a", KEY_A, KEY_A)]).digest());
+        assert_ne!(BASE, map(&[Gamma::SCHEMA], &[("a", KEY_A, KEY_A)]).digest());
+        assert_ne!(BASE, map(&[Alpha::SCHEMA], &[("b", KEY_A, KEY_A)]).digest());
+        assert_ne!(BASE, map(&[Alpha::SCHEMA], &[("a", KEY_A, KEY_B)]).digest());
+        assert_ne!(BASE, map(&[Alpha::SCHEMA], &[]).digest());
+    }
+}
@@ -0,0 +1,253 @@
+//! Fragmentation and reassembly of oversized frames
+//!
+//! Messages that don't fit the buffer of the receiver, or the MTU of the transport,
+//! may be split into fragments. Each fragment is sent as its own frame, using the
+//! header of the whole message with a [`Fragment`] extension, see the
+//! [`header` module](crate::header#fragment-extension).
+//!
+//! Fragments of a message must be sent in order, and without fragments of other
+//! messages in between. The [`Reassembler`] collects them into a caller provided
+//! buffer, which limits the size of reassembled messages.
+//!
+//! Fragmentation is handled by the [`Server`][crate::server::Server] and
+//! `HostClient`, and is usually not used directly.
+
+use core::ops::DerefMut;
+
+use crate::header::{Fragment, VarHeader};
+
+/// Errors that can occur while reassembling a message
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ReassemblyError {
+    /// The message is larger than the reassembly buffer
+    TooLarge {
+        /// The length of the message, up to and including this fragment
+        len: usize,
+    },
+    /// A fragment was lost, or was not the next fragment of the message
+    OutOfOrder,
+}
+
+/// Collects fragments until a whole message has been received
+pub struct Reassembler<B: DerefMut<Target = [u8]>> {
+    buf: B,
+    hdr: Option<VarHeader>,
+    /// The header of the last discarded message, whose other fragments are ignored
+    discarded: Option<VarHeader>,
+    next: u16,
+    used: usize,
+}
+
+impl<B: DerefMut<Target = [u8]>> Reassembler<B> {
+    /// Create a new reassembler, which can reassemble messages up to the size of `buf`
+    pub fn new(buf: B) -> Self {
+        Self {
+            buf,
+            hdr: None,
+            discarded: None,
+            next: 0,
+            used: 0,
+        }
+    }
+
+    /// The largest message that can be reassembled
+    pub fn max_len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Add the fragment `frag` of the message with header `hdr`
+    ///
+    /// Returns the header and body of the message once the last fragment was added.
+    /// A first fragment (with index zero) discards any partial message. On error,
+    /// the partial message is discarded, and its remaining fragments are ignored, so
+    /// that each message fails at most once.
+    pub fn push(
+        &mut self,
+        hdr: VarHeader,
+        frag: Fragment,
+        body: &[u8],
+    ) -> Result<Option<(VarHeader, &[u8])>, ReassemblyError> {
+        if frag.index == 0 {
+            self.hdr = Some(hdr);
+            self.discarded = None;
+            self.next = 0;
+            self.used = 0;
+        } else if self.discarded == Some(hdr) {
+            return Ok(None);
+        }
+        if self.hdr != Some(hdr) || self.next != frag.index {
+            self.hdr = None;
+            self.discarded = Some(hdr);
+            return Err(ReassemblyError::OutOfOrder);
+        }
+
+        let start = self.used;
+        let end = start + body.len();
+        let Some(dest) = self.buf.get_mut(start..end) else {
+            self.hdr = None;
+            self.discarded = Some(hdr);
+            return Err(ReassemblyError::TooLarge { len: end });
+        };
+        dest.copy_from_slice(body);
+        self.used = end;
+        self.next = self.next.wrapping_add(1);
+
+        if !frag.last {
+            return Ok(None);
+        }
+        self.hdr = None;
+        Ok(Some((hdr, &self.buf[..end])))
+    }
+}
+
+/// The number of body bytes each fragment of a message with header `hdr` can carry,
+/// if frames may be at most `frag_size` bytes long
+///
+/// Returns `None` if `frag_size` is too small to carry any body bytes.
+pub fn fragment_body_len(hdr: &VarHeader, frag_size: usize) -> Option<usize> {
+    frag_size
+        .checked_sub(hdr.encoded_len() + 2)
+        .filter(|len| *len != 0)
+}
+
+/// The longest body a message with header `hdr` can have, if frames may be at most
+/// `frag_size` bytes long
+///
+/// Messages up to this length can be sent, either in one frame or in at most
+/// [`Fragment::MAX_INDEX`] + 1 fragments.
+pub fn max_body_len(hdr: &VarHeader, frag_size: usize) -> usize {
+    let whole = frag_size.saturating_sub(hdr.encoded_len());
+    let fragmented = fragment_body_len(hdr, frag_size)
+        .map_or(0, |per| per * (usize::from(Fragment::MAX_INDEX) + 1));
+    whole.max(fragmented)
+}
+
+/// Encode a message into one frame, or into fragments of at most `frag_size` bytes
+///
+/// Returns `None` if the body is longer than [`max_body_len()`].
+#[cfg(feature = "use-std")]
+pub fn split_to_vecs(hdr: &VarHeader, body: &[u8], frag_size: usize) -> Option<Vec<Vec<u8>>> {
+    if hdr.encoded_len() + body.len() <= frag_size {
+        let mut out = hdr.write_to_vec();
+        out.extend_from_slice(body);
+        return Some(vec![out]);
+    }
+    if body.len() > max_body_len(hdr, frag_size) {
+        return None;
+    }
+
+    let per = fragment_body_len(hdr, frag_size)?;
+    let count = body.len().div_ceil(per);
+    let frames = body
+        .chunks(per)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let frag = Fragment {
+                index: i as u16,
+                last: i + 1 == count,
+            };
+            let mut out = hdr.write_fragment_to_vec(frag);
+            out.extend_from_slice(chunk);
+            out
+        })
+        .collect();
+    Some(frames)
+}
+
+#[cfg(all(test, feature = "use-std"))]
+mod test {
+    use super::{max_body_len, split_to_vecs, Reassembler, ReassemblyError};
+    use crate::{
+        header::{Fragment, VarHeader, VarKey, VarSeq},
+        Key1,
+    };
+
+    fn hdr(seq: u8) -> VarHeader {
+        VarHeader {
+            key: VarKey::Key1(Key1(7)),
+            seq_no: VarSeq::Seq1(seq),
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let body: Vec<u8> = (0..100).collect();
+        let frames = split_to_vecs(&hdr(1), &body, 16).unwrap();
+        // 16 byte frames, with 3 header bytes and 2 extension bytes
+        assert_eq!(frames.len(), 10);
+        assert!(frames.iter().all(|f| f.len() <= 16));
+
+        let mut reasm = Reassembler::new(vec![0u8; 128]);
+        for (i, frame) in frames.iter().enumerate() {
+            let (h, frag, b) = VarHeader::take_fragment_from_slice(frame).unwrap();
+            let res = reasm.push(h, frag.unwrap(), b).unwrap();
+            if i + 1 == frames.len() {
+                assert_eq!(res, Some((hdr(1), body.as_slice())));
+            } else {
+                assert_eq!(res, None);
+            }
+        }
+
+        // Small messages are not fragmented
+        let frames = split_to_vecs(&hdr(1), &body[..10], 16).unwrap();
+        assert_eq!(frames, [[&hdr(1).write_to_vec()[..], &body[..10]].concat()]);
+
+        // Messages needing too many fragments are rejected
+        let max = max_body_len(&hdr(1), 16);
+        assert_eq!(max, 11 * 0x8000);
+        assert!(split_to_vecs(&hdr(1), &vec![0; max], 16).is_some());
+        assert!(split_to_vecs(&hdr(1), &vec![0; max + 1], 16).is_none());
+        // Too small to fragment
+        assert_eq!(max_body_len(&hdr(1), 5), 2);
+        assert!(split_to_vecs(&hdr(1), &[0; 3], 5).is_none());
+    }
+
+    #[test]
+    fn errors() {
+        let mut buf = [0u8; 8];
+        let mut reasm = Reassembler::new(buf.as_mut_slice());
+        let first = Fragment {
+            index: 0,
+            last: false,
+        };
+        let second = Fragment {
+            index: 1,
+            last: true,
+        };
+
+        // Missing first fragment
+        assert_eq!(
+            reasm.push(hdr(1), second, &[1]),
+            Err(ReassemblyError::OutOfOrder)
+        );
+
+        // Fragment of another message
+        assert_eq!(reasm.push(hdr(1), first, &[1, 2]), Ok(None));
+        assert_eq!(
+            reasm.push(hdr(2), second, &[3]),
+            Err(ReassemblyError::OutOfOrder)
+        );
+
+        // Further fragments of a failed message are ignored
+        let third = Fragment {
+            index: 2,
+            last: true,
+        };
+        assert_eq!(reasm.push(hdr(2), third, &[4]), Ok(None));
+
+        // Too large
+        assert_eq!(reasm.push(hdr(1), first, &[0; 6]), Ok(None));
+        assert_eq!(
+            reasm.push(hdr(1), second, &[0; 3]),
+            Err(ReassemblyError::TooLarge { len: 9 })
+        );
+
+        // Starting over works
+        assert_eq!(reasm.push(hdr(3), first, &[1, 2]), Ok(None));
+        assert_eq!(
+            reasm.push(hdr(3), second, &[3]),
+            Ok(Some((hdr(3), &[1u8, 2, 3][..])))
+        );
+    }
+}
@@ -0,0 +1,280 @@
+//! Framing for byte-stream transports
+//!
+//! Transports like TCP, Unix domain sockets, or pipes do not preserve message
+//! boundaries, so every postcard-rpc frame (a [`VarHeader`][crate::header::VarHeader]
+//! followed by the serialized body) must be delimited before it is written to the
+//! stream. Both the host and server impls for these transports use the framings
+//! described here, so a [`HostClient`][crate::host_client::HostClient] can talk to any
+//! stream-based server without further configuration, as long as both sides agree on
+//! the [`Framing`].
+//!
+//! ## Length prefixed
+//!
+//! Each frame is preceded by its length in bytes, encoded as a little-endian `u32`.
+//! The length does not include the four prefix bytes themselves:
+//!
+//! ```text
+//! | len: u32 (LE) | frame: [u8; len]                      |
+//! | 05 00 00 00   | 20 2a 01 ...                          |
+//! ```
+//!
+//! Frames with a length larger than the receiver's maximum frame length are skipped
+//! in their entirety, and do not desynchronize the stream.
+//!
+//! ## COBS
+//!
+//! Each frame is [COBS] encoded, and terminated by a single `0x00` byte. This is the
+//! same framing used by the `cobs-serial` host client and the `embedded-io-async`
+//! server impls. Empty frames (e.g. repeated `0x00` bytes) are ignored, which allows
+//! a sender to "flush" a receiver with a zero byte.
+//!
+//! [COBS]: https://en.wikipedia.org/wiki/Consistent_Overhead_Byte_Stuffing
+
+use std::collections::VecDeque;
+
+/// The default maximum size of a single received frame, in bytes
+pub const DEFAULT_MAX_FRAME_LEN: usize = 64 * 1024;
+
+/// The number of bytes used by the length prefix of [`Framing::LengthPrefixed`]
+pub const LENGTH_PREFIX_LEN: usize = 4;
+
+/// The method used to delimit frames on a byte stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Framing {
+    /// Frames are prefixed with their length as a little-endian `u32`
+    #[default]
+    LengthPrefixed,
+    /// Frames are COBS encoded, and terminated with a `0x00` byte
+    Cobs,
+}
+
+impl Framing {
+    /// Encode a single frame, including any prefix or terminator
+    pub fn encode(&self, frame: &[u8]) -> Vec<u8> {
+        match self {
+            Framing::LengthPrefixed => {
+                let mut out = Vec::with_capacity(LENGTH_PREFIX_LEN + frame.len());
+                out.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+                out.extend_from_slice(frame);
+                out
+            }
+            Framing::Cobs => {
+                let mut out = vec![0u8; cobs::max_encoding_length(frame.len()) + 1];
+                let used = cobs::encode(frame, &mut out);
+                // Keep the terminating zero
+                out.truncate(used + 1);
+                out[used] = 0;
+                out
+            }
+        }
+    }
+}
+
+/// An error that occurred while decoding a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum FrameError {
+    /// The frame was larger than the maximum allowed size, and was discarded
+    #[error("frame too long: {len} > {max}")]
+    TooLong {
+        /// The length of the frame, if known. For COBS framing this is the amount
+        /// of data received before the frame was discarded.
+        len: usize,
+        /// The maximum frame size
+        max: usize,
+    },
+    /// The frame was not validly COBS encoded, and was discarded
+    #[error("malformed COBS frame")]
+    Cobs,
+}
+
+/// An incremental decoder for a [`Framing`]
+///
+/// Data is pushed in as it is read from the stream, in chunks of any size, and
+/// complete frames are taken out with [`FrameDecoder::next_frame()`].
+pub struct FrameDecoder {
+    framing: Framing,
+    max_len: usize,
+    buf: Vec<u8>,
+    /// Bytes still to be thrown away from a too-long frame
+    discard: Discard,
+    errors: VecDeque<FrameError>,
+}
+
+enum Discard {
+    None,
+    /// Discard this many more bytes (length prefixed)
+    Count(usize),
+    /// Discard until the next zero byte (COBS)
+    UntilZero,
+}
+
+impl FrameDecoder {
+    /// Create a new decoder, which will reject frames larger than `max_len`
+    pub fn new(framing: Framing, max_len: usize) -> Self {
+        Self {
+            framing,
+            max_len,
+            buf: Vec::new(),
+            discard: Discard::None,
+            errors: VecDeque::new(),
+        }
+    }
+
+    /// The framing used by this decoder
+    pub fn framing(&self) -> Framing {
+        self.framing
+    }
+
+    /// Push newly received bytes into the decoder
+    pub fn push(&mut self, mut data: &[u8]) {
+        match self.discard {
+            Discard::None => {}
+            Discard::Count(n) => {
+                let skip = n.min(data.len());
+                data = &data[skip..];
+                self.discard = match n - skip {
+                    0 => Discard::None,
+                    n => Discard::Count(n),
+                };
+            }
+            Discard::UntilZero => match data.iter().position(|b| *b == 0) {
+                Some(pos) => {
+                    data = &data[pos + 1..];
+                    self.discard = Discard::None;
+                }
+                None => return,
+            },
+        }
+        self.buf.extend_from_slice(data);
+
+        // Don't let a COBS frame without a terminator grow forever
+        if self.framing == Framing::Cobs
+            && self.buf.len() > cobs::max_encoding_length(self.max_len)
+            && !self.buf.contains(&0)
+        {
+            self.errors.push_back(FrameError::TooLong {
+                len: self.buf.len(),
+                max: self.max_len,
+            });
+            self.buf.clear();
+            self.discard = Discard::UntilZero;
+        }
+    }
+
+    /// Attempt to take a single complete frame from the decoder
+    ///
+    /// Returns `None` if more data is needed. Frames that could not be decoded
+    /// are reported as errors, and decoding can continue afterwards.
+    pub fn next_frame(&mut self) -> Option<Result<Vec<u8>, FrameError>> {
+        if let Some(e) = self.errors.pop_front() {
+            return Some(Err(e));
+        }
+        match self.framing {
+            Framing::LengthPrefixed => self.next_len_prefixed(),
+            Framing::Cobs => self.next_cobs(),
+        }
+    }
+
+    fn next_len_prefixed(&mut self) -> Option<Result<Vec<u8>, FrameError>> {
+        let prefix: [u8; LENGTH_PREFIX_LEN] = self.buf.get(..LENGTH_PREFIX_LEN)?.try_into().ok()?;
+        let len = u32::from_le_bytes(prefix) as usize;
+
+        if len > self.max_len {
+            // Throw away the prefix and whatever we have of the body so far,
+            // and remember how much is left to throw away
+            let have = self.buf.len() - LENGTH_PREFIX_LEN;
+            let skip = have.min(len);
+            self.buf.drain(..LENGTH_PREFIX_LEN + skip);
+            if skip < len {
+                self.discard = Discard::Count(len - skip);
+            }
+            return Some(Err(FrameError::TooLong {
+                len,
+                max: self.max_len,
+            }));
+        }
+
+        if self.buf.len() < LENGTH_PREFIX_LEN + len {
+            return None;
+        }
+        let frame = self.buf[LENGTH_PREFIX_LEN..][..len].to_vec();
+        self.buf.drain(..LENGTH_PREFIX_LEN + len);
+        Some(Ok(frame))
+    }
+
+    fn next_cobs(&mut self) -> Option<Result<Vec<u8>, FrameError>> {
+        loop {
+            let pos = self.buf.iter().position(|b| *b == 0)?;
+            if pos == 0 {
+                // Empty frame, skip it
+                self.buf.remove(0);
+                continue;
+            }
+            let res = cobs::decode_in_place(&mut self.buf[..pos]);
+            let out = match res {
+                Ok(used) if used > self.max_len => Err(FrameError::TooLong {
+                    len: used,
+                    max: self.max_len,
+                }),
+                Ok(used) => Ok(self.buf[..used].to_vec()),
+                Err(_) => Err(FrameError::Cobs),
+            };
+            self.buf.drain(..pos + 1);
+            return Some(out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FrameDecoder, FrameError, Framing};
+
+    const FRAMES: &[&[u8]] = &[
+        &[0x20, 0x2a, 0x01],
+        &[0x00, 0x00, 0x00, 0x01, 0x00],
+        &[0xFF; 300],
+        &[0x42],
+    ];
+
+    #[test]
+    fn roundtrip_split() {
+        for framing in [Framing::LengthPrefixed, Framing::Cobs] {
+            let stream: Vec<u8> = FRAMES.iter().flat_map(|f| framing.encode(f)).collect();
+
+            // Feed the stream in awkwardly sized chunks
+            for chunk_size in [1, 3, 7, 64, stream.len()] {
+                let mut dec = FrameDecoder::new(framing, 1024);
+                let mut got = vec![];
+                for chunk in stream.chunks(chunk_size) {
+                    dec.push(chunk);
+                    while let Some(frame) = dec.next_frame() {
+                        got.push(frame.unwrap());
+                    }
+                }
+                assert_eq!(got, FRAMES, "{framing:?}, chunks of {chunk_size}");
+            }
+        }
+    }
+
+    #[test]
+    fn too_long_is_skipped() {
+        for framing in [Framing::LengthPrefixed, Framing::Cobs] {
+            let mut stream = framing.encode(&[0x01; 64]);
+            stream.extend(framing.encode(&[0x02; 8]));
+
+            for chunk_size in [1, 5, stream.len()] {
+                let mut dec = FrameDecoder::new(framing, 16);
+                let mut got = vec![];
+                for chunk in stream.chunks(chunk_size) {
+                    dec.push(chunk);
+                    while let Some(frame) = dec.next_frame() {
+                        got.push(frame);
+                    }
+                }
+                assert_eq!(got.len(), 2, "{framing:?}, chunks of {chunk_size}");
+                assert!(matches!(got[0], Err(FrameError::TooLong { max: 16, .. })));
+                assert_eq!(got[1], Ok(vec![0x02; 8]));
+            }
+        }
+    }
+}
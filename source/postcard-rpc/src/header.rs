@@ -57,6 +57,20 @@
 //! The length of the key is chosen by the "originator" of the message. For Endpoints
 //! this is the client making the request. For Topics, this is the device sending the
 //! topic message.
+//!
+//! ## Fragment Extension
+//!
+//! Messages that are too large for a single frame may be split into fragments,
+//! see the [`fragment` module](crate::fragment). Each fragment carries the header
+//! of the whole message, with the [`VarHeader::EXT_FRAGMENT_BITS`] set in the
+//! "protocol version" bits of the discriminant, followed by a two byte
+//! [`Fragment`] extension after the sequence number.
+//!
+//! The extension is a little-endian `u16`, where the lower 15 bits are the index of
+//! the fragment, starting at zero, and the msbit is set for the last fragment of a
+//! message.
+//!
+//! Messages that are not fragmented do not use the extension.
 
 use crate::{Key, Key1, Key2, Key4};
 
@@ -348,6 +362,47 @@ pub enum VarSeqKind {
     Seq4,
 }
 
+//////////////////////////////////////////////////////////////////////////////
+// FRAGMENT
+//////////////////////////////////////////////////////////////////////////////
+
+/// The position of a fragment within a message
+///
+/// This is encoded in a header extension, see the
+/// [module level docs](self#fragment-extension) for details.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Fragment {
+    /// The index of this fragment, starting at zero. At most [`Fragment::MAX_INDEX`].
+    pub index: u16,
+    /// Is this the last fragment of the message?
+    pub last: bool,
+}
+
+impl Fragment {
+    /// The largest possible fragment index
+    pub const MAX_INDEX: u16 = 0x7FFF;
+    const LAST_BIT: u16 = 0x8000;
+
+    /// Encode the fragment extension
+    pub fn to_bytes(&self) -> [u8; 2] {
+        let mut val = self.index & Self::MAX_INDEX;
+        if self.last {
+            val |= Self::LAST_BIT;
+        }
+        val.to_le_bytes()
+    }
+
+    /// Decode the fragment extension
+    pub fn from_bytes(bytes: [u8; 2]) -> Self {
+        let val = u16::from_le_bytes(bytes);
+        Self {
+            index: val & Self::MAX_INDEX,
+            last: (val & Self::LAST_BIT) != 0,
+        }
+    }
+}
+
 //////////////////////////////////////////////////////////////////////////////
 // VARHEADER
 //////////////////////////////////////////////////////////////////////////////
@@ -393,9 +448,32 @@ impl VarHeader {
 
     /// Bits for a version number of ZERO
     pub const VER_ZERO_BITS: u8 = 0b00_00_0000;
+    /// Bits for a version number of ZERO, followed by a [`Fragment`] extension
+    pub const EXT_FRAGMENT_BITS: u8 = 0b00_00_1000;
     /// Mask bits
     pub const VER_MASK_BITS: u8 = 0b00_00_1111;
 
+    /// The largest encoded size of a header, without extensions
+    pub const MAX_LEN: usize = 1 + 8 + 4;
+    /// The largest encoded size of a header, with a [`Fragment`] extension
+    pub const MAX_FRAGMENT_LEN: usize = Self::MAX_LEN + 2;
+
+    /// The encoded size of this header, without extensions
+    pub fn encoded_len(&self) -> usize {
+        let key = match self.key {
+            VarKey::Key1(_) => 1,
+            VarKey::Key2(_) => 2,
+            VarKey::Key4(_) => 4,
+            VarKey::Key8(_) => 8,
+        };
+        let seq = match self.seq_no {
+            VarSeq::Seq1(_) => 1,
+            VarSeq::Seq2(_) => 2,
+            VarSeq::Seq4(_) => 4,
+        };
+        1 + key + seq
+    }
+
     /// Encode the header to a Vec of bytes
     #[cfg(feature = "use-std")]
     pub fn write_to_vec(&self) -> Vec<u8> {
@@ -505,21 +583,74 @@ impl VarHeader {
         Some(buf.split_at_mut(used))
     }
 
+    /// Encode the header, with a [`Fragment`] extension, to a Vec of bytes
+    #[cfg(feature = "use-std")]
+    pub fn write_fragment_to_vec(&self, frag: Fragment) -> Vec<u8> {
+        let mut out = self.write_to_vec();
+        out[0] |= Self::EXT_FRAGMENT_BITS;
+        out.extend_from_slice(&frag.to_bytes());
+        out
+    }
+
+    /// Attempt to write the header, with a [`Fragment`] extension, to the given slice
+    ///
+    /// Behaves like [`VarHeader::write_to_slice()`].
+    pub fn write_fragment_to_slice<'a>(
+        &self,
+        frag: Fragment,
+        buf: &'a mut [u8],
+    ) -> Option<(&'a mut [u8], &'a mut [u8])> {
+        let len = self.write_to_slice(buf)?.0.len();
+        let ext = buf.get_mut(len..len + 2)?;
+        ext.copy_from_slice(&frag.to_bytes());
+        buf[0] |= Self::EXT_FRAGMENT_BITS;
+        Some(buf.split_at_mut(len + 2))
+    }
+
     /// Attempt to decode a header from the given bytes.
     ///
     /// If a well-formed header was found, a `Some` will be returned with the
     /// decoded header and unused remaining bytes.
     ///
-    /// If no well-formed header was found, a `None` will be returned.
+    /// If no well-formed header was found, a `None` will be returned. This includes
+    /// headers of fragments, which can be decoded with
+    /// [`VarHeader::take_fragment_from_slice()`].
     pub fn take_from_slice(buf: &[u8]) -> Option<(Self, &[u8])> {
-        let (disc, mut remain) = buf.split_first()?;
+        let (disc, remain) = buf.split_first()?;
 
         // For now, we only trust version zero
         if (*disc & Self::VER_MASK_BITS) != Self::VER_ZERO_BITS {
             return None;
         }
 
-        let key = match (*disc) & Self::KEY_MASK_BITS {
+        Self::take_key_seq(*disc, remain)
+    }
+
+    /// Attempt to decode a header, that may have a [`Fragment`] extension, from the
+    /// given bytes.
+    ///
+    /// Like [`VarHeader::take_from_slice()`], but also accepts the headers of fragments.
+    pub fn take_fragment_from_slice(buf: &[u8]) -> Option<(Self, Option<Fragment>, &[u8])> {
+        let (disc, remain) = buf.split_first()?;
+
+        match *disc & Self::VER_MASK_BITS {
+            Self::VER_ZERO_BITS => {
+                let (hdr, remain) = Self::take_key_seq(*disc, remain)?;
+                Some((hdr, None, remain))
+            }
+            Self::EXT_FRAGMENT_BITS => {
+                let (hdr, remain) = Self::take_key_seq(*disc, remain)?;
+                let (ext, remain) = remain.split_at_checked(2)?;
+                let frag = Fragment::from_bytes([ext[0], ext[1]]);
+                Some((hdr, Some(frag), remain))
+            }
+            _ => None,
+        }
+    }
+
+    /// Decode the key and sequence number that follow the discriminant
+    fn take_key_seq(disc: u8, mut remain: &[u8]) -> Option<(Self, &[u8])> {
+        let key = match disc & Self::KEY_MASK_BITS {
             Self::KEY_ONE_BITS => {
                 let (keybs, remain2) = remain.split_first()?;
                 remain = remain2;
@@ -549,7 +680,7 @@ impl VarHeader {
             // Impossible: all bits covered
             _ => unreachable!(),
         };
-        let seq_no = match (*disc) & Self::SEQ_MASK_BITS {
+        let seq_no = match disc & Self::SEQ_MASK_BITS {
             Self::SEQ_ONE_BITS => {
                 let (seqbs, remain3) = remain.split_first()?;
                 remain = remain3;
@@ -578,7 +709,7 @@ impl VarHeader {
 
 #[cfg(test)]
 mod test {
-    use super::{VarHeader, VarKey, VarSeq};
+    use super::{Fragment, VarHeader, VarKey, VarSeq};
     use crate::{Key, Key1, Key2};
 
     #[test]
@@ -668,6 +799,43 @@ mod test {
         }
     }
 
+    #[test]
+    fn fragment_format() {
+        let hdr = VarHeader {
+            key: VarKey::Key1(Key1(0x42)),
+            seq_no: VarSeq::Seq2(0x1234),
+        };
+        let frag = Fragment {
+            index: 3,
+            last: true,
+        };
+        let exp: &[u8] = &[
+            VarHeader::KEY_ONE_BITS | VarHeader::SEQ_TWO_BITS | VarHeader::EXT_FRAGMENT_BITS,
+            0x42,
+            0x34,
+            0x12,
+            0x03,
+            0x80,
+        ];
+
+        let mut buf = [0u8; VarHeader::MAX_FRAGMENT_LEN];
+        let (used, _) = hdr.write_fragment_to_slice(frag, &mut buf).unwrap();
+        assert_eq!(used, exp);
+        assert_eq!(hdr.write_fragment_to_vec(frag), exp);
+
+        // Fragments are only accepted by `take_fragment_from_slice`
+        assert!(VarHeader::take_from_slice(exp).is_none());
+        let (deser, dfrag, remain) = VarHeader::take_fragment_from_slice(exp).unwrap();
+        assert_eq!((deser, dfrag), (hdr, Some(frag)));
+        assert!(remain.is_empty());
+
+        // Unfragmented headers are unchanged
+        let plain = hdr.write_to_vec();
+        assert_eq!(plain.len(), hdr.encoded_len());
+        let (deser, dfrag, _) = VarHeader::take_fragment_from_slice(&plain).unwrap();
+        assert_eq!((deser, dfrag), (hdr, None));
+    }
+
     #[test]
     fn var_seq_equality() {
         let val32 = 0x12345678;
@@ -0,0 +1,95 @@
+//! Sending bulk uploads
+//!
+//! See [`crate::server::bulk`] for the server side.
+
+use core::{future::poll_fn, task::Poll};
+use std::{future::Future, pin::Pin};
+
+use postcard_schema::Schema;
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+
+use crate::{
+    host_client::{HostClient, HostErr},
+    standard_icd::{
+        crc32, BulkChunkEndpoint, BulkEnd, BulkEndEndpoint, BulkError, BulkResult, BulkStart,
+        BulkStartEndpoint, OwnedBulkChunk,
+    },
+};
+
+/// Errors returned by [`HostClient::bulk_upload()`]
+#[derive(Debug, PartialEq, Error)]
+pub enum BulkUploadError<WireErr> {
+    /// Some kind of communication error occurred
+    #[error("A communication error occurred")]
+    Comms(#[from] HostErr<WireErr>),
+    /// The server rejected the upload
+    #[error("The server rejected the upload: {0}")]
+    Rejected(#[from] BulkError),
+}
+
+/// # Bulk Upload Methods
+impl<WireErr> HostClient<WireErr>
+where
+    WireErr: DeserializeOwned + Schema,
+{
+    /// Upload `data` to the server, which may be larger than its receive buffer
+    ///
+    /// The data is split into chunks of the size requested by the server, and
+    /// up to the requested number of chunks are sent before waiting for their
+    /// acknowledgements. Once all chunks were acknowledged, the server checks
+    /// the CRC of the whole upload.
+    ///
+    /// The server must handle the bulk endpoints of the
+    /// [standard ICD][crate::standard_icd], see [`crate::server::bulk`].
+    pub async fn bulk_upload(&self, data: &[u8]) -> Result<(), BulkUploadError<WireErr>> {
+        let len = u32::try_from(data.len()).map_err(|_| BulkError::TooLarge)?;
+        let params = self
+            .send_resp::<BulkStartEndpoint>(&BulkStart { len })
+            .await??;
+        let chunk_size = (params.chunk_size as usize).max(1);
+        let window = (params.window as usize).max(1);
+
+        type Ack<'a, WireErr> =
+            Pin<Box<dyn Future<Output = Result<BulkResult, HostErr<WireErr>>> + Send + 'a>>;
+        let mut chunks = data.chunks(chunk_size).zip((0u32..).step_by(chunk_size));
+        let mut in_flight: Vec<Ack<'_, WireErr>> = Vec::with_capacity(window);
+        loop {
+            while in_flight.len() < window {
+                let Some((chunk, offset)) = chunks.next() else {
+                    break;
+                };
+                let req = OwnedBulkChunk {
+                    offset,
+                    data: chunk.to_vec(),
+                };
+                in_flight.push(Box::pin(async move {
+                    self.send_resp::<BulkChunkEndpoint>(&req).await
+                }));
+            }
+            if in_flight.is_empty() {
+                break;
+            }
+
+            // Wait for any chunk to be acknowledged. Chunks are polled (and so
+            // sent) in order, as the server requires them to be in order.
+            let ack = poll_fn(|cx| {
+                for (i, fut) in in_flight.iter_mut().enumerate() {
+                    if let Poll::Ready(res) = fut.as_mut().poll(cx) {
+                        drop(in_flight.remove(i));
+                        return Poll::Ready(res);
+                    }
+                }
+                Poll::Pending
+            })
+            .await;
+            ack??;
+        }
+
+        self.send_resp::<BulkEndEndpoint>(&BulkEnd {
+            crc: crc32(0, data),
+        })
+        .await??;
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+//! Querying the capabilities of the server
+//!
+//! See [`CapabilitiesEndpoint`] for the server side.
+
+use core::marker::PhantomData;
+
+use postcard_schema::Schema;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    fragment::max_body_len,
+    host_client::{HostClient, HostContext, HostErr, RpcFrame},
+    standard_icd::{Capabilities, CapabilitiesEndpoint, WireError, ERROR_KEY},
+};
+
+/// # Capability Methods
+impl<WireErr> HostClient<WireErr>
+where
+    WireErr: DeserializeOwned + Schema,
+{
+    /// Ask the server for its [`Capabilities`], and adapt to them
+    ///
+    /// Afterwards, requests use the smallest key size the server accepts, and
+    /// requests that are too long for the server are rejected with
+    /// [`HostErr::RequestTooLarge`] without being sent. If the server supports them,
+    /// requests are cancelled and topics are enabled as they are subscribed to. Servers that don't support
+    /// this reply with an error, and nothing changes.
+    ///
+    /// This is best called right after connecting, which is done automatically
+    /// when [`HostClientConfig::query_capabilities`] is set. Reconnecting clients
+    /// forget the capabilities when the connection is lost, and query them again
+    /// after reconnecting if that is set.
+    ///
+    /// [`HostClientConfig::query_capabilities`]: crate::host_client::HostClientConfig::query_capabilities
+    pub async fn query_capabilities(&self) -> Result<Capabilities, HostErr<WireErr>> {
+        let caps = self.send_resp::<CapabilitiesEndpoint>(&()).await?;
+        if let Some(kkind) = caps.key_kind() {
+            *self.ctx.kkind.write().unwrap() = kkind;
+        }
+        *self.ctx.capabilities.lock().unwrap() = Some(caps);
+        // Topics subscribed to so far were not enabled, unless the client opted in
+        if caps.supports(Capabilities::TOPIC_FILTER) && !self.ctx.control_topics {
+            self.ctx.enable_subscribed(&self.out);
+        }
+        Ok(caps)
+    }
+
+    /// The capabilities of the server, if they were queried with
+    /// [`HostClient::query_capabilities()`]
+    pub fn capabilities(&self) -> Option<Capabilities> {
+        *self.ctx.capabilities.lock().unwrap()
+    }
+}
+
+impl<WireErr> HostClient<WireErr> {
+    /// The same client, expecting the standard [`WireError`] as error type
+    ///
+    /// Used to query the capabilities in the background, which doesn't need the
+    /// user's error type.
+    pub(crate) fn with_standard_errors(&self) -> HostClient<WireError> {
+        HostClient {
+            ctx: self.ctx.clone(),
+            out: self.out.clone(),
+            subscriptions: self.subscriptions.clone(),
+            err_key: ERROR_KEY,
+            stopper: self.stopper.clone(),
+            seq_kind: self.seq_kind,
+            link: self.link.clone(),
+            timeout: self.timeout,
+            _pd: PhantomData,
+        }
+    }
+}
+
+impl HostContext {
+    /// Check that `frame` can be split into fragments if needed, and that the
+    /// server can receive it
+    ///
+    /// Returns the length of the frame and the limit if it is too long.
+    pub(crate) fn check_request_len(&self, frame: &RpcFrame) -> Result<(), (usize, usize)> {
+        let fragment_size = *self.fragment_size.lock().unwrap();
+        if let Some(size) = fragment_size {
+            let max = max_body_len(&frame.header, size);
+            if frame.body.len() > max {
+                return Err((frame.body.len(), max));
+            }
+        }
+        let Some(caps) = *self.capabilities.lock().unwrap() else {
+            return Ok(());
+        };
+        // Fragmented messages only need to fit the reassembly buffer
+        let (len, max) = match (caps.max_reassembly_len, caps.max_request_len) {
+            (Some(max), _) if fragment_size.is_some() => (frame.body.len(), max),
+            (_, Some(max)) => (frame.header.encoded_len() + frame.body.len(), max),
+            (_, None) => return Ok(()),
+        };
+        let max = max as usize;
+        if len > max {
+            return Err((len, max));
+        }
+        Ok(())
+    }
+
+    /// Should messages for an optional feature of the server be sent?
+    ///
+    /// Yes if the server advertised the feature, or the client opted in.
+    pub(crate) fn uses_feature(&self, feature: u8, opted_in: bool) -> bool {
+        opted_in
+            || self
+                .capabilities
+                .lock()
+                .unwrap()
+                .is_some_and(|caps| caps.supports(feature))
+    }
+
+    /// Forget the capabilities of the server, when the connection to it was lost
+    #[cfg(not(target_family = "wasm"))]
+    pub(crate) fn clear_capabilities(&self) {
+        if self.capabilities.lock().unwrap().take().is_some() {
+            // The new server may need longer keys
+            *self.kkind.write().unwrap() = crate::header::VarKeyKind::Key8;
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! Granting credits for topics sent by the server
+//!
+//! See [`crate::server::credits`] for the server side.
+
+use std::sync::atomic::Ordering;
+
+use postcard_schema::Schema;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    header::{VarHeader, VarKey, VarKeyKind, VarSeq},
+    host_client::{HostClient, IoClosed, RpcFrame, SubscribeError, Subscription},
+    standard_icd::{CreditGrant, CreditTopic},
+    Key, Topic,
+};
+
+/// # Flow Control Methods
+impl<WireErr> HostClient<WireErr>
+where
+    WireErr: DeserializeOwned + Schema,
+{
+    /// Allow the server to send `credits` more messages of the given topic
+    ///
+    /// Once credits were granted for a topic, the server stops sending it when
+    /// they run out. [`CreditGrant::UNLIMITED`] turns flow control off again.
+    /// Servers that don't use credits ignore this.
+    pub async fn grant_credits<T: Topic>(&self, credits: u32) -> Result<(), IoClosed> {
+        self.grant_credits_raw(T::TOPIC_KEY, credits).await
+    }
+
+    /// Like [`HostClient::grant_credits()`], for the topic with the given key
+    pub async fn grant_credits_raw(&self, key: Key, credits: u32) -> Result<(), IoClosed> {
+        let seq_no = VarSeq::Seq4(self.ctx.seq.fetch_add(1, Ordering::Relaxed));
+        self.publish::<CreditTopic>(seq_no, &CreditGrant { key, credits })
+            .await
+    }
+
+    /// Subscribe to a [Topic], allowing the server to send only as many messages
+    /// as fit the subscription
+    ///
+    /// `depth` credits are granted right away, and more are granted as messages are
+    /// received, so the subscription never overflows. If the server can't send
+    /// because it has no credits left, it waits or drops the message itself.
+    ///
+    /// Like [`HostClient::subscribe_exclusive()`], this fails if the topic already
+    /// has a subscriber.
+    pub async fn subscribe_credited<T: Topic>(
+        &self,
+        depth: usize,
+    ) -> Result<CreditedSubscription<T::Message, WireErr>, SubscribeError>
+    where
+        T::Message: DeserializeOwned,
+    {
+        let depth = depth.max(1);
+        let sub = self.subscribe_exclusive::<T>(depth).await?;
+        let credits = u32::try_from(depth).unwrap_or(CreditGrant::UNLIMITED - 1);
+        self.grant_credits::<T>(credits)
+            .await
+            .map_err(|_| SubscribeError::IoClosed)?;
+        Ok(CreditedSubscription {
+            sub,
+            client: self.clone(),
+            key: T::TOPIC_KEY,
+            received: 0,
+            batch: (credits / 2).max(1),
+        })
+    }
+}
+
+/// A [`Subscription`] that grants the server more credits as messages are received
+///
+/// Created by [`HostClient::subscribe_credited()`]. When it is dropped, flow control
+/// of the topic is turned off again, so the server doesn't wait for credits that
+/// will never be granted.
+pub struct CreditedSubscription<M, WireErr> {
+    sub: Subscription<M>,
+    client: HostClient<WireErr>,
+    key: Key,
+    received: u32,
+    batch: u32,
+}
+
+impl<M, WireErr> CreditedSubscription<M, WireErr>
+where
+    M: DeserializeOwned,
+    WireErr: DeserializeOwned + Schema,
+{
+    /// Await a message for the given subscription.
+    ///
+    /// Returns [None]` if the subscription was closed
+    pub async fn recv(&mut self) -> Option<M> {
+        let msg = self.sub.recv().await?;
+        self.received += 1;
+        if self.received >= self.batch {
+            // If the client was closed, the subscription will be closed too
+            let _ = self.client.grant_credits_raw(self.key, self.received).await;
+            self.received = 0;
+        }
+        Some(msg)
+    }
+}
+
+impl<M, WireErr> Drop for CreditedSubscription<M, WireErr> {
+    fn drop(&mut self) {
+        let client = &self.client;
+        if client.out.is_closed() {
+            return;
+        }
+        let kkind: VarKeyKind = *client.ctx.kkind.read().unwrap();
+        let mut key = VarKey::Key8(CreditTopic::TOPIC_KEY);
+        key.shrink_to(kkind);
+        let grant = CreditGrant {
+            key: self.key,
+            credits: CreditGrant::UNLIMITED,
+        };
+        let frame = RpcFrame {
+            header: VarHeader {
+                key,
+                seq_no: VarSeq::Seq4(client.ctx.seq.fetch_add(1, Ordering::Relaxed)),
+            },
+            body: postcard::to_stdvec(&grant).expect("Allocations should not ever fail"),
+        };
+        // We can't wait here, if the queue is full the server keeps limiting the topic
+        if client.out.try_send(frame).is_err() {
+            tracing::warn!("Outgoing queue full, failed to turn off flow control of a topic");
+        }
+    }
+}
@@ -0,0 +1,725 @@
+//! Calling endpoints and topics by path, with JSON values
+//!
+//! The [`DynamicClient`] uses the [`SchemaReport`] of the device to convert
+//! [`serde_json::Value`]s to and from postcard, so no Rust types of the ICD are
+//! needed. Values are represented like [`serde_json`] would represent the Rust
+//! types described by the schema:
+//!
+//! * Integers and floats are numbers. 128-bit integers may also be strings.
+//! * Unit types, unit structs, and `None` are `null`.
+//! * Newtype structs are their inner value.
+//! * Sequences and tuples are arrays, structs and maps are objects. Map keys
+//!   that are not strings are written like JSON values, e.g. `"1"`.
+//! * Unit variants of enums are their name as string, other variants are objects
+//!   with a single field, e.g. `{"Newtype": 1}`.
+//!
+//! Options of values that are `null` themselves, like `Option<()>` or
+//! `Option<Option<u8>>`, are not supported, as `None` and `Some` can't be told
+//! apart. Sequences and maps longer than the rest of the message are rejected,
+//! even if their elements take no space, like `Vec<()>`, so malformed messages
+//! can't make the decoder allocate huge arrays.
+//!
+//! Only available with the `dynamic` feature.
+
+use std::sync::atomic::Ordering;
+
+use postcard_schema::{
+    schema::owned::{OwnedDataModelType, OwnedDataModelVariant, OwnedNamedType, OwnedNamedValue},
+    Schema,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use crate::{
+    header::{VarHeader, VarKey, VarSeq},
+    host_client::{
+        HostClient, HostErr, MultiSubRxError, RawMultiSubscription, RpcFrame, SchemaError,
+        SchemaReport,
+    },
+};
+
+/// A value did not match the schema it was converted with
+#[derive(Debug, PartialEq, Error)]
+pub enum ValueError {
+    /// The JSON value does not have the expected shape
+    #[error("expected {expected} at '{path}'")]
+    Mismatch {
+        /// The location of the value, like a JSON pointer
+        path: String,
+        /// A description of the expected value
+        expected: String,
+    },
+    /// The postcard message could not be decoded
+    #[error("the message does not match the schema at '{path}'")]
+    Malformed {
+        /// The location of the value, like a JSON pointer
+        path: String,
+    },
+    /// The postcard message is longer than the schema describes
+    #[error("the message has {0} bytes left over")]
+    TrailingBytes(usize),
+    /// The schema contains a type that can not be converted
+    #[error("the type at '{0}' is not supported")]
+    Unsupported(String),
+}
+
+/// Encode a JSON value as postcard, as described by `ty`
+pub fn to_postcard(ty: &OwnedNamedType, value: &Value) -> Result<Vec<u8>, ValueError> {
+    let mut out = vec![];
+    encode(&ty.ty, value, "", &mut out)?;
+    Ok(out)
+}
+
+/// Decode a postcard message to a JSON value, as described by `ty`
+pub fn from_postcard(ty: &OwnedNamedType, bytes: &[u8]) -> Result<Value, ValueError> {
+    let (value, rest) = decode(&ty.ty, bytes, "")?;
+    if !rest.is_empty() {
+        return Err(ValueError::TrailingBytes(rest.len()));
+    }
+    Ok(value)
+}
+
+fn mismatch(path: &str, expected: &str) -> ValueError {
+    ValueError::Mismatch {
+        path: path.to_string(),
+        expected: expected.to_string(),
+    }
+}
+
+fn put<T: Serialize + ?Sized>(out: &mut Vec<u8>, val: &T) {
+    out.extend(postcard::to_stdvec(val).expect("Allocations should not ever fail"));
+}
+
+fn int<T: TryFrom<i128> + core::str::FromStr>(v: &Value) -> Option<T> {
+    match v {
+        Value::Number(n) => {
+            let i = n.as_i64().map(i128::from).or(n.as_u64().map(i128::from))?;
+            T::try_from(i).ok()
+        }
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn encode_int<T>(v: &Value, path: &str, out: &mut Vec<u8>, name: &str) -> Result<(), ValueError>
+where
+    T: TryFrom<i128> + core::str::FromStr + Serialize,
+{
+    let i = int::<T>(v).ok_or_else(|| mismatch(path, name))?;
+    put(out, &i);
+    Ok(())
+}
+
+fn encode_values(
+    fields: &[OwnedNamedValue],
+    v: &Value,
+    path: &str,
+    out: &mut Vec<u8>,
+) -> Result<(), ValueError> {
+    let obj = v.as_object().ok_or_else(|| mismatch(path, "an object"))?;
+    for field in fields {
+        // Missing fields are only allowed for options
+        let val = obj.get(&field.name).unwrap_or(&Value::Null);
+        encode(&field.ty.ty, val, &format!("{path}/{}", field.name), out)?;
+    }
+    Ok(())
+}
+
+fn encode_tuple(
+    items: &[OwnedNamedType],
+    v: &Value,
+    path: &str,
+    out: &mut Vec<u8>,
+) -> Result<(), ValueError> {
+    let arr = v
+        .as_array()
+        .filter(|a| a.len() == items.len())
+        .ok_or_else(|| mismatch(path, &format!("an array of {} items", items.len())))?;
+    for (idx, (ty, val)) in items.iter().zip(arr).enumerate() {
+        encode(&ty.ty, val, &format!("{path}/{idx}"), out)?;
+    }
+    Ok(())
+}
+
+fn encode(
+    ty: &OwnedDataModelType,
+    v: &Value,
+    path: &str,
+    out: &mut Vec<u8>,
+) -> Result<(), ValueError> {
+    match ty {
+        OwnedDataModelType::Bool => put(out, &v.as_bool().ok_or_else(|| mismatch(path, "a bool"))?),
+        OwnedDataModelType::I8 => encode_int::<i8>(v, path, out, "an i8")?,
+        OwnedDataModelType::U8 => encode_int::<u8>(v, path, out, "a u8")?,
+        OwnedDataModelType::I16 => encode_int::<i16>(v, path, out, "an i16")?,
+        OwnedDataModelType::I32 => encode_int::<i32>(v, path, out, "an i32")?,
+        OwnedDataModelType::I64 => encode_int::<i64>(v, path, out, "an i64")?,
+        OwnedDataModelType::I128 => encode_int::<i128>(v, path, out, "an i128")?,
+        OwnedDataModelType::U16 => encode_int::<u16>(v, path, out, "a u16")?,
+        OwnedDataModelType::U32 => encode_int::<u32>(v, path, out, "a u32")?,
+        OwnedDataModelType::U64 => encode_int::<u64>(v, path, out, "a u64")?,
+        OwnedDataModelType::U128 => encode_int::<u128>(v, path, out, "a u128")?,
+        // Encoded like 64-bit integers
+        OwnedDataModelType::Usize => encode_int::<u64>(v, path, out, "a usize")?,
+        OwnedDataModelType::Isize => encode_int::<i64>(v, path, out, "an isize")?,
+        OwnedDataModelType::F32 => {
+            let f = v.as_f64().ok_or_else(|| mismatch(path, "an f32"))?;
+            put(out, &(f as f32));
+        }
+        OwnedDataModelType::F64 => put(out, &v.as_f64().ok_or_else(|| mismatch(path, "an f64"))?),
+        OwnedDataModelType::Char => {
+            let mut chars = v.as_str().map(str::chars);
+            let c = match chars.as_mut().map(|c| (c.next(), c.next())) {
+                Some((Some(c), None)) => c,
+                _ => return Err(mismatch(path, "a single character")),
+            };
+            put(out, &c);
+        }
+        OwnedDataModelType::String => {
+            put(out, v.as_str().ok_or_else(|| mismatch(path, "a string"))?)
+        }
+        OwnedDataModelType::ByteArray => {
+            let bytes = v
+                .as_array()
+                .and_then(|a| a.iter().map(int::<u8>).collect::<Option<Vec<u8>>>())
+                .ok_or_else(|| mismatch(path, "an array of bytes"))?;
+            put(out, &bytes);
+        }
+        OwnedDataModelType::Option(nt) if is_nullable(&nt.ty) => {
+            return Err(ValueError::Unsupported(path.to_string()))
+        }
+        OwnedDataModelType::Option(nt) => {
+            if v.is_null() {
+                put(out, &0u8);
+            } else {
+                put(out, &1u8);
+                encode(&nt.ty, v, path, out)?;
+            }
+        }
+        OwnedDataModelType::Unit | OwnedDataModelType::UnitStruct => {
+            if !v.is_null() {
+                return Err(mismatch(path, "null"));
+            }
+        }
+        OwnedDataModelType::NewtypeStruct(nt) => encode(&nt.ty, v, path, out)?,
+        OwnedDataModelType::Seq(nt) => {
+            let arr = v.as_array().ok_or_else(|| mismatch(path, "an array"))?;
+            put(out, &arr.len());
+            for (idx, val) in arr.iter().enumerate() {
+                encode(&nt.ty, val, &format!("{path}/{idx}"), out)?;
+            }
+        }
+        OwnedDataModelType::Tuple(nts) | OwnedDataModelType::TupleStruct(nts) => {
+            encode_tuple(nts, v, path, out)?
+        }
+        OwnedDataModelType::Map { key, val } => {
+            let obj = v.as_object().ok_or_else(|| mismatch(path, "an object"))?;
+            put(out, &obj.len());
+            for (k, v) in obj {
+                let path = format!("{path}/{k}");
+                let k = match key.ty {
+                    OwnedDataModelType::String | OwnedDataModelType::Char => Value::from(k.clone()),
+                    _ => serde_json::from_str(k).map_err(|_| mismatch(&path, "a key"))?,
+                };
+                encode(&key.ty, &k, &path, out)?;
+                encode(&val.ty, v, &path, out)?;
+            }
+        }
+        OwnedDataModelType::Struct(fields) => encode_values(fields, v, path, out)?,
+        OwnedDataModelType::Enum(variants) => {
+            let (name, content) = match v {
+                Value::String(name) => (name, &Value::Null),
+                Value::Object(obj) if obj.len() == 1 => obj.iter().next().unwrap(),
+                _ => return Err(mismatch(path, "a variant")),
+            };
+            let Some(idx) = variants.iter().position(|var| var.name == *name) else {
+                return Err(mismatch(path, "a known variant"));
+            };
+            put(out, &(idx as u32));
+            let path = format!("{path}/{name}");
+            match &variants[idx].ty {
+                OwnedDataModelVariant::UnitVariant if content.is_null() => {}
+                OwnedDataModelVariant::UnitVariant => return Err(mismatch(&path, "null")),
+                OwnedDataModelVariant::NewtypeVariant(nt) => encode(&nt.ty, content, &path, out)?,
+                OwnedDataModelVariant::TupleVariant(nts) => encode_tuple(nts, content, &path, out)?,
+                OwnedDataModelVariant::StructVariant(fields) => {
+                    encode_values(fields, content, &path, out)?
+                }
+            }
+        }
+        OwnedDataModelType::Schema => return Err(ValueError::Unsupported(path.to_string())),
+    }
+    Ok(())
+}
+
+fn take<'a, T: DeserializeOwned>(bytes: &'a [u8], path: &str) -> Result<(T, &'a [u8]), ValueError> {
+    postcard::take_from_bytes(bytes).map_err(|_| ValueError::Malformed {
+        path: path.to_string(),
+    })
+}
+
+/// Take the length of a sequence or map, which can't be longer than the rest of
+/// the message
+///
+/// Elements take at least one byte, unless they are zero-sized. Those are
+/// limited too, so a malformed length can't make us allocate a huge array.
+fn take_len<'a>(bytes: &'a [u8], path: &str) -> Result<(usize, &'a [u8]), ValueError> {
+    let (len, rest) = take::<usize>(bytes, path)?;
+    if len > rest.len() {
+        return Err(ValueError::Malformed {
+            path: path.to_string(),
+        });
+    }
+    Ok((len, rest))
+}
+
+/// Is `null` a valid value of this type?
+fn is_nullable(ty: &OwnedDataModelType) -> bool {
+    match ty {
+        OwnedDataModelType::Unit | OwnedDataModelType::UnitStruct => true,
+        OwnedDataModelType::Option(_) => true,
+        OwnedDataModelType::NewtypeStruct(nt) => is_nullable(&nt.ty),
+        _ => false,
+    }
+}
+
+fn take_value<'a, T>(bytes: &'a [u8], path: &str) -> Result<(Value, &'a [u8]), ValueError>
+where
+    T: DeserializeOwned + Into<Value>,
+{
+    let (val, rest) = take::<T>(bytes, path)?;
+    Ok((val.into(), rest))
+}
+
+/// 128-bit integers are numbers if they fit in 64 bits, strings otherwise
+fn wide<T: TryInto<i64> + TryInto<u64> + ToString + Copy>(i: T) -> Value {
+    if let Ok(i) = TryInto::<i64>::try_into(i) {
+        Value::from(i)
+    } else if let Ok(i) = TryInto::<u64>::try_into(i) {
+        Value::from(i)
+    } else {
+        Value::from(i.to_string())
+    }
+}
+
+fn decode_values<'a>(
+    fields: &[OwnedNamedValue],
+    mut bytes: &'a [u8],
+    path: &str,
+) -> Result<(Value, &'a [u8]), ValueError> {
+    let mut obj = Map::new();
+    for field in fields {
+        let (val, rest) = decode(&field.ty.ty, bytes, &format!("{path}/{}", field.name))?;
+        obj.insert(field.name.clone(), val);
+        bytes = rest;
+    }
+    Ok((Value::Object(obj), bytes))
+}
+
+fn decode_tuple<'a>(
+    items: &[OwnedNamedType],
+    mut bytes: &'a [u8],
+    path: &str,
+) -> Result<(Value, &'a [u8]), ValueError> {
+    let mut arr = vec![];
+    for (idx, ty) in items.iter().enumerate() {
+        let (val, rest) = decode(&ty.ty, bytes, &format!("{path}/{idx}"))?;
+        arr.push(val);
+        bytes = rest;
+    }
+    Ok((Value::Array(arr), bytes))
+}
+
+fn decode<'a>(
+    ty: &OwnedDataModelType,
+    bytes: &'a [u8],
+    path: &str,
+) -> Result<(Value, &'a [u8]), ValueError> {
+    Ok(match ty {
+        OwnedDataModelType::Bool => take_value::<bool>(bytes, path)?,
+        OwnedDataModelType::I8 => take_value::<i8>(bytes, path)?,
+        OwnedDataModelType::U8 => take_value::<u8>(bytes, path)?,
+        OwnedDataModelType::I16 => take_value::<i16>(bytes, path)?,
+        OwnedDataModelType::I32 => take_value::<i32>(bytes, path)?,
+        OwnedDataModelType::I64 => take_value::<i64>(bytes, path)?,
+        OwnedDataModelType::U16 => take_value::<u16>(bytes, path)?,
+        OwnedDataModelType::U32 => take_value::<u32>(bytes, path)?,
+        OwnedDataModelType::U64 => take_value::<u64>(bytes, path)?,
+        OwnedDataModelType::Usize => take_value::<u64>(bytes, path)?,
+        OwnedDataModelType::Isize => take_value::<i64>(bytes, path)?,
+        OwnedDataModelType::F32 => take_value::<f32>(bytes, path)?,
+        OwnedDataModelType::F64 => take_value::<f64>(bytes, path)?,
+        OwnedDataModelType::String => take_value::<String>(bytes, path)?,
+        OwnedDataModelType::I128 => {
+            let (i, rest) = take::<i128>(bytes, path)?;
+            (wide(i), rest)
+        }
+        OwnedDataModelType::U128 => {
+            let (i, rest) = take::<u128>(bytes, path)?;
+            (wide(i), rest)
+        }
+        OwnedDataModelType::Char => {
+            let (c, rest) = take::<char>(bytes, path)?;
+            (Value::from(c.to_string()), rest)
+        }
+        OwnedDataModelType::ByteArray => take_value::<Vec<u8>>(bytes, path)?,
+        OwnedDataModelType::Option(nt) if is_nullable(&nt.ty) => {
+            return Err(ValueError::Unsupported(path.to_string()))
+        }
+        OwnedDataModelType::Option(nt) => match take::<u8>(bytes, path)? {
+            (0, rest) => (Value::Null, rest),
+            (1, rest) => decode(&nt.ty, rest, path)?,
+            _ => {
+                return Err(ValueError::Malformed {
+                    path: path.to_string(),
+                })
+            }
+        },
+        OwnedDataModelType::Unit | OwnedDataModelType::UnitStruct => (Value::Null, bytes),
+        OwnedDataModelType::NewtypeStruct(nt) => decode(&nt.ty, bytes, path)?,
+        OwnedDataModelType::Seq(nt) => {
+            let (len, mut bytes) = take_len(bytes, path)?;
+            let mut arr = vec![];
+            for idx in 0..len {
+                let (val, rest) = decode(&nt.ty, bytes, &format!("{path}/{idx}"))?;
+                arr.push(val);
+                bytes = rest;
+            }
+            (Value::Array(arr), bytes)
+        }
+        OwnedDataModelType::Tuple(nts) | OwnedDataModelType::TupleStruct(nts) => {
+            decode_tuple(nts, bytes, path)?
+        }
+        OwnedDataModelType::Map { key, val } => {
+            let (len, mut bytes) = take_len(bytes, path)?;
+            let mut obj = Map::new();
+            for _ in 0..len {
+                let (k, rest) = decode(&key.ty, bytes, path)?;
+                let k = match k {
+                    Value::String(s) => s,
+                    k => k.to_string(),
+                };
+                let (v, rest) = decode(&val.ty, rest, &format!("{path}/{k}"))?;
+                obj.insert(k, v);
+                bytes = rest;
+            }
+            (Value::Object(obj), bytes)
+        }
+        OwnedDataModelType::Struct(fields) => decode_values(fields, bytes, path)?,
+        OwnedDataModelType::Enum(variants) => {
+            let (idx, bytes) = take::<u32>(bytes, path)?;
+            let Some(var) = variants.get(idx as usize) else {
+                return Err(ValueError::Malformed {
+                    path: path.to_string(),
+                });
+            };
+            let path = format!("{path}/{}", var.name);
+            let (content, bytes) = match &var.ty {
+                OwnedDataModelVariant::UnitVariant => {
+                    return Ok((Value::from(var.name.clone()), bytes))
+                }
+                OwnedDataModelVariant::NewtypeVariant(nt) => decode(&nt.ty, bytes, &path)?,
+                OwnedDataModelVariant::TupleVariant(nts) => decode_tuple(nts, bytes, &path)?,
+                OwnedDataModelVariant::StructVariant(fields) => {
+                    decode_values(fields, bytes, &path)?
+                }
+            };
+            let mut obj = Map::new();
+            obj.insert(var.name.clone(), content);
+            (Value::Object(obj), bytes)
+        }
+        OwnedDataModelType::Schema => return Err(ValueError::Unsupported(path.to_string())),
+    })
+}
+
+/// An error of the [`DynamicClient`]
+#[derive(Debug, PartialEq, Error)]
+pub enum DynamicError<WireErr> {
+    /// The device has no endpoint with this path
+    #[error("no endpoint with path '{0}'")]
+    UnknownEndpoint(String),
+    /// The device has no topic with this path, in the requested direction
+    #[error("no topic with path '{0}'")]
+    UnknownTopic(String),
+    /// A value did not match the schema
+    #[error(transparent)]
+    Value(#[from] ValueError),
+    /// Sending the request or receiving the response failed
+    #[error("communication failed: {0}")]
+    Comms(HostErr<WireErr>),
+}
+
+/// A client calling endpoints and topics by path, with JSON values
+///
+/// See the [module documentation][self] for how values are represented.
+pub struct DynamicClient<WireErr> {
+    client: HostClient<WireErr>,
+    report: SchemaReport,
+}
+
+impl<WireErr> DynamicClient<WireErr>
+where
+    WireErr: DeserializeOwned + Schema,
+{
+    /// Create a client, requesting the [`SchemaReport`] of the device
+    pub async fn new(client: HostClient<WireErr>) -> Result<Self, SchemaError<WireErr>> {
+        let report = client.get_schema_report().await?;
+        Ok(Self::with_report(client, report))
+    }
+
+    /// Create a client with a known [`SchemaReport`], for example from a
+    /// [`SchemaCache`][crate::host_client::SchemaCache]
+    pub fn with_report(client: HostClient<WireErr>, report: SchemaReport) -> Self {
+        Self { client, report }
+    }
+
+    /// The underlying [`HostClient`]
+    pub fn client(&self) -> &HostClient<WireErr> {
+        &self.client
+    }
+
+    /// The schema of the device
+    pub fn report(&self) -> &SchemaReport {
+        &self.report
+    }
+
+    /// Send a request to the endpoint with the given path, and await the response
+    pub async fn send_resp(&self, path: &str, req: &Value) -> Result<Value, DynamicError<WireErr>> {
+        let Some(ep) = self.report.endpoints.iter().find(|ep| ep.path == path) else {
+            return Err(DynamicError::UnknownEndpoint(path.to_string()));
+        };
+        let seq_no = self.client.ctx.seq.fetch_add(1, Ordering::Relaxed);
+        let frame = RpcFrame {
+            header: VarHeader {
+                key: VarKey::Key8(ep.req_key),
+                seq_no: VarSeq::Seq4(seq_no),
+            },
+            body: to_postcard(&ep.req_ty, req)?,
+        };
+        let resp = self
+            .client
+            .send_resp_raw(frame, ep.resp_key)
+            .await
+            .map_err(DynamicError::Comms)?;
+        Ok(from_postcard(&ep.resp_ty, &resp.body)?)
+    }
+
+    /// Publish a message to the topic with the given path, sent to the server
+    ///
+    /// Like requests, the message is not sent while the connection is lost, or
+    /// if it is too long for the server, and the sequence number is resized to
+    /// the configured size.
+    pub async fn publish(
+        &self,
+        path: &str,
+        seq_no: VarSeq,
+        msg: &Value,
+    ) -> Result<(), DynamicError<WireErr>> {
+        let Some(tp) = self.report.topics_in.iter().find(|tp| tp.path == path) else {
+            return Err(DynamicError::UnknownTopic(path.to_string()));
+        };
+        let mut frame = RpcFrame {
+            header: VarHeader {
+                key: VarKey::Key8(tp.key),
+                seq_no,
+            },
+            body: to_postcard(&tp.ty, msg)?,
+        };
+        // Check the message like a request, so that a lost connection or a
+        // message the server can't receive is reported as such
+        if self.client.is_closed() {
+            return Err(DynamicError::Comms(HostErr::Closed));
+        }
+        self.client
+            .prepare_request(&mut frame)
+            .map_err(DynamicError::Comms)?;
+        self.client
+            .publish_raw(frame)
+            .await
+            .map_err(|_| DynamicError::Comms(HostErr::Closed))
+    }
+
+    /// Subscribe to the topic with the given path, sent to the client
+    pub async fn subscribe(
+        &self,
+        path: &str,
+        depth: usize,
+    ) -> Result<DynamicSubscription, DynamicError<WireErr>> {
+        let Some(tp) = self.report.topics_out.iter().find(|tp| tp.path == path) else {
+            return Err(DynamicError::UnknownTopic(path.to_string()));
+        };
+        let sub = self
+            .client
+            .subscribe_multi_raw(tp.key, depth)
+            .await
+            .map_err(|_| DynamicError::Comms(HostErr::Closed))?;
+        Ok(DynamicSubscription {
+            sub,
+            ty: tp.ty.clone(),
+        })
+    }
+}
+
+/// A subscription to a topic, decoding messages to JSON values
+pub struct DynamicSubscription {
+    sub: RawMultiSubscription,
+    ty: OwnedNamedType,
+}
+
+impl DynamicSubscription {
+    /// Await a message for the given subscription.
+    ///
+    /// Messages that don't match the schema are skipped.
+    pub async fn recv(&mut self) -> Result<Value, MultiSubRxError> {
+        loop {
+            let frame = self.sub.recv().await?;
+            match from_postcard(&self.ty, &frame.body) {
+                Ok(val) => return Ok(val),
+                Err(e) => tracing::warn!("Skipping a message: {e}"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use postcard_schema::{schema::owned::OwnedNamedType, Schema};
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use super::{from_postcard, to_postcard, ValueError};
+
+    #[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+    struct Newtype(u16);
+
+    #[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+    enum Kind {
+        Unit,
+        Newtype(i32),
+        Tuple(u8, bool),
+        Struct { c: char },
+    }
+
+    #[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+    struct Everything {
+        small: i8,
+        wide: u128,
+        float: f32,
+        name: String,
+        maybe: Option<Newtype>,
+        list: Vec<Kind>,
+        pair: (u64, ()),
+        map: BTreeMap<u8, i64>,
+    }
+
+    #[test]
+    fn round_trip() {
+        let ty = OwnedNamedType::from(Everything::SCHEMA);
+        let value = Everything {
+            small: -5,
+            wide: u128::MAX,
+            float: 1.5,
+            name: "postcard".into(),
+            maybe: Some(Newtype(300)),
+            list: vec![
+                Kind::Unit,
+                Kind::Newtype(-1),
+                Kind::Tuple(7, true),
+                Kind::Struct { c: 'ß' },
+            ],
+            pair: (1 << 40, ()),
+            map: [(1, -2), (3, 4)].into_iter().collect(),
+        };
+        let json = json!({
+            "small": -5,
+            "wide": u128::MAX.to_string(),
+            "float": 1.5,
+            "name": "postcard",
+            "maybe": 300,
+            "list": ["Unit", {"Newtype": -1}, {"Tuple": [7, true]}, {"Struct": {"c": "ß"}}],
+            "pair": [1u64 << 40, null],
+            "map": {"1": -2, "3": 4},
+        });
+
+        let bytes = postcard::to_stdvec(&value).unwrap();
+        assert_eq!(to_postcard(&ty, &json), Ok(bytes.clone()));
+        assert_eq!(from_postcard(&ty, &bytes), Ok(json));
+    }
+
+    #[test]
+    fn errors() {
+        let ty = OwnedNamedType::from(Everything::SCHEMA);
+        let json = json!({
+            "small": 500,
+            "wide": 0,
+            "float": 0.0,
+            "name": "",
+            "list": [],
+            "pair": [0, null],
+            "map": {},
+        });
+        assert_eq!(
+            to_postcard(&ty, &json),
+            Err(ValueError::Mismatch {
+                path: "/small".into(),
+                expected: "an i8".into(),
+            })
+        );
+
+        let ty = OwnedNamedType::from(Kind::SCHEMA);
+        assert_eq!(
+            to_postcard(&ty, &json!({"Tuple": [1]})),
+            Err(ValueError::Mismatch {
+                path: "/Tuple".into(),
+                expected: "an array of 2 items".into(),
+            })
+        );
+        assert_eq!(
+            from_postcard(&ty, &[2, 1]),
+            Err(ValueError::Malformed {
+                path: "/Tuple/1".into()
+            })
+        );
+        assert_eq!(
+            from_postcard(&ty, &[0, 0]),
+            Err(ValueError::TrailingBytes(1))
+        );
+
+        // Lengths are limited by the rest of the message, even for elements taking
+        // no space
+        let huge = postcard::to_stdvec(&usize::MAX).unwrap();
+        let ty = OwnedNamedType::from(<Vec<()>>::SCHEMA);
+        assert_eq!(
+            from_postcard(&ty, &huge),
+            Err(ValueError::Malformed { path: "".into() })
+        );
+        assert_eq!(
+            from_postcard(&ty, &[2, 0, 0]),
+            Err(ValueError::TrailingBytes(2))
+        );
+        let ty = OwnedNamedType::from(<BTreeMap<u8, ()>>::SCHEMA);
+        assert_eq!(
+            from_postcard(&ty, &huge),
+            Err(ValueError::Malformed { path: "".into() })
+        );
+
+        // `Some(())` and `None` would both be `null`
+        let ty = OwnedNamedType::from(<Option<()>>::SCHEMA);
+        assert_eq!(
+            to_postcard(&ty, &json!(null)),
+            Err(ValueError::Unsupported("".into()))
+        );
+        assert_eq!(
+            from_postcard(&ty, &[1]),
+            Err(ValueError::Unsupported("".into()))
+        );
+        let ty = OwnedNamedType::from(<Vec<Option<Option<u8>>>>::SCHEMA);
+        assert_eq!(
+            from_postcard(&ty, &[1, 1, 1, 5]),
+            Err(ValueError::Unsupported("/0".into()))
+        );
+    }
+}
@@ -5,12 +5,13 @@
 
 use core::time::Duration;
 use std::{
-    collections::HashSet,
+    collections::{HashSet, VecDeque},
     future::Future,
     marker::PhantomData,
+    pin::Pin,
     sync::{
-        atomic::{AtomicU32, Ordering},
-        Arc, RwLock,
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Arc, OnceLock, RwLock,
     },
 };
 use thiserror::Error;
@@ -29,12 +30,24 @@ use util::Subscriptions;
 
 use crate::{
     header::{VarHeader, VarKey, VarKeyKind, VarSeq, VarSeqKind},
-    standard_icd::{GetAllSchemaDataTopic, GetAllSchemasEndpoint, OwnedSchemaData},
-    Endpoint, Key, Topic, TopicDirection,
+    standard_icd::{
+        CancelTopic, Capabilities, DeviceInfoEndpoint, GetAllSchemaDataTopic,
+        GetAllSchemasEndpoint, GetSchemaItemTopic, GetSchemaPageEndpoint, OwnedDeviceInfo,
+        OwnedIndexedSchemaData, OwnedSchemaData, SchemaPage, StreamEndTopic, WireError, ERROR_KEY,
+    },
+    DeviceMap, Endpoint, EndpointMap, Key, StreamEndpoint, Topic, TopicDirection, TopicMap,
 };
 
-use self::util::Stopper;
+use self::topic_control::TopicGuard;
+use self::util::{link_connected, link_lost, wait_link_up, Link, Stopper};
+pub use crate::host_client::bulk::BulkUploadError;
+pub use crate::host_client::credits::CreditedSubscription;
+#[cfg(not(target_family = "wasm"))]
+pub use crate::host_client::reconnect::ReconnectBackoff;
+#[cfg(not(target_family = "wasm"))]
+pub use crate::host_client::schema_cache::SchemaCache;
 pub use crate::host_client::util::HostClientConfig;
+pub use crate::host_client::verify::{IcdDiff, ItemDiff, KeyMismatch};
 
 #[cfg(all(feature = "raw-nusb", not(target_family = "wasm")))]
 mod raw_nusb;
@@ -42,20 +55,60 @@ mod raw_nusb;
 #[cfg(all(feature = "cobs-serial", not(target_family = "wasm")))]
 mod serial;
 
+#[cfg(all(feature = "cobs", not(target_family = "wasm")))]
+mod stream;
+
+#[cfg(all(feature = "stdio", not(target_family = "wasm")))]
+mod stdio;
+
+#[cfg(all(feature = "tcp", not(target_family = "wasm")))]
+mod tcp;
+
+#[cfg(all(feature = "udp", not(target_family = "wasm")))]
+mod udp;
+
 #[cfg(all(feature = "webusb", target_family = "wasm"))]
 pub mod webusb;
 
+#[cfg(not(target_family = "wasm"))]
+mod reconnect;
+
+#[cfg(not(target_family = "wasm"))]
+mod schema_cache;
+
+#[cfg(feature = "websocket")]
+pub mod websocket;
+
+#[cfg(feature = "dynamic")]
+pub mod dynamic;
+
 pub(crate) mod util;
 
+mod bulk;
+mod capabilities;
+mod credits;
+mod topic_control;
+mod verify;
+
 #[cfg(feature = "test-utils")]
 pub mod test_channels;
 
 /// Host Error Kind
+///
+/// More kinds of errors may be added in minor releases.
 #[derive(Debug, PartialEq, Error)]
+#[non_exhaustive]
 pub enum HostErr<WireErr> {
     /// An error of the user-specified wire error type
     #[error("a wire error occurred")]
     Wire(WireErr),
+    /// A protocol error reported by the server with the standard [`WireError`]
+    ///
+    /// These are sent on [`ERROR_KEY`], for example if the server didn't know
+    /// the requested endpoint. If the user specified wire error type is sent on
+    /// [`ERROR_KEY`] as well, these are reported as [`HostErr::Wire`] instead.
+    #[error("the server reported a protocol error: {0:?}")]
+    Protocol(WireError),
     /// We got a response that didn't match the expected value or the
     /// user specified wire error type
     ///
@@ -69,6 +122,30 @@ pub enum HostErr<WireErr> {
     /// The interface has been closed, and no further messages are possible
     #[error("the interface has been closed, and no further messages are possible")]
     Closed,
+    /// The connection was lost, and is being re-established
+    ///
+    /// Only returned by clients created with `HostClient::new_reconnecting()`.
+    /// The request may be retried once the connection is back.
+    #[error("the connection was lost, and is being re-established")]
+    Reconnecting,
+    /// No response was received in time
+    ///
+    /// If the response arrives later, it is dropped, and counted in
+    /// [`HostClient::late_responses()`].
+    #[error("no response was received in time")]
+    Timeout,
+    /// The request is longer than the server can receive
+    ///
+    /// Returned if the request can't be split into fragments of the configured
+    /// `fragment_size`, or once the limits of the server are known, see
+    /// [`HostClient::query_capabilities()`]. The request was not sent.
+    #[error("the request is too long for the server")]
+    RequestTooLarge {
+        /// The length of the request
+        len: usize,
+        /// The longest request the server can receive
+        max: usize,
+    },
 }
 
 impl<T> From<WaitError> for HostErr<T> {
@@ -169,10 +246,18 @@ pub trait WireSpawn: 'static {
 ///
 /// [HostClient]s can be cloned, and used across multiple tasks/threads.
 ///
-/// There are currently two ways to create one, based on the transport used:
+/// There are currently seven ways to create one, based on the transport used:
 ///
 /// 1. With raw USB Bulk transfers: [`HostClient::new_raw_nusb()`] (**recommended**)
 /// 2. With cobs CDC-ACM transfers: [`HostClient::new_serial_cobs()`]
+/// 3. With TCP sockets: [`HostClient::try_new_tcp()`]
+/// 4. With UDP sockets: [`HostClient::try_new_udp()`]
+/// 5. With WebSockets, on native or wasm targets: [`HostClient::try_new_websocket()`]
+/// 6. With the stdio of a child process: [`HostClient::spawn_stdio()`]
+/// 7. With any other tokio byte stream: [`HostClient::new_cobs_stream()`]
+///
+/// Any of these can be wrapped with [`HostClient::new_reconnecting()`], which
+/// re-creates the connection whenever it is lost.
 pub struct HostClient<WireErr> {
     ctx: Arc<HostContext>,
     out: mpsc::Sender<RpcFrame>,
@@ -180,6 +265,8 @@ pub struct HostClient<WireErr> {
     err_key: Key,
     stopper: Stopper,
     seq_kind: VarSeqKind,
+    link: Link,
+    timeout: Option<Duration>,
     _pd: PhantomData<fn() -> WireErr>,
 }
 
@@ -203,6 +290,15 @@ where
             map: WaitMap::new(),
             seq: AtomicU32::new(0),
             subscription_timeout: config.subscriber_timeout_if_full,
+            forward: OnceLock::new(),
+            expired: std::sync::Mutex::new(VecDeque::new()),
+            late_responses: AtomicU64::new(0),
+            streams: std::sync::Mutex::new(Vec::new()),
+            subscribers: std::sync::Mutex::new(Vec::new()),
+            capabilities: std::sync::Mutex::new(None),
+            cancel_requests: config.cancel_requests,
+            control_topics: config.control_topics,
+            fragment_size: std::sync::Mutex::new(config.fragment_size),
         });
 
         let err_key = Key::for_path::<WireErr>(config.err_uri_path);
@@ -215,6 +311,8 @@ where
             subscriptions: Arc::new(Mutex::new(Subscriptions::default())),
             stopper: Stopper::new(),
             seq_kind: config.seq_kind,
+            link: None,
+            timeout: config.default_timeout,
         };
 
         let wire = WireContext {
@@ -253,13 +351,128 @@ impl<WireErr> From<UnableToFindType> for SchemaError<WireErr> {
     }
 }
 
+/// Build a [`SchemaReport`], adding the types before the endpoints and topics using them
+fn build_report<WireErr>(
+    data: impl IntoIterator<Item = OwnedSchemaData>,
+) -> Result<SchemaReport, SchemaError<WireErr>> {
+    let mut rpt = SchemaReport::default();
+    let mut e_and_t = vec![];
+
+    for d in data {
+        match d {
+            OwnedSchemaData::Type(d) => {
+                rpt.add_type(d);
+            }
+            e @ OwnedSchemaData::Endpoint { .. } => e_and_t.push(e),
+            t @ OwnedSchemaData::Topic { .. } => e_and_t.push(t),
+        }
+    }
+
+    for e in e_and_t {
+        match e {
+            OwnedSchemaData::Type(_) => unreachable!(),
+            OwnedSchemaData::Endpoint {
+                path,
+                request_key,
+                response_key,
+            } => {
+                rpt.add_endpoint(path, request_key, response_key)?;
+            }
+            OwnedSchemaData::Topic {
+                path,
+                key,
+                direction,
+            } => match direction {
+                TopicDirection::ToServer => rpt.add_topic_in(path, key)?,
+                TopicDirection::ToClient => rpt.add_topic_out(path, key)?,
+            },
+        }
+    }
+
+    Ok(rpt)
+}
+
 /// # Interface Methods
 impl<WireErr> HostClient<WireErr>
 where
     WireErr: DeserializeOwned + Schema,
 {
     /// Obtain a [`SchemaReport`] describing the connected device
+    ///
+    /// The schema items are requested in pages with the [`GetSchemaPageEndpoint`],
+    /// and items that were lost are requested again. Servers that don't support
+    /// this are asked with the [`GetAllSchemasEndpoint`] instead, collecting
+    /// items until none arrived for 500ms.
     pub async fn get_schema_report(&self) -> Result<SchemaReport, SchemaError<WireErr>> {
+        /// The number of items requested at once, which must fit the subscription
+        const PAGE: u32 = 32;
+        /// The number of pages in a row that may arrive without any new items
+        const ATTEMPTS: usize = 3;
+
+        let Ok(mut sub) = self
+            .subscribe_multi::<GetSchemaItemTopic>(2 * PAGE as usize)
+            .await
+        else {
+            return Err(SchemaError::Comms(HostErr::Closed));
+        };
+
+        let mut items: Vec<Option<OwnedSchemaData>> = vec![];
+        let mut received = 0;
+        let mut failed = 0;
+        let mut start = 0;
+        loop {
+            let page = SchemaPage { start, count: PAGE };
+            let info = match self.send_resp::<GetSchemaPageEndpoint>(&page).await {
+                Ok(info) => info,
+                // Servers without paging reject the request
+                Err(HostErr::Wire(_) | HostErr::Protocol(_)) if items.is_empty() => {
+                    drop(sub);
+                    return self.get_schema_report_unpaged().await;
+                }
+                Err(e) => return Err(SchemaError::Comms(e)),
+            };
+            items.resize(info.total as usize, None);
+
+            // All items were published before the reply
+            let before = received;
+            loop {
+                let frame = match sub.rx.try_recv() {
+                    Ok(frame) => frame,
+                    Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::TryRecvError::Empty) => break,
+                    Err(broadcast::error::TryRecvError::Closed) => {
+                        return Err(SchemaError::Comms(HostErr::Closed))
+                    }
+                };
+                let Ok(item) = postcard::from_bytes::<OwnedIndexedSchemaData>(&frame.body) else {
+                    continue;
+                };
+                if let Some(slot @ None) = items.get_mut(item.index as usize) {
+                    *slot = Some(item.data);
+                    received += 1;
+                }
+            }
+
+            if received == before {
+                failed += 1;
+                if failed == ATTEMPTS {
+                    return Err(SchemaError::LostData);
+                }
+            } else {
+                failed = 0;
+            }
+
+            match items.iter().position(Option::is_none) {
+                Some(missing) => start = missing as u32,
+                None => break,
+            }
+        }
+
+        build_report(items.into_iter().flatten())
+    }
+
+    /// Obtain a [`SchemaReport`] with the [`GetAllSchemasEndpoint`]
+    async fn get_schema_report_unpaged(&self) -> Result<SchemaReport, SchemaError<WireErr>> {
         let Ok(mut sub) = self.subscribe_multi::<GetAllSchemaDataTopic>(64).await else {
             return Err(SchemaError::Comms(HostErr::Closed));
         };
@@ -283,39 +496,7 @@ where
             (Err(e), Ok(_)) => return Err(SchemaError::Comms(e)),
             (Err(e1), Err(_e2)) => return Err(SchemaError::Comms(e1)),
         };
-        let mut rpt = SchemaReport::default();
-        let mut e_and_t = vec![];
-
-        for d in data {
-            match d {
-                OwnedSchemaData::Type(d) => {
-                    rpt.add_type(d);
-                }
-                e @ OwnedSchemaData::Endpoint { .. } => e_and_t.push(e),
-                t @ OwnedSchemaData::Topic { .. } => e_and_t.push(t),
-            }
-        }
-
-        for e in e_and_t {
-            match e {
-                OwnedSchemaData::Type(_) => unreachable!(),
-                OwnedSchemaData::Endpoint {
-                    path,
-                    request_key,
-                    response_key,
-                } => {
-                    rpt.add_endpoint(path, request_key, response_key)?;
-                }
-                OwnedSchemaData::Topic {
-                    path,
-                    key,
-                    direction,
-                } => match direction {
-                    TopicDirection::ToServer => rpt.add_topic_in(path, key)?,
-                    TopicDirection::ToClient => rpt.add_topic_out(path, key)?,
-                },
-            }
-        }
+        let rpt = build_report(data)?;
 
         let mut data_matches = true;
         data_matches &= resp.endpoints_sent as usize == rpt.endpoints.len();
@@ -331,14 +512,57 @@ where
         }
     }
 
+    /// Obtain the [`OwnedDeviceInfo`] identifying the connected device
+    ///
+    /// Servers that don't provide one reply with a [`WireError::UnknownKey`] error.
+    pub async fn get_device_info(&self) -> Result<OwnedDeviceInfo, HostErr<WireErr>> {
+        self.send_resp::<DeviceInfoEndpoint>(&()).await
+    }
+
     /// Send a message of type [Endpoint::Request][Endpoint] to `path`, and await
     /// a response of type [Endpoint::Response][Endpoint] (or WireErr) to `path`.
     ///
-    /// This function will wait potentially forever. Consider using with a timeout.
+    /// This function will wait for the
+    /// [`default_timeout`][HostClientConfig::default_timeout] of the client, or
+    /// potentially forever if no default timeout was set. Use
+    /// [`HostClient::send_resp_timeout()`] to wait for a different amount of time.
+    ///
+    /// If the request times out, or the returned future is dropped before the
+    /// response was received, a [`CancelTopic`] message is sent to the server, which
+    /// may stop handling the request. This is only done if the server advertised
+    /// [`Capabilities::CANCEL`], or [`HostClientConfig::cancel_requests`] is set.
     pub async fn send_resp<E: Endpoint>(
         &self,
         t: &E::Request,
     ) -> Result<E::Response, HostErr<WireErr>>
+    where
+        E::Request: Serialize + Schema,
+        E::Response: DeserializeOwned + Schema,
+    {
+        self.send_resp_inner::<E>(t, self.timeout).await
+    }
+
+    /// Like [`HostClient::send_resp()`], but waits at most `timeout` for the response,
+    /// regardless of the default timeout of the client.
+    ///
+    /// Returns [`HostErr::Timeout`] if no response was received in time.
+    pub async fn send_resp_timeout<E: Endpoint>(
+        &self,
+        t: &E::Request,
+        timeout: Duration,
+    ) -> Result<E::Response, HostErr<WireErr>>
+    where
+        E::Request: Serialize + Schema,
+        E::Response: DeserializeOwned + Schema,
+    {
+        self.send_resp_inner::<E>(t, Some(timeout)).await
+    }
+
+    async fn send_resp_inner<E: Endpoint>(
+        &self,
+        t: &E::Request,
+        timeout: Option<Duration>,
+    ) -> Result<E::Response, HostErr<WireErr>>
     where
         E::Request: Serialize + Schema,
         E::Response: DeserializeOwned + Schema,
@@ -355,42 +579,124 @@ where
             },
             body: msg,
         };
-        let frame = self.send_resp_raw(frame, E::RESP_KEY).await?;
+        let frame = self
+            .send_resp_raw_inner(frame, E::RESP_KEY, timeout)
+            .await?;
         let r = postcard::from_bytes::<E::Response>(&frame.body)?;
         Ok(r)
     }
 
     /// Perform an endpoint request/response,but without handling the
     /// Ser/De automatically
+    ///
+    /// Uses the [`default_timeout`][HostClientConfig::default_timeout] of the client.
     pub async fn send_resp_raw(
+        &self,
+        rqst: RpcFrame,
+        resp_key: Key,
+    ) -> Result<RpcFrame, HostErr<WireErr>> {
+        self.send_resp_raw_inner(rqst, resp_key, self.timeout).await
+    }
+
+    async fn send_resp_raw_inner(
         &self,
         mut rqst: RpcFrame,
         resp_key: Key,
+        timeout: Option<Duration>,
     ) -> Result<RpcFrame, HostErr<WireErr>> {
         let cancel_fut = self.stopper.wait_stopped();
-        let kkind: VarKeyKind = *self.ctx.kkind.read().unwrap();
-        rqst.header.key.shrink_to(kkind);
+        let kkind = self.prepare_request(&mut rqst)?;
+        let lost_fut = link_lost(&self.link);
         let mut resp_key = VarKey::Key8(resp_key);
         let mut err_key = VarKey::Key8(self.err_key);
         resp_key.shrink_to(kkind);
         err_key.shrink_to(kkind);
-
-        // Prepare to receive the reply, BEFORE we send the request.
-        // This uses the `enqueue` feature of WaitMap, which makes sure that
-        // our receiver is ready to "catch" before we even send the request.
-        let ok_resp = self.ctx.map.wait(VarHeader {
+        let ok_hdr = VarHeader {
             seq_no: rqst.header.seq_no,
             key: resp_key,
-        });
-        let err_resp = self.ctx.map.wait(VarHeader {
+        };
+        let err_hdr = VarHeader {
             seq_no: rqst.header.seq_no,
             key: err_key,
+        };
+        // Protocol errors are sent on the standard error key, unless it is the
+        // same as the one of the user's error type
+        let proto_hdr = (self.err_key != ERROR_KEY).then(|| {
+            let mut key = VarKey::Key8(ERROR_KEY);
+            key.shrink_to(kkind);
+            VarHeader {
+                seq_no: rqst.header.seq_no,
+                key,
+            }
         });
+
+        let timeout_fut = async {
+            match timeout {
+                Some(t) => tokio::time::sleep(t).await,
+                None => core::future::pending().await,
+            }
+        };
+
+        let res = select! {
+            r = self.wait_resp(rqst, ok_hdr, err_hdr, proto_hdr, kkind) => r,
+            _c = cancel_fut => Err(HostErr::Closed),
+            _l = lost_fut => Err(HostErr::Reconnecting),
+            _t = timeout_fut => Err(HostErr::Timeout),
+        };
+
+        // The waits for the response have been dropped by now, so any late
+        // response won't be matched anymore, and will be counted instead.
+        if let Err(HostErr::Timeout) = res {
+            tracing::warn!(
+                "Request with seq_no {} timed out",
+                Into::<u32>::into(ok_hdr.seq_no)
+            );
+            self.ctx.expire(&[Some(ok_hdr), Some(err_hdr), proto_hdr]);
+        }
+        res
+    }
+
+    /// Check that a request can be sent, and resize its header to the key size in
+    /// use and the configured sequence number size
+    ///
+    /// Returns the key size in use.
+    fn prepare_request(&self, rqst: &mut RpcFrame) -> Result<VarKeyKind, HostErr<WireErr>> {
+        if !link_connected(&self.link) {
+            return Err(HostErr::Reconnecting);
+        }
+        let kkind: VarKeyKind = *self.ctx.kkind.read().unwrap();
+        rqst.header.key.shrink_to(kkind);
+        rqst.header.seq_no.resize(self.seq_kind);
+        if let Err((len, max)) = self.ctx.check_request_len(rqst) {
+            return Err(HostErr::RequestTooLarge { len, max });
+        }
+        Ok(kkind)
+    }
+
+    /// Send a request, and wait for either the response or an error
+    async fn wait_resp(
+        &self,
+        rqst: RpcFrame,
+        ok_hdr: VarHeader,
+        err_hdr: VarHeader,
+        proto_hdr: Option<VarHeader>,
+        kkind: VarKeyKind,
+    ) -> Result<RpcFrame, HostErr<WireErr>> {
+        // Prepare to receive the reply, BEFORE we send the request.
+        // This uses the `enqueue` feature of WaitMap, which makes sure that
+        // our receiver is ready to "catch" before we even send the request.
+        let ok_resp = self.ctx.map.wait(ok_hdr);
+        let err_resp = self.ctx.map.wait(err_hdr);
+        let proto_resp = proto_hdr.map(|hdr| self.ctx.map.wait(hdr));
         let mut ok_resp = std::pin::pin!(ok_resp);
         let mut err_resp = std::pin::pin!(err_resp);
+        let mut proto_resp = std::pin::pin!(proto_resp);
         let setup_fut: Result<(), WaitError> = async {
             ok_resp.as_mut().subscribe().await?;
             err_resp.as_mut().subscribe().await?;
+            if let Some(proto_resp) = proto_resp.as_mut().as_pin_mut() {
+                proto_resp.subscribe().await?;
+            }
             Ok(())
         }
         .await;
@@ -415,10 +721,19 @@ where
             });
         };
 
+        let seq_no = rqst.header.seq_no;
         self.out.send(rqst).await.map_err(|_| HostErr::Closed)?;
 
-        select! {
-            _c = cancel_fut => Err(HostErr::Closed),
+        // From now on, if we stop waiting before getting a response, tell the
+        // server that we are no longer interested
+        let mut cancel = CancelOnDrop {
+            ctx: &self.ctx,
+            out: &self.out,
+            seq_no,
+            armed: true,
+        };
+
+        let res = select! {
             o = ok_resp => {
                 let (hdr, resp) = o?;
                 if hdr.key.kind() != kkind {
@@ -434,7 +749,89 @@ where
                 let r = postcard::from_bytes::<WireErr>(&resp)?;
                 Err(HostErr::Wire(r))
             },
+            p = async {
+                match proto_resp.as_mut().as_pin_mut() {
+                    Some(proto_resp) => proto_resp.await,
+                    None => core::future::pending().await,
+                }
+            } => {
+                let (hdr, resp) = p?;
+                if hdr.key.kind() != kkind {
+                    *self.ctx.kkind.write().unwrap() = hdr.key.kind();
+                }
+                let r = postcard::from_bytes::<WireError>(&resp)?;
+                Err(HostErr::Protocol(r))
+            },
+        };
+        cancel.armed = false;
+        res
+    }
+
+    /// Send a request to a [StreamEndpoint], and receive a stream of
+    /// [Endpoint::Response][Endpoint]s.
+    ///
+    /// The stream ends when the server sends a [`StreamEndTopic`] message or an
+    /// error, see [`ResponseStream::recv()`]. Unlike `send_resp`, no timeout is
+    /// used. If the [ResponseStream] is dropped before the stream has ended, a
+    /// [`CancelTopic`] message is sent to the server, under the same conditions as
+    /// for `send_resp`.
+    pub async fn send_stream<E: StreamEndpoint>(
+        &self,
+        t: &E::Request,
+    ) -> Result<ResponseStream<E::Response, WireErr>, HostErr<WireErr>>
+    where
+        E::Request: Serialize + Schema,
+        E::Response: DeserializeOwned + Schema,
+    {
+        if self.stopper.is_stopped() {
+            return Err(HostErr::Closed);
         }
+        let mut frame = RpcFrame {
+            // NOTE: prepare_request shrinks down key and sequence kinds to the
+            // appropriate amount
+            header: VarHeader {
+                key: VarKey::Key8(E::REQ_KEY),
+                seq_no: VarSeq::Seq4(self.ctx.seq.fetch_add(1, Ordering::Relaxed)),
+            },
+            body: postcard::to_stdvec(t).expect("Allocations should not ever fail"),
+        };
+        let kkind = self.prepare_request(&mut frame)?;
+        let lost = Box::pin(link_lost(&self.link));
+        let seq_no = frame.header.seq_no;
+        let mut resp_key = VarKey::Key8(E::RESP_KEY);
+        resp_key.shrink_to(kkind);
+
+        // Register the stream BEFORE we send the request, so that we don't
+        // miss any items
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.ctx.streams.lock().unwrap().push(StreamEntry {
+            seq_no,
+            resp_key,
+            err_key: VarKey::Key8(self.err_key),
+            tx,
+        });
+
+        let res = select! {
+            _ = self.stopper.wait_stopped() => Err(HostErr::Closed),
+            res = self.out.send(frame) => res.map_err(|_| HostErr::Closed),
+        };
+        if let Err(e) = res {
+            self.ctx.remove_stream(seq_no);
+            return Err(e);
+        }
+
+        Ok(ResponseStream {
+            rx,
+            ctx: self.ctx.clone(),
+            out: self.out.clone(),
+            seq_no,
+            resp_key,
+            err_key: VarKey::Key8(self.err_key),
+            stopper: self.stopper.clone(),
+            lost,
+            done: false,
+            _pd: PhantomData,
+        })
     }
 
     /// Publish a [Topic] [Message][Topic::Message].
@@ -521,6 +918,7 @@ where
         };
         Ok(MultiSubscription {
             rx,
+            _topic: self.topic_guard(T::TOPIC_KEY),
             _pd: PhantomData,
         })
     }
@@ -558,7 +956,10 @@ where
                 rx
             }
         };
-        Ok(RawMultiSubscription { rx })
+        Ok(RawMultiSubscription {
+            rx,
+            _topic: self.topic_guard(key),
+        })
     }
 
     ///////////////////////////////////////////////////////////////////////////
@@ -619,6 +1020,7 @@ where
         }
         Ok(Subscription {
             rx,
+            _topic: self.topic_guard(T::TOPIC_KEY),
             _pd: PhantomData,
         })
     }
@@ -661,7 +1063,10 @@ where
                 guard.exclusive_list.push((key, tx));
             }
         }
-        Ok(RawSubscription { rx })
+        Ok(RawSubscription {
+            rx,
+            _topic: self.topic_guard(key),
+        })
     }
 
     ///////////////////////////////////////////////////////////////////////////
@@ -720,6 +1125,7 @@ where
         }
         Ok(Subscription {
             rx,
+            _topic: self.topic_guard(T::TOPIC_KEY),
             _pd: PhantomData,
         })
     }
@@ -765,7 +1171,10 @@ where
                 guard.exclusive_list.push((key, tx));
             }
         }
-        Ok(RawSubscription { rx })
+        Ok(RawSubscription {
+            rx,
+            _topic: self.topic_guard(key),
+        })
     }
 
     /// Permanently close the connection to the client
@@ -779,11 +1188,40 @@ where
         self.stopper.stop()
     }
 
+    /// The number of responses that arrived after their request timed out
+    ///
+    /// These responses are dropped. Only recently expired requests are tracked,
+    /// so a response that is very late may not be counted.
+    pub fn late_responses(&self) -> u64 {
+        self.ctx.late_responses.load(Ordering::Relaxed)
+    }
+
     /// Has this host client been closed?
     pub fn is_closed(&self) -> bool {
         self.stopper.is_stopped()
     }
 
+    /// Is this host client currently connected?
+    ///
+    /// This is only ever `false` while clients created with
+    /// `HostClient::new_reconnecting()` are reconnecting, or once the client has
+    /// been closed.
+    pub fn is_connected(&self) -> bool {
+        !self.is_closed() && link_connected(&self.link)
+    }
+
+    /// Wait for the host client to be connected
+    ///
+    /// Completes immediately for clients that don't reconnect. Returns an error
+    /// if the client is closed.
+    pub async fn wait_connected(&self) -> Result<(), IoClosed> {
+        select! {
+            biased;
+            _ = self.stopper.wait_stopped() => Err(IoClosed),
+            _ = wait_link_up(&self.link) => Ok(()),
+        }
+    }
+
     /// Wait for the host client to be closed
     pub async fn wait_closed(&self) {
         self.stopper.wait_stopped().await;
@@ -794,6 +1232,7 @@ where
 /// automatically deserialized
 pub struct RawSubscription {
     rx: mpsc::Receiver<RpcFrame>,
+    _topic: TopicGuard,
 }
 
 impl RawSubscription {
@@ -808,6 +1247,7 @@ impl RawSubscription {
 /// A structure that represents a subscription to the given topic
 pub struct Subscription<M> {
     rx: mpsc::Receiver<RpcFrame>,
+    _topic: TopicGuard,
     _pd: PhantomData<M>,
 }
 
@@ -832,6 +1272,7 @@ where
 /// automatically deserialized
 pub struct RawMultiSubscription {
     rx: broadcast::Receiver<RpcFrame>,
+    _topic: TopicGuard,
 }
 
 impl RawMultiSubscription {
@@ -850,6 +1291,7 @@ impl RawMultiSubscription {
 /// A structure that represents a subscription to the given topic
 pub struct MultiSubscription<M> {
     rx: broadcast::Receiver<RpcFrame>,
+    _topic: TopicGuard,
     _pd: PhantomData<M>,
 }
 
@@ -887,6 +1329,93 @@ where
     }
 }
 
+/// A stream of responses to a request to a [StreamEndpoint]
+///
+/// Created with [`HostClient::send_stream()`].
+pub struct ResponseStream<T, WireErr> {
+    rx: mpsc::UnboundedReceiver<RpcFrame>,
+    ctx: Arc<HostContext>,
+    out: mpsc::Sender<RpcFrame>,
+    seq_no: VarSeq,
+    resp_key: VarKey,
+    err_key: VarKey,
+    stopper: Stopper,
+    lost: Pin<Box<dyn Future<Output = ()> + Send>>,
+    done: bool,
+    _pd: PhantomData<fn() -> (T, WireErr)>,
+}
+
+impl<T, WireErr> ResponseStream<T, WireErr>
+where
+    T: DeserializeOwned,
+    WireErr: DeserializeOwned,
+{
+    /// Await the next item of the stream.
+    ///
+    /// Returns [None] once the server has ended the stream. If the server
+    /// reported an error, or the connection was lost, the error is returned
+    /// once, and [None] is returned afterwards.
+    pub async fn recv(&mut self) -> Option<Result<T, HostErr<WireErr>>> {
+        if self.done {
+            return None;
+        }
+        let frame = select! {
+            biased;
+            f = self.rx.recv() => f,
+            _ = self.stopper.wait_stopped() => {
+                self.done = true;
+                return Some(Err(HostErr::Closed));
+            }
+            _ = &mut self.lost => {
+                self.done = true;
+                return Some(Err(HostErr::Reconnecting));
+            }
+        };
+        let Some(frame) = frame else {
+            self.done = true;
+            return None;
+        };
+        if frame.header.key == self.err_key {
+            self.done = true;
+            return Some(match postcard::from_bytes::<WireErr>(&frame.body) {
+                Ok(e) => Err(HostErr::Wire(e)),
+                Err(e) => Err(e.into()),
+            });
+        }
+        if frame.header.key == VarKey::Key8(ERROR_KEY) {
+            self.done = true;
+            return Some(match postcard::from_bytes::<WireError>(&frame.body) {
+                Ok(e) => Err(HostErr::Protocol(e)),
+                Err(e) => Err(e.into()),
+            });
+        }
+        Some(postcard::from_bytes::<T>(&frame.body).map_err(Into::into))
+    }
+
+    /// The sequence number of the request
+    pub fn seq_no(&self) -> VarSeq {
+        self.seq_no
+    }
+}
+
+impl<T, WireErr> Drop for ResponseStream<T, WireErr> {
+    fn drop(&mut self) {
+        // If the stream is still registered, the server hasn't ended it yet. Any
+        // responses still on the way are counted as late, like those of requests
+        // that timed out.
+        if self.ctx.remove_stream(self.seq_no) {
+            self.ctx.send_cancel(&self.out, self.seq_no);
+            let hdr = |key| {
+                Some(VarHeader {
+                    key,
+                    seq_no: self.seq_no,
+                })
+            };
+            self.ctx.expire(&[hdr(self.resp_key), hdr(self.err_key)]);
+        }
+    }
+}
+
 // Manual Clone impl because WireErr may not impl Clone
 impl<WireErr> Clone for HostClient<WireErr> {
     fn clone(&self) -> Self {
@@ -898,6 +1427,24 @@ impl<WireErr> Clone for HostClient<WireErr> {
             subscriptions: self.subscriptions.clone(),
             stopper: self.stopper.clone(),
             seq_kind: self.seq_kind,
+            link: self.link.clone(),
+            timeout: self.timeout,
+        }
+    }
+}
+
+/// Sends a [`CancelTopic`] message for a request when dropped, unless disarmed
+struct CancelOnDrop<'a> {
+    ctx: &'a HostContext,
+    out: &'a mpsc::Sender<RpcFrame>,
+    seq_no: VarSeq,
+    armed: bool,
+}
+
+impl Drop for CancelOnDrop<'_> {
+    fn drop(&mut self) {
+        if self.armed {
+            self.ctx.send_cancel(self.out, self.seq_no);
         }
     }
 }
@@ -913,7 +1460,7 @@ pub struct WireContext {
 }
 
 /// A single postcard-rpc frame
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct RpcFrame {
     /// The wire header
     pub header: VarHeader,
@@ -936,6 +1483,33 @@ pub struct HostContext {
     map: WaitMap<VarHeader, (VarHeader, Vec<u8>)>,
     seq: AtomicU32,
     subscription_timeout: Duration,
+    /// If set, all received frames are passed here instead of being processed
+    forward: OnceLock<mpsc::Sender<Vec<u8>>>,
+    /// Headers of the most recent responses we stopped waiting for
+    expired: std::sync::Mutex<VecDeque<VarHeader>>,
+    late_responses: AtomicU64,
+    /// Response streams that are currently open
+    streams: std::sync::Mutex<Vec<StreamEntry>>,
+    /// The number of subscriptions to each topic
+    subscribers: std::sync::Mutex<Vec<(Key, usize)>>,
+    /// The capabilities of the server, if queried
+    capabilities: std::sync::Mutex<Option<Capabilities>>,
+    /// Send cancellations even if the server didn't advertise them
+    cancel_requests: bool,
+    /// Enable and disable topics even if the server didn't advertise it
+    control_topics: bool,
+    /// The largest frame sent, if long outgoing frames are split into fragments
+    ///
+    /// Reconnecting clients use the one of the current inner client.
+    fragment_size: std::sync::Mutex<Option<usize>>,
+}
+
+/// An open response stream, see [`HostClient::send_stream()`]
+struct StreamEntry {
+    seq_no: VarSeq,
+    resp_key: VarKey,
+    err_key: VarKey,
+    tx: mpsc::UnboundedSender<RpcFrame>,
 }
 
 impl core::fmt::Debug for HostContext {
@@ -970,12 +1544,21 @@ pub enum ProcessError {
 }
 
 impl HostContext {
+    /// The number of expired requests remembered, to detect late responses
+    const EXPIRED_DEPTH: usize = 64;
+
     /// Like `HostContext::process` but tells you if we processed the message or
     /// nobody wanted it
     pub fn process_did_wake(&self, frame: RpcFrame) -> Result<bool, ProcessError> {
         match self.map.wake(&frame.header, (frame.header, frame.body)) {
             WakeOutcome::Woke => Ok(true),
-            WakeOutcome::NoMatch(_) => Ok(false),
+            WakeOutcome::NoMatch((header, body)) => {
+                let Some(frame) = self.process_stream(RpcFrame { header, body }) else {
+                    return Ok(true);
+                };
+                self.check_late(&frame.header);
+                Ok(false)
+            }
             WakeOutcome::Closed(_) => Err(ProcessError::Closed),
         }
     }
@@ -984,12 +1567,95 @@ impl HostContext {
     ///
     /// Returns an Err if the map was closed.
     pub fn process(&self, frame: RpcFrame) -> Result<(), ProcessError> {
-        if let WakeOutcome::Closed(_) = self.map.wake(&frame.header, (frame.header, frame.body)) {
-            Err(ProcessError::Closed)
+        self.process_did_wake(frame).map(drop)
+    }
+
+    /// Tell the server that we are no longer interested in the request with `seq_no`
+    ///
+    /// Only done if the server advertised cancellation, or the client opted in.
+    fn send_cancel(&self, out: &mpsc::Sender<RpcFrame>, seq_no: VarSeq) {
+        if out.is_closed() || !self.uses_feature(Capabilities::CANCEL, self.cancel_requests) {
+            return;
+        }
+        let kkind: VarKeyKind = *self.kkind.read().unwrap();
+        let mut key = VarKey::Key8(CancelTopic::TOPIC_KEY);
+        key.shrink_to(kkind);
+        let seq_no: u32 = seq_no.into();
+        let frame = RpcFrame {
+            // Use a fresh sequence number, so that servers that don't know about
+            // cancellation won't reply with an error for the cancelled request
+            header: VarHeader {
+                key,
+                seq_no: VarSeq::Seq4(self.seq.fetch_add(1, Ordering::Relaxed)),
+            },
+            body: postcard::to_stdvec(&seq_no).expect("Allocations should not ever fail"),
+        };
+        // We can't wait here, if the queue is full the server won't be told
+        if out.try_send(frame).is_err() {
+            tracing::warn!("Outgoing queue full, failed to cancel request with seq_no {seq_no}");
+        }
+    }
+
+    /// Pass frames belonging to an open response stream to its receiver
+    ///
+    /// Returns the frame back if it does not belong to any stream.
+    fn process_stream(&self, frame: RpcFrame) -> Option<RpcFrame> {
+        let mut streams = self.streams.lock().unwrap();
+        let Some(idx) = streams.iter().position(|s| s.seq_no == frame.header.seq_no) else {
+            return Some(frame);
+        };
+        let stream = &streams[idx];
+        if frame.header.key == stream.resp_key {
+            // If the receiver is gone, it will remove the stream itself
+            let _ = stream.tx.send(frame);
+        } else if frame.header.key == stream.err_key || frame.header.key == VarKey::Key8(ERROR_KEY)
+        {
+            let _ = stream.tx.send(frame);
+            streams.remove(idx);
+        } else if frame.header.key == VarKey::Key8(StreamEndTopic::TOPIC_KEY) {
+            // Dropping the sender ends the stream, once all items were received
+            streams.remove(idx);
         } else {
-            Ok(())
+            return Some(frame);
+        }
+        None
+    }
+
+    /// Stop passing frames to the stream with `seq_no`
+    ///
+    /// Returns `true` if the stream was still open.
+    fn remove_stream(&self, seq_no: VarSeq) -> bool {
+        let mut streams = self.streams.lock().unwrap();
+        let before = streams.len();
+        streams.retain(|s| s.seq_no != seq_no);
+        streams.len() != before
+    }
+
+    /// Remember the headers of a request that timed out
+    fn expire(&self, hdrs: &[Option<VarHeader>]) {
+        let mut expired = self.expired.lock().unwrap();
+        for hdr in hdrs.iter().flatten() {
+            if expired.len() >= Self::EXPIRED_DEPTH {
+                expired.pop_front();
+            }
+            expired.push_back(*hdr);
         }
     }
+
+    /// Count and log unmatched frames that are responses to expired requests
+    fn check_late(&self, hdr: &VarHeader) {
+        let mut expired = self.expired.lock().unwrap();
+        let Some(idx) = expired.iter().position(|h| h == hdr) else {
+            return;
+        };
+        expired.remove(idx);
+        drop(expired);
+        self.late_responses.fetch_add(1, Ordering::Relaxed);
+        tracing::warn!(
+            "Dropping late response with seq_no {}, the request timed out",
+            Into::<u32>::into(hdr.seq_no)
+        );
+    }
 }
 
 /// A report describing the schema spoken by the connected device
@@ -1179,4 +1845,61 @@ impl SchemaReport {
         });
         Ok(())
     }
+
+    /// Build a report from the map of a server, as made by
+    /// [`define_dispatch!()`][crate::define_dispatch]
+    pub fn from_device_map(map: &DeviceMap) -> Result<Self, UnableToFindType> {
+        let mut me = Self::default();
+        for ty in map.types.iter() {
+            me.add_type(OwnedNamedType::from(*ty));
+        }
+        for (path, req_key, resp_key) in map.endpoints.iter() {
+            me.add_endpoint(path.to_string(), *req_key, *resp_key)?;
+        }
+        for (path, key) in map.topics_in.iter() {
+            me.add_topic_in(path.to_string(), *key)?;
+        }
+        for (path, key) in map.topics_out.iter() {
+            me.add_topic_out(path.to_string(), *key)?;
+        }
+        Ok(me)
+    }
+
+    /// Build a report from the lists of an ICD, as made by the
+    /// [`endpoints!()`][crate::endpoints] and [`topics!()`][crate::topics] macros
+    pub fn from_icd(
+        endpoints: &EndpointMap,
+        topics_in: &TopicMap,
+        topics_out: &TopicMap,
+    ) -> Result<Self, UnableToFindType> {
+        let mut me = Self::default();
+        let types = endpoints
+            .types
+            .iter()
+            .chain(topics_in.types.iter())
+            .chain(topics_out.types.iter());
+        for ty in types {
+            me.add_type(OwnedNamedType::from(*ty));
+        }
+        for (path, req_key, resp_key) in endpoints.endpoints.iter() {
+            me.add_endpoint(path.to_string(), *req_key, *resp_key)?;
+        }
+        for (path, key) in topics_in.topics.iter() {
+            me.add_topic_in(path.to_string(), *key)?;
+        }
+        for (path, key) in topics_out.topics.iter() {
+            me.add_topic_out(path.to_string(), *key)?;
+        }
+        Ok(me)
+    }
+
+    /// Serialize the report, for example to cache it on disk
+    pub fn to_bytes(&self) -> Vec<u8> {
+        postcard::to_stdvec(self).expect("Allocations should not ever fail")
+    }
+
+    /// Deserialize a report created with [`SchemaReport::to_bytes()`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, postcard::Error> {
+        postcard::from_bytes(bytes)
+    }
 }
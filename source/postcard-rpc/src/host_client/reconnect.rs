@@ -0,0 +1,299 @@
+//! A [HostClient] that automatically reconnects
+//!
+//! The reconnecting client does not talk to the wire directly. Instead, it owns an
+//! "inner" [HostClient] created by a user provided factory, and passes frames
+//! through it unchanged. When the inner client closes (for example because the
+//! device was reset), a new one is created with the factory, while the outer client
+//! and its subscriptions stay alive.
+
+use core::{fmt::Debug, time::Duration};
+use std::future::Future;
+
+use postcard_schema::Schema;
+use serde::de::DeserializeOwned;
+use tokio::{
+    select,
+    sync::{mpsc, watch},
+};
+
+use crate::{
+    header::VarHeader,
+    host_client::{
+        util::LinkState, HostClient, HostClientConfig, RpcFrame, WireRx, WireSpawn, WireTx,
+    },
+    standard_icd::WireError,
+};
+
+/// Backoff settings used when reconnecting
+///
+/// After each failed connection attempt, the delay before the next attempt is
+/// doubled, up to `max`. The delay is reset to `initial` once a connection has
+/// been established.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReconnectBackoff {
+    /// Delay after the first failed attempt
+    pub initial: Duration,
+    /// Largest delay between attempts
+    pub max: Duration,
+}
+
+impl Default for ReconnectBackoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(100),
+            max: Duration::from_secs(5),
+        }
+    }
+}
+
+/// # Reconnecting Constructor Methods
+///
+/// These methods are used to create a new [HostClient] instance that reconnects
+/// when the connection to the device is lost.
+impl<WireErr> HostClient<WireErr>
+where
+    WireErr: DeserializeOwned + Schema,
+{
+    /// Create a new [HostClient] that (re)connects using the given `factory`
+    ///
+    /// `factory` is called to create a new connection, using any of the other
+    /// constructors. If it fails, it is retried according to `backoff`. Whenever
+    /// the connection it created closes, `factory` is called again. The returned
+    /// client is only closed when [`HostClient::close()`] is called.
+    ///
+    /// Subscriptions made with `subscribe`, `subscribe_multi`, or
+    /// `subscribe_exclusive` (and their raw variants) stay registered across
+    /// reconnects, and will continue to receive messages once the connection has
+    /// been re-established.
+    ///
+    /// While there is no connection, requests fail immediately with
+    /// [`HostErr::Reconnecting`][crate::host_client::HostErr::Reconnecting], and
+    /// published messages are dropped. Requests that were in-flight when the
+    /// connection was lost also fail with that error. Use
+    /// [`HostClient::wait_connected()`] to wait for a connection.
+    ///
+    /// The `seq_kind` and `err_uri_path` of `config` are used by the returned
+    /// client, and should match the ones used by the clients made by `factory`.
+    /// If `query_capabilities` is set, the capabilities are queried again after
+    /// each reconnect.
+    ///
+    /// Must be called from within a tokio runtime.
+    ///
+    /// ## Example
+    ///
+    /// ```rust,no_run
+    /// use postcard_rpc::host_client::{HostClient, HostClientConfig, ReconnectBackoff};
+    /// use postcard_rpc::header::VarSeqKind;
+    /// use postcard_rpc::standard_icd::{WireError, ERROR_PATH};
+    /// use std::time::Duration;
+    ///
+    /// # #[cfg(feature = "tcp")]
+    /// # async fn run() {
+    /// let client = HostClient::<WireError>::new_reconnecting(
+    ///     || HostClient::try_new_tcp(
+    ///         "192.168.1.10:5000",
+    ///         postcard_rpc::framing::Framing::LengthPrefixed,
+    ///         ERROR_PATH,
+    ///         8,
+    ///         VarSeqKind::Seq1,
+    ///     ),
+    ///     &HostClientConfig::default()
+    ///         .with_seq_kind(VarSeqKind::Seq1)
+    ///         .with_default_timeout(Some(Duration::from_secs(1))),
+    ///     ReconnectBackoff::default(),
+    /// );
+    /// client.wait_connected().await;
+    /// # }
+    /// ```
+    pub fn new_reconnecting<F, Fut, E>(
+        factory: F,
+        config: &HostClientConfig<'_>,
+        backoff: ReconnectBackoff,
+    ) -> Self
+    where
+        WireErr: 'static,
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<HostClient<WireErr>, E>> + Send + 'static,
+        E: Debug + Send + 'static,
+    {
+        let (current_tx, current_rx) = watch::channel(None);
+        let (fwd_tx, fwd_rx) = mpsc::channel(config.outgoing_depth.max(1));
+        let (link_tx, link_rx) = watch::channel(LinkState {
+            generation: 0,
+            connected: false,
+        });
+
+        // Fragmentation is up to the inner clients, and capabilities can only be
+        // queried once connected
+        let query_capabilities = config.query_capabilities;
+        let config = config
+            .clone()
+            .with_fragment_size(None)
+            .with_query_capabilities(false);
+        let mut me = Self::new_with_wire_and_config(
+            ProxyTx {
+                current: current_rx,
+            },
+            ProxyRx { rx: fwd_rx },
+            ProxySpawn,
+            &config,
+        );
+        me.link = Some(link_rx);
+
+        core::mem::drop(tokio::task::spawn(supervisor(
+            factory,
+            backoff,
+            me.with_standard_errors(),
+            query_capabilities,
+            current_tx,
+            fwd_tx,
+            link_tx,
+        )));
+
+        me
+    }
+}
+
+/// Keeps the inner client connected, until the outer client is stopped
+async fn supervisor<WireErr, F, Fut, E>(
+    mut factory: F,
+    backoff: ReconnectBackoff,
+    outer: HostClient<WireError>,
+    query_capabilities: bool,
+    current: watch::Sender<Option<mpsc::Sender<RpcFrame>>>,
+    fwd: mpsc::Sender<Vec<u8>>,
+    link: watch::Sender<LinkState>,
+) where
+    WireErr: DeserializeOwned + Schema,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<HostClient<WireErr>, E>>,
+    E: Debug + Send,
+{
+    let ctx = &outer.ctx;
+    let stop = &outer.stopper;
+    let mut delay = backoff.initial;
+    loop {
+        let res = select! {
+            _ = stop.wait_stopped() => return,
+            res = factory() => res,
+        };
+        let inner = match res {
+            Ok(inner) => Some(inner),
+            Err(e) => {
+                tracing::warn!("Reconnect failed: {e:?}, retrying in {delay:?}");
+                None
+            }
+        };
+        let Some(inner) = inner else {
+            select! {
+                _ = stop.wait_stopped() => return,
+                _ = tokio::time::sleep(delay) => {},
+            }
+            delay = (delay * 2).min(backoff.max);
+            continue;
+        };
+        delay = backoff.initial;
+
+        // Route everything the inner client receives to the outer client
+        let _ = inner.ctx.forward.set(fwd.clone());
+        *ctx.fragment_size.lock().unwrap() = *inner.ctx.fragment_size.lock().unwrap();
+        current.send_replace(Some(inner.out.clone()));
+        // The server may have restarted, enable the topics we are subscribed to
+        // again. Unless the client opted in, this is done once the capabilities
+        // were queried.
+        ctx.enable_subscribed(&inner.out);
+        link.send_modify(|l| {
+            l.generation += 1;
+            l.connected = true;
+        });
+        tracing::info!("Connected");
+
+        // Runs until the connection is lost, like the other branches
+        let query = async {
+            if query_capabilities {
+                if let Err(e) = outer.query_capabilities().await {
+                    tracing::warn!("Querying the capabilities failed: {e:?}");
+                }
+            }
+            core::future::pending::<()>().await
+        };
+        select! {
+            _ = stop.wait_stopped() => {
+                inner.close();
+                return;
+            }
+            _ = inner.wait_closed() => {},
+            _ = query => {},
+        }
+
+        current.send_replace(None);
+        // The next server may be a different one, with different capabilities
+        ctx.clear_capabilities();
+        link.send_modify(|l| l.connected = false);
+        tracing::warn!("Connection lost, reconnecting");
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+// Wire Interface Implementation
+//////////////////////////////////////////////////////////////////////////////
+
+/// Tokio Spawn Interface Implementor
+struct ProxySpawn;
+
+impl WireSpawn for ProxySpawn {
+    fn spawn(&mut self, fut: impl Future<Output = ()> + Send + 'static) {
+        // Explicitly drop the joinhandle as it impls Future and this makes
+        // clippy mad if you just let it drop implicitly
+        core::mem::drop(tokio::task::spawn(fut));
+    }
+}
+
+/// Passes outgoing frames to the current inner client
+struct ProxyTx {
+    current: watch::Receiver<Option<mpsc::Sender<RpcFrame>>>,
+}
+
+/// Receives frames forwarded by the inner clients
+struct ProxyRx {
+    rx: mpsc::Receiver<Vec<u8>>,
+}
+
+#[derive(thiserror::Error, Debug)]
+enum ProxyError {
+    #[error("Reconnect task stopped")]
+    Stopped,
+}
+
+impl WireTx for ProxyTx {
+    type Error = ProxyError;
+
+    async fn send(&mut self, data: Vec<u8>) -> Result<(), Self::Error> {
+        // Don't hold the borrow across the await
+        let current = self.current.borrow().clone();
+        let Some(current) = current else {
+            tracing::warn!("Not connected, dropping outgoing frame");
+            return Ok(());
+        };
+        let Some((header, body)) = VarHeader::take_from_slice(&data) else {
+            return Ok(());
+        };
+        let frame = RpcFrame {
+            header,
+            body: body.to_vec(),
+        };
+        if current.send(frame).await.is_err() {
+            // The supervisor will notice, and reconnect
+            tracing::warn!("Connection lost, dropping outgoing frame");
+        }
+        Ok(())
+    }
+}
+
+impl WireRx for ProxyRx {
+    type Error = ProxyError;
+
+    async fn receive(&mut self) -> Result<Vec<u8>, Self::Error> {
+        self.rx.recv().await.ok_or(ProxyError::Stopped)
+    }
+}
@@ -0,0 +1,90 @@
+//! Caching [`SchemaReport`]s on disk, keyed by the digest of the schema
+//!
+//! See [`DeviceMap::digest()`][crate::DeviceMap::digest] for the server side.
+
+use std::{fs, io, path::PathBuf};
+
+use postcard_schema::Schema;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    host_client::{HostClient, HostErr, SchemaError, SchemaReport},
+    standard_icd::SchemaDigestEndpoint,
+};
+
+/// A directory of [`SchemaReport`]s, one file per schema digest
+///
+/// Used with [`HostClient::get_schema_report_cached()`], so the schema is only
+/// transferred if the device reports a digest that is not cached yet.
+#[derive(Debug, Clone)]
+pub struct SchemaCache {
+    dir: PathBuf,
+}
+
+impl SchemaCache {
+    /// Use the given directory, which is created when the first report is stored
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The file the report with the given digest is stored in
+    pub fn path(&self, digest: u64) -> PathBuf {
+        self.dir.join(format!("{digest:016x}.schema"))
+    }
+
+    /// Load the report with the given digest
+    ///
+    /// Returns `None` if it was not stored, or could not be read.
+    pub fn load(&self, digest: u64) -> Option<SchemaReport> {
+        let bytes = fs::read(self.path(digest)).ok()?;
+        SchemaReport::from_bytes(&bytes).ok()
+    }
+
+    /// Store the report with the given digest
+    pub fn store(&self, digest: u64, report: &SchemaReport) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        // Write to a temporary file first, so readers never see a partial report
+        let path = self.path(digest);
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, report.to_bytes())?;
+        fs::rename(tmp, path)
+    }
+}
+
+/// # Schema Cache Methods
+impl<WireErr> HostClient<WireErr>
+where
+    WireErr: DeserializeOwned + Schema,
+{
+    /// Obtain the digest of the schema spoken by the connected device
+    ///
+    /// Servers that don't support this reply with an error.
+    pub async fn get_schema_digest(&self) -> Result<u64, HostErr<WireErr>> {
+        self.send_resp::<SchemaDigestEndpoint>(&()).await
+    }
+
+    /// Obtain a [`SchemaReport`] describing the connected device, using the cache
+    /// if it holds the report for the digest of the device
+    ///
+    /// Otherwise, the report is requested with [`HostClient::get_schema_report()`]
+    /// and stored in the cache. Servers that don't report a digest are always
+    /// asked for the full report, which is not cached.
+    pub async fn get_schema_report_cached(
+        &self,
+        cache: &SchemaCache,
+    ) -> Result<SchemaReport, SchemaError<WireErr>> {
+        let digest = match self.get_schema_digest().await {
+            Ok(digest) => digest,
+            Err(HostErr::Wire(_) | HostErr::Protocol(_)) => return self.get_schema_report().await,
+            Err(e) => return Err(SchemaError::Comms(e)),
+        };
+        if let Some(report) = cache.load(digest) {
+            return Ok(report);
+        }
+        let report = self.get_schema_report().await?;
+        if let Err(e) = cache.store(digest, &report) {
+            tracing::warn!("Failed to cache the schema report: {e:?}");
+        }
+        Ok(report)
+    }
+}
@@ -0,0 +1,99 @@
+//! Implementation of transport using the stdio of a child process
+//!
+//! Frames are sent to the child's stdin and received from the child's stdout,
+//! using [`Framing::Cobs`]. The child's stderr is not used for protocol traffic.
+
+use std::process::Stdio;
+
+use postcard_schema::Schema;
+use serde::de::DeserializeOwned;
+use tokio::process::Command;
+
+use crate::{framing::Framing, header::VarSeqKind, host_client::HostClient};
+
+/// # Child Process Constructor Methods
+///
+/// These methods are used to create a new [HostClient] instance talking to a child
+/// process over its stdin and stdout, for example a device simulator built as a host
+/// binary. The child can use
+/// [`new_from_stdio()`](crate::server::impls::tokio_stream::new_from_stdio) to serve
+/// requests.
+///
+/// **Requires feature**: `stdio`
+impl<WireErr> HostClient<WireErr>
+where
+    WireErr: DeserializeOwned + Schema,
+{
+    /// Spawn `command` as a child process, and create a new [HostClient] talking to it
+    ///
+    /// The stdin and stdout of `command` are replaced with pipes used for protocol
+    /// traffic. The stderr of `command` is left as configured, which by default is
+    /// inherited from the current process, so the child's logs show up alongside
+    /// ours without mixing into the protocol.
+    ///
+    /// When the [HostClient] is closed, the child's stdin is closed, and the child is
+    /// expected to exit. When the child exits, the [HostClient] is closed. If the
+    /// tokio runtime shuts down first, the child is killed.
+    ///
+    /// `err_uri_path` is the path associated with the `WireErr` message type.
+    ///
+    /// Must be called from within a tokio runtime.
+    ///
+    /// ## Example
+    ///
+    /// ```rust,no_run
+    /// use postcard_rpc::host_client::HostClient;
+    /// use postcard_rpc::header::VarSeqKind;
+    /// use postcard_rpc::standard_icd::{WireError, ERROR_PATH};
+    /// use tokio::process::Command;
+    ///
+    /// # async fn run() {
+    /// let mut command = Command::new("./target/debug/simulator");
+    /// command.arg("--fast");
+    ///
+    /// let client = HostClient::<WireError>::spawn_stdio(
+    ///     command,
+    ///     // the URI/path for `Error` messages
+    ///     ERROR_PATH,
+    ///     // Outgoing queue depth in messages
+    ///     8,
+    ///     // Use one-byte sequence numbers
+    ///     VarSeqKind::Seq1,
+    /// ).unwrap();
+    /// # }
+    /// ```
+    pub fn spawn_stdio(
+        command: impl Into<Command>,
+        err_uri_path: &str,
+        outgoing_depth: usize,
+        seq_no_kind: VarSeqKind,
+    ) -> std::io::Result<Self> {
+        let mut command = command.into();
+        command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true);
+        let mut child = command.spawn()?;
+
+        // These are always present, as we asked for pipes above
+        let stdin = child.stdin.take().ok_or(std::io::ErrorKind::BrokenPipe)?;
+        let stdout = child.stdout.take().ok_or(std::io::ErrorKind::BrokenPipe)?;
+
+        // Reap the child once it exits
+        core::mem::drop(tokio::task::spawn(async move {
+            match child.wait().await {
+                Ok(status) => tracing::info!("Child process exited: {status}"),
+                Err(e) => tracing::warn!("Failed to wait for child process: {e:?}"),
+            }
+        }));
+
+        Ok(HostClient::new_stream(
+            stdout,
+            stdin,
+            Framing::Cobs,
+            err_uri_path,
+            outgoing_depth,
+            seq_no_kind,
+        ))
+    }
+}
@@ -0,0 +1,215 @@
+//! Implementation of transport using tokio byte streams
+//!
+//! Frames are delimited on the stream using one of the [`Framing`] methods
+//! described in the [`framing`][crate::framing] module.
+
+use std::future::Future;
+
+use postcard_schema::Schema;
+use serde::de::DeserializeOwned;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::{
+    framing::{FrameDecoder, Framing, DEFAULT_MAX_FRAME_LEN},
+    header::VarSeqKind,
+    host_client::{HostClient, WireRx, WireSpawn, WireTx},
+};
+
+/// The size of the buffer used for each `read()` from the stream
+const READ_BUF_SIZE: usize = 4096;
+
+/// # Stream Constructor Methods
+///
+/// These methods are used to create a new [HostClient] instance for use with any
+/// tokio [`AsyncRead`] and [`AsyncWrite`] pair, for example a pty, a socket, or the
+/// stdio of a child process. See the [`framing`][crate::framing] module for a
+/// description of how frames are sent over the stream.
+///
+/// **Requires feature**: `cobs`, which is enabled by the `cobs-serial`, `tcp` and `stdio`
+/// features.
+impl<WireErr> HostClient<WireErr>
+where
+    WireErr: DeserializeOwned + Schema,
+{
+    /// Create a new [HostClient] using COBS framing over a reader and writer
+    ///
+    /// `err_uri_path` is the path associated with the `WireErr` message type.
+    ///
+    /// Must be called from within a tokio runtime.
+    ///
+    /// ## Example
+    ///
+    /// ```rust,no_run
+    /// use postcard_rpc::host_client::HostClient;
+    /// use postcard_rpc::header::VarSeqKind;
+    /// use serde::{Serialize, Deserialize};
+    /// use postcard_schema::Schema;
+    ///
+    /// /// A "wire error" type your server can use to respond to any
+    /// /// kind of request, for example if deserializing a request fails
+    /// #[derive(Debug, PartialEq, Schema, Serialize, Deserialize)]
+    /// pub enum Error {
+    ///    SomethingBad
+    /// }
+    ///
+    /// # async fn run() {
+    /// let stream = tokio::net::TcpStream::connect("192.168.1.10:5000").await.unwrap();
+    /// let (reader, writer) = tokio::io::split(stream);
+    ///
+    /// let client = HostClient::<Error>::new_cobs_stream(
+    ///     reader,
+    ///     writer,
+    ///     // the URI/path for `Error` messages
+    ///     "error",
+    ///     // Outgoing queue depth in messages
+    ///     8,
+    ///     // Use one-byte sequence numbers
+    ///     VarSeqKind::Seq1,
+    /// );
+    /// # }
+    /// ```
+    pub fn new_cobs_stream<R, W>(
+        reader: R,
+        writer: W,
+        err_uri_path: &str,
+        outgoing_depth: usize,
+        seq_no_kind: VarSeqKind,
+    ) -> Self
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        Self::new_stream(
+            reader,
+            writer,
+            Framing::Cobs,
+            err_uri_path,
+            outgoing_depth,
+            seq_no_kind,
+        )
+    }
+
+    /// Create a new [HostClient] using the given [`Framing`] over a reader and writer
+    ///
+    /// See [`HostClient::new_cobs_stream`] for more details
+    pub fn new_stream<R, W>(
+        reader: R,
+        writer: W,
+        framing: Framing,
+        err_uri_path: &str,
+        outgoing_depth: usize,
+        seq_no_kind: VarSeqKind,
+    ) -> Self
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        HostClient::new_with_wire(
+            StreamWireTx {
+                tx: writer,
+                framing,
+            },
+            StreamWireRx {
+                rx: reader,
+                buf: Box::new([0u8; READ_BUF_SIZE]),
+                dec: FrameDecoder::new(framing, DEFAULT_MAX_FRAME_LEN),
+            },
+            StreamSpawn,
+            seq_no_kind,
+            err_uri_path,
+            outgoing_depth,
+        )
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+// Wire Interface Implementation
+//////////////////////////////////////////////////////////////////////////////
+
+/// Tokio Stream Wire Interface Implementor
+///
+/// Uses Tokio for spawning tasks
+struct StreamSpawn;
+
+impl WireSpawn for StreamSpawn {
+    fn spawn(&mut self, fut: impl Future<Output = ()> + Send + 'static) {
+        // Explicitly drop the joinhandle as it impls Future and this makes
+        // clippy mad if you just let it drop implicitly
+        core::mem::drop(tokio::task::spawn(fut));
+    }
+}
+
+/// Tokio Stream Wire Transmit Interface Implementor
+struct StreamWireTx<W> {
+    tx: W,
+    framing: Framing,
+}
+
+#[derive(thiserror::Error, Debug)]
+enum StreamWireTxError {
+    #[error("Transfer Error on Send")]
+    Transfer(#[from] std::io::Error),
+}
+
+impl<W: AsyncWrite + Send + Unpin + 'static> WireTx for StreamWireTx<W> {
+    type Error = StreamWireTxError;
+
+    #[inline]
+    fn send(&mut self, data: Vec<u8>) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        self.send_inner(data)
+    }
+}
+
+impl<W: AsyncWrite + Send + Unpin + 'static> StreamWireTx<W> {
+    async fn send_inner(&mut self, data: Vec<u8>) -> Result<(), StreamWireTxError> {
+        let msg = self.framing.encode(&data);
+        self.tx.write_all(&msg).await?;
+        self.tx.flush().await?;
+        Ok(())
+    }
+}
+
+/// Tokio Stream Wire Receive Interface Implementor
+struct StreamWireRx<R> {
+    rx: R,
+    buf: Box<[u8; READ_BUF_SIZE]>,
+    dec: FrameDecoder,
+}
+
+#[derive(thiserror::Error, Debug)]
+enum StreamWireRxError {
+    #[error("Transfer Error on Recv")]
+    Transfer(#[from] std::io::Error),
+    #[error("Stream closed by peer")]
+    Closed,
+}
+
+impl<R: AsyncRead + Send + Unpin + 'static> WireRx for StreamWireRx<R> {
+    type Error = StreamWireRxError;
+
+    #[inline]
+    fn receive(&mut self) -> impl Future<Output = Result<Vec<u8>, Self::Error>> + Send {
+        self.recv_inner()
+    }
+}
+
+impl<R: AsyncRead + Send + Unpin + 'static> StreamWireRx<R> {
+    async fn recv_inner(&mut self) -> Result<Vec<u8>, StreamWireRxError> {
+        loop {
+            // Do we have any messages already prepared?
+            while let Some(res) = self.dec.next_frame() {
+                match res {
+                    Ok(frame) => return Ok(frame),
+                    // Ignore framing errors, the decoder has already resynchronized
+                    Err(e) => tracing::warn!("Discarding frame: {e}"),
+                }
+            }
+
+            let used = self.rx.read(self.buf.as_mut_slice()).await?;
+            if used == 0 {
+                return Err(StreamWireRxError::Closed);
+            }
+            self.dec.push(&self.buf[..used]);
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Implementation of transport using TCP
+//!
+//! Frames are delimited on the stream using one of the [`Framing`] methods
+//! described in the [`framing`][crate::framing] module.
+
+use postcard_schema::Schema;
+use serde::de::DeserializeOwned;
+use tokio::net::{TcpStream, ToSocketAddrs};
+
+use crate::{framing::Framing, header::VarSeqKind, host_client::HostClient};
+
+/// # TCP Constructor Methods
+///
+/// These methods are used to create a new [HostClient] instance for use with a
+/// tokio [`TcpStream`]. See the [`framing`][crate::framing] module for a description
+/// of how frames are sent over the stream.
+///
+/// **Requires feature**: `tcp`
+impl<WireErr> HostClient<WireErr>
+where
+    WireErr: DeserializeOwned + Schema,
+{
+    /// Try to create a new [HostClient] connected to a TCP server
+    ///
+    /// `addr` is the address of the server, and `framing` is the [`Framing`] used
+    /// by the server. `err_uri_path` is the path associated with the `WireErr`
+    /// message type.
+    ///
+    /// Returns an error if the connection could not be established.
+    ///
+    /// This constructor is available when the `tcp` feature is enabled.
+    ///
+    /// ## Example
+    ///
+    /// ```rust,no_run
+    /// use postcard_rpc::host_client::HostClient;
+    /// use postcard_rpc::header::VarSeqKind;
+    /// use postcard_rpc::framing::Framing;
+    /// use serde::{Serialize, Deserialize};
+    /// use postcard_schema::Schema;
+    ///
+    /// /// A "wire error" type your server can use to respond to any
+    /// /// kind of request, for example if deserializing a request fails
+    /// #[derive(Debug, PartialEq, Schema, Serialize, Deserialize)]
+    /// pub enum Error {
+    ///    SomethingBad
+    /// }
+    ///
+    /// # async fn run() {
+    /// let client = HostClient::<Error>::try_new_tcp(
+    ///     // the address of the server
+    ///     "192.168.1.10:5000",
+    ///     // frames are length-prefixed
+    ///     Framing::LengthPrefixed,
+    ///     // the URI/path for `Error` messages
+    ///     "error",
+    ///     // Outgoing queue depth in messages
+    ///     8,
+    ///     // Use one-byte sequence numbers
+    ///     VarSeqKind::Seq1,
+    /// ).await.unwrap();
+    /// # }
+    /// ```
+    pub async fn try_new_tcp(
+        addr: impl ToSocketAddrs,
+        framing: Framing,
+        err_uri_path: &str,
+        outgoing_depth: usize,
+        seq_no_kind: VarSeqKind,
+    ) -> std::io::Result<Self> {
+        let stream = TcpStream::connect(addr).await?;
+        Ok(Self::new_tcp_stream(
+            stream,
+            framing,
+            err_uri_path,
+            outgoing_depth,
+            seq_no_kind,
+        ))
+    }
+
+    /// Create a new [HostClient] from an already connected [`TcpStream`]
+    ///
+    /// See [`HostClient::try_new_tcp`] for more details
+    pub fn new_tcp_stream(
+        stream: TcpStream,
+        framing: Framing,
+        err_uri_path: &str,
+        outgoing_depth: usize,
+        seq_no_kind: VarSeqKind,
+    ) -> Self {
+        // We send small frames, don't wait around to coalesce them
+        if let Err(e) = stream.set_nodelay(true) {
+            tracing::warn!("Failed to set TCP_NODELAY: {e:?}");
+        }
+        let (rx, tx) = stream.into_split();
+
+        HostClient::new_stream(rx, tx, framing, err_uri_path, outgoing_depth, seq_no_kind)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use crate::{
+        framing::{FrameDecoder, Framing},
+        header::{VarHeader, VarKey, VarSeqKind},
+        host_client::HostClient,
+        standard_icd::{LoggingTopic, PingEndpoint, WireError, ERROR_PATH},
+        Endpoint, Topic,
+    };
+
+    /// A minimal server: answers pings, and logs the value of every ping
+    async fn fake_server(mut stream: TcpStream, framing: Framing) {
+        let mut dec = FrameDecoder::new(framing, 1024);
+        let mut buf = [0u8; 64];
+        loop {
+            let Some(frame) = dec.next_frame() else {
+                let used = stream.read(&mut buf).await.unwrap();
+                if used == 0 {
+                    return;
+                }
+                dec.push(&buf[..used]);
+                continue;
+            };
+            let frame = frame.unwrap();
+            let (hdr, body) = VarHeader::take_from_slice(&frame).unwrap();
+            assert_eq!(hdr.key, VarKey::Key8(PingEndpoint::REQ_KEY));
+            let val: u32 = postcard::from_bytes(body).unwrap();
+
+            let mut out = VarHeader {
+                key: VarKey::Key8(LoggingTopic::TOPIC_KEY),
+                seq_no: hdr.seq_no,
+            }
+            .write_to_vec();
+            out.extend(postcard::to_stdvec(&format!("ping {val}")).unwrap());
+            stream.write_all(&framing.encode(&out)).await.unwrap();
+
+            let mut out = VarHeader {
+                key: VarKey::Key8(PingEndpoint::RESP_KEY),
+                seq_no: hdr.seq_no,
+            }
+            .write_to_vec();
+            out.extend(postcard::to_stdvec(&val).unwrap());
+            stream.write_all(&framing.encode(&out)).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn tcp_end_to_end() {
+        for framing in [Framing::LengthPrefixed, Framing::Cobs] {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let server = tokio::task::spawn(async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                fake_server(stream, framing).await;
+            });
+
+            let client = HostClient::<WireError>::try_new_tcp(
+                addr,
+                framing,
+                ERROR_PATH,
+                8,
+                VarSeqKind::Seq2,
+            )
+            .await
+            .unwrap();
+            let mut logs = client.subscribe_multi::<LoggingTopic>(8).await.unwrap();
+
+            for i in 0..32u32 {
+                let val = i * 0x0101_0101;
+                let resp = client.send_resp::<PingEndpoint>(&val).await.unwrap();
+                assert_eq!(resp, val);
+                assert_eq!(logs.recv().await.unwrap(), format!("ping {val}"));
+            }
+
+            // Closing the client closes the socket, which stops the server
+            client.close();
+            server.await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn tcp_server_disconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::task::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            drop(stream);
+        });
+
+        let client = HostClient::<WireError>::try_new_tcp(
+            addr,
+            Framing::LengthPrefixed,
+            ERROR_PATH,
+            8,
+            VarSeqKind::Seq1,
+        )
+        .await
+        .unwrap();
+        server.await.unwrap();
+
+        client.wait_closed().await;
+        assert!(client.is_closed());
+    }
+}
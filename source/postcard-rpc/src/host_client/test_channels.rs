@@ -2,7 +2,7 @@
 
 use crate::{
     header::VarSeqKind,
-    host_client::{HostClient, WireRx, WireSpawn, WireTx},
+    host_client::{HostClient, HostClientConfig, WireRx, WireSpawn, WireTx},
     standard_icd::WireError,
 };
 use core::fmt::Display;
@@ -24,6 +24,15 @@ pub fn new_from_channels(
     )
 }
 
+/// Create a new HostClient from the given server channels, using `config`
+pub fn new_from_channels_with_config(
+    tx: mpsc::Sender<Vec<u8>>,
+    rx: mpsc::Receiver<Vec<u8>>,
+    config: &HostClientConfig<'_>,
+) -> HostClient<WireError> {
+    HostClient::new_with_wire_and_config(ChannelTx { tx }, ChannelRx { rx }, TokSpawn, config)
+}
+
 /// Server error kinds
 #[derive(Debug)]
 pub enum ChannelError {
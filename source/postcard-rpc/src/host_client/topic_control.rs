@@ -0,0 +1,166 @@
+//! Enabling and disabling topics sent by the server
+//!
+//! See [`crate::server::topic_filter`] for the server side.
+
+use std::sync::{atomic::Ordering, Arc};
+
+use postcard_schema::Schema;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::mpsc;
+
+use crate::{
+    header::{VarHeader, VarKey, VarKeyKind, VarSeq},
+    host_client::{HostClient, HostContext, HostErr, RpcFrame},
+    standard_icd::{
+        standard_topics_out, Capabilities, TopicControlResult, TopicDisableEndpoint, TopicEnable,
+        TopicEnableEndpoint,
+    },
+    Endpoint, Key, Topic,
+};
+
+/// # Topic Control Methods
+impl<WireErr> HostClient<WireErr>
+where
+    WireErr: DeserializeOwned + Schema,
+{
+    /// Ask the server to send the given topic, at most `max_rate` messages per second
+    ///
+    /// Topics are enabled automatically when they are subscribed to, if the server
+    /// advertised [`Capabilities::TOPIC_FILTER`] or
+    /// [`HostClientConfig::control_topics`][crate::host_client::HostClientConfig::control_topics]
+    /// is set. Otherwise, or to limit their rate, this is needed.
+    pub async fn enable_topic<T: Topic>(
+        &self,
+        max_rate: Option<u32>,
+    ) -> Result<TopicControlResult, HostErr<WireErr>> {
+        self.send_resp::<TopicEnableEndpoint>(&TopicEnable {
+            key: T::TOPIC_KEY,
+            max_rate,
+        })
+        .await
+    }
+
+    /// Ask the server to stop sending the given topic
+    ///
+    /// Topics are disabled automatically when the last subscription to them is
+    /// dropped, under the same conditions as they are enabled.
+    pub async fn disable_topic<T: Topic>(&self) -> Result<TopicControlResult, HostErr<WireErr>> {
+        self.send_resp::<TopicDisableEndpoint>(&T::TOPIC_KEY).await
+    }
+
+    /// Count a subscriber of the topic with the given key, enabling the topic
+    /// for the first one
+    pub(crate) fn topic_guard(&self, key: Key) -> TopicGuard {
+        // Topics of the standard ICD are always sent
+        let standard = standard_topics_out().any(|(_, k)| *k == key);
+        if !standard && self.ctx.add_subscriber(key) {
+            self.ctx.send_topic_control(&self.out, key, true);
+        }
+        TopicGuard {
+            ctx: self.ctx.clone(),
+            out: self.out.clone(),
+            key: (!standard).then_some(key),
+        }
+    }
+}
+
+/// Counts a subscriber of a topic, disabling the topic when the last one is dropped
+pub(crate) struct TopicGuard {
+    ctx: Arc<HostContext>,
+    out: mpsc::Sender<RpcFrame>,
+    key: Option<Key>,
+}
+
+impl Drop for TopicGuard {
+    fn drop(&mut self) {
+        let Some(key) = self.key else {
+            return;
+        };
+        if self.ctx.remove_subscriber(key) {
+            self.ctx.send_topic_control(&self.out, key, false);
+        }
+    }
+}
+
+impl HostContext {
+    /// Returns `true` for the first subscriber
+    fn add_subscriber(&self, key: Key) -> bool {
+        let mut subs = self.subscribers.lock().unwrap();
+        match subs.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, count)) => {
+                *count += 1;
+                *count == 1
+            }
+            None => {
+                subs.push((key, 1));
+                true
+            }
+        }
+    }
+
+    /// Returns `true` for the last subscriber
+    fn remove_subscriber(&self, key: Key) -> bool {
+        let mut subs = self.subscribers.lock().unwrap();
+        let Some(pos) = subs.iter().position(|(k, _)| *k == key) else {
+            return false;
+        };
+        subs[pos].1 -= 1;
+        if subs[pos].1 != 0 {
+            return false;
+        }
+        subs.swap_remove(pos);
+        true
+    }
+
+    /// Enable all topics that have subscribers, for example after reconnecting
+    pub(crate) fn enable_subscribed(&self, out: &mpsc::Sender<RpcFrame>) {
+        let keys: Vec<Key> = self
+            .subscribers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(k, _)| *k)
+            .collect();
+        for key in keys {
+            self.send_topic_control(out, key, true);
+        }
+    }
+
+    /// Send a request to enable or disable a topic, without waiting for the response
+    ///
+    /// Only done if the server advertised topic filtering, or the client opted in.
+    fn send_topic_control(&self, out: &mpsc::Sender<RpcFrame>, key: Key, enable: bool) {
+        if out.is_closed() || !self.uses_feature(Capabilities::TOPIC_FILTER, self.control_topics) {
+            return;
+        }
+        let frame = if enable {
+            self.request_frame::<TopicEnableEndpoint>(&TopicEnable {
+                key,
+                max_rate: None,
+            })
+        } else {
+            self.request_frame::<TopicDisableEndpoint>(&key)
+        };
+        // Servers that don't support this reply with an error, which is ignored
+        // like the response
+        if out.try_send(frame).is_err() {
+            tracing::warn!("Outgoing queue full, failed to enable or disable a topic");
+        }
+    }
+
+    fn request_frame<E: Endpoint>(&self, req: &E::Request) -> RpcFrame
+    where
+        E::Request: Serialize,
+    {
+        let kkind: VarKeyKind = *self.kkind.read().unwrap();
+        let mut key = VarKey::Key8(E::REQ_KEY);
+        key.shrink_to(kkind);
+        RpcFrame {
+            header: VarHeader {
+                key,
+                seq_no: VarSeq::Seq4(self.seq.fetch_add(1, Ordering::Relaxed)),
+            },
+            body: postcard::to_stdvec(req).expect("Allocations should not ever fail"),
+        }
+    }
+}
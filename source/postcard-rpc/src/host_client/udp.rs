@@ -0,0 +1,266 @@
+//! Implementation of transport using UDP
+//!
+//! Each frame (a [`VarHeader`] followed by the serialized body) is sent as exactly
+//! one UDP datagram, with no further framing.
+
+use std::future::Future;
+
+use postcard_schema::Schema;
+use serde::de::DeserializeOwned;
+use tokio::{
+    net::{ToSocketAddrs, UdpSocket},
+    select,
+    sync::mpsc,
+};
+
+use crate::{
+    header::{VarHeader, VarKey, VarSeqKind},
+    host_client::{HostClient, WireRx, WireSpawn, WireTx},
+    standard_icd::{FrameTooLong, WireError, ERROR_KEY},
+};
+
+/// The default maximum datagram size, in bytes
+///
+/// This is the payload size of a UDP datagram that fits in a single Ethernet
+/// frame (1500 byte MTU) without fragmentation, when using IPv4.
+const DEFAULT_MAX_DATAGRAM_LEN: usize = 1472;
+
+/// The largest possible UDP payload
+const MAX_UDP_PAYLOAD: usize = 65535;
+
+/// # UDP Constructor Methods
+///
+/// These methods are used to create a new [HostClient] instance for use with a
+/// tokio [`UdpSocket`]. Each frame is sent as a single datagram.
+///
+/// Requests larger than the maximum datagram size are not sent. Instead, the
+/// request fails with [`WireError::FrameTooLong`], delivered as if the server had
+/// rejected it. This error is only visible if `WireErr` is [`WireError`], and
+/// `err_uri_path` is [`ERROR_PATH`][crate::standard_icd::ERROR_PATH].
+///
+/// **Requires feature**: `udp`
+impl<WireErr> HostClient<WireErr>
+where
+    WireErr: DeserializeOwned + Schema,
+{
+    /// Try to create a new [HostClient] talking to a UDP server
+    ///
+    /// `addr` is the address of the server. A local socket is bound to an
+    /// ephemeral port, and frames up to 1472 bytes (the largest UDP payload
+    /// that fits in a 1500 byte Ethernet MTU) may be sent. `err_uri_path` is
+    /// the path associated with the `WireErr` message type.
+    ///
+    /// This constructor is available when the `udp` feature is enabled.
+    ///
+    /// ## Example
+    ///
+    /// ```rust,no_run
+    /// use postcard_rpc::host_client::HostClient;
+    /// use postcard_rpc::header::VarSeqKind;
+    /// use postcard_rpc::standard_icd::{WireError, ERROR_PATH};
+    ///
+    /// # async fn run() {
+    /// let client = HostClient::<WireError>::try_new_udp(
+    ///     // the address of the server
+    ///     "192.168.1.10:5000",
+    ///     // the URI/path for `Error` messages
+    ///     ERROR_PATH,
+    ///     // Outgoing queue depth in messages
+    ///     8,
+    ///     // Use one-byte sequence numbers
+    ///     VarSeqKind::Seq1,
+    /// ).await.unwrap();
+    /// # }
+    /// ```
+    pub async fn try_new_udp(
+        addr: impl ToSocketAddrs,
+        err_uri_path: &str,
+        outgoing_depth: usize,
+        seq_no_kind: VarSeqKind,
+    ) -> std::io::Result<Self> {
+        let mut last_err = None;
+        for remote in tokio::net::lookup_host(addr).await? {
+            let local = if remote.is_ipv4() {
+                "0.0.0.0:0"
+            } else {
+                "[::]:0"
+            };
+            let res = async {
+                let socket = UdpSocket::bind(local).await?;
+                socket.connect(remote).await?;
+                Ok::<_, std::io::Error>(socket)
+            }
+            .await;
+            match res {
+                Ok(socket) => {
+                    return Ok(Self::new_udp_socket(
+                        socket,
+                        DEFAULT_MAX_DATAGRAM_LEN,
+                        err_uri_path,
+                        outgoing_depth,
+                        seq_no_kind,
+                    ))
+                }
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| std::io::ErrorKind::AddrNotAvailable.into()))
+    }
+
+    /// Create a new [HostClient] from an already connected [`UdpSocket`]
+    ///
+    /// `max_datagram_len` is the largest frame that will be sent. See
+    /// [`HostClient::try_new_udp`] for more details.
+    pub fn new_udp_socket(
+        socket: UdpSocket,
+        max_datagram_len: usize,
+        err_uri_path: &str,
+        outgoing_depth: usize,
+        seq_no_kind: VarSeqKind,
+    ) -> Self {
+        let socket = std::sync::Arc::new(socket);
+        let (loop_tx, loop_rx) = mpsc::unbounded_channel();
+
+        HostClient::new_with_wire(
+            UdpWireTx {
+                socket: socket.clone(),
+                max_len: max_datagram_len,
+                looped: loop_tx,
+            },
+            UdpWireRx {
+                socket,
+                buf: vec![0u8; MAX_UDP_PAYLOAD].into_boxed_slice(),
+                looped: loop_rx,
+            },
+            UdpSpawn,
+            seq_no_kind,
+            err_uri_path,
+            outgoing_depth,
+        )
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+// Wire Interface Implementation
+//////////////////////////////////////////////////////////////////////////////
+
+/// Tokio UDP Wire Interface Implementor
+///
+/// Uses Tokio for spawning tasks
+struct UdpSpawn;
+
+impl WireSpawn for UdpSpawn {
+    fn spawn(&mut self, fut: impl Future<Output = ()> + Send + 'static) {
+        // Explicitly drop the joinhandle as it impls Future and this makes
+        // clippy mad if you just let it drop implicitly
+        core::mem::drop(tokio::task::spawn(fut));
+    }
+}
+
+/// Tokio UDP Wire Transmit Interface Implementor
+struct UdpWireTx {
+    socket: std::sync::Arc<UdpSocket>,
+    max_len: usize,
+    /// Frames that should be "received" without going over the wire
+    looped: mpsc::UnboundedSender<Vec<u8>>,
+}
+
+#[derive(thiserror::Error, Debug)]
+enum UdpWireTxError {
+    #[error("Transfer Error on Send")]
+    Transfer(#[from] std::io::Error),
+}
+
+impl WireTx for UdpWireTx {
+    type Error = UdpWireTxError;
+
+    #[inline]
+    fn send(&mut self, data: Vec<u8>) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        self.send_inner(data)
+    }
+}
+
+impl UdpWireTx {
+    async fn send_inner(&mut self, data: Vec<u8>) -> Result<(), UdpWireTxError> {
+        if data.len() > self.max_len {
+            // Don't send a truncated frame, instead answer the request with an error
+            tracing::warn!(
+                "Frame of {} bytes exceeds max datagram size of {}, rejecting",
+                data.len(),
+                self.max_len
+            );
+            if let Some((hdr, _)) = VarHeader::take_from_slice(&data) {
+                let mut key = VarKey::Key8(ERROR_KEY);
+                key.shrink_to(hdr.key.kind());
+                let mut frame = VarHeader {
+                    key,
+                    seq_no: hdr.seq_no,
+                }
+                .write_to_vec();
+                let err = WireError::FrameTooLong(FrameTooLong {
+                    len: data.len() as u32,
+                    max: self.max_len as u32,
+                });
+                frame.extend(postcard::to_stdvec(&err).expect("alloc should never fail"));
+                let _ = self.looped.send(frame);
+            }
+            return Ok(());
+        }
+        match self.socket.send(&data).await {
+            Ok(_) => Ok(()),
+            // Nobody is listening (yet), which is not fatal for a connectionless socket
+            Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+                tracing::warn!("UDP send refused, is the server running?");
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Tokio UDP Wire Receive Interface Implementor
+struct UdpWireRx {
+    socket: std::sync::Arc<UdpSocket>,
+    buf: Box<[u8]>,
+    looped: mpsc::UnboundedReceiver<Vec<u8>>,
+}
+
+#[derive(thiserror::Error, Debug)]
+enum UdpWireRxError {
+    #[error("Transfer Error on Recv")]
+    Transfer(#[from] std::io::Error),
+}
+
+impl WireRx for UdpWireRx {
+    type Error = UdpWireRxError;
+
+    #[inline]
+    fn receive(&mut self) -> impl Future<Output = Result<Vec<u8>, Self::Error>> + Send {
+        self.recv_inner()
+    }
+}
+
+impl UdpWireRx {
+    async fn recv_inner(&mut self) -> Result<Vec<u8>, UdpWireRxError> {
+        let UdpWireRx {
+            socket,
+            buf,
+            looped,
+        } = self;
+        loop {
+            select! {
+                used = socket.recv(buf) => match used {
+                    Ok(used) => return Ok(buf[..used].to_vec()),
+                    // Reported for an earlier send, nobody is listening (yet)
+                    Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+                        tracing::warn!("UDP send refused, is the server running?");
+                    }
+                    Err(e) => return Err(e.into()),
+                },
+                // The sender half lives as long as the Tx worker, once it is gone
+                // we are shutting down anyway.
+                Some(frame) = looped.recv() => return Ok(frame),
+            }
+        }
+    }
+}
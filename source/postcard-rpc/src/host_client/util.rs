@@ -1,21 +1,23 @@
 use core::time::Duration;
 // the contents of this file can probably be moved up to `mod.rs`
-use std::{fmt::Debug, sync::Arc};
+use std::{fmt::Debug, future::Future, sync::Arc};
 
 use maitake_sync::WaitQueue;
 use postcard_schema::Schema;
 use serde::de::DeserializeOwned;
 use tokio::{
     select,
-    sync::{broadcast, mpsc, Mutex},
+    sync::{broadcast, mpsc, watch, Mutex},
 };
 use tracing::{debug, trace, warn};
 
 use crate::{
+    fragment::{split_to_vecs, Reassembler},
     header::{VarHeader, VarKey, VarSeqKind},
     host_client::{
         HostClient, HostContext, ProcessError, RpcFrame, WireContext, WireRx, WireSpawn, WireTx,
     },
+    standard_icd::ERROR_PATH,
     Key,
 };
 
@@ -78,7 +80,62 @@ impl Default for Stopper {
     }
 }
 
+/// The state of the connection of a reconnecting [HostClient]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct LinkState {
+    /// Incremented each time a connection is established
+    pub(crate) generation: u64,
+    /// Is there currently a connection?
+    pub(crate) connected: bool,
+}
+
+/// Shared access to the current [`LinkState`], if reconnecting
+pub(crate) type Link = Option<watch::Receiver<LinkState>>;
+
+/// Is the link up? Always true for clients that don't reconnect
+pub(crate) fn link_connected(link: &Link) -> bool {
+    link.as_ref().is_none_or(|l| l.borrow().connected)
+}
+
+/// Waits until the link is up
+pub(crate) async fn wait_link_up(link: &Link) {
+    if let Some(l) = link {
+        let mut l = l.clone();
+        if l.wait_for(|s| s.connected).await.is_err() {
+            // The supervisor is gone, the stopper will handle this
+            core::future::pending::<()>().await;
+        }
+    }
+}
+
+/// Completes when the connection that was current when this was called is lost
+///
+/// Never completes for clients that don't reconnect.
+pub(crate) fn link_lost(link: &Link) -> impl Future<Output = ()> + Send + 'static {
+    let link = link.clone();
+    async move {
+        let Some(mut l) = link else {
+            return core::future::pending().await;
+        };
+        let generation = l.borrow().generation;
+        let lost = l
+            .wait_for(|s| s.generation != generation || !s.connected)
+            .await
+            .is_ok();
+        if !lost {
+            // The supervisor is gone, the stopper will handle this
+            core::future::pending::<()>().await;
+        }
+    }
+}
+
 /// HostClient configuration
+///
+/// Start from [`HostClientConfig::default()`], and change the fields that matter,
+/// either directly or with the `with_*` methods. New fields may be added in minor
+/// releases, so this can't be built with a struct literal.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct HostClientConfig<'c> {
     /// The sequence kind to use
     pub seq_kind: VarSeqKind,
@@ -93,6 +150,133 @@ pub struct HostClientConfig<'c> {
     ///
     /// Does not apply to subscribe_multi channels.
     pub subscriber_timeout_if_full: Duration,
+
+    /// Timeout used by `send_resp` and `send_resp_raw`, or `None` to wait forever.
+    ///
+    /// Individual requests can use a different timeout with `send_resp_timeout`.
+    pub default_timeout: Option<Duration>,
+
+    /// Largest frame to send, or `None` to never fragment outgoing messages.
+    ///
+    /// Larger messages are split into fragments, see [`crate::fragment`]. The server
+    /// must have been configured to reassemble them. Requests needing too many
+    /// fragments fail with `HostErr::RequestTooLarge`, published messages are dropped.
+    ///
+    /// Ignored by reconnecting clients, set it on the clients made by the factory instead.
+    pub fragment_size: Option<usize>,
+
+    /// The largest fragmented message from the server that is reassembled
+    ///
+    /// Longer messages are dropped. The buffer is only allocated once the first
+    /// fragment arrives.
+    ///
+    /// Ignored by reconnecting clients, set it on the clients made by the factory instead.
+    pub max_reassembly_len: usize,
+
+    /// Query the [`Capabilities`][crate::standard_icd::Capabilities] of the server
+    /// when connecting, see `HostClient::query_capabilities`.
+    ///
+    /// The query runs in the background, requests sent before it completes use full
+    /// keys. Reconnecting clients query again after each reconnect, set it on the
+    /// reconnecting client instead of the clients made by the factory.
+    pub query_capabilities: bool,
+
+    /// Tell the server about requests that are no longer waited for, even if it
+    /// didn't advertise [`Capabilities::CANCEL`][crate::standard_icd::Capabilities::CANCEL].
+    ///
+    /// Otherwise, cancellations are only sent once the capabilities were queried,
+    /// and the server supports them.
+    pub cancel_requests: bool,
+
+    /// Enable and disable topics as they are subscribed to, even if the server
+    /// didn't advertise [`Capabilities::TOPIC_FILTER`][crate::standard_icd::Capabilities::TOPIC_FILTER].
+    ///
+    /// Otherwise, topics are only enabled once the capabilities were queried, and
+    /// the server supports it.
+    pub control_topics: bool,
+}
+
+impl Default for HostClientConfig<'_> {
+    /// Four byte sequence numbers, errors on [`ERROR_PATH`], an outgoing queue of
+    /// eight messages, no timeouts or fragmentation, reassembly of up to 64 KiB, and
+    /// capabilities are not queried, so requests are not cancelled and topics are
+    /// not enabled
+    fn default() -> Self {
+        Self {
+            seq_kind: VarSeqKind::Seq4,
+            err_uri_path: ERROR_PATH,
+            outgoing_depth: 8,
+            subscriber_timeout_if_full: Duration::ZERO,
+            default_timeout: None,
+            fragment_size: None,
+            max_reassembly_len: 64 * 1024,
+            query_capabilities: false,
+            cancel_requests: false,
+            control_topics: false,
+        }
+    }
+}
+
+impl<'c> HostClientConfig<'c> {
+    /// Set [`HostClientConfig::seq_kind`]
+    pub fn with_seq_kind(mut self, seq_kind: VarSeqKind) -> Self {
+        self.seq_kind = seq_kind;
+        self
+    }
+
+    /// Set [`HostClientConfig::err_uri_path`]
+    pub fn with_err_uri_path(mut self, err_uri_path: &'c str) -> Self {
+        self.err_uri_path = err_uri_path;
+        self
+    }
+
+    /// Set [`HostClientConfig::outgoing_depth`]
+    pub fn with_outgoing_depth(mut self, outgoing_depth: usize) -> Self {
+        self.outgoing_depth = outgoing_depth;
+        self
+    }
+
+    /// Set [`HostClientConfig::subscriber_timeout_if_full`]
+    pub fn with_subscriber_timeout_if_full(mut self, timeout: Duration) -> Self {
+        self.subscriber_timeout_if_full = timeout;
+        self
+    }
+
+    /// Set [`HostClientConfig::default_timeout`]
+    pub fn with_default_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.default_timeout = timeout;
+        self
+    }
+
+    /// Set [`HostClientConfig::fragment_size`]
+    pub fn with_fragment_size(mut self, fragment_size: Option<usize>) -> Self {
+        self.fragment_size = fragment_size;
+        self
+    }
+
+    /// Set [`HostClientConfig::max_reassembly_len`]
+    pub fn with_max_reassembly_len(mut self, max_reassembly_len: usize) -> Self {
+        self.max_reassembly_len = max_reassembly_len;
+        self
+    }
+
+    /// Set [`HostClientConfig::query_capabilities`]
+    pub fn with_query_capabilities(mut self, query: bool) -> Self {
+        self.query_capabilities = query;
+        self
+    }
+
+    /// Set [`HostClientConfig::cancel_requests`]
+    pub fn with_cancel_requests(mut self, cancel: bool) -> Self {
+        self.cancel_requests = cancel;
+        self
+    }
+
+    /// Set [`HostClientConfig::control_topics`]
+    pub fn with_control_topics(mut self, control: bool) -> Self {
+        self.control_topics = control;
+        self
+    }
 }
 
 impl<WireErr> HostClient<WireErr>
@@ -116,12 +300,10 @@ where
         WRX: WireRx,
         WSP: WireSpawn,
     {
-        let config = HostClientConfig {
-            seq_kind,
-            err_uri_path,
-            outgoing_depth,
-            subscriber_timeout_if_full: Duration::ZERO,
-        };
+        let config = HostClientConfig::default()
+            .with_seq_kind(seq_kind)
+            .with_err_uri_path(err_uri_path)
+            .with_outgoing_depth(outgoing_depth);
 
         Self::new_with_wire_and_config(tx, rx, sp, &config)
     }
@@ -145,26 +327,44 @@ where
 
         let WireContext { outgoing, incoming } = wire_ctx;
 
-        sp.spawn(out_worker(tx, outgoing, me.stopper.clone()));
+        sp.spawn(out_worker(
+            tx,
+            outgoing,
+            config.fragment_size,
+            me.stopper.clone(),
+        ));
         sp.spawn(in_worker(
             rx,
             incoming,
             me.subscriptions.clone(),
+            config.max_reassembly_len,
             me.stopper.clone(),
         ));
+        if config.query_capabilities {
+            let client = me.with_standard_errors();
+            sp.spawn(async move {
+                if let Err(e) = client.query_capabilities().await {
+                    tracing::warn!("Querying the capabilities failed: {e:?}");
+                }
+            });
+        }
 
         me
     }
 }
 
 /// Output worker, feeding frames to the `Client`.
-async fn out_worker<W>(wire: W, rec: mpsc::Receiver<RpcFrame>, stop: Stopper)
-where
+async fn out_worker<W>(
+    wire: W,
+    rec: mpsc::Receiver<RpcFrame>,
+    fragment_size: Option<usize>,
+    stop: Stopper,
+) where
     W: WireTx,
     W::Error: Debug,
 {
     let cancel_fut = stop.wait_stopped();
-    let operate_fut = out_worker_inner(wire, rec);
+    let operate_fut = out_worker_inner(wire, rec, fragment_size);
     select! {
         biased;
         _ = cancel_fut => {},
@@ -175,8 +375,11 @@ where
     }
 }
 
-async fn out_worker_inner<W>(mut wire: W, mut rec: mpsc::Receiver<RpcFrame>)
-where
+async fn out_worker_inner<W>(
+    mut wire: W,
+    mut rec: mpsc::Receiver<RpcFrame>,
+    fragment_size: Option<usize>,
+) where
     W: WireTx,
     W::Error: Debug,
 {
@@ -185,9 +388,22 @@ where
             tracing::info!("Receiver Closed");
             return;
         };
-        if let Err(e) = wire.send(msg.to_bytes()).await {
-            tracing::error!("Output Queue Error: {e:?}, exiting");
-            return;
+        let frames = match fragment_size {
+            None => vec![msg.to_bytes()],
+            Some(size) => match split_to_vecs(&msg.header, &msg.body, size) {
+                Some(frames) => frames,
+                None => {
+                    // Requests were checked before being queued, so this is a publish
+                    tracing::error!("Message too large to fragment, dropping it");
+                    continue;
+                }
+            },
+        };
+        for frame in frames {
+            if let Err(e) = wire.send(frame).await {
+                tracing::error!("Output Queue Error: {e:?}, exiting");
+                return;
+            }
         }
     }
 }
@@ -197,13 +413,14 @@ async fn in_worker<W>(
     wire: W,
     host_ctx: Arc<HostContext>,
     subscriptions: Arc<Mutex<Subscriptions>>,
+    max_reassembly_len: usize,
     stop: Stopper,
 ) where
     W: WireRx,
     W::Error: Debug,
 {
     let cancel_fut = stop.wait_stopped();
-    let operate_fut = in_worker_inner(wire, host_ctx, subscriptions.clone());
+    let operate_fut = in_worker_inner(wire, host_ctx, subscriptions.clone(), max_reassembly_len);
     select! {
         biased;
         _ = cancel_fut => {},
@@ -224,16 +441,49 @@ async fn in_worker_inner<W>(
     mut wire: W,
     host_ctx: Arc<HostContext>,
     subscriptions: Arc<Mutex<Subscriptions>>,
+    max_reassembly_len: usize,
 ) where
     W: WireRx,
     W::Error: Debug,
 {
+    let mut reassembly = None;
     loop {
-        let Ok(res) = wire.receive().await else {
+        let Ok(mut res) = wire.receive().await else {
             warn!("in_worker: wire receive error, exiting");
             return;
         };
 
+        // Reassemble fragmented messages, the rest of the client never sees fragments
+        let Some((hdr, frag, body)) = VarHeader::take_fragment_from_slice(&res) else {
+            warn!("Header decode error!");
+            continue;
+        };
+        if let Some(frag) = frag {
+            let reasm =
+                reassembly.get_or_insert_with(|| Reassembler::new(vec![0u8; max_reassembly_len]));
+            match reasm.push(hdr, frag, body) {
+                Ok(Some((hdr, body))) => {
+                    let mut whole = hdr.write_to_vec();
+                    whole.extend_from_slice(body);
+                    res = whole;
+                }
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!("Dropping fragmented message: {e:?}");
+                    continue;
+                }
+            }
+        }
+
+        // We are the inner client of a reconnecting client, which handles
+        // everything we receive
+        if let Some(fwd) = host_ctx.forward.get() {
+            if fwd.send(res).await.is_err() {
+                return;
+            }
+            continue;
+        }
+
         let Some((hdr, body)) = VarHeader::take_from_slice(&res) else {
             warn!("Header decode error!");
             continue;
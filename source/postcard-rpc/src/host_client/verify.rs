@@ -0,0 +1,195 @@
+//! Checking that a device speaks the ICD the host was compiled against
+
+use std::fmt;
+
+use postcard_schema::Schema;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    host_client::{HostClient, SchemaError, SchemaReport},
+    standard_icd::{standard_endpoints, standard_topics_in, standard_topics_out},
+    EndpointMap, Key, TopicMap,
+};
+
+/// The differences between the schema of a device and the ICD of the host
+///
+/// Items of the standard ICD are not compared, as they depend on the version of
+/// `postcard-rpc` rather than the ICD.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IcdDiff {
+    /// The differences in endpoints
+    pub endpoints: ItemDiff,
+    /// The differences in topics sent to the server
+    pub topics_in: ItemDiff,
+    /// The differences in topics sent to the client
+    pub topics_out: ItemDiff,
+}
+
+/// The differences in one kind of item, by path
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ItemDiff {
+    /// Paths the host knows, but the device does not
+    pub missing: Vec<String>,
+    /// Paths the device has, but the host does not know
+    pub extra: Vec<String>,
+    /// Paths both know, but with different types
+    pub mismatched: Vec<KeyMismatch>,
+}
+
+/// A path with a different key on the host and device
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyMismatch {
+    /// The path of the endpoint or topic
+    pub path: String,
+    /// The key used by the host
+    pub expected: Key,
+    /// The key used by the device
+    pub found: Key,
+}
+
+impl IcdDiff {
+    /// Compare a [`SchemaReport`] with the ICD of the host
+    pub fn new(
+        report: &SchemaReport,
+        endpoints: &EndpointMap,
+        topics_in: &TopicMap,
+        topics_out: &TopicMap,
+    ) -> Self {
+        // Endpoints are compared by their request and response keys
+        let host_eps = endpoints
+            .endpoints
+            .iter()
+            .map(|(path, req, resp)| (*path, vec![*req, *resp]));
+        let device_eps = report
+            .endpoints
+            .iter()
+            .map(|ep| (ep.path.as_str(), vec![ep.req_key, ep.resp_key]));
+        let std_eps = standard_endpoints().map(|e| e.0);
+        Self {
+            endpoints: ItemDiff::new(host_eps, device_eps, std_eps),
+            topics_in: ItemDiff::new(
+                topics_in.topics.iter().map(|(p, k)| (*p, vec![*k])),
+                report
+                    .topics_in
+                    .iter()
+                    .map(|t| (t.path.as_str(), vec![t.key])),
+                standard_topics_in().map(|t| t.0),
+            ),
+            topics_out: ItemDiff::new(
+                topics_out.topics.iter().map(|(p, k)| (*p, vec![*k])),
+                report
+                    .topics_out
+                    .iter()
+                    .map(|t| (t.path.as_str(), vec![t.key])),
+                standard_topics_out().map(|t| t.0),
+            ),
+        }
+    }
+
+    /// Do the host and device agree on everything?
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.is_empty() && self.topics_in.is_empty() && self.topics_out.is_empty()
+    }
+
+    /// Does the device support everything the host knows?
+    ///
+    /// Unlike [`IcdDiff::is_empty()`], this allows the device to have extra items.
+    pub fn is_compatible(&self) -> bool {
+        self.endpoints.is_compatible()
+            && self.topics_in.is_compatible()
+            && self.topics_out.is_compatible()
+    }
+}
+
+impl ItemDiff {
+    fn new<'a>(
+        host: impl Iterator<Item = (&'a str, Vec<Key>)>,
+        device: impl Iterator<Item = (&'a str, Vec<Key>)>,
+        standard: impl Iterator<Item = &'a str>,
+    ) -> Self {
+        let standard: Vec<&str> = standard.collect();
+        let host: Vec<_> = host.filter(|(p, _)| !standard.contains(p)).collect();
+        let device: Vec<_> = device.filter(|(p, _)| !standard.contains(p)).collect();
+
+        let mut diff = ItemDiff::default();
+        for (path, keys) in host.iter() {
+            let Some((_, found)) = device.iter().find(|(p, _)| p == path) else {
+                diff.missing.push(path.to_string());
+                continue;
+            };
+            for (expected, found) in keys.iter().zip(found.iter()) {
+                if expected != found {
+                    diff.mismatched.push(KeyMismatch {
+                        path: path.to_string(),
+                        expected: *expected,
+                        found: *found,
+                    });
+                }
+            }
+        }
+        for (path, _) in device.iter() {
+            if !host.iter().any(|(p, _)| p == path) {
+                diff.extra.push(path.to_string());
+            }
+        }
+        diff
+    }
+
+    /// Are there no differences?
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.mismatched.is_empty()
+    }
+
+    /// Is nothing missing or mismatched?
+    pub fn is_compatible(&self) -> bool {
+        self.missing.is_empty() && self.mismatched.is_empty()
+    }
+
+    fn fmt_kind(&self, f: &mut fmt::Formatter<'_>, kind: &str) -> fmt::Result {
+        for path in self.missing.iter() {
+            writeln!(f, "missing {kind} '{path}'")?;
+        }
+        for path in self.extra.iter() {
+            writeln!(f, "extra {kind} '{path}'")?;
+        }
+        for m in self.mismatched.iter() {
+            writeln!(
+                f,
+                "mismatched {kind} '{}': expected key {:?}, found {:?}",
+                m.path, m.expected, m.found
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Lists each difference on its own line
+impl fmt::Display for IcdDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.endpoints.fmt_kind(f, "endpoint")?;
+        self.topics_in.fmt_kind(f, "topic in")?;
+        self.topics_out.fmt_kind(f, "topic out")
+    }
+}
+
+/// # Verification Methods
+impl<WireErr> HostClient<WireErr>
+where
+    WireErr: DeserializeOwned + Schema,
+{
+    /// Compare the schema of the connected device with the ICD of the host
+    ///
+    /// Typically called with the lists generated by the [`endpoints!()`][crate::endpoints]
+    /// and [`topics!()`][crate::topics] macros of the ICD crate, right after connecting.
+    /// Use [`IcdDiff::is_compatible()`] to check whether all items used by the host
+    /// are supported by the device.
+    pub async fn verify(
+        &self,
+        endpoints: &EndpointMap,
+        topics_in: &TopicMap,
+        topics_out: &TopicMap,
+    ) -> Result<IcdDiff, SchemaError<WireErr>> {
+        let report = self.get_schema_report().await?;
+        Ok(IcdDiff::new(&report, endpoints, topics_in, topics_out))
+    }
+}
@@ -0,0 +1,329 @@
+//! Implementation of transport using WebSockets
+//!
+//! Each frame (a [`VarHeader`][crate::header::VarHeader] followed by the serialized
+//! body) is sent as exactly one binary WebSocket message, with no further framing.
+//! Text messages are ignored.
+//!
+//! On native targets this uses [`tokio-tungstenite`], on wasm targets this uses the
+//! browser's WebSocket API.
+//!
+//! [`tokio-tungstenite`]: https://docs.rs/tokio-tungstenite
+
+use std::future::Future;
+
+use futures_util::{SinkExt, StreamExt};
+use postcard_schema::Schema;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    header::VarSeqKind,
+    host_client::{HostClient, WireRx, WireSpawn, WireTx},
+};
+
+#[cfg(not(target_family = "wasm"))]
+use tokio::io::{AsyncRead, AsyncWrite};
+#[cfg(not(target_family = "wasm"))]
+use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
+
+#[cfg(target_family = "wasm")]
+use gloo_net::websocket::{futures::WebSocket, Message};
+
+/// WebSocket Error type
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// Error originating from the WebSocket library
+    #[cfg(not(target_family = "wasm"))]
+    #[error("WebSocket error: {0}")]
+    WebSocket(#[from] tokio_tungstenite::tungstenite::Error),
+    /// Error originating from the browser
+    #[cfg(target_family = "wasm")]
+    #[error("Browser error: {0}")]
+    Browser(String),
+}
+
+/// # WebSocket Constructor Methods
+///
+/// These methods are used to create a new [HostClient] instance for use with a
+/// WebSocket server. Each frame is sent as a single binary message.
+///
+/// **Requires feature**: `websocket`
+impl<WireErr> HostClient<WireErr>
+where
+    WireErr: DeserializeOwned + Schema,
+{
+    /// Try to create a new [HostClient] connected to a WebSocket server
+    ///
+    /// `url` is the `ws://` or `wss://` URL of the server. `err_uri_path` is the
+    /// path associated with the `WireErr` message type.
+    ///
+    /// Returns an error if the connection could not be established.
+    ///
+    /// This constructor is available on native and wasm targets when the
+    /// `websocket` feature is enabled. On native targets, it must be called from
+    /// within a tokio runtime.
+    ///
+    /// ## Example
+    ///
+    /// ```rust,no_run
+    /// use postcard_rpc::host_client::HostClient;
+    /// use postcard_rpc::header::VarSeqKind;
+    /// use postcard_rpc::standard_icd::{WireError, ERROR_PATH};
+    ///
+    /// # async fn run() {
+    /// let client = HostClient::<WireError>::try_new_websocket(
+    ///     // the URL of the server
+    ///     "ws://192.168.1.10:5000",
+    ///     // the URI/path for `Error` messages
+    ///     ERROR_PATH,
+    ///     // Outgoing queue depth in messages
+    ///     8,
+    ///     // Use one-byte sequence numbers
+    ///     VarSeqKind::Seq1,
+    /// ).await.unwrap();
+    /// # }
+    /// ```
+    #[cfg(not(target_family = "wasm"))]
+    pub async fn try_new_websocket(
+        url: &str,
+        err_uri_path: &str,
+        outgoing_depth: usize,
+        seq_no_kind: VarSeqKind,
+    ) -> Result<Self, Error> {
+        let (ws, _resp) = tokio_tungstenite::connect_async(url).await?;
+        Ok(Self::new_websocket_stream(
+            ws,
+            err_uri_path,
+            outgoing_depth,
+            seq_no_kind,
+        ))
+    }
+
+    /// Create a new [HostClient] from an already connected [`WebSocketStream`]
+    ///
+    /// See [`HostClient::try_new_websocket`] for more details
+    #[cfg(not(target_family = "wasm"))]
+    pub fn new_websocket_stream<S>(
+        ws: WebSocketStream<S>,
+        err_uri_path: &str,
+        outgoing_depth: usize,
+        seq_no_kind: VarSeqKind,
+    ) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    {
+        let (sink, stream) = ws.split();
+
+        HostClient::new_with_wire(
+            WsWireTx { sink },
+            WsWireRx { stream },
+            WsSpawn,
+            seq_no_kind,
+            err_uri_path,
+            outgoing_depth,
+        )
+    }
+
+    /// Try to create a new [HostClient] connected to a WebSocket server
+    ///
+    /// `url` is the `ws://` or `wss://` URL of the server. `err_uri_path` is the
+    /// path associated with the `WireErr` message type.
+    ///
+    /// Returns an error if the browser refuses to open the connection. Requests
+    /// made before the connection is established are held until it opens.
+    ///
+    /// This constructor is available on native and wasm targets when the
+    /// `websocket` feature is enabled.
+    #[cfg(target_family = "wasm")]
+    pub async fn try_new_websocket(
+        url: &str,
+        err_uri_path: &str,
+        outgoing_depth: usize,
+        seq_no_kind: VarSeqKind,
+    ) -> Result<Self, Error> {
+        let ws = WebSocket::open(url).map_err(|e| Error::Browser(e.to_string()))?;
+        let (sink, stream) = ws.split();
+
+        Ok(HostClient::new_with_wire(
+            WsWireTx { sink },
+            WsWireRx { stream },
+            WsSpawn,
+            seq_no_kind,
+            err_uri_path,
+            outgoing_depth,
+        ))
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+// Wire Interface Implementation
+//////////////////////////////////////////////////////////////////////////////
+
+/// WebSocket Wire Interface Implementor
+///
+/// Uses Tokio for spawning tasks on native targets, and the browser's event loop
+/// on wasm targets
+struct WsSpawn;
+
+#[cfg(not(target_family = "wasm"))]
+impl WireSpawn for WsSpawn {
+    fn spawn(&mut self, fut: impl Future<Output = ()> + Send + 'static) {
+        // Explicitly drop the joinhandle as it impls Future and this makes
+        // clippy mad if you just let it drop implicitly
+        core::mem::drop(tokio::task::spawn(fut));
+    }
+}
+
+#[cfg(target_family = "wasm")]
+impl WireSpawn for WsSpawn {
+    fn spawn(&mut self, fut: impl Future<Output = ()> + 'static) {
+        wasm_bindgen_futures::spawn_local(fut);
+    }
+}
+
+/// WebSocket Wire Transmit Interface Implementor
+struct WsWireTx<S> {
+    sink: S,
+}
+
+#[derive(thiserror::Error, Debug)]
+enum WsWireTxError {
+    #[error("Transfer Error on Send: {0}")]
+    Transfer(String),
+}
+
+impl<S> WsWireTx<S>
+where
+    S: futures_util::Sink<Message> + Unpin,
+    S::Error: core::fmt::Display,
+{
+    async fn send_inner(&mut self, data: Vec<u8>) -> Result<(), WsWireTxError> {
+        self.sink
+            .send(binary_message(data))
+            .await
+            .map_err(|e| WsWireTxError::Transfer(e.to_string()))
+    }
+}
+
+/// WebSocket Wire Receive Interface Implementor
+struct WsWireRx<S> {
+    stream: S,
+}
+
+#[derive(thiserror::Error, Debug)]
+enum WsWireRxError {
+    #[error("Connection closed")]
+    Closed,
+    #[error("Transfer Error on Recv: {0}")]
+    Transfer(String),
+}
+
+impl<S, E> WsWireRx<S>
+where
+    S: futures_util::Stream<Item = Result<Message, E>> + Unpin,
+    E: core::fmt::Display,
+{
+    async fn recv_inner(&mut self) -> Result<Vec<u8>, WsWireRxError> {
+        loop {
+            let msg = self
+                .stream
+                .next()
+                .await
+                .ok_or(WsWireRxError::Closed)?
+                .map_err(|e| WsWireRxError::Transfer(e.to_string()))?;
+            if let Some(frame) = binary_payload(msg)? {
+                return Ok(frame);
+            }
+        }
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+mod native {
+    use super::*;
+
+    pub(super) fn binary_message(data: Vec<u8>) -> Message {
+        Message::Binary(data.into())
+    }
+
+    pub(super) fn binary_payload(msg: Message) -> Result<Option<Vec<u8>>, WsWireRxError> {
+        match msg {
+            Message::Binary(b) => Ok(Some(b.into())),
+            Message::Close(_) => Err(WsWireRxError::Closed),
+            Message::Text(_) => {
+                tracing::warn!("Ignoring WebSocket text message");
+                Ok(None)
+            }
+            // Pings are answered by tungstenite
+            Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => Ok(None),
+        }
+    }
+
+    impl<S> WireTx for WsWireTx<S>
+    where
+        S: futures_util::Sink<Message> + Send + Unpin + 'static,
+        S::Error: core::fmt::Display,
+    {
+        type Error = WsWireTxError;
+
+        #[inline]
+        fn send(&mut self, data: Vec<u8>) -> impl Future<Output = Result<(), Self::Error>> + Send {
+            self.send_inner(data)
+        }
+    }
+
+    impl<S, E> WireRx for WsWireRx<S>
+    where
+        S: futures_util::Stream<Item = Result<Message, E>> + Send + Unpin + 'static,
+        E: core::fmt::Display,
+    {
+        type Error = WsWireRxError;
+
+        #[inline]
+        fn receive(&mut self) -> impl Future<Output = Result<Vec<u8>, Self::Error>> + Send {
+            self.recv_inner()
+        }
+    }
+}
+
+#[cfg(target_family = "wasm")]
+mod wasm {
+    use super::*;
+    use futures_util::stream::{SplitSink, SplitStream};
+
+    pub(super) fn binary_message(data: Vec<u8>) -> Message {
+        Message::Bytes(data)
+    }
+
+    pub(super) fn binary_payload(msg: Message) -> Result<Option<Vec<u8>>, WsWireRxError> {
+        match msg {
+            Message::Bytes(b) => Ok(Some(b)),
+            Message::Text(_) => {
+                tracing::warn!("Ignoring WebSocket text message");
+                Ok(None)
+            }
+        }
+    }
+
+    impl WireTx for WsWireTx<SplitSink<WebSocket, Message>> {
+        type Error = WsWireTxError;
+
+        #[inline]
+        fn send(&mut self, data: Vec<u8>) -> impl Future<Output = Result<(), Self::Error>> {
+            self.send_inner(data)
+        }
+    }
+
+    impl WireRx for WsWireRx<SplitStream<WebSocket>> {
+        type Error = WsWireRxError;
+
+        #[inline]
+        fn receive(&mut self) -> impl Future<Output = Result<Vec<u8>, Self::Error>> {
+            self.recv_inner()
+        }
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+use native::{binary_message, binary_payload};
+#[cfg(target_family = "wasm")]
+use wasm::{binary_message, binary_payload};
@@ -127,6 +127,8 @@ use header::{VarKey, VarKeyKind};
 use postcard_schema::{schema::NamedType, Schema};
 use serde::{Deserialize, Serialize};
 
+mod digest;
+pub mod fragment;
 pub mod header;
 mod macros;
 pub mod server;
@@ -136,9 +138,18 @@ pub mod uniques;
 #[cfg(feature = "cobs")]
 pub mod accumulator;
 
+#[cfg(all(feature = "cobs", feature = "use-std"))]
+pub mod framing;
+
 #[cfg(feature = "use-std")]
 pub mod host_client;
 
+#[cfg(feature = "codegen")]
+pub mod codegen;
+
+#[cfg(feature = "capture")]
+pub mod capture;
+
 #[cfg(any(test, feature = "test-utils"))]
 pub mod test_utils;
 
@@ -430,6 +441,16 @@ pub trait Endpoint {
     const RESP_KEY1: Key1 = Key1::from_key8(Self::RESP_KEY);
 }
 
+/// A marker trait denoting an endpoint that replies with a stream of responses
+///
+/// Each [Endpoint::Response] of the stream is sent with the sequence number of the
+/// request, and the stream is ended by a
+/// [`StreamEndTopic`][crate::standard_icd::StreamEndTopic] message, or an error.
+///
+/// Typically implemented with the [endpoints] macro, by marking the response type
+/// of the endpoint with `stream`.
+pub trait StreamEndpoint: Endpoint {}
+
 /// A marker trait denoting a single topic
 ///
 /// Unlike [Endpoint]s, [Topic]s are unidirectional, and can be sent
@@ -482,6 +503,18 @@ pub struct DeviceMap {
     pub min_key_len: VarKeyKind,
 }
 
+impl DeviceMap {
+    /// A 64-bit digest of the types, endpoints, and topics in this map
+    ///
+    /// Devices with the same digest report the same schema, so hosts may cache
+    /// the schema by digest. Servers report it with the
+    /// [`SchemaDigestEndpoint`][crate::standard_icd::SchemaDigestEndpoint], if their
+    /// dispatcher adds `schema_digest` to its `standard` parts.
+    pub const fn digest(&self) -> u64 {
+        digest::device_map(self)
+    }
+}
+
 /// An overview of a list of endpoints
 ///
 /// Typically generated by the [`endpoints!()`] macro. Contains a list of
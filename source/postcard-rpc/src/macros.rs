@@ -91,8 +91,34 @@ macro_rules! endpoint {
 ///     | Endpoint2      | Req2          | Resp2         | "endpoints/two"   |
 /// }
 /// ```
+///
+/// Endpoints that reply with a stream of responses, see [StreamEndpoint][crate::StreamEndpoint],
+/// are marked with `stream` after the response type:
+///
+/// ```rust
+/// # use postcard_schema::Schema;
+/// # use serde::{Serialize, Deserialize};
+/// use postcard_rpc::endpoints;
+///
+/// #[derive(Debug, Serialize, Deserialize, Schema)]
+/// pub struct Sample {
+///     millivolts: u32,
+/// }
+///
+/// endpoints!{
+///     list = ENDPOINTS_LIST;
+///     | EndpointTy     | RequestTy     | ResponseTy        | Path              |
+///     | ----------     | ---------     | ----------        | ----              |
+///     | SampleEndpoint | u32           | Sample stream     | "adc/samples"     |
+/// }
+/// ```
 #[macro_export]
 macro_rules! endpoints {
+    (@stream_impl [$(#[$meta:meta])?] [] $($rest:tt)*) => {};
+    (@stream_impl [$(#[$meta:meta])?] [stream] $ep_name:ident [$($lt:lifetime)*]) => {
+        $(#[$meta])?
+        impl < $($lt,)* > $crate::StreamEndpoint for $ep_name < $($lt,)* > {}
+    };
     (@ep_tys $([[$($meta:meta)?] $ep_name:ident])*) => {
         $crate::endpoints!(@ep_tys omit_std=false; $([[$($meta)?] $ep_name])*)
     };
@@ -164,7 +190,7 @@ macro_rules! endpoints {
            $(omit_std = $omit:tt;)?
            | EndpointTy     | RequestTy                                | ResponseTy                                  | Path              | $( Cfg           |)?
            | $(-)*          | $(-)*                                    | $(-)*                                       | $(-)*             | $($(-)*          |)?
-        $( | $ep_name:ident | $req_ty:tt $(< $($req_lt:lifetime),+ >)? | $resp_ty:tt $(< $($resp_lt:lifetime),+ >)?  | $path_str:literal | $($meta:meta)? $(|)? )*
+        $( | $ep_name:ident | $req_ty:tt $(< $($req_lt:lifetime),+ >)? | $resp_ty:tt $(< $($resp_lt:lifetime),+ >)? $($kind:ident)? | $path_str:literal | $($meta:meta)? $(|)? )*
     ) => {
         // struct definitions and trait impls
         $(
@@ -188,6 +214,10 @@ macro_rules! endpoints {
                 const REQ_KEY: $crate::Key = $crate::Key::for_path::<$req_ty>($path_str);
                 const RESP_KEY: $crate::Key = $crate::Key::for_path::<$resp_ty>($path_str);
             }
+
+            $crate::endpoints!(
+                @stream_impl [$(#[$meta])?] [$($kind)?] $ep_name [$($($req_lt)+)? $($($resp_lt)+)?]
+            );
         )*
 
         /// Macro Generated Endpoint Map
@@ -451,6 +481,7 @@ mod endpoints_test {
         | AlphaEndpoint1 | AReq          | AResp         | "test/alpha1"     |
         | AlphaEndpoint2 | AReq          | AResp         | "test/alpha2"     |
         | AlphaEndpoint3 | AReq          | AResp         | "test/alpha3"     |
+        | AlphaEndpoint4 | AReq          | AResp stream  | "test/alpha4"     |
     }
 
     topics! {
@@ -480,7 +511,10 @@ mod endpoints_test {
         for ep in ENDPOINT_LIST.endpoints {
             println!("{}", ep.0);
         }
-        assert_eq!(ENDPOINT_LIST.endpoints.len(), 5);
+        assert_eq!(ENDPOINT_LIST.endpoints.len(), 6);
+
+        fn is_stream<E: crate::StreamEndpoint>() {}
+        is_stream::<AlphaEndpoint4>();
     }
 
     #[test]
@@ -0,0 +1,216 @@
+//! Receiving bulk uploads
+//!
+//! Bulk uploads allow clients to send blobs that are larger than the receive
+//! buffer of the server. The client starts an upload with
+//! [`BulkStartEndpoint`][crate::standard_icd::BulkStartEndpoint], sends the data
+//! in chunks with [`BulkChunkEndpoint`][crate::standard_icd::BulkChunkEndpoint],
+//! and finishes with [`BulkEndEndpoint`][crate::standard_icd::BulkEndEndpoint],
+//! which carries a CRC of the whole upload.
+//!
+//! Servers handle these endpoints by forwarding the requests to a [`BulkReceiver`],
+//! which stores the data with a [`BulkHandler`]. The handlers should be `blocking`
+//! or `async`, so chunks are processed in the order they were sent. The bulk
+//! endpoints are only part of the device map of servers that handle them.
+
+use crate::standard_icd::{
+    crc32, BulkEnd, BulkError, BulkParams, BulkResult, BulkStart, BulkStartResult,
+};
+
+/// The most bytes a [`BulkChunkEndpoint`][crate::standard_icd::BulkChunkEndpoint]
+/// frame needs in addition to the data of the chunk
+///
+/// This is the largest header (13 bytes), plus the offset and length of the
+/// data (up to 5 bytes each).
+pub const BULK_CHUNK_OVERHEAD: usize = 23;
+
+/// Storage for the data of bulk uploads
+pub trait BulkHandler {
+    /// The largest upload that can be stored, in bytes
+    fn capacity(&self) -> usize;
+
+    /// Prepare for a new upload of `len` bytes
+    ///
+    /// This is called when an upload starts, any previous upload that was not
+    /// finished is abandoned.
+    fn start(&mut self, len: usize) -> Result<(), BulkError> {
+        let _ = len;
+        Ok(())
+    }
+
+    /// Store `data` at `offset` from the start of the upload
+    fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), BulkError>;
+
+    /// Called once all `len` bytes were received, and the CRC matched
+    fn finish(&mut self, len: usize) -> Result<(), BulkError>;
+}
+
+/// Stores uploads in a slice, without doing anything once they are finished
+///
+/// The length of the last finished upload can be retrieved with
+/// [`BulkReceiver::last_len()`].
+impl BulkHandler for &mut [u8] {
+    fn capacity(&self) -> usize {
+        self.len()
+    }
+
+    fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), BulkError> {
+        let dest = self
+            .get_mut(offset..offset + data.len())
+            .ok_or(BulkError::TooLarge)?;
+        dest.copy_from_slice(data);
+        Ok(())
+    }
+
+    fn finish(&mut self, _len: usize) -> Result<(), BulkError> {
+        Ok(())
+    }
+}
+
+/// The state of an upload in progress
+struct Upload {
+    len: u32,
+    received: u32,
+    crc: u32,
+}
+
+/// Tracks the progress of bulk uploads, passing the data to a [`BulkHandler`]
+pub struct BulkReceiver<H: BulkHandler> {
+    handler: H,
+    params: BulkParams,
+    upload: Option<Upload>,
+    last_len: Option<usize>,
+}
+
+impl<H: BulkHandler> BulkReceiver<H> {
+    /// Create a new receiver
+    ///
+    /// `rx_buf_len` is the size of the receive buffer of the server, which is
+    /// used to pick the size of the chunks. `window` is the number of chunks the
+    /// client may send before waiting for acknowledgements, usually the number of
+    /// frames the transport can buffer.
+    pub fn new(handler: H, rx_buf_len: usize, window: u32) -> Self {
+        let chunk_size = rx_buf_len.saturating_sub(BULK_CHUNK_OVERHEAD).max(1);
+        Self {
+            handler,
+            params: BulkParams {
+                chunk_size: u32::try_from(chunk_size).unwrap_or(u32::MAX),
+                window: window.max(1),
+            },
+            upload: None,
+            last_len: None,
+        }
+    }
+
+    /// The [`BulkHandler`] used to store uploads
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+
+    /// The [`BulkHandler`] used to store uploads
+    pub fn handler_mut(&mut self) -> &mut H {
+        &mut self.handler
+    }
+
+    /// The length of the last upload that was finished successfully
+    pub fn last_len(&self) -> Option<usize> {
+        self.last_len
+    }
+
+    /// Handle a [`BulkStartEndpoint`][crate::standard_icd::BulkStartEndpoint] request
+    pub fn start(&mut self, req: &BulkStart) -> BulkStartResult {
+        self.upload = None;
+        let len = req.len as usize;
+        if len > self.handler.capacity() {
+            return Err(BulkError::TooLarge);
+        }
+        self.handler.start(len)?;
+        self.upload = Some(Upload {
+            len: req.len,
+            received: 0,
+            crc: 0,
+        });
+        Ok(self.params)
+    }
+
+    /// Handle a [`BulkChunkEndpoint`][crate::standard_icd::BulkChunkEndpoint] request
+    pub fn chunk(&mut self, offset: u32, data: &[u8]) -> BulkResult {
+        let upload = self.upload.as_mut().ok_or(BulkError::NotStarted)?;
+        let fits = data.len() <= self.params.chunk_size as usize
+            && (data.len() as u64) + (offset as u64) <= upload.len as u64;
+        if offset != upload.received || !fits {
+            return Err(BulkError::BadChunk);
+        }
+        if let Err(e) = self.handler.write(offset as usize, data) {
+            self.upload = None;
+            return Err(e);
+        }
+        upload.received += data.len() as u32;
+        upload.crc = crc32(upload.crc, data);
+        Ok(())
+    }
+
+    /// Handle a [`BulkEndEndpoint`][crate::standard_icd::BulkEndEndpoint] request
+    ///
+    /// The upload is over after this, whether it was successful or not.
+    pub fn end(&mut self, req: &BulkEnd) -> BulkResult {
+        let upload = self.upload.take().ok_or(BulkError::NotStarted)?;
+        if upload.received != upload.len {
+            return Err(BulkError::Incomplete);
+        }
+        if upload.crc != req.crc {
+            return Err(BulkError::CrcMismatch);
+        }
+        self.handler.finish(upload.len as usize)?;
+        self.last_len = Some(upload.len as usize);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BulkReceiver;
+    use crate::standard_icd::{crc32, BulkEnd, BulkError, BulkStart};
+
+    #[test]
+    fn crc() {
+        assert_eq!(crc32(0, b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(crc32(0, b"1234"), b"56789"), 0xCBF4_3926);
+        assert_eq!(crc32(0, b""), 0);
+    }
+
+    #[test]
+    fn receive() {
+        let mut storage = [0u8; 16];
+        let mut rx = BulkReceiver::new(storage.as_mut_slice(), 23 + 4, 2);
+
+        assert_eq!(rx.chunk(0, b"abcd"), Err(BulkError::NotStarted));
+        assert_eq!(rx.start(&BulkStart { len: 17 }), Err(BulkError::TooLarge));
+
+        let params = rx.start(&BulkStart { len: 10 }).unwrap();
+        assert_eq!(params.chunk_size, 4);
+        assert_eq!(params.window, 2);
+        assert_eq!(rx.chunk(0, b"abcd"), Ok(()));
+        // Chunks must be in order, and not too long
+        assert_eq!(rx.chunk(8, b"ij"), Err(BulkError::BadChunk));
+        assert_eq!(rx.chunk(4, b"efghi"), Err(BulkError::BadChunk));
+        assert_eq!(rx.chunk(4, b"efgh"), Ok(()));
+        assert_eq!(rx.end(&BulkEnd { crc: 0 }), Err(BulkError::Incomplete));
+
+        rx.start(&BulkStart { len: 10 }).unwrap();
+        for (i, chunk) in b"abcdefghij".chunks(4).enumerate() {
+            assert_eq!(rx.chunk(i as u32 * 4, chunk), Ok(()));
+        }
+        assert_eq!(rx.end(&BulkEnd { crc: 1 }), Err(BulkError::CrcMismatch));
+        assert_eq!(rx.last_len(), None);
+
+        rx.start(&BulkStart { len: 10 }).unwrap();
+        for (i, chunk) in b"abcdefghij".chunks(4).enumerate() {
+            assert_eq!(rx.chunk(i as u32 * 4, chunk), Ok(()));
+        }
+        let crc = crc32(0, b"abcdefghij");
+        assert_eq!(rx.end(&BulkEnd { crc }), Ok(()));
+        assert_eq!(rx.last_len(), Some(10));
+        assert_eq!(&rx.handler()[..10], b"abcdefghij");
+        assert_eq!(rx.end(&BulkEnd { crc }), Err(BulkError::NotStarted));
+    }
+}
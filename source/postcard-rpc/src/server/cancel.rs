@@ -0,0 +1,138 @@
+//! Cancellation of in-flight requests
+//!
+//! Clients may send a [`CancelTopic`][crate::standard_icd::CancelTopic] message
+//! containing the sequence number of a request they are no longer waiting for.
+//! Handlers of the `spawn_cancel` kind receive a [`CancelToken`], which reports
+//! whether this has happened for the request they are handling. Handlers of the
+//! `stream` kind can check [`StreamSender::is_cancelled()`][super::StreamSender::is_cancelled].
+//!
+//! Handlers of the other kinds can't be cancelled. In particular, `spawn` handlers
+//! get no token, use `spawn_cancel` for long running requests instead. The
+//! [`CancelTopic`][crate::standard_icd::CancelTopic] is only part of the device map
+//! of servers that have handlers of the `spawn_cancel` or `stream` kinds.
+//!
+//! Servers opt in to cancellation with
+//! [`Server::with_cancellations()`][super::Server::with_cancellations], and then
+//! advertise it in their [`Capabilities`][crate::standard_icd::Capabilities::CANCEL].
+//! Without it, requests are never cancelled.
+
+use super::slots::{Slot, Slots};
+
+/// The number of requests that can be cancelled at the same time
+const SLOTS: usize = 16;
+
+/// Tracks the cancellable requests that are currently being handled
+///
+/// Clients cancel a request by sending a [`CancelTopic`][crate::standard_icd::CancelTopic]
+/// message with its sequence number. Handlers of the `spawn_cancel` kind see this
+/// through their [`CancelToken`], handlers of the `stream` kind through
+/// [`StreamSender::is_cancelled()`][super::StreamSender::is_cancelled].
+///
+/// Each [`Server`][super::Server] needs its own instance, so that clients can only
+/// cancel their own requests.
+///
+/// Up to 16 requests are tracked at a time. Requests started while all slots are
+/// in use receive a token that is never cancelled.
+pub struct Cancellations {
+    /// Slots are keyed by sequence number, and marked when cancelled
+    slots: Slots<SLOTS, 1>,
+}
+
+impl Cancellations {
+    /// Create a new, empty, set of cancellations
+    pub const fn new() -> Self {
+        Self {
+            slots: Slots::new(),
+        }
+    }
+
+    /// Obtain a token for a request with the given sequence number
+    pub fn token(&'static self, seq_no: u32) -> CancelToken {
+        CancelToken {
+            slot: self.slots.claim([seq_no], |_| ()).map(|slot| (self, slot)),
+        }
+    }
+
+    /// Cancel all in-flight requests with the given sequence number
+    ///
+    /// Returns `true` if any request was cancelled.
+    pub fn cancel(&self, seq_no: u32) -> bool {
+        let mut any = false;
+        for slot in self.slots.matching([seq_no]) {
+            any |= self.slots.mark(slot);
+        }
+        any
+    }
+}
+
+impl Default for Cancellations {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A token passed to `spawn_cancel` handlers
+///
+/// The client may cancel a request it is no longer interested in, for example
+/// because it timed out. Handlers should check [`CancelToken::is_cancelled()`]
+/// periodically, and return early if it was. A reply sent after the request
+/// was cancelled is ignored by the client.
+pub struct CancelToken {
+    slot: Option<(&'static Cancellations, Slot)>,
+}
+
+impl CancelToken {
+    /// A token that is never cancelled
+    pub const fn never() -> Self {
+        Self { slot: None }
+    }
+
+    /// Has the client cancelled this request?
+    pub fn is_cancelled(&self) -> bool {
+        let Some((c, slot)) = self.slot else {
+            return false;
+        };
+        c.slots.is_marked(slot)
+    }
+}
+
+impl Drop for CancelToken {
+    fn drop(&mut self) {
+        if let Some((c, slot)) = self.slot {
+            c.slots.free(slot);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CancelToken, Cancellations};
+
+    #[test]
+    fn cancel_tokens() {
+        static CANCELS: Cancellations = Cancellations::new();
+
+        let a = CANCELS.token(1);
+        let b = CANCELS.token(2);
+        assert!(!a.is_cancelled());
+        assert!(CANCELS.cancel(2));
+        assert!(!a.is_cancelled());
+        assert!(b.is_cancelled());
+
+        // Unknown sequence numbers are ignored
+        assert!(!CANCELS.cancel(3));
+
+        // A new request with a reused sequence number starts out fresh
+        drop(b);
+        let b2 = CANCELS.token(2);
+        assert!(!b2.is_cancelled());
+
+        // Once all slots are used, tokens are never cancelled
+        let many: Vec<CancelToken> = (10..30).map(|i| CANCELS.token(i)).collect();
+        assert!(CANCELS.cancel(20));
+        assert!(!CANCELS.cancel(29));
+        assert!(many[10].is_cancelled());
+        assert!(!many[19].is_cancelled());
+        assert!(!CancelToken::never().is_cancelled());
+    }
+}
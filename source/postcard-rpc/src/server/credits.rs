@@ -0,0 +1,196 @@
+//! Credit based flow control for topics sent to the client
+//!
+//! A client that can't keep up with a topic may limit how many of its messages
+//! the server sends, by granting credits with the
+//! [`CreditTopic`][crate::standard_icd::CreditTopic]. Once the client granted
+//! credits for a topic, each message published to it uses up one credit.
+//! [`Sender::publish()`][super::Sender::publish] waits for more credits when they
+//! run out, while [`Sender::try_publish()`][super::Sender::try_publish] drops the
+//! message and counts it, see [`Credits::dropped()`].
+//!
+//! Topics the client never granted credits for are not limited. Clients turn flow
+//! control off again when they stop listening to a topic, and all grants are
+//! forgotten when the client disconnects. Servers opt in to flow control with
+//! [`Server::with_credits()`][super::Server::with_credits], and by adding `credits`
+//! to the `standard` parts of their [`define_dispatch!`][crate::define_dispatch].
+
+use maitake_sync::WaitQueue;
+use portable_atomic::{AtomicU32, Ordering};
+
+use super::slots::{key_words, Slots};
+use crate::{standard_icd::CreditGrant, Key};
+
+/// The number of topics that can be flow controlled at the same time
+const SLOTS: usize = 8;
+
+/// Tracks the credits granted by the client for each topic
+///
+/// Up to 8 topics are tracked at a time. Grants for more topics are ignored,
+/// and those topics are not limited.
+pub struct Credits {
+    slots: Slots<SLOTS, 2>,
+    credits: [AtomicU32; SLOTS],
+    dropped: [AtomicU32; SLOTS],
+    waiters: WaitQueue,
+}
+
+impl Credits {
+    /// Create a new set of credits, where no topic is limited
+    pub const fn new() -> Self {
+        Self {
+            slots: Slots::new(),
+            credits: [const { AtomicU32::new(0) }; SLOTS],
+            dropped: [const { AtomicU32::new(0) }; SLOTS],
+            waiters: WaitQueue::new(),
+        }
+    }
+
+    fn slot(&self, key: Key) -> Option<usize> {
+        self.slots.find(key_words(key)).map(|slot| slot.idx)
+    }
+
+    fn claim(&self, key: Key) -> Option<usize> {
+        let slot = self.slots.claim(key_words(key), |idx| {
+            self.credits[idx].store(0, Ordering::Release);
+            self.dropped[idx].store(0, Ordering::Release);
+        })?;
+        Some(slot.idx)
+    }
+
+    /// Handle a grant from the client
+    ///
+    /// Returns `false` if the grant was ignored because all slots are in use.
+    pub fn grant(&self, grant: &CreditGrant) -> bool {
+        if grant.credits == CreditGrant::UNLIMITED {
+            if let Some(slot) = self.slots.find(key_words(grant.key)) {
+                self.slots.free(slot);
+            }
+            self.waiters.wake_all();
+            return true;
+        }
+
+        let Some(idx) = self.slot(grant.key).or_else(|| self.claim(grant.key)) else {
+            return false;
+        };
+        let _ = self.credits[idx].fetch_update(Ordering::AcqRel, Ordering::Acquire, |c| {
+            Some(c.saturating_add(grant.credits))
+        });
+        self.waiters.wake_all();
+        true
+    }
+
+    /// The number of credits left for the topic with the given key, or `None` if
+    /// the topic is not limited
+    pub fn available(&self, key: Key) -> Option<u32> {
+        let idx = self.slot(key)?;
+        Some(self.credits[idx].load(Ordering::Acquire))
+    }
+
+    /// The number of messages of the topic with the given key that were dropped by
+    /// [`Sender::try_publish()`][super::Sender::try_publish], since the client first
+    /// granted credits for it
+    pub fn dropped(&self, key: Key) -> u32 {
+        self.slot(key)
+            .map(|idx| self.dropped[idx].load(Ordering::Acquire))
+            .unwrap_or(0)
+    }
+
+    /// Take a credit, returns `false` if there are none left
+    fn try_take_inner(&self, key: Key) -> bool {
+        let Some(idx) = self.slot(key) else {
+            return true;
+        };
+        self.credits[idx]
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |c| c.checked_sub(1))
+            .is_ok()
+    }
+
+    /// Take a credit for sending a message of the topic with the given key
+    ///
+    /// Returns `false`, and counts the message as dropped, if there are no
+    /// credits left.
+    pub fn try_take(&self, key: Key) -> bool {
+        if self.try_take_inner(key) {
+            return true;
+        }
+        if let Some(idx) = self.slot(key) {
+            self.dropped[idx].fetch_add(1, Ordering::AcqRel);
+        }
+        false
+    }
+
+    /// Wait until a credit for sending a message of the topic with the given key
+    /// could be taken
+    pub async fn take(&self, key: Key) {
+        // The queue is never closed
+        let _ = self.waiters.wait_for(|| self.try_take_inner(key)).await;
+    }
+
+    /// Forget all grants, so that no topic is limited anymore
+    ///
+    /// This is done by the [`Server`][super::Server] when the client disconnects, as
+    /// the next client doesn't know about the grants of the previous one. Publishers
+    /// waiting for credits are woken.
+    pub fn reset(&self) {
+        self.slots.reset();
+        self.waiters.wake_all();
+    }
+}
+
+impl Default for Credits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Credits;
+    use crate::{standard_icd::CreditGrant, Key};
+
+    #[test]
+    fn credits() {
+        static CREDITS: Credits = Credits::new();
+        let a = Key::for_path::<u32>("a");
+        let b = Key::for_path::<u32>("b");
+        let grant = |key, credits| CREDITS.grant(&CreditGrant { key, credits });
+
+        // Topics are not limited until credits are granted
+        assert_eq!(CREDITS.available(a), None);
+        assert!(CREDITS.try_take(a));
+
+        assert!(grant(a, 2));
+        assert_eq!(CREDITS.available(a), Some(2));
+        assert!(CREDITS.try_take(a));
+        assert!(CREDITS.try_take(a));
+        assert!(!CREDITS.try_take(a));
+        assert!(!CREDITS.try_take(a));
+        assert_eq!(CREDITS.dropped(a), 2);
+
+        // Other topics are not affected
+        assert!(CREDITS.try_take(b));
+        assert_eq!(CREDITS.dropped(b), 0);
+
+        assert!(grant(a, 1));
+        assert!(CREDITS.try_take(a));
+        assert!(!CREDITS.try_take(a));
+
+        // Flow control can be turned off again
+        assert!(grant(a, CreditGrant::UNLIMITED));
+        assert_eq!(CREDITS.available(a), None);
+        assert!(CREDITS.try_take(a));
+
+        // Only a limited number of topics is tracked
+        for i in 0..8 {
+            assert!(grant(Key::for_path::<u32>(&format!("{i}")), 1));
+        }
+        assert!(!grant(b, 1));
+        assert_eq!(CREDITS.available(b), None);
+
+        // Resetting forgets all grants
+        CREDITS.reset();
+        assert_eq!(CREDITS.available(Key::for_path::<u32>("0")), None);
+        assert!(grant(b, 1));
+        assert_eq!(CREDITS.available(b), Some(1));
+    }
+}
@@ -19,16 +19,31 @@
 ///     spawn_impl: WireSpawnImpl;
 ///     // This is the TestContext you define to be passed to all handlers
 ///     context: TestContext;
+///     // Optional: the `DeviceInfo` returned by the `DeviceInfoEndpoint`. This is
+///     // evaluated for each request, so it may also call a function. Without it,
+///     // the endpoint is not part of the device map
+///     device_info: DEVICE_INFO;
+///     // Optional: the parts of the standard ICD this server uses, which are left
+///     // out of its device map otherwise. Those used by handlers, like the bulk
+///     // upload endpoints, or cancellation for `spawn_cancel` handlers, are added
+///     // automatically
+///     standard: [capabilities, credits, topic_filter, schema_pages, schema_digest];
 ///
 ///     endpoints: {
 ///         // This is the list you get from the `endpoints()` macro
 ///         list: ENDPOINT_LIST;
 ///
-///         // These are all of your endpoints and the handlers they map to
-///         | EndpointTy        | kind      | handler               |
-///         | ----------        | ----      | -------               |
-///         | AlphaEndpoint     | async     | test_alpha_handler    |
-///         | BetaEndpoint      | spawn     | test_beta_handler     |
+///         // These are all of your endpoints and the handlers they map to. Only
+///         // `spawn_cancel` and `stream` handlers can see that the client cancelled
+///         // a request, `spawn` handlers always run to completion. Endpoints of the
+///         // standard ICD, like the bulk upload endpoints, may be handled here too,
+///         // and are only added to the device map if they are
+///         | EndpointTy        | kind          | handler               |
+///         | ----------        | ----          | -------               |
+///         | AlphaEndpoint     | async         | test_alpha_handler    |
+///         | BetaEndpoint      | spawn         | test_beta_handler     |
+///         | GammaEndpoint     | spawn_cancel  | test_gamma_handler    |
+///         | DeltaEndpoint     | stream        | test_delta_handler    |
 ///     };
 ///     topics_in: {
 ///         // This is the list you get from the `topics!()` macro
@@ -91,6 +106,38 @@ macro_rules! define_dispatch {
             }
         }
     };
+    // This is the "spawn an embassy task" arm for defining an endpoint, where the task
+    // also gets a token to check if the request was cancelled by the client
+    (@ep_arm spawn_cancel ($endpoint:ty) $handler:ident $context:ident $header:ident $req:ident $outputter:ident ($spawn_fn:path) $spawner:ident) => {
+        {
+            let context = $crate::server::SpawnContext::spawn_ctxt($context);
+            let cancel = $outputter.cancel_token($header.seq_no);
+            if $spawn_fn($spawner, $handler(context, $header.clone(), $req, $outputter.clone(), cancel)).is_err() {
+                let err = $crate::standard_icd::WireError::FailedToSpawn;
+                $outputter.error($header.seq_no, err).await
+            } else {
+                Ok(())
+            }
+        }
+    };
+    // This is the "spawn an embassy task" arm for defining a streaming endpoint, where the
+    // task gets a sender for the responses instead of returning one
+    (@ep_arm stream ($endpoint:ty) $handler:ident $context:ident $header:ident $req:ident $outputter:ident ($spawn_fn:path) $spawner:ident) => {
+        {
+            let context = $crate::server::SpawnContext::spawn_ctxt($context);
+            let stream = $crate::server::StreamSender::<_, $endpoint>::new(
+                $outputter.clone(),
+                $header.seq_no,
+                $outputter.cancel_token($header.seq_no),
+            );
+            if $spawn_fn($spawner, $handler(context, $header.clone(), $req, stream)).is_err() {
+                let err = $crate::standard_icd::WireError::FailedToSpawn;
+                $outputter.error($header.seq_no, err).await
+            } else {
+                Ok(())
+            }
+        }
+    };
 
     //////////////////////////////////////////////////////////////////////////////
     // TOPIC HANDLER EXPANSION ARMS
@@ -117,6 +164,23 @@ macro_rules! define_dispatch {
 
 
 
+    //////////////////////////////////////////////////////////////////////////////
+    // OPTIONAL STANDARD ICD ARMS
+    //////////////////////////////////////////////////////////////////////////////
+
+    // Can handlers of this kind be cancelled?
+    (@cancellable spawn_cancel) => { true };
+    (@cancellable stream) => { true };
+    (@cancellable $ep_flavor:tt) => { false };
+
+    // Was the device info given?
+    (@device_info) => { false };
+    (@device_info $device_info:expr) => { true };
+
+    // Do handlers of this kind reply with a stream?
+    (@streaming stream) => { true };
+    (@streaming $ep_flavor:tt) => { false };
+
     //////////////////////////////////////////////////////////////////////////////
     // Implementation of the dispatch trait for the app, where the Key length
     // is N, where N is 1, 2, 4, or 8
@@ -126,6 +190,7 @@ macro_rules! define_dispatch {
         $req_key_name:ident / $topic_key_name:ident = $bytes_ty:ty;
         ($($endpoint:ty | $ep_flavor:tt | $ep_handler:ident)*)
         ($($topic_in:ty | $tp_flavor:tt | $tp_handler:ident)*)
+        ($($device_info:expr)?)
     ) => {
         impl $app_name<$n> {
             /// Check if there are any unexpected duplicates, typically this occurs because
@@ -133,14 +198,24 @@ macro_rules! define_dispatch {
             #[doc(hidden)]
             pub const fn has_dupe() -> bool {
                 const DUPE: bool = const {
-                    const ALL_KEYS: &[$key_ty] = &[
-                        <$crate::standard_icd::PingEndpoint as $crate::Endpoint>::$req_key_name,
-                        <$crate::standard_icd::GetAllSchemasEndpoint as $crate::Endpoint>::$req_key_name,
+                    // Each key, and whether it is used. Keys of unused optional parts
+                    // of the standard ICD may collide with the keys of the app
+                    const ALL_KEYS: &[($key_ty, bool)] = &[
+                        (<$crate::standard_icd::PingEndpoint as $crate::Endpoint>::$req_key_name, true),
+                        (<$crate::standard_icd::GetAllSchemasEndpoint as $crate::Endpoint>::$req_key_name, true),
+                        (<$crate::standard_icd::GetSchemaPageEndpoint as $crate::Endpoint>::$req_key_name, standard::SCHEMA_PAGES),
+                        (<$crate::standard_icd::SchemaDigestEndpoint as $crate::Endpoint>::$req_key_name, standard::SCHEMA_DIGEST),
+                        (<$crate::standard_icd::CancelTopic as $crate::Topic>::$topic_key_name, standard::CANCEL),
+                        (<$crate::standard_icd::CreditTopic as $crate::Topic>::$topic_key_name, standard::CREDITS),
+                        (<$crate::standard_icd::TopicEnableEndpoint as $crate::Endpoint>::$req_key_name, standard::TOPIC_FILTER),
+                        (<$crate::standard_icd::TopicDisableEndpoint as $crate::Endpoint>::$req_key_name, standard::TOPIC_FILTER),
+                        (<$crate::standard_icd::CapabilitiesEndpoint as $crate::Endpoint>::$req_key_name, standard::CAPABILITIES),
+                        (<$crate::standard_icd::DeviceInfoEndpoint as $crate::Endpoint>::$req_key_name, standard::DEVICE_INFO),
                         $(
-                            <$endpoint as $crate::Endpoint>::$req_key_name,
+                            (<$endpoint as $crate::Endpoint>::$req_key_name, true),
                         )*
                         $(
-                            <$topic_in as $crate::Topic>::$topic_key_name,
+                            (<$topic_in as $crate::Topic>::$topic_key_name, true),
                         )*
                     ];
                     const LEN: usize = ALL_KEYS.len();
@@ -149,7 +224,9 @@ macro_rules! define_dispatch {
                     while i < LEN {
                         let mut j = i + 1;
                         while j < LEN {
-                            dupe |= ALL_KEYS[i].const_cmp(&ALL_KEYS[j]);
+                            let (a, a_used) = &ALL_KEYS[i];
+                            let (b, b_used) = &ALL_KEYS[j];
+                            dupe |= *a_used && *b_used && a.const_cmp(b);
                             j += 1;
                         }
                         i += 1;
@@ -196,6 +273,62 @@ macro_rules! define_dispatch {
                     <$crate::standard_icd::GetAllSchemasEndpoint as $crate::Endpoint>::$req_key_name => {
                         tx.send_all_schemas(hdr, self.device_map).await
                     }
+                    <$crate::standard_icd::GetSchemaPageEndpoint as $crate::Endpoint>::$req_key_name if standard::SCHEMA_PAGES => {
+                        let Ok(page) = $crate::postcard::from_bytes::<<$crate::standard_icd::GetSchemaPageEndpoint as $crate::Endpoint>::Request>(body) else {
+                            let err = $crate::standard_icd::WireError::DeserFailed;
+                            return tx.error(hdr.seq_no, err).await;
+                        };
+                        tx.send_schema_page(hdr, self.device_map, page).await
+                    }
+                    <$crate::standard_icd::SchemaDigestEndpoint as $crate::Endpoint>::$req_key_name if standard::SCHEMA_DIGEST => {
+                        tx.reply::<$crate::standard_icd::SchemaDigestEndpoint>(hdr.seq_no, &self.schema_digest).await
+                    }
+                    <$crate::standard_icd::CancelTopic as $crate::Topic>::$topic_key_name if standard::CANCEL => {
+                        // This is a topic, not much to be done if it is malformed
+                        // Cancellations are ignored by servers that don't track them
+                        let cancellations = tx.cancellations();
+                        if let (Some(cancellations), Ok(seq_no)) = (cancellations, $crate::postcard::from_bytes::<<$crate::standard_icd::CancelTopic as $crate::Topic>::Message>(body)) {
+                            cancellations.cancel(seq_no);
+                        }
+                        Ok(())
+                    }
+                    <$crate::standard_icd::TopicEnableEndpoint as $crate::Endpoint>::$req_key_name if standard::TOPIC_FILTER => {
+                        let Ok(req) = $crate::postcard::from_bytes::<<$crate::standard_icd::TopicEnableEndpoint as $crate::Endpoint>::Request>(body) else {
+                            let err = $crate::standard_icd::WireError::DeserFailed;
+                            return tx.error(hdr.seq_no, err).await;
+                        };
+                        let res = tx.set_topic_enabled(self.device_map, req.key, true, req.max_rate);
+                        tx.reply::<$crate::standard_icd::TopicEnableEndpoint>(hdr.seq_no, &res).await
+                    }
+                    <$crate::standard_icd::TopicDisableEndpoint as $crate::Endpoint>::$req_key_name if standard::TOPIC_FILTER => {
+                        let Ok(key) = $crate::postcard::from_bytes::<<$crate::standard_icd::TopicDisableEndpoint as $crate::Endpoint>::Request>(body) else {
+                            let err = $crate::standard_icd::WireError::DeserFailed;
+                            return tx.error(hdr.seq_no, err).await;
+                        };
+                        let res = tx.set_topic_enabled(self.device_map, key, false, None);
+                        tx.reply::<$crate::standard_icd::TopicDisableEndpoint>(hdr.seq_no, &res).await
+                    }
+                    <$crate::standard_icd::CapabilitiesEndpoint as $crate::Endpoint>::$req_key_name if standard::CAPABILITIES => {
+                        let mut caps = tx.capabilities(<Self as $crate::server::Dispatch>::min_key_len(self));
+                        caps.features &= standard::FEATURES;
+                        tx.reply::<$crate::standard_icd::CapabilitiesEndpoint>(hdr.seq_no, &caps).await
+                    }
+                    $(
+                        <$crate::standard_icd::DeviceInfoEndpoint as $crate::Endpoint>::$req_key_name => {
+                            // Always the borrowed form, which serializes like the owned one
+                            let info: $crate::standard_icd::DeviceInfo<'_> = $device_info;
+                            let key = <$crate::standard_icd::DeviceInfoEndpoint as $crate::Endpoint>::RESP_KEY;
+                            tx.reply_keyed(hdr.seq_no, key, &info).await
+                        }
+                    )?
+                    <$crate::standard_icd::CreditTopic as $crate::Topic>::$topic_key_name if standard::CREDITS => {
+                        // Grants are ignored by servers that don't use credits
+                        let credits = tx.credits();
+                        if let (Some(credits), Ok(grant)) = (credits, $crate::postcard::from_bytes::<<$crate::standard_icd::CreditTopic as $crate::Topic>::Message>(body)) {
+                            credits.grant(&grant);
+                        }
+                        Ok(())
+                    }
                     // WARNING! If you add any more standard icd endpoints, make sure you ALSO add them
                     // to has_dupe above!
                     //
@@ -262,6 +395,8 @@ macro_rules! define_dispatch {
         tx_impl: $tx_impl:ty;
         spawn_impl: $spawn_impl:ty;
         context: $context_ty:ty;
+        $(device_info: $device_info:expr;)?
+        $(standard: [$($standard:ident),* $(,)?];)?
 
         endpoints: {
             list: $endpoint_list:path;
@@ -282,6 +417,213 @@ macro_rules! define_dispatch {
         };
     ) => {
 
+        // The optional parts of the standard ICD are only added to the device map
+        // when the app uses them, so they don't take up space otherwise
+        mod standard {
+            use super::*;
+            use $crate::{postcard_schema::schema::NamedType, standard_icd as icd, Endpoint, Key};
+
+            // This is a list of all REQUEST KEYS in the actual handlers
+            const EP_HANDLER_IN_KEYS: &[Key] = &[
+                $(<$endpoint as $crate::Endpoint>::REQ_KEY,)*
+            ];
+
+            const fn handles(key: Key) -> bool {
+                let x = u64::from_le_bytes(key.to_bytes());
+                let mut i = 0;
+                while i < EP_HANDLER_IN_KEYS.len() {
+                    if u64::from_le_bytes(EP_HANDLER_IN_KEYS[i].to_bytes()) == x {
+                        return true;
+                    }
+                    i += 1;
+                }
+                false
+            }
+
+            const fn endpoints(
+                used: bool,
+                map: &'static $crate::EndpointMap,
+            ) -> &'static [(&'static str, Key, Key)] {
+                if used { map.endpoints } else { &[] }
+            }
+
+            const fn endpoint_types(
+                used: bool,
+                map: &'static $crate::EndpointMap,
+            ) -> &'static [&'static NamedType] {
+                if used { map.types } else { &[] }
+            }
+
+            const fn topics(
+                used: bool,
+                map: &'static $crate::TopicMap,
+            ) -> &'static [(&'static str, Key)] {
+                if used { map.topics } else { &[] }
+            }
+
+            const fn topic_types(
+                used: bool,
+                map: &'static $crate::TopicMap,
+            ) -> &'static [&'static NamedType] {
+                if used { map.types } else { &[] }
+            }
+
+            // The parts of the standard ICD the app opted in to, and those it may
+            const OPTED_IN: &[&str] = &[$($(stringify!($standard),)*)?];
+            const OPTIONAL: &[&str] = &[
+                "capabilities",
+                "credits",
+                "topic_filter",
+                "schema_pages",
+                "schema_digest",
+            ];
+
+            const fn str_eq(a: &str, b: &str) -> bool {
+                let (a, b) = (a.as_bytes(), b.as_bytes());
+                if a.len() != b.len() {
+                    return false;
+                }
+                let mut i = 0;
+                while i < a.len() {
+                    if a[i] != b[i] {
+                        return false;
+                    }
+                    i += 1;
+                }
+                true
+            }
+
+            const fn contains(list: &[&str], name: &str) -> bool {
+                let mut i = 0;
+                while i < list.len() {
+                    if str_eq(list[i], name) {
+                        return true;
+                    }
+                    i += 1;
+                }
+                false
+            }
+
+            const _KNOWN_CHECK: () = const {
+                let mut i = 0;
+                while i < OPTED_IN.len() {
+                    assert!(
+                        contains(OPTIONAL, OPTED_IN[i]),
+                        "Unknown part of the standard ICD in `standard`! Expected one of: capabilities, credits, topic_filter, schema_pages, schema_digest",
+                    );
+                    i += 1;
+                }
+            };
+
+            /// Bulk uploads are used if any of their endpoints is handled
+            pub const BULK: bool = handles(<icd::BulkStartEndpoint as Endpoint>::REQ_KEY)
+                || handles(<icd::BulkChunkEndpoint as Endpoint>::REQ_KEY)
+                || handles(<icd::BulkEndEndpoint as Endpoint>::REQ_KEY);
+
+            /// Cancellation is used if any handler can be cancelled
+            pub const CANCEL: bool = false $(|| $crate::define_dispatch!(@cancellable $ep_flavor))*;
+
+            /// The end of streams is used if any handler replies with a stream
+            pub const STREAM: bool = false $(|| $crate::define_dispatch!(@streaming $ep_flavor))*;
+
+            /// Credits are used if the app opted in to them
+            pub const CREDITS: bool = contains(OPTED_IN, "credits");
+
+            /// Enabling and disabling topics is used if the app opted in to it
+            pub const TOPIC_FILTER: bool = contains(OPTED_IN, "topic_filter");
+
+            /// The capabilities are used if the app opted in to them
+            pub const CAPABILITIES: bool = contains(OPTED_IN, "capabilities");
+
+            /// Paged schema transfers are used if the app opted in to them
+            pub const SCHEMA_PAGES: bool = contains(OPTED_IN, "schema_pages");
+
+            /// The schema digest is used if the app opted in to it
+            pub const SCHEMA_DIGEST: bool = contains(OPTED_IN, "schema_digest");
+
+            /// The device info is used if it was given
+            pub const DEVICE_INFO: bool = $crate::define_dispatch!(@device_info $($device_info)?);
+
+            /// The features of the capabilities that are part of the device map
+            pub const FEATURES: u8 = {
+                let mut features = 0;
+                if CANCEL {
+                    features |= icd::Capabilities::CANCEL;
+                }
+                if TOPIC_FILTER {
+                    features |= icd::Capabilities::TOPIC_FILTER;
+                }
+                if CREDITS {
+                    features |= icd::Capabilities::CREDITS;
+                }
+                features
+            };
+
+            const APP_ENDPOINTS: &[(&str, Key, Key)] = $endpoint_list.endpoints;
+            const BULK_ENDPOINTS: &[(&str, Key, Key)] =
+                endpoints(BULK, &icd::STANDARD_ICD_BULK_ENDPOINTS);
+            const TOPIC_FILTER_ENDPOINTS: &[(&str, Key, Key)] =
+                endpoints(TOPIC_FILTER, &icd::STANDARD_ICD_TOPIC_FILTER_ENDPOINTS);
+            const CAPABILITIES_ENDPOINTS: &[(&str, Key, Key)] =
+                endpoints(CAPABILITIES, &icd::STANDARD_ICD_CAPABILITIES_ENDPOINTS);
+            const DEVICE_INFO_ENDPOINTS: &[(&str, Key, Key)] =
+                endpoints(DEVICE_INFO, &icd::STANDARD_ICD_DEVICE_INFO_ENDPOINTS);
+            const SCHEMA_PAGE_ENDPOINTS: &[(&str, Key, Key)] =
+                endpoints(SCHEMA_PAGES, &icd::STANDARD_ICD_SCHEMA_PAGE_ENDPOINTS);
+            const SCHEMA_DIGEST_ENDPOINTS: &[(&str, Key, Key)] =
+                endpoints(SCHEMA_DIGEST, &icd::STANDARD_ICD_SCHEMA_DIGEST_ENDPOINTS);
+
+            const APP_TOPICS_IN: &[(&str, Key)] = $topic_in_list.topics;
+            const CANCEL_TOPICS: &[(&str, Key)] = topics(CANCEL, &icd::STANDARD_ICD_CANCEL_TOPICS);
+            const CREDIT_TOPICS: &[(&str, Key)] = topics(CREDITS, &icd::STANDARD_ICD_CREDIT_TOPICS);
+
+            const APP_TOPICS_OUT: &[(&str, Key)] = $topic_out_list.topics;
+            const STREAM_TOPICS: &[(&str, Key)] = topics(STREAM, &icd::STANDARD_ICD_STREAM_TOPICS);
+            const SCHEMA_PAGE_TOPICS: &[(&str, Key)] =
+                topics(SCHEMA_PAGES, &icd::STANDARD_ICD_SCHEMA_PAGE_TOPICS);
+
+            // The endpoints, topics, and types of the app and the used parts of
+            // the standard ICD
+            pub const ENDPOINTS: &[(&str, Key, Key)] = $crate::concat_arrays! {
+                init = ("", unsafe { Key::from_bytes([0; 8]) }, unsafe { Key::from_bytes([0; 8]) });
+                ty = (&'static str, Key, Key);
+                [
+                    APP_ENDPOINTS,
+                    BULK_ENDPOINTS,
+                    TOPIC_FILTER_ENDPOINTS,
+                    CAPABILITIES_ENDPOINTS,
+                    DEVICE_INFO_ENDPOINTS,
+                    SCHEMA_PAGE_ENDPOINTS,
+                    SCHEMA_DIGEST_ENDPOINTS
+                ]
+            };
+            pub const TOPICS_IN: &[(&str, Key)] = $crate::concat_arrays! {
+                init = ("", unsafe { Key::from_bytes([0; 8]) });
+                ty = (&'static str, Key);
+                [APP_TOPICS_IN, CANCEL_TOPICS, CREDIT_TOPICS]
+            };
+            pub const TOPICS_OUT: &[(&str, Key)] = $crate::concat_arrays! {
+                init = ("", unsafe { Key::from_bytes([0; 8]) });
+                ty = (&'static str, Key);
+                [APP_TOPICS_OUT, STREAM_TOPICS, SCHEMA_PAGE_TOPICS]
+            };
+            pub const TYPES: &[&[&NamedType]] = &[
+                $endpoint_list.types,
+                $topic_in_list.types,
+                $topic_out_list.types,
+                endpoint_types(BULK, &icd::STANDARD_ICD_BULK_ENDPOINTS),
+                endpoint_types(TOPIC_FILTER, &icd::STANDARD_ICD_TOPIC_FILTER_ENDPOINTS),
+                endpoint_types(CAPABILITIES, &icd::STANDARD_ICD_CAPABILITIES_ENDPOINTS),
+                endpoint_types(DEVICE_INFO, &icd::STANDARD_ICD_DEVICE_INFO_ENDPOINTS),
+                endpoint_types(SCHEMA_PAGES, &icd::STANDARD_ICD_SCHEMA_PAGE_ENDPOINTS),
+                endpoint_types(SCHEMA_DIGEST, &icd::STANDARD_ICD_SCHEMA_DIGEST_ENDPOINTS),
+                topic_types(CANCEL, &icd::STANDARD_ICD_CANCEL_TOPICS),
+                topic_types(CREDITS, &icd::STANDARD_ICD_CREDIT_TOPICS),
+                topic_types(STREAM, &icd::STANDARD_ICD_STREAM_TOPICS),
+                topic_types(SCHEMA_PAGES, &icd::STANDARD_ICD_SCHEMA_PAGE_TOPICS),
+            ];
+        }
+
         // Here, we calculate how many bytes (1, 2, 4, or 8) are required to uniquely
         // match on the given messages we receive and send†.
         //
@@ -296,45 +638,45 @@ macro_rules! define_dispatch {
             use $crate::Key;
 
             // Create a list of JUST the REQUEST keys from the endpoint report
-            const EP_IN_KEYS_SZ: usize = $endpoint_list.endpoints.len();
+            const EP_IN_KEYS_SZ: usize = standard::ENDPOINTS.len();
             const EP_IN_KEYS: [Key; EP_IN_KEYS_SZ] = const {
                 let mut keys = [unsafe { Key::from_bytes([0; 8]) }; EP_IN_KEYS_SZ];
                 let mut i = 0;
                 while i < EP_IN_KEYS_SZ {
-                    keys[i] = $endpoint_list.endpoints[i].1;
+                    keys[i] = standard::ENDPOINTS[i].1;
                     i += 1;
                 }
                 keys
             };
             // Create a list of JUST the RESPONSE keys from the endpoint report
-            const EP_OUT_KEYS_SZ: usize = $endpoint_list.endpoints.len();
+            const EP_OUT_KEYS_SZ: usize = standard::ENDPOINTS.len();
             const EP_OUT_KEYS: [Key; EP_OUT_KEYS_SZ] = const {
                 let mut keys = [unsafe { Key::from_bytes([0; 8]) }; EP_OUT_KEYS_SZ];
                 let mut i = 0;
                 while i < EP_OUT_KEYS_SZ {
-                    keys[i] = $endpoint_list.endpoints[i].2;
+                    keys[i] = standard::ENDPOINTS[i].2;
                     i += 1;
                 }
                 keys
             };
             // Create a list of JUST the MESSAGE keys from the TOPICS IN report
-            const TP_IN_KEYS_SZ: usize = $topic_in_list.topics.len();
+            const TP_IN_KEYS_SZ: usize = standard::TOPICS_IN.len();
             const TP_IN_KEYS: [Key; TP_IN_KEYS_SZ] = const {
                 let mut keys = [unsafe { Key::from_bytes([0; 8]) }; TP_IN_KEYS_SZ];
                 let mut i = 0;
                 while i < TP_IN_KEYS_SZ {
-                    keys[i] = $topic_in_list.topics[i].1;
+                    keys[i] = standard::TOPICS_IN[i].1;
                     i += 1;
                 }
                 keys
             };
             // Create a list of JUST the MESSAGE keys from the TOPICS OUT report
-            const TP_OUT_KEYS_SZ: usize = $topic_out_list.topics.len();
+            const TP_OUT_KEYS_SZ: usize = standard::TOPICS_OUT.len();
             const TP_OUT_KEYS: [Key; TP_OUT_KEYS_SZ] = const {
                 let mut keys = [unsafe { Key::from_bytes([0; 8]) }; TP_OUT_KEYS_SZ];
                 let mut i = 0;
                 while i < TP_OUT_KEYS_SZ {
-                    keys[i] = $topic_out_list.topics[i].1;
+                    keys[i] = standard::TOPICS_OUT[i].1;
                     i += 1;
                 }
                 keys
@@ -440,6 +782,7 @@ macro_rules! define_dispatch {
                 pub context: $context_ty,
                 pub spawn: $spawn_impl,
                 pub device_map: &'static $crate::DeviceMap,
+                pub schema_digest: u64,
             }
 
             impl<const N: usize> $app_name<N> {
@@ -450,20 +793,16 @@ macro_rules! define_dispatch {
                 ) -> Self {
                     const MAP: &$crate::DeviceMap = &$crate::DeviceMap {
                         types: const {
-                            const LISTS: &[&[&'static $crate::postcard_schema::schema::NamedType]] = &[
-                                $endpoint_list.types,
-                                $topic_in_list.types,
-                                $topic_out_list.types,
-                            ];
-                            const TTL_COUNT: usize = $endpoint_list.types.len() + $topic_in_list.types.len() + $topic_out_list.types.len();
+                            const LISTS: &[&[&'static $crate::postcard_schema::schema::NamedType]] = standard::TYPES;
+                            const TTL_COUNT: usize = $crate::uniques::total_len(LISTS);
 
                             const BIG_RPT: ([Option<&'static $crate::postcard_schema::schema::NamedType>; TTL_COUNT], usize) = $crate::uniques::merge_nty_lists(LISTS);
                             const SMALL_RPT: [&'static $crate::postcard_schema::schema::NamedType; BIG_RPT.1] = $crate::uniques::cruncher(BIG_RPT.0.as_slice());
                             SMALL_RPT.as_slice()
                         },
-                        endpoints: &$endpoint_list.endpoints,
-                        topics_in: &$topic_in_list.topics,
-                        topics_out: &$topic_out_list.topics,
+                        endpoints: standard::ENDPOINTS,
+                        topics_in: standard::TOPICS_IN,
+                        topics_out: standard::TOPICS_OUT,
                         min_key_len: const {
                             match sizer::NEEDED_SZ {
                                 1 => $crate::header::VarKeyKind::Key1,
@@ -478,6 +817,7 @@ macro_rules! define_dispatch {
                         context,
                         spawn,
                         device_map: MAP,
+                        schema_digest: const { MAP.digest() },
                     }
                 }
             }
@@ -487,24 +827,28 @@ macro_rules! define_dispatch {
                 REQ_KEY1 / TOPIC_KEY1 = u8;
                 ($($endpoint | $ep_flavor | $ep_handler)*)
                 ($($topic_in | $tp_flavor | $tp_handler)*)
+                ($($device_info)?)
             }
             $crate::define_dispatch! {
                 @matcher 2 $app_name $tx_impl; $spawn_fn $crate::Key2; $crate::header::VarKeyKind::Key2;
                 REQ_KEY2 / TOPIC_KEY2 = [u8; 2];
                 ($($endpoint | $ep_flavor | $ep_handler)*)
                 ($($topic_in | $tp_flavor | $tp_handler)*)
+                ($($device_info)?)
             }
             $crate::define_dispatch! {
                 @matcher 4 $app_name $tx_impl; $spawn_fn $crate::Key4; $crate::header::VarKeyKind::Key4;
                 REQ_KEY4 / TOPIC_KEY4 = [u8; 4];
                 ($($endpoint | $ep_flavor | $ep_handler)*)
                 ($($topic_in | $tp_flavor | $tp_handler)*)
+                ($($device_info)?)
             }
             $crate::define_dispatch! {
                 @matcher 8 $app_name $tx_impl; $spawn_fn $crate::Key; $crate::header::VarKeyKind::Key8;
                 REQ_KEY / TOPIC_KEY = [u8; 8];
                 ($($endpoint | $ep_flavor | $ep_handler)*)
                 ($($topic_in | $tp_flavor | $tp_handler)*)
+                ($($device_info)?)
             }
         }
 
@@ -20,6 +20,15 @@ pub mod usb_gadget;
 #[cfg(feature = "test-utils")]
 pub mod test_channels;
 
+#[cfg(feature = "tokio-stream-server")]
+pub mod tokio_stream;
+
+#[cfg(feature = "udp-server")]
+pub mod udp;
+
+#[cfg(feature = "websocket-server")]
+pub mod websocket;
+
 #[cfg(any(
     feature = "embassy-usb-0_5-server",
     feature = "embassy-usb-0_6-server",
@@ -67,7 +76,12 @@ pub(crate) mod embassy_shared {
     }
 }
 
-#[cfg(feature = "tokio")]
+#[cfg(any(
+    feature = "tokio",
+    feature = "tokio-stream-server",
+    feature = "udp-server",
+    feature = "websocket-server",
+))]
 pub(crate) mod tokio_shared {
     use core::convert::Infallible;
     use tokio::runtime;
@@ -0,0 +1,456 @@
+//! Implementation using tokio byte streams
+//!
+//! This impl works with any tokio [`AsyncRead`] + [`AsyncWrite`] stream, and also
+//! provides helpers for accepting connections from a [`TcpListener`] or a
+//! [`UnixListener`]. Frames are delimited using one of the methods described in the
+//! [`framing`][crate::framing] module, the same ones used by the
+//! [`HostClient`][crate::host_client::HostClient] stream constructors.
+//!
+//! It can also be used over the process' own stdin and stdout, see
+//! [`new_from_stdio()`].
+//!
+//! When created from a listener, the server handles one client at a time. Once the
+//! client disconnects, [`Server::run()`][crate::server::Server::run] returns, and
+//! calling it again will wait for the next client to connect.
+
+use core::{
+    fmt::Arguments,
+    sync::atomic::{AtomicU32, Ordering},
+};
+use std::{io, sync::Arc, time::Duration};
+
+use crate::{
+    framing::{FrameDecoder, FrameError, Framing, DEFAULT_MAX_FRAME_LEN},
+    header::{VarHeader, VarKey, VarKeyKind, VarSeq},
+    server::{
+        AsWireRxErrorKind, AsWireTxErrorKind, WireRx, WireRxErrorKind, WireTx, WireTxErrorKind,
+    },
+    standard_icd::LoggingTopic,
+    Topic,
+};
+use thiserror::Error;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, ToSocketAddrs},
+    sync::{watch, Mutex},
+};
+
+/// The size of the buffer used for each `read()` from the stream
+const READ_BUF_SIZE: usize = 4096;
+
+/// How long to wait before retrying after a failed `accept()`
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+type BoxedReader = Box<dyn AsyncRead + Send + Unpin>;
+type BoxedWriter = Box<dyn AsyncWrite + Send + Unpin>;
+
+//////////////////////////////////////////////////////////////////////////////
+// DISPATCH IMPL
+//////////////////////////////////////////////////////////////////////////////
+
+/// A collection of types and aliases useful for importing the correct types
+pub mod dispatch_impl {
+    use crate::{
+        header::VarKeyKind,
+        server::{Dispatch, Server},
+    };
+
+    pub use crate::server::impls::tokio_shared::tokio_spawn as spawn_fn;
+
+    /// The settings necessary for creating a new stream server
+    pub struct Settings {
+        /// The frame sender
+        pub tx: WireTxImpl,
+        /// The frame receiver
+        pub rx: WireRxImpl,
+        /// The size of the receive buffer
+        pub buf: usize,
+        /// The sender key size to use
+        pub kkind: VarKeyKind,
+    }
+
+    /// Type alias for `WireTx` impl
+    pub type WireTxImpl = super::StreamWireTx;
+    /// Type alias for `WireRx` impl
+    pub type WireRxImpl = super::StreamWireRx;
+    /// Type alias for `WireSpawn` impl
+    pub type WireSpawnImpl = crate::server::impls::tokio_shared::TokioWireSpawn;
+    /// Type alias for the receive buffer
+    pub type WireRxBuf = Box<[u8]>;
+
+    /// Create a new server using the [`Settings`] and [`Dispatch`] implementation
+    pub fn new_server<D>(
+        dispatch: D,
+        settings: Settings,
+    ) -> crate::server::Server<WireTxImpl, WireRxImpl, WireRxBuf, D>
+    where
+        D: Dispatch<Tx = WireTxImpl>,
+    {
+        let buf = vec![0; settings.buf];
+        Server::new(
+            settings.tx,
+            settings.rx,
+            buf.into_boxed_slice(),
+            dispatch,
+            settings.kkind,
+        )
+    }
+}
+
+pub use super::tokio_shared::tokio_spawn;
+pub use super::tokio_shared::TokioWireSpawn as StreamWireSpawn;
+
+//////////////////////////////////////////////////////////////////////////////
+// CONSTRUCTORS
+//////////////////////////////////////////////////////////////////////////////
+
+/// Create a new [`StreamWireTx`] and [`StreamWireRx`] pair from a single stream
+///
+/// Once the stream is closed, the pair will always report that the connection is
+/// closed.
+pub fn new_from_stream<S>(stream: S, framing: Framing) -> (StreamWireTx, StreamWireRx)
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (r, w) = tokio::io::split(stream);
+    new_pair(Listener::None, framing, Some((Box::new(r), Box::new(w))))
+}
+
+/// Create a new [`StreamWireTx`] and [`StreamWireRx`] pair using the process' stdin
+/// and stdout, with [`Framing::Cobs`]
+///
+/// This is intended for running a device simulator as a child process of the
+/// host, see [`HostClient::spawn_stdio()`]. Once stdin is closed, the pair will
+/// always report that the connection is closed.
+///
+/// Stdout is used for protocol traffic only: anything else printed to stdout
+/// will corrupt frames. Logs (including `tracing` output) should be written to
+/// stderr instead, for example using
+/// `tracing_subscriber::fmt().with_writer(std::io::stderr)`.
+///
+/// [`HostClient::spawn_stdio()`]: crate::host_client::HostClient::spawn_stdio
+pub fn new_from_stdio() -> (StreamWireTx, StreamWireRx) {
+    new_pair(
+        Listener::None,
+        Framing::Cobs,
+        Some((Box::new(tokio::io::stdin()), Box::new(tokio::io::stdout()))),
+    )
+}
+
+/// Create a new [`StreamWireTx`] and [`StreamWireRx`] pair from a [`TcpListener`]
+///
+/// Clients are accepted one at a time.
+pub fn new_from_tcp_listener(
+    listener: TcpListener,
+    framing: Framing,
+) -> (StreamWireTx, StreamWireRx) {
+    new_pair(Listener::Tcp(listener), framing, None)
+}
+
+/// Bind a [`TcpListener`] to the given address, and create a new [`StreamWireTx`] and
+/// [`StreamWireRx`] pair from it
+///
+/// See [`new_from_tcp_listener()`] for more details.
+pub async fn bind_tcp(
+    addr: impl ToSocketAddrs,
+    framing: Framing,
+) -> io::Result<(StreamWireTx, StreamWireRx)> {
+    let listener = TcpListener::bind(addr).await?;
+    Ok(new_from_tcp_listener(listener, framing))
+}
+
+/// Create a new [`StreamWireTx`] and [`StreamWireRx`] pair from a [`UnixListener`]
+///
+/// Clients are accepted one at a time.
+#[cfg(unix)]
+pub fn new_from_unix_listener(
+    listener: UnixListener,
+    framing: Framing,
+) -> (StreamWireTx, StreamWireRx) {
+    new_pair(Listener::Unix(listener), framing, None)
+}
+
+fn new_pair(
+    listener: Listener,
+    framing: Framing,
+    stream: Option<(BoxedReader, BoxedWriter)>,
+) -> (StreamWireTx, StreamWireRx) {
+    let (reader, writer) = match stream {
+        Some((r, w)) => (Some(r), Some(w)),
+        None => (None, None),
+    };
+    let (connected_tx, connected_rx) = watch::channel(reader.is_some());
+    let writer = Arc::new(Mutex::new(writer));
+    let tx = StreamWireTx {
+        writer: writer.clone(),
+        connected: connected_rx,
+        framing,
+        log_ctr: Arc::new(AtomicU32::new(0)),
+    };
+    let rx = StreamWireRx {
+        listener,
+        reader,
+        writer,
+        connected: connected_tx,
+        buf: Box::new([0u8; READ_BUF_SIZE]),
+        dec: FrameDecoder::new(framing, DEFAULT_MAX_FRAME_LEN),
+    };
+    (tx, rx)
+}
+
+//////////////////////////////////////////////////////////////////////////////
+// TX
+//////////////////////////////////////////////////////////////////////////////
+
+/// A [`WireTx`] impl using a tokio byte stream
+#[derive(Clone)]
+pub struct StreamWireTx {
+    writer: Arc<Mutex<Option<BoxedWriter>>>,
+    connected: watch::Receiver<bool>,
+    framing: Framing,
+    log_ctr: Arc<AtomicU32>,
+}
+
+impl StreamWireTx {
+    async fn inner_send(&self, msg: &[u8]) -> Result<(), StreamWireTxError> {
+        let msg = self.framing.encode(msg);
+        let mut writer = self.writer.lock().await;
+        let w = writer.as_mut().ok_or(StreamWireTxError::ConnectionClosed)?;
+        // Some writers (like stdout) buffer until flushed
+        let res = match w.write_all(&msg).await {
+            Ok(()) => w.flush().await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = res {
+            // The receiver will notice the closed connection separately
+            *writer = None;
+            return Err(StreamWireTxError::Transfer(e));
+        }
+        Ok(())
+    }
+
+    fn log_header(&self, kkind: VarKeyKind) -> VarHeader {
+        let ctr = self.log_ctr.fetch_add(1, Ordering::Relaxed);
+        let key = match kkind {
+            VarKeyKind::Key1 => VarKey::Key1(LoggingTopic::TOPIC_KEY1),
+            VarKeyKind::Key2 => VarKey::Key2(LoggingTopic::TOPIC_KEY2),
+            VarKeyKind::Key4 => VarKey::Key4(LoggingTopic::TOPIC_KEY4),
+            VarKeyKind::Key8 => VarKey::Key8(LoggingTopic::TOPIC_KEY),
+        };
+        VarHeader {
+            key,
+            seq_no: VarSeq::Seq4(ctr),
+        }
+    }
+}
+
+impl WireTx for StreamWireTx {
+    type Error = StreamWireTxError;
+
+    async fn wait_connection(&self) {
+        let mut connected = self.connected.clone();
+        let _ = connected.wait_for(|&c| c).await;
+    }
+
+    async fn send<T: serde::Serialize + ?Sized>(
+        &self,
+        hdr: VarHeader,
+        msg: &T,
+    ) -> Result<(), Self::Error> {
+        let mut buf = hdr.write_to_vec();
+        postcard::to_io(msg, &mut buf).map_err(|_| StreamWireTxError::Serialize)?;
+        self.inner_send(&buf).await
+    }
+
+    async fn send_raw(&self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.inner_send(buf).await
+    }
+
+    async fn send_log_str(&self, kkind: VarKeyKind, s: &str) -> Result<(), Self::Error> {
+        let wh = self.log_header(kkind);
+        self.send::<str>(wh, s).await
+    }
+
+    async fn send_log_fmt<'a>(
+        &self,
+        kkind: VarKeyKind,
+        a: Arguments<'a>,
+    ) -> Result<(), Self::Error> {
+        let wh = self.log_header(kkind);
+        let msg = format!("{a}");
+        self.send::<<LoggingTopic as Topic>::Message>(wh, &msg)
+            .await
+    }
+}
+
+/// A wire tx error
+#[derive(Debug, Error)]
+pub enum StreamWireTxError {
+    /// There is no connected client
+    #[error("connection closed")]
+    ConnectionClosed,
+    /// Writing to the stream failed
+    #[error("transfer error on send")]
+    Transfer(#[source] io::Error),
+    /// The message could not be serialized
+    #[error("serialization failed")]
+    Serialize,
+}
+
+impl AsWireTxErrorKind for StreamWireTxError {
+    fn as_kind(&self) -> WireTxErrorKind {
+        match self {
+            StreamWireTxError::ConnectionClosed => WireTxErrorKind::ConnectionClosed,
+            StreamWireTxError::Transfer(_) => WireTxErrorKind::ConnectionClosed,
+            StreamWireTxError::Serialize => WireTxErrorKind::Other,
+        }
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+// RX
+//////////////////////////////////////////////////////////////////////////////
+
+enum Listener {
+    None,
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+/// A [`WireRx`] impl using a tokio byte stream
+pub struct StreamWireRx {
+    listener: Listener,
+    reader: Option<BoxedReader>,
+    writer: Arc<Mutex<Option<BoxedWriter>>>,
+    connected: watch::Sender<bool>,
+    buf: Box<[u8; READ_BUF_SIZE]>,
+    dec: FrameDecoder,
+}
+
+impl StreamWireRx {
+    async fn install(&mut self, reader: BoxedReader, writer: BoxedWriter) {
+        *self.writer.lock().await = Some(writer);
+        self.reader = Some(reader);
+        self.dec = FrameDecoder::new(self.dec.framing(), DEFAULT_MAX_FRAME_LEN);
+        self.connected.send_replace(true);
+    }
+
+    async fn disconnect(&mut self) {
+        // Without a listener there will never be another connection, so leave
+        // `connected` as-is to avoid the Server waiting forever, the missing
+        // writer will cause any sends to fail.
+        if !matches!(self.listener, Listener::None) {
+            self.connected.send_replace(false);
+        }
+        self.reader = None;
+        *self.writer.lock().await = None;
+    }
+
+    async fn accept(listener: &Listener) -> io::Result<(BoxedReader, BoxedWriter)> {
+        match listener {
+            Listener::None => Err(io::ErrorKind::NotConnected.into()),
+            Listener::Tcp(l) => {
+                let (stream, addr) = l.accept().await?;
+                tracing::info!("Accepted TCP client from {addr}");
+                if let Err(e) = stream.set_nodelay(true) {
+                    tracing::warn!("Failed to set TCP_NODELAY: {e:?}");
+                }
+                let (r, w) = stream.into_split();
+                Ok((Box::new(r), Box::new(w)))
+            }
+            #[cfg(unix)]
+            Listener::Unix(l) => {
+                let (stream, _addr) = l.accept().await?;
+                tracing::info!("Accepted Unix socket client");
+                let (r, w) = stream.into_split();
+                Ok((Box::new(r), Box::new(w)))
+            }
+        }
+    }
+}
+
+impl WireRx for StreamWireRx {
+    type Error = StreamWireRxError;
+
+    async fn wait_connection(&mut self) {
+        if self.reader.is_some() || matches!(self.listener, Listener::None) {
+            return;
+        }
+        loop {
+            match Self::accept(&self.listener).await {
+                Ok((r, w)) => {
+                    self.install(r, w).await;
+                    return;
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to accept client: {e:?}");
+                    tokio::time::sleep(ACCEPT_RETRY_DELAY).await;
+                }
+            }
+        }
+    }
+
+    async fn receive<'a>(&mut self, buf: &'a mut [u8]) -> Result<&'a mut [u8], Self::Error> {
+        loop {
+            // Do we have any messages already prepared?
+            if let Some(res) = self.dec.next_frame() {
+                let frame = res.map_err(StreamWireRxError::Framing)?;
+                let out = buf
+                    .get_mut(..frame.len())
+                    .ok_or(StreamWireRxError::MessageTooLarge)?;
+                out.copy_from_slice(&frame);
+                return Ok(out);
+            }
+
+            let reader = self
+                .reader
+                .as_mut()
+                .ok_or(StreamWireRxError::ConnectionClosed)?;
+            match reader.read(self.buf.as_mut_slice()).await {
+                Ok(0) => {
+                    self.disconnect().await;
+                    return Err(StreamWireRxError::ConnectionClosed);
+                }
+                Ok(used) => self.dec.push(&self.buf[..used]),
+                Err(e) => {
+                    self.disconnect().await;
+                    return Err(StreamWireRxError::Transfer(e));
+                }
+            }
+        }
+    }
+}
+
+/// A wire rx error
+#[derive(Debug, Error)]
+pub enum StreamWireRxError {
+    /// There is no connected client
+    #[error("connection closed")]
+    ConnectionClosed,
+    /// Reading from the stream failed
+    #[error("transfer error on recv")]
+    Transfer(#[source] io::Error),
+    /// The client sent a message too large for the receive buffer
+    #[error("message too large")]
+    MessageTooLarge,
+    /// The client sent a badly framed message
+    #[error("framing error")]
+    Framing(#[source] FrameError),
+}
+
+impl AsWireRxErrorKind for StreamWireRxError {
+    fn as_kind(&self) -> WireRxErrorKind {
+        match self {
+            StreamWireRxError::ConnectionClosed => WireRxErrorKind::ConnectionClosed,
+            StreamWireRxError::Transfer(_) => WireRxErrorKind::ConnectionClosed,
+            StreamWireRxError::MessageTooLarge => WireRxErrorKind::ReceivedMessageTooLarge,
+            StreamWireRxError::Framing(FrameError::TooLong { .. }) => {
+                WireRxErrorKind::ReceivedMessageTooLarge
+            }
+            StreamWireRxError::Framing(FrameError::Cobs) => WireRxErrorKind::Other,
+        }
+    }
+}
@@ -0,0 +1,325 @@
+//! Implementation using tokio UDP sockets
+//!
+//! Each frame (a [`VarHeader`] followed by the serialized body) is sent as exactly
+//! one UDP datagram, with no further framing.
+//!
+//! UDP is connectionless, so the server replies to (and publishes topics to) the
+//! address of the peer that sent the most recent frame. Until the first frame is
+//! received, there is nobody to send to, and sends will fail.
+//!
+//! Received frames larger than the server's receive buffer, or the configured
+//! maximum datagram size, are rejected with a [`WireError::FrameTooLong`] error
+//! sent back to the peer, instead of being truncated. Replies that are too long
+//! to send are reported to the peer the same way, while topic messages that are
+//! too long are only reported to the publisher, as [`UdpWireTxError::FrameTooLong`].
+
+use core::{
+    fmt::Arguments,
+    sync::atomic::{AtomicU32, Ordering},
+};
+use std::{io, net::SocketAddr, sync::Arc};
+
+use crate::{
+    header::{VarHeader, VarKey, VarKeyKind, VarSeq},
+    server::{
+        AsWireRxErrorKind, AsWireTxErrorKind, WireRx, WireRxErrorKind, WireTx, WireTxErrorKind,
+    },
+    standard_icd::{FrameTooLong, LoggingTopic, WireError, ERROR_KEY},
+    Topic,
+};
+use thiserror::Error;
+use tokio::{
+    net::{ToSocketAddrs, UdpSocket},
+    sync::watch,
+};
+
+/// The default maximum datagram size, in bytes
+///
+/// This is the payload size of a UDP datagram that fits in a single Ethernet
+/// frame (1500 byte MTU) without fragmentation, when using IPv4.
+pub const DEFAULT_MAX_DATAGRAM_LEN: usize = 1472;
+
+/// The largest possible UDP payload
+const MAX_UDP_PAYLOAD: usize = 65535;
+
+//////////////////////////////////////////////////////////////////////////////
+// DISPATCH IMPL
+//////////////////////////////////////////////////////////////////////////////
+
+/// A collection of types and aliases useful for importing the correct types
+pub mod dispatch_impl {
+    use crate::{
+        header::VarKeyKind,
+        server::{Dispatch, Server},
+    };
+
+    pub use crate::server::impls::tokio_shared::tokio_spawn as spawn_fn;
+
+    /// The settings necessary for creating a new UDP server
+    pub struct Settings {
+        /// The frame sender
+        pub tx: WireTxImpl,
+        /// The frame receiver
+        pub rx: WireRxImpl,
+        /// The size of the receive buffer
+        pub buf: usize,
+        /// The sender key size to use
+        pub kkind: VarKeyKind,
+    }
+
+    /// Type alias for `WireTx` impl
+    pub type WireTxImpl = super::UdpWireTx;
+    /// Type alias for `WireRx` impl
+    pub type WireRxImpl = super::UdpWireRx;
+    /// Type alias for `WireSpawn` impl
+    pub type WireSpawnImpl = crate::server::impls::tokio_shared::TokioWireSpawn;
+    /// Type alias for the receive buffer
+    pub type WireRxBuf = Box<[u8]>;
+
+    /// Create a new server using the [`Settings`] and [`Dispatch`] implementation
+    pub fn new_server<D>(
+        dispatch: D,
+        settings: Settings,
+    ) -> crate::server::Server<WireTxImpl, WireRxImpl, WireRxBuf, D>
+    where
+        D: Dispatch<Tx = WireTxImpl>,
+    {
+        let buf = vec![0; settings.buf];
+        Server::new(
+            settings.tx,
+            settings.rx,
+            buf.into_boxed_slice(),
+            dispatch,
+            settings.kkind,
+        )
+    }
+}
+
+pub use super::tokio_shared::tokio_spawn;
+pub use super::tokio_shared::TokioWireSpawn as UdpWireSpawn;
+
+//////////////////////////////////////////////////////////////////////////////
+// CONSTRUCTORS
+//////////////////////////////////////////////////////////////////////////////
+
+/// Create a new [`UdpWireTx`] and [`UdpWireRx`] pair from a bound [`UdpSocket`]
+///
+/// `max_datagram_len` is the largest frame that will be sent or received.
+pub fn new_from_socket(socket: UdpSocket, max_datagram_len: usize) -> (UdpWireTx, UdpWireRx) {
+    let socket = Arc::new(socket);
+    let (peer_tx, peer_rx) = watch::channel(None);
+    let tx = UdpWireTx {
+        socket: socket.clone(),
+        peer: peer_rx,
+        max_len: max_datagram_len,
+        log_ctr: Arc::new(AtomicU32::new(0)),
+    };
+    let rx = UdpWireRx {
+        socket,
+        peer: peer_tx,
+        max_len: max_datagram_len,
+        buf: vec![0u8; MAX_UDP_PAYLOAD].into_boxed_slice(),
+    };
+    (tx, rx)
+}
+
+/// Bind a [`UdpSocket`] to the given address, and create a new [`UdpWireTx`] and
+/// [`UdpWireRx`] pair from it, using [`DEFAULT_MAX_DATAGRAM_LEN`]
+pub async fn bind(addr: impl ToSocketAddrs) -> io::Result<(UdpWireTx, UdpWireRx)> {
+    let socket = UdpSocket::bind(addr).await?;
+    Ok(new_from_socket(socket, DEFAULT_MAX_DATAGRAM_LEN))
+}
+
+//////////////////////////////////////////////////////////////////////////////
+// TX
+//////////////////////////////////////////////////////////////////////////////
+
+/// A [`WireTx`] impl using a tokio UDP socket
+#[derive(Clone)]
+pub struct UdpWireTx {
+    socket: Arc<UdpSocket>,
+    peer: watch::Receiver<Option<SocketAddr>>,
+    max_len: usize,
+    log_ctr: Arc<AtomicU32>,
+}
+
+impl UdpWireTx {
+    /// The address of the peer that sent the most recent frame, if any
+    pub fn peer(&self) -> Option<SocketAddr> {
+        *self.peer.borrow()
+    }
+
+    async fn inner_send(&self, msg: &[u8]) -> Result<(), UdpWireTxError> {
+        let peer = self.peer().ok_or(UdpWireTxError::NoPeer)?;
+        if msg.len() > self.max_len {
+            // Replies that are too long are reported to the peer by the `Sender`,
+            // which knows whether the frame is a reply or a topic message
+            return Err(UdpWireTxError::FrameTooLong);
+        }
+        self.socket.send_to(msg, peer).await?;
+        Ok(())
+    }
+
+    fn log_header(&self, kkind: VarKeyKind) -> VarHeader {
+        let ctr = self.log_ctr.fetch_add(1, Ordering::Relaxed);
+        let key = match kkind {
+            VarKeyKind::Key1 => VarKey::Key1(LoggingTopic::TOPIC_KEY1),
+            VarKeyKind::Key2 => VarKey::Key2(LoggingTopic::TOPIC_KEY2),
+            VarKeyKind::Key4 => VarKey::Key4(LoggingTopic::TOPIC_KEY4),
+            VarKeyKind::Key8 => VarKey::Key8(LoggingTopic::TOPIC_KEY),
+        };
+        VarHeader {
+            key,
+            seq_no: VarSeq::Seq4(ctr),
+        }
+    }
+}
+
+impl WireTx for UdpWireTx {
+    type Error = UdpWireTxError;
+
+    async fn send<T: serde::Serialize + ?Sized>(
+        &self,
+        hdr: VarHeader,
+        msg: &T,
+    ) -> Result<(), Self::Error> {
+        let mut buf = hdr.write_to_vec();
+        postcard::to_io(msg, &mut buf).map_err(|_| UdpWireTxError::Serialize)?;
+        self.inner_send(&buf).await
+    }
+
+    async fn send_raw(&self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.inner_send(buf).await
+    }
+
+    async fn send_log_str(&self, kkind: VarKeyKind, s: &str) -> Result<(), Self::Error> {
+        let wh = self.log_header(kkind);
+        self.send::<str>(wh, s).await
+    }
+
+    async fn send_log_fmt<'a>(
+        &self,
+        kkind: VarKeyKind,
+        a: Arguments<'a>,
+    ) -> Result<(), Self::Error> {
+        let wh = self.log_header(kkind);
+        let msg = format!("{a}");
+        self.send::<<LoggingTopic as Topic>::Message>(wh, &msg)
+            .await
+    }
+
+    fn max_frame_len(&self) -> Option<usize> {
+        Some(self.max_len)
+    }
+}
+
+/// A wire tx error
+#[derive(Debug, Error)]
+pub enum UdpWireTxError {
+    /// No frame has been received yet, so there is nobody to send to
+    #[error("no peer")]
+    NoPeer,
+    /// The frame is larger than the maximum datagram size
+    #[error("frame too long")]
+    FrameTooLong,
+    /// Sending the datagram failed
+    #[error("transfer error on send")]
+    Transfer(#[from] io::Error),
+    /// The message could not be serialized
+    #[error("serialization failed")]
+    Serialize,
+}
+
+impl AsWireTxErrorKind for UdpWireTxError {
+    fn as_kind(&self) -> WireTxErrorKind {
+        // None of these are fatal, the next peer might be reachable
+        match self {
+            UdpWireTxError::FrameTooLong => WireTxErrorKind::MessageTooLarge,
+            _ => WireTxErrorKind::Other,
+        }
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+// RX
+//////////////////////////////////////////////////////////////////////////////
+
+/// A [`WireRx`] impl using a tokio UDP socket
+pub struct UdpWireRx {
+    socket: Arc<UdpSocket>,
+    peer: watch::Sender<Option<SocketAddr>>,
+    max_len: usize,
+    buf: Box<[u8]>,
+}
+
+impl WireRx for UdpWireRx {
+    type Error = UdpWireRxError;
+
+    async fn receive<'a>(&mut self, buf: &'a mut [u8]) -> Result<&'a mut [u8], Self::Error> {
+        loop {
+            let (used, addr) = match self.socket.recv_from(&mut self.buf).await {
+                Ok(r) => r,
+                // Reported for an earlier send on some platforms, not fatal
+                Err(e) if e.kind() == io::ErrorKind::ConnectionReset => continue,
+                Err(e) => return Err(UdpWireRxError::Transfer(e)),
+            };
+            self.peer.send_if_modified(|peer| {
+                let changed = *peer != Some(addr);
+                *peer = Some(addr);
+                changed
+            });
+
+            let max = self.max_len.min(buf.len());
+            if used > max {
+                if let Some(err) = too_long_frame(&self.buf[..used], max) {
+                    let _ = self.socket.send_to(&err, addr).await;
+                }
+                return Err(UdpWireRxError::MessageTooLarge);
+            }
+            let out = &mut buf[..used];
+            out.copy_from_slice(&self.buf[..used]);
+            return Ok(out);
+        }
+    }
+}
+
+/// A wire rx error
+#[derive(Debug, Error)]
+pub enum UdpWireRxError {
+    /// Receiving from the socket failed
+    #[error("transfer error on recv")]
+    Transfer(#[source] io::Error),
+    /// The peer sent a message too large for the receive buffer
+    #[error("message too large")]
+    MessageTooLarge,
+}
+
+impl AsWireRxErrorKind for UdpWireRxError {
+    fn as_kind(&self) -> WireRxErrorKind {
+        match self {
+            UdpWireRxError::Transfer(_) => WireRxErrorKind::ConnectionClosed,
+            UdpWireRxError::MessageTooLarge => WireRxErrorKind::ReceivedMessageTooLarge,
+        }
+    }
+}
+
+/// Create a [`WireError::FrameTooLong`] error frame in response to the given frame
+///
+/// Returns `None` if the frame doesn't have a valid header.
+fn too_long_frame(frame: &[u8], max: usize) -> Option<Vec<u8>> {
+    let (hdr, _) = VarHeader::take_from_slice(frame)?;
+    // Use the same key size as the frame we are responding to
+    let mut key = VarKey::Key8(ERROR_KEY);
+    key.shrink_to(hdr.key.kind());
+    let mut msg = VarHeader {
+        key,
+        seq_no: hdr.seq_no,
+    }
+    .write_to_vec();
+    let err = WireError::FrameTooLong(FrameTooLong {
+        len: frame.len() as u32,
+        max: max as u32,
+    });
+    postcard::to_io(&err, &mut msg).ok()?;
+    Some(msg)
+}
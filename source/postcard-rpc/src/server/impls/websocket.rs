@@ -0,0 +1,396 @@
+//! Implementation using tokio WebSockets
+//!
+//! Each frame (a [`VarHeader`] followed by the serialized body) is sent as exactly
+//! one binary WebSocket message, with no further framing. Text messages from the
+//! client are ignored.
+//!
+//! When created from a listener, the server handles one client at a time. Once the
+//! client disconnects, [`Server::run()`][crate::server::Server::run] returns, and
+//! calling it again will wait for the next client to connect.
+
+use core::{
+    fmt::Arguments,
+    pin::Pin,
+    sync::atomic::{AtomicU32, Ordering},
+};
+use std::{io, sync::Arc, time::Duration};
+
+use crate::{
+    header::{VarHeader, VarKey, VarKeyKind, VarSeq},
+    server::{
+        AsWireRxErrorKind, AsWireTxErrorKind, WireRx, WireRxErrorKind, WireTx, WireTxErrorKind,
+    },
+    standard_icd::LoggingTopic,
+    Topic,
+};
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use thiserror::Error;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, ToSocketAddrs},
+    sync::{watch, Mutex},
+};
+use tokio_tungstenite::{
+    tungstenite::{self, Message},
+    WebSocketStream,
+};
+
+/// How long to wait before retrying after a failed `accept()`
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// How long a client may take to complete the WebSocket handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+type BoxedSink = Pin<Box<dyn Sink<Message, Error = tungstenite::Error> + Send>>;
+type BoxedStream = Pin<Box<dyn Stream<Item = Result<Message, tungstenite::Error>> + Send>>;
+
+//////////////////////////////////////////////////////////////////////////////
+// DISPATCH IMPL
+//////////////////////////////////////////////////////////////////////////////
+
+/// A collection of types and aliases useful for importing the correct types
+pub mod dispatch_impl {
+    use crate::{
+        header::VarKeyKind,
+        server::{Dispatch, Server},
+    };
+
+    pub use crate::server::impls::tokio_shared::tokio_spawn as spawn_fn;
+
+    /// The settings necessary for creating a new WebSocket server
+    pub struct Settings {
+        /// The frame sender
+        pub tx: WireTxImpl,
+        /// The frame receiver
+        pub rx: WireRxImpl,
+        /// The size of the receive buffer
+        pub buf: usize,
+        /// The sender key size to use
+        pub kkind: VarKeyKind,
+    }
+
+    /// Type alias for `WireTx` impl
+    pub type WireTxImpl = super::WsWireTx;
+    /// Type alias for `WireRx` impl
+    pub type WireRxImpl = super::WsWireRx;
+    /// Type alias for `WireSpawn` impl
+    pub type WireSpawnImpl = crate::server::impls::tokio_shared::TokioWireSpawn;
+    /// Type alias for the receive buffer
+    pub type WireRxBuf = Box<[u8]>;
+
+    /// Create a new server using the [`Settings`] and [`Dispatch`] implementation
+    pub fn new_server<D>(
+        dispatch: D,
+        settings: Settings,
+    ) -> crate::server::Server<WireTxImpl, WireRxImpl, WireRxBuf, D>
+    where
+        D: Dispatch<Tx = WireTxImpl>,
+    {
+        let buf = vec![0; settings.buf];
+        Server::new(
+            settings.tx,
+            settings.rx,
+            buf.into_boxed_slice(),
+            dispatch,
+            settings.kkind,
+        )
+    }
+}
+
+pub use super::tokio_shared::tokio_spawn;
+pub use super::tokio_shared::TokioWireSpawn as WsWireSpawn;
+
+//////////////////////////////////////////////////////////////////////////////
+// CONSTRUCTORS
+//////////////////////////////////////////////////////////////////////////////
+
+/// Create a new [`WsWireTx`] and [`WsWireRx`] pair from a single, already
+/// established, [`WebSocketStream`]
+///
+/// Once the connection is closed, the pair will always report that the connection
+/// is closed.
+pub fn new_from_websocket<S>(ws: WebSocketStream<S>) -> (WsWireTx, WsWireRx)
+where
+    S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    let (sink, stream) = ws.split();
+    new_pair(None, Some((Box::pin(sink), Box::pin(stream))))
+}
+
+/// Create a new [`WsWireTx`] and [`WsWireRx`] pair from a [`TcpListener`]
+///
+/// Clients are accepted one at a time, and must complete a WebSocket handshake.
+pub fn new_from_tcp_listener(listener: TcpListener) -> (WsWireTx, WsWireRx) {
+    new_pair(Some(listener), None)
+}
+
+/// Bind a [`TcpListener`] to the given address, and create a new [`WsWireTx`] and
+/// [`WsWireRx`] pair from it
+///
+/// See [`new_from_tcp_listener()`] for more details.
+pub async fn bind(addr: impl ToSocketAddrs) -> io::Result<(WsWireTx, WsWireRx)> {
+    let listener = TcpListener::bind(addr).await?;
+    Ok(new_from_tcp_listener(listener))
+}
+
+fn new_pair(
+    listener: Option<TcpListener>,
+    ws: Option<(BoxedSink, BoxedStream)>,
+) -> (WsWireTx, WsWireRx) {
+    let (sink, stream) = match ws {
+        Some((si, st)) => (Some(si), Some(st)),
+        None => (None, None),
+    };
+    let (connected_tx, connected_rx) = watch::channel(stream.is_some());
+    let sink = Arc::new(Mutex::new(sink));
+    let tx = WsWireTx {
+        sink: sink.clone(),
+        connected: connected_rx,
+        log_ctr: Arc::new(AtomicU32::new(0)),
+    };
+    let rx = WsWireRx {
+        listener,
+        stream,
+        sink,
+        connected: connected_tx,
+    };
+    (tx, rx)
+}
+
+//////////////////////////////////////////////////////////////////////////////
+// TX
+//////////////////////////////////////////////////////////////////////////////
+
+/// A [`WireTx`] impl using a tokio WebSocket
+#[derive(Clone)]
+pub struct WsWireTx {
+    sink: Arc<Mutex<Option<BoxedSink>>>,
+    connected: watch::Receiver<bool>,
+    log_ctr: Arc<AtomicU32>,
+}
+
+impl WsWireTx {
+    async fn inner_send(&self, msg: Vec<u8>) -> Result<(), WsWireTxError> {
+        let mut sink = self.sink.lock().await;
+        let s = sink.as_mut().ok_or(WsWireTxError::ConnectionClosed)?;
+        if let Err(e) = s.send(Message::Binary(msg.into())).await {
+            // The receiver will notice the closed connection separately
+            *sink = None;
+            return Err(WsWireTxError::Transfer(e));
+        }
+        Ok(())
+    }
+
+    fn log_header(&self, kkind: VarKeyKind) -> VarHeader {
+        let ctr = self.log_ctr.fetch_add(1, Ordering::Relaxed);
+        let key = match kkind {
+            VarKeyKind::Key1 => VarKey::Key1(LoggingTopic::TOPIC_KEY1),
+            VarKeyKind::Key2 => VarKey::Key2(LoggingTopic::TOPIC_KEY2),
+            VarKeyKind::Key4 => VarKey::Key4(LoggingTopic::TOPIC_KEY4),
+            VarKeyKind::Key8 => VarKey::Key8(LoggingTopic::TOPIC_KEY),
+        };
+        VarHeader {
+            key,
+            seq_no: VarSeq::Seq4(ctr),
+        }
+    }
+}
+
+impl WireTx for WsWireTx {
+    type Error = WsWireTxError;
+
+    async fn wait_connection(&self) {
+        let mut connected = self.connected.clone();
+        let _ = connected.wait_for(|&c| c).await;
+    }
+
+    async fn send<T: serde::Serialize + ?Sized>(
+        &self,
+        hdr: VarHeader,
+        msg: &T,
+    ) -> Result<(), Self::Error> {
+        let mut buf = hdr.write_to_vec();
+        postcard::to_io(msg, &mut buf).map_err(|_| WsWireTxError::Serialize)?;
+        self.inner_send(buf).await
+    }
+
+    async fn send_raw(&self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.inner_send(buf.to_vec()).await
+    }
+
+    async fn send_log_str(&self, kkind: VarKeyKind, s: &str) -> Result<(), Self::Error> {
+        let wh = self.log_header(kkind);
+        self.send::<str>(wh, s).await
+    }
+
+    async fn send_log_fmt<'a>(
+        &self,
+        kkind: VarKeyKind,
+        a: Arguments<'a>,
+    ) -> Result<(), Self::Error> {
+        let wh = self.log_header(kkind);
+        let msg = format!("{a}");
+        self.send::<<LoggingTopic as Topic>::Message>(wh, &msg)
+            .await
+    }
+}
+
+/// A wire tx error
+#[derive(Debug, Error)]
+pub enum WsWireTxError {
+    /// There is no connected client
+    #[error("connection closed")]
+    ConnectionClosed,
+    /// Sending the message failed
+    #[error("transfer error on send")]
+    Transfer(#[source] tungstenite::Error),
+    /// The message could not be serialized
+    #[error("serialization failed")]
+    Serialize,
+}
+
+impl AsWireTxErrorKind for WsWireTxError {
+    fn as_kind(&self) -> WireTxErrorKind {
+        match self {
+            WsWireTxError::ConnectionClosed => WireTxErrorKind::ConnectionClosed,
+            WsWireTxError::Transfer(_) => WireTxErrorKind::ConnectionClosed,
+            WsWireTxError::Serialize => WireTxErrorKind::Other,
+        }
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+// RX
+//////////////////////////////////////////////////////////////////////////////
+
+/// A [`WireRx`] impl using a tokio WebSocket
+pub struct WsWireRx {
+    listener: Option<TcpListener>,
+    stream: Option<BoxedStream>,
+    sink: Arc<Mutex<Option<BoxedSink>>>,
+    connected: watch::Sender<bool>,
+}
+
+impl WsWireRx {
+    async fn install(&mut self, sink: BoxedSink, stream: BoxedStream) {
+        *self.sink.lock().await = Some(sink);
+        self.stream = Some(stream);
+        self.connected.send_replace(true);
+    }
+
+    async fn disconnect(&mut self) {
+        // Without a listener there will never be another connection, so leave
+        // `connected` as-is to avoid the Server waiting forever, the missing
+        // sink will cause any sends to fail.
+        if self.listener.is_some() {
+            self.connected.send_replace(false);
+        }
+        self.stream = None;
+        *self.sink.lock().await = None;
+    }
+
+    async fn accept(listener: &TcpListener) -> Result<(BoxedSink, BoxedStream), WsAcceptError> {
+        let (stream, addr) = listener.accept().await?;
+        if let Err(e) = stream.set_nodelay(true) {
+            tracing::warn!("Failed to set TCP_NODELAY: {e:?}");
+        }
+        let ws = tokio::time::timeout(HANDSHAKE_TIMEOUT, tokio_tungstenite::accept_async(stream))
+            .await
+            .map_err(|_| WsAcceptError::HandshakeTimeout)??;
+        tracing::info!("Accepted WebSocket client from {addr}");
+        let (sink, stream) = ws.split();
+        Ok((Box::pin(sink), Box::pin(stream)))
+    }
+}
+
+#[derive(Debug, Error)]
+enum WsAcceptError {
+    #[error("accept failed")]
+    Accept(#[from] io::Error),
+    #[error("handshake failed")]
+    Handshake(#[from] tungstenite::Error),
+    #[error("handshake timed out")]
+    HandshakeTimeout,
+}
+
+impl WireRx for WsWireRx {
+    type Error = WsWireRxError;
+
+    async fn wait_connection(&mut self) {
+        let Some(listener) = self.listener.as_ref() else {
+            return;
+        };
+        if self.stream.is_some() {
+            return;
+        }
+        loop {
+            match Self::accept(listener).await {
+                Ok((sink, stream)) => {
+                    self.install(sink, stream).await;
+                    return;
+                }
+                Err(WsAcceptError::Accept(e)) => {
+                    tracing::warn!("Failed to accept client: {e:?}");
+                    tokio::time::sleep(ACCEPT_RETRY_DELAY).await;
+                }
+                // Only this client is at fault, try the next one right away
+                Err(e) => tracing::warn!("Failed to accept client: {e:?}"),
+            }
+        }
+    }
+
+    async fn receive<'a>(&mut self, buf: &'a mut [u8]) -> Result<&'a mut [u8], Self::Error> {
+        loop {
+            let stream = self
+                .stream
+                .as_mut()
+                .ok_or(WsWireRxError::ConnectionClosed)?;
+            let frame = match stream.next().await {
+                Some(Ok(Message::Binary(frame))) => frame,
+                Some(Ok(Message::Text(_))) => {
+                    tracing::warn!("Ignoring WebSocket text message");
+                    continue;
+                }
+                // Pings are answered by tungstenite
+                Some(Ok(Message::Ping(_) | Message::Pong(_) | Message::Frame(_))) => continue,
+                Some(Ok(Message::Close(_))) | None => {
+                    self.disconnect().await;
+                    return Err(WsWireRxError::ConnectionClosed);
+                }
+                Some(Err(e)) => {
+                    self.disconnect().await;
+                    return Err(WsWireRxError::Transfer(e));
+                }
+            };
+            let out = buf
+                .get_mut(..frame.len())
+                .ok_or(WsWireRxError::MessageTooLarge)?;
+            out.copy_from_slice(&frame);
+            return Ok(out);
+        }
+    }
+}
+
+/// A wire rx error
+#[derive(Debug, Error)]
+pub enum WsWireRxError {
+    /// There is no connected client
+    #[error("connection closed")]
+    ConnectionClosed,
+    /// Receiving from the WebSocket failed
+    #[error("transfer error on recv")]
+    Transfer(#[source] tungstenite::Error),
+    /// The client sent a message too large for the receive buffer
+    #[error("message too large")]
+    MessageTooLarge,
+}
+
+impl AsWireRxErrorKind for WsWireRxError {
+    fn as_kind(&self) -> WireRxErrorKind {
+        match self {
+            WsWireRxError::ConnectionClosed => WireRxErrorKind::ConnectionClosed,
+            WsWireRxError::Transfer(_) => WireRxErrorKind::ConnectionClosed,
+            WsWireRxError::MessageTooLarge => WireRxErrorKind::ReceivedMessageTooLarge,
+        }
+    }
+}
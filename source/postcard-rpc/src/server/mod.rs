@@ -13,6 +13,8 @@
 //!
 //! * A no-std impl using embassy and embassy-usb to provide transport over USB
 //! * A std impl using Tokio channels to provide transport for testing
+//! * A std impl using Tokio TCP or Unix domain sockets to provide transport over a network
+//! * A std impl using Tokio WebSockets to provide transport to native or browser clients
 //!
 //! Impls are expected to implement three traits:
 //!
@@ -25,13 +27,27 @@
 #[doc(hidden)]
 pub mod dispatch_macro;
 
+pub mod bulk;
+mod cancel;
+pub mod credits;
 pub mod impls;
+mod slots;
+pub mod topic_filter;
 
-use core::{fmt::Arguments, ops::DerefMut};
+pub use self::cancel::{CancelToken, Cancellations};
+pub use self::credits::Credits;
+pub use self::topic_filter::TopicFilter;
+
+use core::{fmt::Arguments, marker::PhantomData, ops::DerefMut};
 
 use crate::{
-    header::{VarHeader, VarKey, VarKeyKind, VarSeq},
-    DeviceMap, Key, TopicDirection,
+    fragment::{Reassembler, ReassemblyError},
+    header::{Fragment, VarHeader, VarKey, VarKeyKind, VarSeq},
+    standard_icd::{
+        Capabilities, FrameTooLong, SchemaPage, StreamEndTopic, TopicControlError,
+        TopicControlResult, WireError,
+    },
+    DeviceMap, Key, StreamEndpoint, TopicDirection,
 };
 use postcard_schema::Schema;
 use serde::Serialize;
@@ -74,6 +90,14 @@ pub trait WireTx {
         kkind: VarKeyKind,
         a: Arguments<'a>,
     ) -> Result<(), Self::Error>;
+
+    /// The longest frame that can be sent, including the header
+    ///
+    /// Returns `None` if unknown, which is the default. This is reported to the
+    /// client by the [`CapabilitiesEndpoint`][crate::standard_icd::CapabilitiesEndpoint].
+    fn max_frame_len(&self) -> Option<usize> {
+        None
+    }
 }
 
 /// The base [`WireTx`] Error Kind
@@ -92,6 +116,9 @@ pub enum WireTxErrorKind {
     /// Timeout (WireTx impl specific) reached
     #[error("timeout reached")]
     Timeout,
+    /// The message was too large for the connection to send
+    #[error("the message was too large for the connection to send")]
+    MessageTooLarge,
 }
 
 /// A conversion trait to convert a user error into a base Kind type
@@ -194,6 +221,11 @@ pub trait WireSpawn: Clone {
 pub struct Sender<Tx: WireTx> {
     tx: Tx,
     kkind: VarKeyKind,
+    credits: Option<&'static Credits>,
+    topics: Option<&'static TopicFilter>,
+    cancellations: Option<&'static Cancellations>,
+    max_request_len: Option<usize>,
+    max_reassembly_len: Option<usize>,
 }
 
 impl<Tx: WireTx> Sender<Tx> {
@@ -204,10 +236,124 @@ impl<Tx: WireTx> Sender<Tx> {
     ///
     /// `kkind` should usually come from [`Dispatch::min_key_len()`].
     pub fn new(tx: Tx, kkind: VarKeyKind) -> Self {
-        Self { tx, kkind }
+        Self {
+            tx,
+            kkind,
+            credits: None,
+            topics: None,
+            cancellations: None,
+            max_request_len: None,
+            max_reassembly_len: None,
+        }
+    }
+
+    /// Limit published topics by the credits granted by the client
+    ///
+    /// See the [`credits` module](crate::server::credits) for details.
+    pub fn with_credits(mut self, credits: &'static Credits) -> Self {
+        self.credits = Some(credits);
+        self
+    }
+
+    /// The credits used to limit published topics, if any
+    pub fn credits(&self) -> Option<&'static Credits> {
+        self.credits
+    }
+
+    /// Skip publishing topics the client has not enabled
+    ///
+    /// See the [`topic_filter` module](crate::server::topic_filter) for details.
+    pub fn with_topic_filter(mut self, topics: &'static TopicFilter) -> Self {
+        self.topics = Some(topics);
+        self
+    }
+
+    /// The filter used to skip published topics, if any
+    pub fn topic_filter(&self) -> Option<&'static TopicFilter> {
+        self.topics
+    }
+
+    /// Let the client cancel in-flight requests
+    ///
+    /// See [`Cancellations`] for details.
+    pub fn with_cancellations(mut self, cancellations: &'static Cancellations) -> Self {
+        self.cancellations = Some(cancellations);
+        self
+    }
+
+    /// The requests the client may cancel, if any
+    pub fn cancellations(&self) -> Option<&'static Cancellations> {
+        self.cancellations
+    }
+
+    /// Obtain a [`CancelToken`] for a request with the given sequence number
+    ///
+    /// Without [`Cancellations`], the token is never cancelled.
+    pub fn cancel_token(&self, seq_no: VarSeq) -> CancelToken {
+        match self.cancellations {
+            Some(c) => c.token(seq_no.into()),
+            None => CancelToken::never(),
+        }
+    }
+
+    /// Implements the [`CapabilitiesEndpoint`][crate::standard_icd::CapabilitiesEndpoint]
+    ///
+    /// The receive limits are filled in by the [`Server`] this sender belongs to.
+    /// The dispatcher leaves out the features that are not part of its device map.
+    pub fn capabilities(&self, min_key_len: VarKeyKind) -> Capabilities {
+        let to_u32 = |len: usize| u32::try_from(len).unwrap_or(u32::MAX);
+        let mut features = 0;
+        if self.cancellations.is_some() {
+            features |= Capabilities::CANCEL;
+        }
+        if self.topics.is_some() {
+            features |= Capabilities::TOPIC_FILTER;
+        }
+        if self.credits.is_some() {
+            features |= Capabilities::CREDITS;
+        }
+        Capabilities {
+            protocol_version: Capabilities::PROTOCOL_VERSION,
+            min_key_len: match min_key_len {
+                VarKeyKind::Key1 => 1,
+                VarKeyKind::Key2 => 2,
+                VarKeyKind::Key4 => 4,
+                VarKeyKind::Key8 => 8,
+            },
+            // Any size is accepted, and used for the reply
+            seq_sizes: 1 | 2 | 4,
+            max_request_len: self.max_request_len.map(to_u32),
+            max_response_len: self.tx.max_frame_len().map(to_u32),
+            max_reassembly_len: self.max_reassembly_len.map(to_u32),
+            features,
+        }
+    }
+
+    /// Implements the [`TopicEnableEndpoint`][crate::standard_icd::TopicEnableEndpoint]
+    /// and [`TopicDisableEndpoint`][crate::standard_icd::TopicDisableEndpoint] endpoints
+    pub fn set_topic_enabled(
+        &self,
+        device_map: &DeviceMap,
+        key: Key,
+        enabled: bool,
+        max_rate: Option<u32>,
+    ) -> TopicControlResult {
+        if !device_map.topics_out.iter().any(|(_, k)| *k == key) {
+            return Err(TopicControlError::UnknownTopic);
+        }
+        match (self.topics, enabled, max_rate) {
+            (Some(topics), _, _) => topics.set(key, enabled, max_rate),
+            // Without a filter, all topics are always enabled
+            (None, true, None) => Ok(()),
+            (None, true, Some(_)) => Err(TopicControlError::RateUnsupported),
+            (None, false, _) => Err(TopicControlError::Unsupported),
+        }
     }
 
     /// Send a reply for the given endpoint
+    ///
+    /// If the reply is too large for the connection to send, the client is sent a
+    /// [`WireError::FrameTooLong`] error instead, so it isn't left waiting.
     #[inline]
     pub async fn reply<E>(&self, seq_no: VarSeq, resp: &E::Response) -> Result<(), Tx::Error>
     where
@@ -217,7 +363,20 @@ impl<Tx: WireTx> Sender<Tx> {
         let mut key = VarKey::Key8(E::RESP_KEY);
         key.shrink_to(self.kkind);
         let wh = VarHeader { key, seq_no };
-        self.tx.send::<E::Response>(wh, resp).await
+        let res = self.tx.send::<E::Response>(wh, resp).await;
+        if let Err(e) = &res {
+            if let (WireTxErrorKind::MessageTooLarge, Some(max)) =
+                (e.as_kind(), self.tx.max_frame_len())
+            {
+                let body =
+                    postcard::serialize_with_flavor(resp, postcard::ser_flavors::Size::default())
+                        .unwrap_or(0);
+                let _ = self
+                    .frame_too_long(seq_no, wh.encoded_len() + body, max)
+                    .await;
+            }
+        }
+        res
     }
 
     /// Send a reply with the given Key
@@ -237,6 +396,12 @@ impl<Tx: WireTx> Sender<Tx> {
     }
 
     /// Publish a Topic message
+    ///
+    /// Messages of topics the client has disabled, or limited to a lower rate, are
+    /// skipped. If the client limited the topic with credits, this waits until a
+    /// credit is available. This should not be used for limited topics from `blocking` or
+    /// `async` handlers, as credits are granted by the client through the server,
+    /// which waits for the handler. Use [`Sender::try_publish()`] there instead.
     #[inline]
     pub async fn publish<T>(&self, seq_no: VarSeq, msg: &T::Message) -> Result<(), Tx::Error>
     where
@@ -244,12 +409,50 @@ impl<Tx: WireTx> Sender<Tx> {
         T: crate::Topic,
         T::Message: Serialize + Schema,
     {
+        if self.topics.is_some_and(|t| !t.check(T::TOPIC_KEY)) {
+            return Ok(());
+        }
+        if let Some(credits) = self.credits {
+            credits.take(T::TOPIC_KEY).await;
+        }
         let mut key = VarKey::Key8(T::TOPIC_KEY);
         key.shrink_to(self.kkind);
         let wh = VarHeader { key, seq_no };
         self.tx.send::<T::Message>(wh, msg).await
     }
 
+    /// Publish a Topic message, unless the client has not granted a credit for it
+    ///
+    /// Messages that are not sent for lack of credits are counted, see
+    /// [`Credits::dropped()`]. Messages of disabled topics are skipped, like
+    /// with [`Sender::publish()`].
+    pub async fn try_publish<T>(
+        &self,
+        seq_no: VarSeq,
+        msg: &T::Message,
+    ) -> Result<(), PublishError<Tx::Error>>
+    where
+        T: ?Sized,
+        T: crate::Topic,
+        T::Message: Serialize + Schema,
+    {
+        if self.topics.is_some_and(|t| !t.check(T::TOPIC_KEY)) {
+            return Ok(());
+        }
+        if let Some(credits) = self.credits {
+            if !credits.try_take(T::TOPIC_KEY) {
+                return Err(PublishError::NoCredits);
+            }
+        }
+        let mut key = VarKey::Key8(T::TOPIC_KEY);
+        key.shrink_to(self.kkind);
+        let wh = VarHeader { key, seq_no };
+        self.tx
+            .send::<T::Message>(wh, msg)
+            .await
+            .map_err(PublishError::Tx)
+    }
+
     /// Log a `str` directly to the [`LoggingTopic`][crate::standard_icd::LoggingTopic]
     #[inline]
     pub async fn log_str(&self, msg: &str) -> Result<(), Tx::Error> {
@@ -272,83 +475,121 @@ impl<Tx: WireTx> Sender<Tx> {
             .await
     }
 
+    /// Send a [`WireError::FrameTooLong`] error
+    async fn frame_too_long(
+        &self,
+        seq_no: VarSeq,
+        len: usize,
+        max: usize,
+    ) -> Result<(), Tx::Error> {
+        let err = WireError::FrameTooLong(FrameTooLong {
+            len: u32::try_from(len).unwrap_or(u32::MAX),
+            max: u32::try_from(max).unwrap_or(u32::MAX),
+        });
+        self.error(seq_no, err).await
+    }
+
+    /// Send a reply for the given endpoint, which may be larger than a single frame
+    ///
+    /// See [`Sender::send_fragmented()`].
+    pub async fn reply_fragmented<E>(
+        &self,
+        seq_no: VarSeq,
+        resp: &E::Response,
+        scratch: &mut [u8],
+        frag_size: usize,
+    ) -> Result<(), FragmentedSendError<Tx::Error>>
+    where
+        E: crate::Endpoint,
+        E::Response: Serialize + Schema,
+    {
+        let mut key = VarKey::Key8(E::RESP_KEY);
+        key.shrink_to(self.kkind);
+        let wh = VarHeader { key, seq_no };
+        self.send_fragmented(wh, resp, scratch, frag_size).await
+    }
+
+    /// Send a message that may be larger than a single frame
+    ///
+    /// The message is serialized to `scratch`, and sent in fragments of at most
+    /// `frag_size` bytes, see the [`fragment` module](crate::fragment). Messages that
+    /// fit in `frag_size` are sent as a single, unfragmented, frame.
+    ///
+    /// Fragments of different messages must not be sent at the same time, as the client
+    /// would be unable to reassemble them.
+    pub async fn send_fragmented<T>(
+        &self,
+        hdr: VarHeader,
+        msg: &T,
+        scratch: &mut [u8],
+        frag_size: usize,
+    ) -> Result<(), FragmentedSendError<Tx::Error>>
+    where
+        T: Serialize + ?Sized,
+    {
+        // Leave room for a header in front of the body. The header of each fragment
+        // is written directly in front of its part of the body, overwriting the end
+        // of the previous fragment, which has already been sent.
+        let ext_len = hdr.encoded_len() + 2;
+        let body_buf = scratch
+            .get_mut(ext_len..)
+            .ok_or(FragmentedSendError::TooLarge)?;
+        let body_len = postcard::to_slice(msg, body_buf)
+            .map_err(|_| FragmentedSendError::TooLarge)?
+            .len();
+
+        let plain_len = hdr.encoded_len();
+        if plain_len + body_len <= frag_size {
+            let start = ext_len - plain_len;
+            hdr.write_to_slice(&mut scratch[start..])
+                .ok_or(FragmentedSendError::TooLarge)?;
+            let frame = &scratch[start..ext_len + body_len];
+            return self
+                .tx
+                .send_raw(frame)
+                .await
+                .map_err(FragmentedSendError::Tx);
+        }
+
+        let per = crate::fragment::fragment_body_len(&hdr, frag_size)
+            .ok_or(FragmentedSendError::TooLarge)?;
+        let count = body_len.div_ceil(per);
+        if count > usize::from(Fragment::MAX_INDEX) + 1 {
+            return Err(FragmentedSendError::TooLarge);
+        }
+        for i in 0..count {
+            let start = ext_len + i * per;
+            let end = (start + per).min(ext_len + body_len);
+            let frag = Fragment {
+                index: i as u16,
+                last: i + 1 == count,
+            };
+            hdr.write_fragment_to_slice(frag, &mut scratch[start - ext_len..])
+                .ok_or(FragmentedSendError::TooLarge)?;
+            let frame = &scratch[start - ext_len..end];
+            self.tx
+                .send_raw(frame)
+                .await
+                .map_err(FragmentedSendError::Tx)?;
+        }
+        Ok(())
+    }
+
     /// Implements the [`GetAllSchemasEndpoint`][crate::standard_icd::GetAllSchemasEndpoint] endpoint
     pub async fn send_all_schemas(
         &self,
         hdr: &VarHeader,
         device_map: &DeviceMap,
     ) -> Result<(), Tx::Error> {
-        #[cfg(feature = "use-std")]
-        use crate::standard_icd::OwnedSchemaData as SchemaData;
-        #[cfg(not(feature = "use-std"))]
-        use crate::standard_icd::SchemaData;
         use crate::standard_icd::{GetAllSchemaDataTopic, GetAllSchemasEndpoint, SchemaTotals};
 
-        let mut msg_ctr = 0;
         let mut err_ctr = 0;
 
-        // First, send all types
-        for ty in device_map.types {
-            let res = self
-                .publish::<GetAllSchemaDataTopic>(
-                    VarSeq::Seq2(msg_ctr),
-                    &SchemaData::Type((*ty).into()),
-                )
-                .await;
-            if res.is_err() {
-                err_ctr += 1;
-            };
-            msg_ctr += 1;
-        }
-
-        // Then all endpoints
-        for ep in device_map.endpoints {
-            let res = self
-                .publish::<GetAllSchemaDataTopic>(
-                    VarSeq::Seq2(msg_ctr),
-                    &SchemaData::Endpoint {
-                        path: ep.0.into(),
-                        request_key: ep.1,
-                        response_key: ep.2,
-                    },
-                )
-                .await;
-            if res.is_err() {
-                err_ctr += 1;
-            }
-
-            msg_ctr += 1;
-        }
-
-        // Then output topics
-        for to in device_map.topics_out {
-            let res = self
-                .publish::<GetAllSchemaDataTopic>(
-                    VarSeq::Seq2(msg_ctr),
-                    &SchemaData::Topic {
-                        direction: TopicDirection::ToClient,
-                        path: to.0.into(),
-                        key: to.1,
-                    },
-                )
-                .await;
-            if res.is_err() {
-                err_ctr += 1;
-            }
-            msg_ctr += 1;
-        }
-
-        // Then input topics
-        for ti in device_map.topics_in {
+        // Send all items, in the order of their indices
+        let mut msg_ctr = 0;
+        while let Some(data) = schema_data(device_map, msg_ctr as usize) {
             let res = self
-                .publish::<GetAllSchemaDataTopic>(
-                    VarSeq::Seq2(msg_ctr),
-                    &SchemaData::Topic {
-                        direction: TopicDirection::ToServer,
-                        path: ti.0.into(),
-                        key: ti.1,
-                    },
-                )
+                .publish_standard::<GetAllSchemaDataTopic>(VarSeq::Seq2(msg_ctr), &data)
                 .await;
             if res.is_err() {
                 err_ctr += 1;
@@ -371,6 +612,169 @@ impl<Tx: WireTx> Sender<Tx> {
 
         Ok(())
     }
+
+    /// Implements the [`GetSchemaPageEndpoint`][crate::standard_icd::GetSchemaPageEndpoint] endpoint
+    ///
+    /// The endpoint is only part of the device map of dispatchers that add
+    /// `schema_pages` to their `standard` parts.
+    pub async fn send_schema_page(
+        &self,
+        hdr: &VarHeader,
+        device_map: &DeviceMap,
+        page: SchemaPage,
+    ) -> Result<(), Tx::Error> {
+        #[cfg(not(feature = "use-std"))]
+        use crate::standard_icd::IndexedSchemaData;
+        #[cfg(feature = "use-std")]
+        use crate::standard_icd::OwnedIndexedSchemaData as IndexedSchemaData;
+        use crate::standard_icd::{GetSchemaItemTopic, GetSchemaPageEndpoint, SchemaPageInfo};
+
+        let total = device_map.types.len()
+            + device_map.endpoints.len()
+            + device_map.topics_out.len()
+            + device_map.topics_in.len();
+        let mut errors = 0;
+        let end = page.start.saturating_add(page.count);
+        for index in page.start..end {
+            let Some(data) = schema_data(device_map, index as usize) else {
+                break;
+            };
+            let msg = IndexedSchemaData { index, data };
+            let res = self
+                .publish_standard::<GetSchemaItemTopic>(VarSeq::Seq4(index), &msg)
+                .await;
+            if res.is_err() {
+                errors += 1;
+            }
+        }
+
+        // The client knows it has received all items that were sent once it gets this
+        let info = SchemaPageInfo {
+            total: total as u32,
+            errors,
+        };
+        self.reply::<GetSchemaPageEndpoint>(hdr.seq_no, &info).await
+    }
+
+    /// Publish a message of a standard topic, which is never disabled or limited
+    async fn publish_standard<T>(&self, seq_no: VarSeq, msg: &T::Message) -> Result<(), Tx::Error>
+    where
+        T: ?Sized,
+        T: crate::Topic,
+        T::Message: Serialize,
+    {
+        let mut key = VarKey::Key8(T::TOPIC_KEY);
+        key.shrink_to(self.kkind);
+        let wh = VarHeader { key, seq_no };
+        self.tx.send::<T::Message>(wh, msg).await
+    }
+}
+
+#[cfg(feature = "use-std")]
+type SchemaData<'a> = crate::standard_icd::OwnedSchemaData;
+#[cfg(not(feature = "use-std"))]
+type SchemaData<'a> = crate::standard_icd::SchemaData<'a>;
+
+/// The schema item with the given index
+///
+/// These are numbered from zero, first all types, then all endpoints, topics out,
+/// and topics in.
+fn schema_data(device_map: &DeviceMap, index: usize) -> Option<SchemaData<'_>> {
+    let mut index = index;
+    if let Some(ty) = device_map.types.get(index) {
+        return Some(SchemaData::Type((*ty).into()));
+    }
+    index -= device_map.types.len();
+    if let Some(ep) = device_map.endpoints.get(index) {
+        return Some(SchemaData::Endpoint {
+            path: ep.0.into(),
+            request_key: ep.1,
+            response_key: ep.2,
+        });
+    }
+    index -= device_map.endpoints.len();
+    if let Some(to) = device_map.topics_out.get(index) {
+        return Some(SchemaData::Topic {
+            direction: TopicDirection::ToClient,
+            path: to.0.into(),
+            key: to.1,
+        });
+    }
+    index -= device_map.topics_out.len();
+    let ti = device_map.topics_in.get(index)?;
+    Some(SchemaData::Topic {
+        direction: TopicDirection::ToServer,
+        path: ti.0.into(),
+        key: ti.1,
+    })
+}
+
+/// The [`StreamSender`] is used to reply to a request to a [`StreamEndpoint`]
+///
+/// Handlers of the `stream` kind receive one, and may send any number of responses
+/// before ending the stream with [`StreamSender::end()`] or [`StreamSender::error()`].
+/// If neither is called, the client will keep waiting for the end of the stream.
+///
+/// The end of the stream is sent on the [`StreamEndTopic`], which is only part of
+/// the device map of servers that have handlers of the `stream` kind.
+pub struct StreamSender<Tx: WireTx, E: StreamEndpoint> {
+    sender: Sender<Tx>,
+    seq_no: VarSeq,
+    cancel: CancelToken,
+    _pd: PhantomData<fn() -> E>,
+}
+
+impl<Tx: WireTx, E: StreamEndpoint> StreamSender<Tx, E> {
+    /// Create a new StreamSender, replying to the request with the given `seq_no`
+    ///
+    /// This is usually done by [`define_dispatch!()`][crate::define_dispatch].
+    pub fn new(sender: Sender<Tx>, seq_no: VarSeq, cancel: CancelToken) -> Self {
+        Self {
+            sender,
+            seq_no,
+            cancel,
+            _pd: PhantomData,
+        }
+    }
+
+    /// Send the next response of the stream
+    #[inline]
+    pub async fn send(&self, resp: &E::Response) -> Result<(), Tx::Error>
+    where
+        E::Response: Serialize,
+    {
+        self.sender.reply::<E>(self.seq_no, resp).await
+    }
+
+    /// End the stream successfully
+    pub async fn end(self) -> Result<(), Tx::Error> {
+        self.sender
+            .publish_standard::<StreamEndTopic>(self.seq_no, &())
+            .await
+    }
+
+    /// End the stream with an error
+    pub async fn error(self, error: WireError) -> Result<(), Tx::Error> {
+        self.sender.error(self.seq_no, error).await
+    }
+
+    /// Has the client stopped listening to this stream?
+    ///
+    /// When this returns `true`, the handler should stop sending responses. Calling
+    /// [`StreamSender::end()`] is not necessary in that case.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// The sequence number of the request
+    pub fn seq_no(&self) -> VarSeq {
+        self.seq_no
+    }
+
+    /// The underlying [`Sender`], for example for logging or publishing topics
+    pub fn sender(&self) -> &Sender<Tx> {
+        &self.sender
+    }
 }
 
 //////////////////////////////////////////////////////////////////////////////
@@ -389,6 +793,7 @@ where
     rx: Rx,
     buf: Buf,
     dis: D,
+    reassembly: Option<Reassembler<Buf>>,
 }
 
 /// A type representing the different errors [`Server::run()`] may return
@@ -420,6 +825,28 @@ where
     }
 }
 
+/// Errors returned by [`Sender::try_publish()`]
+#[derive(Debug, Error)]
+pub enum PublishError<E: core::error::Error> {
+    /// The client has not granted a credit for the topic
+    #[error("no credits left for the topic")]
+    NoCredits,
+    /// Sending the message failed
+    #[error("sending the message failed")]
+    Tx(#[source] E),
+}
+
+/// Errors returned by [`Sender::send_fragmented()`]
+#[derive(Debug, Error)]
+pub enum FragmentedSendError<E: core::error::Error> {
+    /// The message did not fit the scratch buffer, or needed too many fragments
+    #[error("the message is too large to be sent")]
+    TooLarge,
+    /// Sending a fragment failed
+    #[error("sending a fragment failed")]
+    Tx(#[source] E),
+}
+
 impl<Tx, Rx, Buf, D> Server<Tx, Rx, Buf, D>
 where
     Tx: WireTx,
@@ -437,14 +864,59 @@ where
     /// * The user provided dispatching method, usually generated by [`define_dispatch!()`][crate::define_dispatch]
     /// * a [`VarKeyKind`], which controls the key sizes sent by the [`WireTx`] impl
     pub fn new(tx: Tx, rx: Rx, buf: Buf, dis: D, kkind: VarKeyKind) -> Self {
+        let mut tx = Sender::new(tx, kkind);
+        tx.max_request_len = Some(buf.len());
         Self {
-            tx: Sender { tx, kkind },
+            tx,
             rx,
             buf,
             dis,
+            reassembly: None,
         }
     }
 
+    /// Accept fragmented messages, reassembling them in `buf`
+    ///
+    /// Messages larger than `buf` are rejected with [`WireError::FrameTooLong`], and
+    /// messages with lost fragments with [`WireError::DeserFailed`]. Without a
+    /// reassembly buffer, all fragmented messages are rejected. See the
+    /// [`fragment` module](crate::fragment) for details.
+    pub fn with_reassembly(mut self, buf: Buf) -> Self {
+        self.tx.max_reassembly_len = Some(buf.len());
+        self.reassembly = Some(Reassembler::new(buf));
+        self
+    }
+
+    /// Limit published topics by the credits granted by the client
+    ///
+    /// This applies to the [`Sender`] of the server, and all copies of it made
+    /// afterwards. See the [`credits` module](crate::server::credits) for details.
+    pub fn with_credits(mut self, credits: &'static Credits) -> Self {
+        self.tx.credits = Some(credits);
+        self
+    }
+
+    /// Skip publishing topics the client has not enabled
+    ///
+    /// This applies to the [`Sender`] of the server, and all copies of it made
+    /// afterwards. See the [`topic_filter` module](crate::server::topic_filter)
+    /// for details.
+    pub fn with_topic_filter(mut self, topics: &'static TopicFilter) -> Self {
+        self.tx.topics = Some(topics);
+        self
+    }
+
+    /// Let the client cancel in-flight requests
+    ///
+    /// This applies to the [`Sender`] of the server, and all copies of it made
+    /// afterwards. Each server needs its own [`Cancellations`], so clients can't
+    /// cancel requests of other connections. See
+    /// [`Cancellations`] for details.
+    pub fn with_cancellations(mut self, cancellations: &'static Cancellations) -> Self {
+        self.tx.cancellations = Some(cancellations);
+        self
+    }
+
     /// Run until a fatal error occurs
     ///
     /// The server will receive frames, and dispatch them. When a fatal error occurs,
@@ -452,13 +924,35 @@ where
     ///
     /// The caller may decide to wait until a connection is re-established, reset any
     /// state, or immediately begin re-running.
+    ///
+    /// Credits granted and topics enabled by the client are forgotten when this
+    /// returns, and when it is called again, so that publishers don't wait for
+    /// credits from a client that is gone, and the next client starts out with the
+    /// default topics.
     pub async fn run(&mut self) -> ServerError<Tx, Rx> {
+        self.reset_client_state();
+        let err = self.run_inner().await;
+        self.reset_client_state();
+        err
+    }
+
+    fn reset_client_state(&self) {
+        if let Some(credits) = self.tx.credits {
+            credits.reset();
+        }
+        if let Some(topics) = self.tx.topics {
+            topics.reset();
+        }
+    }
+
+    async fn run_inner(&mut self) -> ServerError<Tx, Rx> {
         loop {
             let Self {
                 tx,
                 rx,
                 buf,
                 dis: d,
+                reassembly,
             } = self;
             rx.wait_connection().await;
             tx.tx.wait_connection().await;
@@ -473,17 +967,35 @@ where
                     }
                 }
             };
-            let Some((hdr, body)) = VarHeader::take_from_slice(used) else {
+            let Some((hdr, frag, body)) = VarHeader::take_fragment_from_slice(used) else {
                 // TODO: send a nak on badly formed messages? We don't have
                 // much to say because we don't have a key or seq no or anything
                 continue;
             };
-            let fut = d.handle(tx, &hdr, body);
-            if let Err(e) = fut.await {
+            let res = match frag {
+                None => d.handle(tx, &hdr, body).await,
+                Some(frag) => match reassembly.as_mut().map(|r| r.push(hdr, frag, body)) {
+                    Some(Ok(Some((hdr, body)))) => d.handle(tx, &hdr, body).await,
+                    Some(Ok(None)) => Ok(()),
+                    // A fragment was lost, the message can't be decoded
+                    Some(Err(ReassemblyError::OutOfOrder)) => {
+                        tx.error(hdr.seq_no, WireError::DeserFailed).await
+                    }
+                    Some(Err(ReassemblyError::TooLarge { len })) => {
+                        let max = reassembly.as_ref().map_or(0, |r| r.max_len());
+                        tx.frame_too_long(hdr.seq_no, len, max).await
+                    }
+                    // Only report the first fragment of each message
+                    None if frag.index == 0 => tx.frame_too_long(hdr.seq_no, body.len(), 0).await,
+                    None => Ok(()),
+                },
+            };
+            if let Err(e) = res {
                 let kind = e.as_kind();
                 match kind {
                     WireTxErrorKind::ConnectionClosed => return ServerError::TxFatal(e),
                     WireTxErrorKind::Other => {}
+                    WireTxErrorKind::MessageTooLarge => {}
                     WireTxErrorKind::Timeout => return ServerError::TxFatal(e),
                 }
             }
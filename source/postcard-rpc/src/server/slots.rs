@@ -0,0 +1,226 @@
+//! A fixed size table of slots, keyed by a few words
+//!
+//! This is the shared bookkeeping of [`Cancellations`][super::Cancellations] and
+//! friends, which track a small number of requests or topics without allocating
+//! or locking. Each slot has a state, its key, and a generation counter, which
+//! prevents a stale [`Slot`] from affecting a later claim of the same slot.
+//!
+//! Lookups start at the home slot of a key, and only visit slots that are in use,
+//! so a lookup in an empty table, or of a key in its home slot, is cheap.
+
+use portable_atomic::{AtomicU32, Ordering};
+
+use crate::Key;
+
+// The low two bits of a slot state are the kind, the rest is the generation
+const KIND_MASK: u32 = 0b11;
+const FREE: u32 = 0b00;
+const CLAIMED: u32 = 0b01;
+const ACTIVE: u32 = 0b10;
+const MARKED: u32 = 0b11;
+const GEN_ONE: u32 = 0b100;
+
+/// Split a key into two words, as there may not be 64-bit atomics
+pub(crate) fn key_words(key: Key) -> [u32; 2] {
+    let b = key.to_bytes();
+    [
+        u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        u32::from_le_bytes([b[4], b[5], b[6], b[7]]),
+    ]
+}
+
+/// A claimed slot, and the generation it was claimed in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Slot {
+    pub(crate) idx: usize,
+    gen: u32,
+}
+
+/// A table of `N` slots, keyed by `W` words
+///
+/// `N` may be at most 32.
+pub(crate) struct Slots<const N: usize, const W: usize> {
+    states: [AtomicU32; N],
+    keys: [[AtomicU32; W]; N],
+    /// One bit per slot, set while the slot is in use
+    used: AtomicU32,
+}
+
+impl<const N: usize, const W: usize> Slots<N, W> {
+    pub(crate) const fn new() -> Self {
+        assert!(N <= 32, "at most 32 slots are supported");
+        Self {
+            states: [const { AtomicU32::new(FREE) }; N],
+            keys: [const { [const { AtomicU32::new(0) }; W] }; N],
+            used: AtomicU32::new(0),
+        }
+    }
+
+    fn home(key: &[u32; W]) -> usize {
+        key.first().copied().unwrap_or(0) as usize % N
+    }
+
+    /// The slots that may hold the given key, starting at its home slot
+    fn candidates(&self, key: &[u32; W]) -> impl Iterator<Item = usize> {
+        let used = self.used.load(Ordering::Acquire);
+        let home = Self::home(key);
+        let after = used & (!0 << home);
+        bits(after).chain(bits(used & !after))
+    }
+
+    /// Claim a free slot for the given key
+    ///
+    /// `init` is called with the index of the slot before it becomes visible to
+    /// lookups. Returns `None` if all slots are in use.
+    pub(crate) fn claim(&self, key: [u32; W], init: impl FnOnce(usize)) -> Option<Slot> {
+        let home = Self::home(&key);
+        for idx in (0..N).map(|i| (home + i) % N) {
+            let state = &self.states[idx];
+            let cur = state.load(Ordering::Acquire);
+            if cur & KIND_MASK != FREE {
+                continue;
+            }
+            let gen = (cur & !KIND_MASK).wrapping_add(GEN_ONE);
+            if state
+                .compare_exchange(cur, gen | CLAIMED, Ordering::AcqRel, Ordering::Acquire)
+                .is_err()
+            {
+                continue;
+            }
+            for (k, w) in self.keys[idx].iter().zip(key) {
+                k.store(w, Ordering::Release);
+            }
+            init(idx);
+            self.used.fetch_or(1 << idx, Ordering::AcqRel);
+            state.store(gen | ACTIVE, Ordering::Release);
+            return Some(Slot { idx, gen });
+        }
+        None
+    }
+
+    /// All slots in use with the given key, marked or not
+    pub(crate) fn matching(&self, key: [u32; W]) -> impl Iterator<Item = Slot> + '_ {
+        self.candidates(&key).filter_map(move |idx| {
+            let cur = self.states[idx].load(Ordering::Acquire);
+            let live = matches!(cur & KIND_MASK, ACTIVE | MARKED);
+            let same = self.keys[idx]
+                .iter()
+                .zip(key)
+                .all(|(k, w)| k.load(Ordering::Acquire) == w);
+            (live && same).then_some(Slot {
+                idx,
+                gen: cur & !KIND_MASK,
+            })
+        })
+    }
+
+    /// The first slot in use with the given key
+    pub(crate) fn find(&self, key: [u32; W]) -> Option<Slot> {
+        self.matching(key).next()
+    }
+
+    /// Mark a slot, returns `false` if it was already marked or is gone
+    pub(crate) fn mark(&self, slot: Slot) -> bool {
+        self.states[slot.idx]
+            .compare_exchange(
+                slot.gen | ACTIVE,
+                slot.gen | MARKED,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .is_ok()
+    }
+
+    /// Is the slot still in use, and marked?
+    pub(crate) fn is_marked(&self, slot: Slot) -> bool {
+        self.states[slot.idx].load(Ordering::Acquire) == slot.gen | MARKED
+    }
+
+    /// Free a slot, returns `false` if it was already freed
+    pub(crate) fn free(&self, slot: Slot) -> bool {
+        // Hold the slot while it is removed from `used`, so that it can't be
+        // claimed again in the meantime
+        let state = &self.states[slot.idx];
+        let held = [ACTIVE, MARKED].into_iter().any(|kind| {
+            state
+                .compare_exchange(
+                    slot.gen | kind,
+                    slot.gen | CLAIMED,
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                )
+                .is_ok()
+        });
+        if !held {
+            return false;
+        }
+        self.used.fetch_and(!(1 << slot.idx), Ordering::AcqRel);
+        // Keep the generation, so the next claim of this slot gets a new one
+        state.store(slot.gen | FREE, Ordering::Release);
+        true
+    }
+
+    /// Free all slots
+    pub(crate) fn reset(&self) {
+        for (idx, state) in self.states.iter().enumerate() {
+            let cur = state.load(Ordering::Acquire);
+            if matches!(cur & KIND_MASK, ACTIVE | MARKED) {
+                self.free(Slot {
+                    idx,
+                    gen: cur & !KIND_MASK,
+                });
+            }
+        }
+    }
+}
+
+/// The indices of the set bits, from low to high
+fn bits(mut word: u32) -> impl Iterator<Item = usize> {
+    core::iter::from_fn(move || {
+        let idx = word.trailing_zeros();
+        word &= word.wrapping_sub(1);
+        (idx < 32).then_some(idx as usize)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::Slots;
+
+    #[test]
+    fn slots() {
+        static SLOTS: Slots<4, 1> = Slots::new();
+
+        // Keys 1 and 5 share a home slot
+        let a = SLOTS.claim([1], |_| ()).unwrap();
+        let b = SLOTS.claim([5], |_| ()).unwrap();
+        assert_eq!(a.idx, 1);
+        assert_eq!(b.idx, 2);
+        assert_eq!(SLOTS.find([5]), Some(b));
+        assert_eq!(SLOTS.find([2]), None);
+
+        assert!(SLOTS.mark(b));
+        assert!(!SLOTS.mark(b));
+        assert!(SLOTS.is_marked(b));
+        assert!(!SLOTS.is_marked(a));
+
+        // A freed slot is claimed again in a new generation
+        assert!(SLOTS.free(b));
+        assert!(!SLOTS.free(b));
+        assert_eq!(SLOTS.find([5]), None);
+        let b2 = SLOTS.claim([6], |_| ()).unwrap();
+        assert_eq!(b2.idx, 2);
+        assert!(!SLOTS.mark(b));
+        assert!(!SLOTS.is_marked(b2));
+
+        assert!(SLOTS.claim([0], |_| ()).is_some());
+        assert!(SLOTS.claim([0], |_| ()).is_some());
+        assert!(SLOTS.claim([0], |_| ()).is_none());
+        assert_eq!(SLOTS.matching([0]).count(), 2);
+
+        SLOTS.reset();
+        assert_eq!(SLOTS.find([1]), None);
+        assert_eq!(SLOTS.matching([0]).count(), 0);
+        assert!(SLOTS.claim([3], |_| ()).is_some());
+    }
+}
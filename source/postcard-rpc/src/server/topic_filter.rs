@@ -0,0 +1,245 @@
+//! Enabling and disabling topics sent to the client
+//!
+//! Clients may enable or disable individual topics with the
+//! [`TopicEnableEndpoint`][crate::standard_icd::TopicEnableEndpoint] and
+//! [`TopicDisableEndpoint`][crate::standard_icd::TopicDisableEndpoint], optionally
+//! limiting how many messages per second are sent. The `HostClient` does this
+//! automatically when topics are subscribed to. Messages of disabled topics are
+//! skipped by [`Sender::publish()`][super::Sender::publish].
+//!
+//! Servers opt in with [`Server::with_topic_filter()`][super::Server::with_topic_filter],
+//! and by adding `topic_filter` to the `standard` parts of their
+//! [`define_dispatch!`][crate::define_dispatch].
+//! All topics go back to the default of the filter when the client disconnects.
+
+use portable_atomic::{AtomicU32, Ordering};
+
+use super::slots::{key_words, Slots};
+use crate::{
+    standard_icd::{TopicControlError, TopicControlResult},
+    Key,
+};
+
+/// The number of topics that can differ from the default
+const SLOTS: usize = 32;
+
+/// Tracks which topics the client enabled
+///
+/// Up to 32 topics can differ from the default of the filter, by being enabled in
+/// a filter that starts out disabled, disabled in one that starts out enabled, or
+/// by being rate limited. Other topics use the default, and take no slot.
+pub struct TopicFilter {
+    default_enabled: bool,
+    clock: Option<fn() -> u32>,
+    slots: Slots<SLOTS, 2>,
+    /// One bit per slot, set if the topic is enabled
+    enabled: AtomicU32,
+    /// The minimum time between two messages, in microseconds
+    intervals: [AtomicU32; SLOTS],
+    /// The time the last message was sent, in microseconds
+    last_sent: [AtomicU32; SLOTS],
+}
+
+impl TopicFilter {
+    const fn new(default_enabled: bool) -> Self {
+        Self {
+            default_enabled,
+            clock: None,
+            slots: Slots::new(),
+            enabled: AtomicU32::new(0),
+            intervals: [const { AtomicU32::new(0) }; SLOTS],
+            last_sent: [const { AtomicU32::new(0) }; SLOTS],
+        }
+    }
+
+    /// Create a filter where topics are sent until the client disables them
+    ///
+    /// This works with clients that don't enable topics.
+    pub const fn new_enabled() -> Self {
+        Self::new(true)
+    }
+
+    /// Create a filter where topics are not sent until the client enables them
+    pub const fn new_disabled() -> Self {
+        Self::new(false)
+    }
+
+    /// Allow the client to limit the rate of topics
+    ///
+    /// `clock` returns a monotonic time in microseconds, which may wrap around.
+    /// Without a clock, requests to limit the rate are rejected.
+    pub const fn with_clock(mut self, clock: fn() -> u32) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    fn slot(&self, key: Key) -> Option<usize> {
+        self.slots.find(key_words(key)).map(|slot| slot.idx)
+    }
+
+    fn claim(&self, key: Key) -> Option<usize> {
+        let slot = self.slots.claim(key_words(key), |idx| {
+            self.enabled.fetch_and(!(1 << idx), Ordering::AcqRel);
+        })?;
+        Some(slot.idx)
+    }
+
+    /// Enable or disable the topic with the given key
+    ///
+    /// `max_rate` limits enabled topics to this many messages per second.
+    pub fn set(&self, key: Key, enabled: bool, max_rate: Option<u32>) -> TopicControlResult {
+        let interval = match (max_rate, self.clock) {
+            (None, _) => 0,
+            (Some(_), None) => return Err(TopicControlError::RateUnsupported),
+            (Some(rate), Some(_)) => 1_000_000 / rate.clamp(1, 1_000_000),
+        };
+        if enabled == self.default_enabled && interval == 0 {
+            // Back to the default, the slot is no longer needed
+            if let Some(slot) = self.slots.find(key_words(key)) {
+                self.slots.free(slot);
+            }
+            return Ok(());
+        }
+        let idx = self
+            .slot(key)
+            .or_else(|| self.claim(key))
+            .ok_or(TopicControlError::TooManyTopics)?;
+
+        self.intervals[idx].store(interval, Ordering::Release);
+        if let Some(clock) = self.clock {
+            // Allow the next message to be sent right away
+            let now = clock();
+            self.last_sent[idx].store(now.wrapping_sub(interval), Ordering::Release);
+        }
+        let bit = 1 << idx;
+        if enabled {
+            self.enabled.fetch_or(bit, Ordering::AcqRel);
+        } else {
+            self.enabled.fetch_and(!bit, Ordering::AcqRel);
+        }
+        Ok(())
+    }
+
+    /// Is the topic with the given key enabled?
+    pub fn is_enabled(&self, key: Key) -> bool {
+        match self.slot(key) {
+            None => self.default_enabled,
+            Some(idx) => self.enabled.load(Ordering::Acquire) & (1 << idx) != 0,
+        }
+    }
+
+    /// Should a message of the topic with the given key be sent now?
+    ///
+    /// Returns `false` if the topic is disabled, or a message was sent too recently.
+    /// Otherwise, the message is counted against the rate limit of the topic.
+    pub fn check(&self, key: Key) -> bool {
+        let Some(idx) = self.slot(key) else {
+            return self.default_enabled;
+        };
+        if self.enabled.load(Ordering::Acquire) & (1 << idx) == 0 {
+            return false;
+        }
+        let interval = self.intervals[idx].load(Ordering::Acquire);
+        let Some(clock) = self.clock.filter(|_| interval != 0) else {
+            return true;
+        };
+        let now = clock();
+        let last = self.last_sent[idx].load(Ordering::Acquire);
+        now.wrapping_sub(last) >= interval
+            && self.last_sent[idx]
+                .compare_exchange(last, now, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+    }
+
+    /// Put all topics back to the default of the filter
+    ///
+    /// This is done by the [`Server`][super::Server] when the client disconnects, as
+    /// the next client doesn't know which topics the previous one enabled.
+    pub fn reset(&self) {
+        self.slots.reset();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use portable_atomic::{AtomicU32, Ordering};
+
+    use super::TopicFilter;
+    use crate::{standard_icd::TopicControlError, Key};
+
+    static NOW: AtomicU32 = AtomicU32::new(0);
+
+    fn now() -> u32 {
+        NOW.load(Ordering::Relaxed)
+    }
+
+    #[test]
+    fn enable_disable() {
+        let a = Key::for_path::<u32>("a");
+        let b = Key::for_path::<u32>("b");
+
+        let filter = TopicFilter::new_enabled();
+        assert!(filter.check(a));
+        assert_eq!(filter.set(a, false, None), Ok(()));
+        assert!(!filter.check(a));
+        assert!(!filter.is_enabled(a));
+        assert!(filter.check(b));
+        assert_eq!(filter.set(a, true, None), Ok(()));
+        assert!(filter.check(a));
+        assert_eq!(
+            filter.set(a, true, Some(10)),
+            Err(TopicControlError::RateUnsupported)
+        );
+
+        let filter = TopicFilter::new_disabled();
+        assert!(!filter.check(a));
+        assert_eq!(filter.set(a, true, None), Ok(()));
+        assert!(filter.check(a));
+        assert!(!filter.check(b));
+
+        for i in 0..31 {
+            let key = Key::for_path::<u32>(&format!("{i}"));
+            assert_eq!(filter.set(key, true, None), Ok(()));
+        }
+        assert_eq!(
+            filter.set(b, true, None),
+            Err(TopicControlError::TooManyTopics)
+        );
+
+        // Disabling a topic frees its slot
+        assert_eq!(filter.set(a, false, None), Ok(()));
+        assert!(!filter.check(a));
+        assert_eq!(filter.set(b, true, None), Ok(()));
+        assert!(filter.check(b));
+
+        // Resetting disables all topics again
+        filter.reset();
+        assert!(!filter.check(b));
+        assert_eq!(filter.set(a, true, None), Ok(()));
+        assert!(filter.check(a));
+    }
+
+    #[test]
+    fn max_rate() {
+        let a = Key::for_path::<u32>("a");
+        let filter = TopicFilter::new_disabled().with_clock(now);
+
+        // 100 messages per second, or one every 10ms
+        NOW.store(u32::MAX - 5_000, Ordering::Relaxed);
+        assert_eq!(filter.set(a, true, Some(100)), Ok(()));
+        assert!(filter.check(a));
+        assert!(!filter.check(a));
+
+        // Works when the clock wraps around
+        NOW.fetch_add(9_999, Ordering::Relaxed);
+        assert!(!filter.check(a));
+        NOW.fetch_add(1, Ordering::Relaxed);
+        assert!(filter.check(a));
+        assert!(!filter.check(a));
+
+        // Removing the limit
+        assert_eq!(filter.set(a, true, None), Ok(()));
+        assert!(filter.check(a));
+        assert!(filter.check(a));
+    }
+}
@@ -2,7 +2,11 @@
 //!
 //! This is used by [`define_dispatch!()`][crate::define_dispatch] as well.
 
-use crate::{endpoints, topics, Key, TopicDirection};
+use crate::{
+    endpoints,
+    header::{VarKeyKind, VarSeqKind},
+    topics, EndpointMap, Key, TopicDirection, TopicMap,
+};
 use postcard_schema::Schema;
 use serde::{Deserialize, Serialize};
 
@@ -144,15 +148,351 @@ pub struct SchemaTotals {
     pub errors: u32,
 }
 
+/// A request for a range of schema items, see [`GetSchemaPageEndpoint`]
+///
+/// The items of a server are numbered from zero: first all types, then all
+/// endpoints, topics out, and topics in.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SchemaPage {
+    /// The index of the first item
+    pub start: u32,
+    /// The number of items
+    pub count: u32,
+}
+
+/// The reply to a [`GetSchemaPageEndpoint`] request, sent after the requested items
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SchemaPageInfo {
+    /// The total number of schema items
+    pub total: u32,
+    /// The number of requested items that failed to send
+    pub errors: u32,
+}
+
+/// A single element of schema information and its index, see [`GetSchemaItemTopic`]
+#[cfg(not(feature = "use-std"))]
+#[derive(Serialize, Schema, Debug, PartialEq, Copy, Clone)]
+pub struct IndexedSchemaData<'a> {
+    /// The index of the item, see [`SchemaPage`]
+    pub index: u32,
+    /// The item
+    pub data: SchemaData<'a>,
+}
+
+/// A single element of schema information and its index, see [`GetSchemaItemTopic`]
+#[cfg(feature = "use-std")]
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone)]
+pub struct OwnedIndexedSchemaData {
+    /// The index of the item, see [`SchemaPage`]
+    pub index: u32,
+    /// The item
+    pub data: OwnedSchemaData,
+}
+
+/// The start of a bulk upload, see [`BulkStartEndpoint`]
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BulkStart {
+    /// The total length of the upload, in bytes
+    pub len: u32,
+}
+
+/// The parameters of an accepted bulk upload, chosen by the server
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BulkParams {
+    /// The maximum number of bytes in a single chunk
+    pub chunk_size: u32,
+    /// The maximum number of chunks that may be sent without being acknowledged
+    pub window: u32,
+}
+
+/// A single chunk of a bulk upload
+#[cfg(not(feature = "use-std"))]
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BulkChunk<'a> {
+    /// The offset of this chunk from the start of the upload
+    pub offset: u32,
+    /// The contents of this chunk
+    pub data: &'a [u8],
+}
+
+/// A single chunk of a bulk upload
+#[cfg(feature = "use-std")]
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone)]
+pub struct OwnedBulkChunk {
+    /// The offset of this chunk from the start of the upload
+    pub offset: u32,
+    /// The contents of this chunk
+    pub data: Vec<u8>,
+}
+
+/// The end of a bulk upload, see [`BulkEndEndpoint`]
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BulkEnd {
+    /// The CRC of the whole upload, as calculated by [`crc32()`]
+    pub crc: u32,
+}
+
+/// An error reported by the server during a bulk upload
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BulkError {
+    /// No upload was in progress
+    NotStarted,
+    /// The upload is larger than the server can store
+    TooLarge,
+    /// The chunk did not start where the previous one ended, or was too long
+    BadChunk,
+    /// The upload was ended before all data was received
+    Incomplete,
+    /// The received data did not match the CRC
+    CrcMismatch,
+    /// The server failed to store the data
+    Storage,
+}
+
+impl core::fmt::Display for BulkError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            BulkError::NotStarted => f.write_str("No upload was in progress"),
+            BulkError::TooLarge => f.write_str("The upload is larger than the server can store"),
+            BulkError::BadChunk => {
+                f.write_str("The chunk did not start where the previous one ended, or was too long")
+            }
+            BulkError::Incomplete => {
+                f.write_str("The upload was ended before all data was received")
+            }
+            BulkError::CrcMismatch => f.write_str("The received data did not match the CRC"),
+            BulkError::Storage => f.write_str("The server failed to store the data"),
+        }
+    }
+}
+
+impl core::error::Error for BulkError {}
+
+/// The response of [`BulkStartEndpoint`]
+pub type BulkStartResult = Result<BulkParams, BulkError>;
+
+/// The response of [`BulkChunkEndpoint`] and [`BulkEndEndpoint`]
+pub type BulkResult = Result<(), BulkError>;
+
+/// Update a CRC-32 (IEEE, as used by zlib and ethernet) with `data`
+///
+/// Start with a `crc` of `0`. This is used to verify bulk uploads.
+pub const fn crc32(crc: u32, data: &[u8]) -> u32 {
+    // Nibble-wise lookup table, trading some speed for a small table
+    const TABLE: [u32; 16] = const {
+        let mut table = [0u32; 16];
+        let mut i = 0;
+        while i < 16 {
+            let mut c = i as u32;
+            let mut j = 0;
+            while j < 4 {
+                c = if c & 1 != 0 {
+                    (c >> 1) ^ 0xEDB8_8320
+                } else {
+                    c >> 1
+                };
+                j += 1;
+            }
+            table[i] = c;
+            i += 1;
+        }
+        table
+    };
+
+    let mut crc = !crc;
+    let mut i = 0;
+    while i < data.len() {
+        crc ^= data[i] as u32;
+        crc = (crc >> 4) ^ TABLE[(crc & 0xF) as usize];
+        crc = (crc >> 4) ^ TABLE[(crc & 0xF) as usize];
+        i += 1;
+    }
+    !crc
+}
+
+/// A request to enable a topic sent to the client, see [`TopicEnableEndpoint`]
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Copy, Clone)]
+pub struct TopicEnable {
+    /// The key of the topic, as in [`Topic::TOPIC_KEY`][crate::Topic::TOPIC_KEY]
+    pub key: Key,
+    /// The most messages per second the server should send, if limited
+    pub max_rate: Option<u32>,
+}
+
+/// An error reported by the server when enabling or disabling a topic
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TopicControlError {
+    /// The server does not support disabling topics
+    Unsupported,
+    /// The server does not send a topic with this key
+    UnknownTopic,
+    /// The server can't track any more topics
+    TooManyTopics,
+    /// The server does not support limiting the rate of topics
+    RateUnsupported,
+}
+
+impl core::fmt::Display for TopicControlError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TopicControlError::Unsupported => {
+                f.write_str("The server does not support disabling topics")
+            }
+            TopicControlError::UnknownTopic => {
+                f.write_str("The server does not send a topic with this key")
+            }
+            TopicControlError::TooManyTopics => {
+                f.write_str("The server can't track any more topics")
+            }
+            TopicControlError::RateUnsupported => {
+                f.write_str("The server does not support limiting the rate of topics")
+            }
+        }
+    }
+}
+
+impl core::error::Error for TopicControlError {}
+
+/// The response of [`TopicEnableEndpoint`] and [`TopicDisableEndpoint`]
+pub type TopicControlResult = Result<(), TopicControlError>;
+
+/// The capabilities of a server, see [`CapabilitiesEndpoint`]
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Capabilities {
+    /// The version of the wire protocol, see [`Capabilities::PROTOCOL_VERSION`]
+    pub protocol_version: u8,
+    /// The smallest key size, in bytes, that distinguishes all endpoints and topics
+    /// of the server
+    pub min_key_len: u8,
+    /// The sequence number sizes the server accepts, as a bitmask of their lengths
+    /// in bytes, e.g. `0b0111` for 1, 2, and 4 bytes
+    pub seq_sizes: u8,
+    /// The longest frame the server can receive, including the header, if known
+    pub max_request_len: Option<u32>,
+    /// The longest frame the server can send, including the header, if known
+    pub max_response_len: Option<u32>,
+    /// The longest message the server can reassemble from fragments, if it
+    /// accepts fragmented messages
+    pub max_reassembly_len: Option<u32>,
+    /// The optional features the server was configured with, and has in its
+    /// device map, as a bitmask of
+    /// [`Capabilities::CANCEL`], [`Capabilities::TOPIC_FILTER`], and
+    /// [`Capabilities::CREDITS`]
+    pub features: u8,
+}
+
+impl Capabilities {
+    /// The current version of the wire protocol, as sent in the header
+    pub const PROTOCOL_VERSION: u8 = 0;
+
+    /// Requests can be cancelled with the [`CancelTopic`]
+    pub const CANCEL: u8 = 1 << 0;
+
+    /// Topics can be enabled and disabled with the [`TopicEnableEndpoint`] and
+    /// [`TopicDisableEndpoint`]
+    pub const TOPIC_FILTER: u8 = 1 << 1;
+
+    /// Topics can be flow controlled with the [`CreditTopic`]
+    pub const CREDITS: u8 = 1 << 2;
+
+    /// Was the server configured with this feature, one of [`Capabilities::CANCEL`]
+    /// and friends?
+    pub fn supports(&self, feature: u8) -> bool {
+        self.features & feature == feature
+    }
+
+    /// Does the server accept sequence numbers of this size?
+    pub fn supports_seq(&self, kind: VarSeqKind) -> bool {
+        let len = match kind {
+            VarSeqKind::Seq1 => 1,
+            VarSeqKind::Seq2 => 2,
+            VarSeqKind::Seq4 => 4,
+        };
+        self.seq_sizes & len != 0
+    }
+
+    /// The smallest key size that distinguishes all endpoints and topics of the server
+    ///
+    /// Returns `None` if the size is not one of 1, 2, 4, or 8 bytes.
+    pub fn key_kind(&self) -> Option<VarKeyKind> {
+        match self.min_key_len {
+            1 => Some(VarKeyKind::Key1),
+            2 => Some(VarKeyKind::Key2),
+            4 => Some(VarKeyKind::Key4),
+            8 => Some(VarKeyKind::Key8),
+            _ => None,
+        }
+    }
+}
+
+/// The identity of a device, see [`DeviceInfoEndpoint`]
+///
+/// Servers reply with this type, also when using the standard library.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DeviceInfo<'a> {
+    /// The name of the product
+    pub product: &'a str,
+    /// The version of the firmware
+    pub firmware_version: &'a str,
+    /// The git commit the firmware was built from
+    pub git_hash: &'a str,
+    /// When the firmware was built
+    pub build_time: &'a str,
+    /// A serial number unique to this device
+    pub serial_number: &'a str,
+}
+
+/// The identity of a device, see [`DeviceInfoEndpoint`]
+#[cfg(feature = "use-std")]
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone)]
+pub struct OwnedDeviceInfo {
+    /// The name of the product
+    pub product: String,
+    /// The version of the firmware
+    pub firmware_version: String,
+    /// The git commit the firmware was built from
+    pub git_hash: String,
+    /// When the firmware was built
+    pub build_time: String,
+    /// A serial number unique to this device
+    pub serial_number: String,
+}
+
+/// Credits for a topic sent to the client, see [`CreditTopic`]
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Copy, Clone)]
+pub struct CreditGrant {
+    /// The key of the topic, as in [`Topic::TOPIC_KEY`][crate::Topic::TOPIC_KEY]
+    pub key: Key,
+    /// The number of additional messages the server may send
+    ///
+    /// [`CreditGrant::UNLIMITED`] turns flow control for the topic off again.
+    pub credits: u32,
+}
+
+impl CreditGrant {
+    /// Turns flow control off for the topic
+    pub const UNLIMITED: u32 = u32::MAX;
+}
+
 endpoints! {
     list = STANDARD_ICD_ENDPOINTS;
     // NOTE: "omit_std" should ONLY be used by the standard_icd! You should NOT set this
     // in your code!
     omit_std = true;
-    | EndpointTy            | RequestTy     | ResponseTy    | Path                       |
-    | ----------            | ---------     | ----------    | ----                       |
-    | PingEndpoint          | u32           | u32           | "postcard-rpc/ping"        |
-    | GetAllSchemasEndpoint | ()            | SchemaTotals  | "postcard-rpc/schemas/get" |
+    | EndpointTy            | RequestTy      | ResponseTy         | Path                          | Cfg                           |
+    | ----------            | ---------      | ----------         | ----                          | ---                           |
+    | PingEndpoint          | u32            | u32                | "postcard-rpc/ping"           |                               |
+    | GetAllSchemasEndpoint | ()             | SchemaTotals       | "postcard-rpc/schemas/get"    |                               |
 }
 
 topics! {
@@ -161,12 +501,12 @@ topics! {
     // NOTE: "omit_std" should ONLY be used by the standard_icd! You should NOT set this
     // in your code!
     omit_std = true;
-    | TopicTy               | MessageTy         | Path                          | Cfg                           |
-    | -------               | ---------         | ----                          | ---                           |
-    | GetAllSchemaDataTopic | SchemaData<'a>    | "postcard-rpc/schema/data"    | cfg(not(feature = "use-std")) |
-    | GetAllSchemaDataTopic | OwnedSchemaData   | "postcard-rpc/schema/data"    | cfg(feature = "use-std")      |
-    | LoggingTopic          | str               | "postcard-rpc/logging"        | cfg(not(feature = "use-std")) |
-    | LoggingTopic          | String            | "postcard-rpc/logging"        | cfg(feature = "use-std")      |
+    | TopicTy               | MessageTy              | Path                       | Cfg                           |
+    | -------               | ---------              | ----                       | ---                           |
+    | GetAllSchemaDataTopic | SchemaData<'a>         | "postcard-rpc/schema/data" | cfg(not(feature = "use-std")) |
+    | GetAllSchemaDataTopic | OwnedSchemaData        | "postcard-rpc/schema/data" | cfg(feature = "use-std")      |
+    | LoggingTopic          | str                    | "postcard-rpc/logging"     | cfg(not(feature = "use-std")) |
+    | LoggingTopic          | String                 | "postcard-rpc/logging"     | cfg(feature = "use-std")      |
 }
 
 topics! {
@@ -178,3 +518,163 @@ topics! {
     | TopicTy           | MessageTy         | Path                          | Cfg                           |
     | -------           | ---------         | ----                          | ---                           |
 }
+
+// The optional parts of the standard ICD are only part of the device map of a
+// server that uses them, see `define_dispatch!`
+
+endpoints! {
+    list = STANDARD_ICD_BULK_ENDPOINTS;
+    // NOTE: "omit_std" should ONLY be used by the standard_icd! You should NOT set this
+    // in your code!
+    omit_std = true;
+    | EndpointTy        | RequestTy      | ResponseTy      | Path                      | Cfg                           |
+    | ----------        | ---------      | ----------      | ----                      | ---                           |
+    | BulkStartEndpoint | BulkStart      | BulkStartResult | "postcard-rpc/bulk/start" |                               |
+    | BulkChunkEndpoint | BulkChunk<'a>  | BulkResult      | "postcard-rpc/bulk/chunk" | cfg(not(feature = "use-std")) |
+    | BulkChunkEndpoint | OwnedBulkChunk | BulkResult      | "postcard-rpc/bulk/chunk" | cfg(feature = "use-std")      |
+    | BulkEndEndpoint   | BulkEnd        | BulkResult      | "postcard-rpc/bulk/end"   |                               |
+}
+
+topics! {
+    list = STANDARD_ICD_CANCEL_TOPICS;
+    direction = crate::TopicDirection::ToServer;
+    // NOTE: "omit_std" should ONLY be used by the standard_icd! You should NOT set this
+    // in your code!
+    omit_std = true;
+    | TopicTy     | MessageTy | Path                  | Cfg |
+    | -------     | --------- | ----                  | --- |
+    | CancelTopic | u32       | "postcard-rpc/cancel" |     |
+}
+
+topics! {
+    list = STANDARD_ICD_STREAM_TOPICS;
+    direction = crate::TopicDirection::ToClient;
+    // NOTE: "omit_std" should ONLY be used by the standard_icd! You should NOT set this
+    // in your code!
+    omit_std = true;
+    | TopicTy        | MessageTy | Path                      | Cfg |
+    | -------        | --------- | ----                      | --- |
+    | StreamEndTopic | ()        | "postcard-rpc/stream/end" |     |
+}
+
+topics! {
+    list = STANDARD_ICD_CREDIT_TOPICS;
+    direction = crate::TopicDirection::ToServer;
+    // NOTE: "omit_std" should ONLY be used by the standard_icd! You should NOT set this
+    // in your code!
+    omit_std = true;
+    | TopicTy     | MessageTy   | Path                   | Cfg |
+    | -------     | ---------   | ----                   | --- |
+    | CreditTopic | CreditGrant | "postcard-rpc/credits" |     |
+}
+
+endpoints! {
+    list = STANDARD_ICD_TOPIC_FILTER_ENDPOINTS;
+    // NOTE: "omit_std" should ONLY be used by the standard_icd! You should NOT set this
+    // in your code!
+    omit_std = true;
+    | EndpointTy           | RequestTy   | ResponseTy         | Path                          | Cfg |
+    | ----------           | ---------   | ----------         | ----                          | --- |
+    | TopicEnableEndpoint  | TopicEnable | TopicControlResult | "postcard-rpc/topics/enable"  |     |
+    | TopicDisableEndpoint | Key         | TopicControlResult | "postcard-rpc/topics/disable" |     |
+}
+
+endpoints! {
+    list = STANDARD_ICD_CAPABILITIES_ENDPOINTS;
+    // NOTE: "omit_std" should ONLY be used by the standard_icd! You should NOT set this
+    // in your code!
+    omit_std = true;
+    | EndpointTy           | RequestTy | ResponseTy   | Path                        | Cfg |
+    | ----------           | --------- | ----------   | ----                        | --- |
+    | CapabilitiesEndpoint | ()        | Capabilities | "postcard-rpc/capabilities" |     |
+}
+
+endpoints! {
+    list = STANDARD_ICD_DEVICE_INFO_ENDPOINTS;
+    // NOTE: "omit_std" should ONLY be used by the standard_icd! You should NOT set this
+    // in your code!
+    omit_std = true;
+    | EndpointTy         | RequestTy | ResponseTy      | Path                       | Cfg                           |
+    | ----------         | --------- | ----------      | ----                       | ---                           |
+    | DeviceInfoEndpoint | ()        | DeviceInfo<'a>  | "postcard-rpc/device-info" | cfg(not(feature = "use-std")) |
+    | DeviceInfoEndpoint | ()        | OwnedDeviceInfo | "postcard-rpc/device-info" | cfg(feature = "use-std")      |
+}
+
+endpoints! {
+    list = STANDARD_ICD_SCHEMA_PAGE_ENDPOINTS;
+    // NOTE: "omit_std" should ONLY be used by the standard_icd! You should NOT set this
+    // in your code!
+    omit_std = true;
+    | EndpointTy            | RequestTy  | ResponseTy     | Path                        | Cfg |
+    | ----------            | ---------  | ----------     | ----                        | --- |
+    | GetSchemaPageEndpoint | SchemaPage | SchemaPageInfo | "postcard-rpc/schemas/page" |     |
+}
+
+topics! {
+    list = STANDARD_ICD_SCHEMA_PAGE_TOPICS;
+    direction = crate::TopicDirection::ToClient;
+    // NOTE: "omit_std" should ONLY be used by the standard_icd! You should NOT set this
+    // in your code!
+    omit_std = true;
+    | TopicTy            | MessageTy              | Path                       | Cfg                           |
+    | -------            | ---------              | ----                       | ---                           |
+    | GetSchemaItemTopic | IndexedSchemaData<'a>  | "postcard-rpc/schema/item" | cfg(not(feature = "use-std")) |
+    | GetSchemaItemTopic | OwnedIndexedSchemaData | "postcard-rpc/schema/item" | cfg(feature = "use-std")      |
+}
+
+endpoints! {
+    list = STANDARD_ICD_SCHEMA_DIGEST_ENDPOINTS;
+    // NOTE: "omit_std" should ONLY be used by the standard_icd! You should NOT set this
+    // in your code!
+    omit_std = true;
+    | EndpointTy           | RequestTy | ResponseTy | Path                          | Cfg |
+    | ----------           | --------- | ---------- | ----                          | --- |
+    | SchemaDigestEndpoint | ()        | u64        | "postcard-rpc/schemas/digest" |     |
+}
+
+/// The optional endpoints of the standard ICD
+pub const STANDARD_ICD_OPTIONAL_ENDPOINTS: &[&EndpointMap] = &[
+    &STANDARD_ICD_BULK_ENDPOINTS,
+    &STANDARD_ICD_TOPIC_FILTER_ENDPOINTS,
+    &STANDARD_ICD_CAPABILITIES_ENDPOINTS,
+    &STANDARD_ICD_DEVICE_INFO_ENDPOINTS,
+    &STANDARD_ICD_SCHEMA_PAGE_ENDPOINTS,
+    &STANDARD_ICD_SCHEMA_DIGEST_ENDPOINTS,
+];
+
+/// The optional topics of the standard ICD, sent by the client
+pub const STANDARD_ICD_OPTIONAL_TOPICS_IN: &[&TopicMap] =
+    &[&STANDARD_ICD_CANCEL_TOPICS, &STANDARD_ICD_CREDIT_TOPICS];
+
+/// The optional topics of the standard ICD, sent by the server
+pub const STANDARD_ICD_OPTIONAL_TOPICS_OUT: &[&TopicMap] = &[
+    &STANDARD_ICD_STREAM_TOPICS,
+    &STANDARD_ICD_SCHEMA_PAGE_TOPICS,
+];
+
+/// All endpoints of the standard ICD, including the optional ones
+pub fn standard_endpoints() -> impl Iterator<Item = &'static (&'static str, Key, Key)> {
+    let optional = STANDARD_ICD_OPTIONAL_ENDPOINTS.iter();
+    STANDARD_ICD_ENDPOINTS
+        .endpoints
+        .iter()
+        .chain(optional.flat_map(|map| map.endpoints))
+}
+
+/// All topics of the standard ICD sent by the client, including the optional ones
+pub fn standard_topics_in() -> impl Iterator<Item = &'static (&'static str, Key)> {
+    let optional = STANDARD_ICD_OPTIONAL_TOPICS_IN.iter();
+    STANDARD_ICD_TOPICS_IN
+        .topics
+        .iter()
+        .chain(optional.flat_map(|map| map.topics))
+}
+
+/// All topics of the standard ICD sent by the server, including the optional ones
+pub fn standard_topics_out() -> impl Iterator<Item = &'static (&'static str, Key)> {
+    let optional = STANDARD_ICD_OPTIONAL_TOPICS_OUT.iter();
+    STANDARD_ICD_TOPICS_OUT
+        .topics
+        .iter()
+        .chain(optional.flat_map(|map| map.topics))
+}
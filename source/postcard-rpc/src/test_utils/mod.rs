@@ -15,6 +15,9 @@ use tokio::{
     sync::mpsc::{channel, Receiver, Sender},
 };
 
+#[cfg(all(feature = "test-utils", feature = "capture"))]
+pub mod replay;
+
 /// Rx Helper type
 pub struct LocalRx {
     fake_error: Stopper,
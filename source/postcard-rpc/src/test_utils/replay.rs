@@ -0,0 +1,442 @@
+//! Replaying captured traffic against a dispatcher
+//!
+//! A [`Replay`] takes the frames of a [capture](crate::capture), feeds the frames
+//! sent to the server into a dispatcher running on the
+//! [`test_channels`](crate::server::impls::test_channels) wire, and compares the
+//! frames it sends back with the ones in the capture:
+//!
+//! ```rust,no_run
+//! # use postcard_rpc::{capture::CaptureReader, test_utils::replay::Replay, standard_icd::LoggingTopic};
+//! # async fn demo<D>(app: D, device_map: &postcard_rpc::DeviceMap)
+//! # where D: postcard_rpc::server::Dispatch<Tx = postcard_rpc::server::impls::test_channels::ChannelWireTx>
+//! # {
+//! let frames = CaptureReader::open("device.pcapng").unwrap();
+//! let report = Replay::from_server_capture(frames.map(Result::unwrap))
+//!     .ignore_topic::<LoggingTopic>()
+//!     .run(app, device_map)
+//!     .await;
+//! assert!(report.is_match(), "{report}");
+//! # }
+//! ```
+//!
+//! Responses are matched with recorded ones by their header, so handlers may reply
+//! in a different order than recorded. Frames to the server are sent in the
+//! recorded order, each one once all responses recorded before it were sent.
+//!
+//! Fields that differ between runs, like timestamps, can be masked with
+//! [`Replay::mask_response()`] and [`Replay::mask_topic()`], or whole topics can
+//! be left out with [`Replay::ignore_topic()`]. Topics published with sequence
+//! numbers that differ between runs can be matched by their order instead, with
+//! [`Replay::ignore_topic_seq()`].
+
+use core::{fmt, pin::pin, time::Duration};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::{select, sync::mpsc, time::timeout};
+
+use crate::{
+    capture::{describe, CapturedFrame, Direction},
+    header::{Fragment, VarHeader, VarKey},
+    server::{
+        impls::test_channels::{ChannelWireRx, ChannelWireTx},
+        Dispatch, Server,
+    },
+    standard_icd::{
+        standard_endpoints, standard_topics_in, standard_topics_out, ERROR_KEY, ERROR_PATH,
+    },
+    DeviceMap, Endpoint, Key, Topic,
+};
+
+/// A frame of a capture
+struct Recorded {
+    header: VarHeader,
+    fragment: Option<Fragment>,
+    body: Vec<u8>,
+}
+
+impl Recorded {
+    fn parse(frame: &[u8]) -> Option<Self> {
+        let (header, fragment, body) = VarHeader::take_fragment_from_slice(frame)?;
+        Some(Self {
+            header,
+            fragment,
+            body: body.to_vec(),
+        })
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = match self.fragment {
+            Some(frag) => self.header.write_fragment_to_vec(frag),
+            None => self.header.write_to_vec(),
+        };
+        out.extend_from_slice(&self.body);
+        out
+    }
+}
+
+/// A step of a replay
+enum Step {
+    /// Send a frame to the server
+    Send(Recorded),
+    /// Wait for the server to send a frame
+    Expect(Recorded),
+}
+
+/// Decodes a body, masks the nondeterministic fields, and encodes it again
+type Mask = Box<dyn Fn(&[u8]) -> Option<Vec<u8>> + Send>;
+
+/// Replays the frames of a capture against a dispatcher
+///
+/// See the [module level docs](self) for details.
+pub struct Replay {
+    steps: Vec<Step>,
+    masks: Vec<(Key, Mask)>,
+    ignored: Vec<Key>,
+    ignored_seq: Vec<Key>,
+    timeout: Duration,
+    settle: Duration,
+    buf_len: usize,
+}
+
+impl Replay {
+    /// Replay a capture made on the server side
+    pub fn from_server_capture(frames: impl IntoIterator<Item = CapturedFrame>) -> Self {
+        Self::new(frames, Direction::Received)
+    }
+
+    /// Replay a capture made on the client side
+    pub fn from_client_capture(frames: impl IntoIterator<Item = CapturedFrame>) -> Self {
+        Self::new(frames, Direction::Sent)
+    }
+
+    /// Use the frames with the given direction as the frames sent to the server
+    fn new(frames: impl IntoIterator<Item = CapturedFrame>, to_server: Direction) -> Self {
+        let steps = frames
+            .into_iter()
+            .map(|f| {
+                let recorded = Recorded {
+                    header: f.frame.header,
+                    fragment: f.fragment,
+                    body: f.frame.body,
+                };
+                if f.direction == to_server {
+                    Step::Send(recorded)
+                } else {
+                    Step::Expect(recorded)
+                }
+            })
+            .collect();
+        Self {
+            steps,
+            masks: Vec::new(),
+            ignored: Vec::new(),
+            ignored_seq: Vec::new(),
+            timeout: Duration::from_secs(1),
+            settle: Duration::from_millis(20),
+            buf_len: 1024,
+        }
+    }
+
+    /// Mask nondeterministic fields of the responses of an endpoint
+    ///
+    /// Both the recorded and the replayed responses are passed through `mask`
+    /// before comparing them, which should overwrite the fields that may differ.
+    /// Responses that fail to decode are compared unchanged.
+    pub fn mask_response<E>(self, mask: impl Fn(&mut E::Response) + Send + 'static) -> Self
+    where
+        E: Endpoint,
+        E::Response: Serialize + DeserializeOwned,
+    {
+        self.mask::<E::Response>(E::RESP_KEY, mask)
+    }
+
+    /// Mask nondeterministic fields of the messages of a topic sent by the server
+    ///
+    /// Works like [`Replay::mask_response()`].
+    pub fn mask_topic<T>(self, mask: impl Fn(&mut T::Message) + Send + 'static) -> Self
+    where
+        T: Topic,
+        T::Message: Serialize + DeserializeOwned,
+    {
+        self.mask::<T::Message>(T::TOPIC_KEY, mask)
+    }
+
+    fn mask<M>(mut self, key: Key, mask: impl Fn(&mut M) + Send + 'static) -> Self
+    where
+        M: Serialize + DeserializeOwned,
+    {
+        let mask = move |body: &[u8]| {
+            let mut msg = postcard::from_bytes::<M>(body).ok()?;
+            mask(&mut msg);
+            postcard::to_stdvec(&msg).ok()
+        };
+        self.masks.push((key, Box::new(mask)));
+        self
+    }
+
+    /// Leave out all messages of a topic sent by the server, like logs
+    pub fn ignore_topic<T: Topic>(mut self) -> Self {
+        self.ignored.push(T::TOPIC_KEY);
+        self
+    }
+
+    /// Match the messages of a topic sent by the server by their order, ignoring
+    /// their sequence numbers
+    ///
+    /// For topics published with a sequence number that differs between runs,
+    /// like a counter or a timestamp. The first message sent is compared with the
+    /// first one recorded, and so on.
+    pub fn ignore_topic_seq<T: Topic>(mut self) -> Self {
+        self.ignored_seq.push(T::TOPIC_KEY);
+        self
+    }
+
+    /// How long to wait for each recorded frame, one second by default
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// How long to wait for unexpected frames after the last recorded one, 20ms by default
+    pub fn settle(mut self, settle: Duration) -> Self {
+        self.settle = settle;
+        self
+    }
+
+    /// The size of the receive buffer of the server, 1024 bytes by default
+    pub fn buffer_len(mut self, len: usize) -> Self {
+        self.buf_len = len;
+        self
+    }
+
+    fn is_ignored(&self, header: &VarHeader) -> bool {
+        self.ignored.iter().any(|k| VarKey::Key8(*k) == header.key)
+    }
+
+    fn same_frame(&self, expected: &Recorded, actual: &Recorded) -> bool {
+        let seq_ignored = self
+            .ignored_seq
+            .iter()
+            .any(|k| VarKey::Key8(*k) == expected.header.key);
+        let same_header = if seq_ignored {
+            expected.header.key == actual.header.key
+        } else {
+            expected.header == actual.header
+        };
+        same_header && expected.fragment == actual.fragment
+    }
+
+    fn same_body(&self, expected: &Recorded, actual: &Recorded) -> bool {
+        let mask = self
+            .masks
+            .iter()
+            .find(|(k, _)| VarKey::Key8(*k) == actual.header.key);
+        if let Some((_, mask)) = mask {
+            if let (Some(e), Some(a)) = (mask(&expected.body), mask(&actual.body)) {
+                return e == a;
+            }
+        }
+        expected.body == actual.body
+    }
+
+    /// Run the replay against a dispatcher, using the server of the
+    /// [`test_channels`](crate::server::impls::test_channels) wire
+    ///
+    /// `device_map` is used to report the paths of diverging frames, for
+    /// dispatchers made with [`define_dispatch!`](crate::define_dispatch), it is
+    /// their `device_map` field.
+    pub async fn run<D>(mut self, dispatch: D, device_map: &DeviceMap) -> ReplayReport
+    where
+        D: Dispatch<Tx = ChannelWireTx>,
+    {
+        let (to_server, server_rx) = mpsc::channel(self.steps.len().max(1));
+        let (server_tx, mut from_server) = mpsc::channel(self.steps.len().max(1));
+        let kkind = dispatch.min_key_len();
+        let mut server = Server::new(
+            ChannelWireTx::new(server_tx),
+            ChannelWireRx::new(server_rx),
+            vec![0u8; self.buf_len].into_boxed_slice(),
+            dispatch,
+            kkind,
+        );
+
+        let steps = core::mem::take(&mut self.steps);
+        let me = &self;
+        let drive = async move {
+            let mut report = ReplayReport::default();
+            // Frames sent by the server that were not matched yet
+            let mut sent: Vec<Recorded> = Vec::new();
+            let receive = |frame: Vec<u8>, sent: &mut Vec<Recorded>| {
+                if let Some(frame) = Recorded::parse(&frame) {
+                    if !me.is_ignored(&frame.header) {
+                        sent.push(frame);
+                    }
+                }
+            };
+
+            for step in steps {
+                match step {
+                    Step::Send(frame) => {
+                        report.requests += 1;
+                        // Fails if the server stopped, the rest is reported missing
+                        let _ = to_server.send(frame.to_bytes()).await;
+                    }
+                    Step::Expect(expected) if me.is_ignored(&expected.header) => {}
+                    Step::Expect(expected) => {
+                        report.responses += 1;
+                        let actual = loop {
+                            if let Some(i) = sent.iter().position(|a| me.same_frame(&expected, a)) {
+                                break Some(sent.remove(i));
+                            }
+                            match timeout(me.timeout, from_server.recv()).await {
+                                Ok(Some(frame)) => receive(frame, &mut sent),
+                                _ => break None,
+                            }
+                        };
+                        let path = path_of(device_map, &expected.header.key);
+                        match actual {
+                            Some(actual) if me.same_body(&expected, &actual) => {}
+                            Some(actual) => report.divergences.push(Divergence::Mismatch {
+                                path,
+                                header: expected.header,
+                                expected: expected.body,
+                                actual: actual.body,
+                            }),
+                            None => report.divergences.push(Divergence::Missing {
+                                path,
+                                header: expected.header,
+                                expected: expected.body,
+                            }),
+                        }
+                    }
+                }
+            }
+
+            while let Ok(Some(frame)) = timeout(me.settle, from_server.recv()).await {
+                receive(frame, &mut sent);
+            }
+            for actual in sent {
+                report.divergences.push(Divergence::Unexpected {
+                    path: path_of(device_map, &actual.header.key),
+                    header: actual.header,
+                    actual: actual.body,
+                });
+            }
+            report
+        };
+
+        let mut drive = pin!(drive);
+        select! {
+            report = &mut drive => return report,
+            _ = server.run() => {}
+        }
+        // The server stopped, so the remaining frames are reported missing
+        drive.await
+    }
+}
+
+/// The path of the endpoint or topic a key belongs to
+fn path_of(map: &DeviceMap, key: &VarKey) -> Option<&'static str> {
+    let matches = |k: &Key| VarKey::Key8(*k) == *key;
+    let endpoints = map.endpoints.iter().chain(standard_endpoints());
+    let topics = map
+        .topics_in
+        .iter()
+        .chain(map.topics_out)
+        .chain(standard_topics_in())
+        .chain(standard_topics_out());
+    endpoints
+        .filter(|(_, req, resp)| matches(req) || matches(resp))
+        .map(|(path, _, _)| *path)
+        .chain(topics.filter(|(_, k)| matches(k)).map(|(path, _)| *path))
+        .chain(matches(&ERROR_KEY).then_some(ERROR_PATH))
+        .next()
+}
+
+/// A difference between the frames sent by the server and the capture
+#[derive(Debug, Clone, PartialEq)]
+pub enum Divergence {
+    /// The server sent a frame with a different body
+    Mismatch {
+        /// The path of the endpoint or topic, if known
+        path: Option<&'static str>,
+        /// The header of the frame
+        header: VarHeader,
+        /// The recorded body
+        expected: Vec<u8>,
+        /// The body sent by the server
+        actual: Vec<u8>,
+    },
+    /// The server did not send a recorded frame
+    Missing {
+        /// The path of the endpoint or topic, if known
+        path: Option<&'static str>,
+        /// The header of the frame
+        header: VarHeader,
+        /// The recorded body
+        expected: Vec<u8>,
+    },
+    /// The server sent a frame that was not recorded
+    Unexpected {
+        /// The path of the endpoint or topic, if known
+        path: Option<&'static str>,
+        /// The header of the frame
+        header: VarHeader,
+        /// The body sent by the server
+        actual: Vec<u8>,
+    },
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (kind, path, header) = match self {
+            Divergence::Mismatch { path, header, .. } => ("different", path, header),
+            Divergence::Missing { path, header, .. } => ("missing", path, header),
+            Divergence::Unexpected { path, header, .. } => ("unexpected", path, header),
+        };
+        let path = path.unwrap_or("<unknown>");
+        let header = describe(&header.write_to_vec());
+        write!(f, "{kind} frame for '{path}' ({header})")?;
+        match self {
+            Divergence::Mismatch {
+                expected, actual, ..
+            } => write!(f, ": expected {expected:02x?}, got {actual:02x?}"),
+            Divergence::Missing { expected, .. } => write!(f, ": expected {expected:02x?}"),
+            Divergence::Unexpected { actual, .. } => write!(f, ": got {actual:02x?}"),
+        }
+    }
+}
+
+/// The outcome of a [`Replay`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplayReport {
+    /// The number of frames sent to the server
+    pub requests: usize,
+    /// The number of recorded frames from the server that were compared
+    pub responses: usize,
+    /// The differences found
+    pub divergences: Vec<Divergence>,
+}
+
+impl ReplayReport {
+    /// Did the server send exactly the recorded frames?
+    pub fn is_match(&self) -> bool {
+        self.divergences.is_empty()
+    }
+}
+
+impl fmt::Display for ReplayReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "replayed {} frames, compared {} responses, found {} divergences",
+            self.requests,
+            self.responses,
+            self.divergences.len()
+        )?;
+        for d in self.divergences.iter() {
+            write!(f, "\n  {d}")?;
+        }
+        Ok(())
+    }
+}